@@ -0,0 +1,165 @@
+//! Criterion benches of the live collection in `IMAGE_DIR`, the same measurements the
+//! photo_benchmark tool reports, with statistics to compare before and after a change:
+//!
+//! `IMAGE_DIR=~/Pictures cargo bench --bench collection`
+//!
+//! Inference is measured for the analysis stages listed in `BENCH_STAGES`, e.g.
+//! `BENCH_STAGES=detection,tags`, and `BENCH_SAMPLES` photos (default 20) are extracted,
+//! decoded and analyzed.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use photo_mcp_server::{
+    ANALYZERS, IC,
+    core::{
+        image_cache::{LabelFilter, PhotoCache, PhotoInfo},
+        manifest::Manifest,
+        name_folding::NameFolding,
+    },
+};
+
+/// Page size of the benchmarked searches
+const SEARCH_LIMIT: usize = 50;
+
+fn env_samples() -> usize {
+    std::env::var("BENCH_SAMPLES")
+        .ok()
+        .and_then(|samples| samples.parse().ok())
+        .unwrap_or(20)
+}
+
+// Photos spread evenly over the collection
+fn sample(cache: &PhotoCache, count: usize) -> Vec<&PhotoInfo> {
+    let step = (cache.images.len() / count.max(1)).max(1);
+    cache.images.iter().step_by(step).take(count).collect()
+}
+
+fn index_load(c: &mut Criterion) {
+    c.bench_function("index_load", |b| {
+        b.iter(|| Manifest::load(black_box(IC.image_dir())))
+    });
+}
+
+// One search of every type, with query values taken from the collection so that they match
+fn search(c: &mut Criterion) {
+    let vocabulary = IC.query_vocabulary();
+    let filter = LabelFilter::new(None, None);
+    let folding = NameFolding::configured();
+    let busiest_month = IC
+        .year_month_index()
+        .iter()
+        .flat_map(|(year, months)| {
+            months
+                .iter()
+                .map(|(month, infos)| (*year, *month, infos.len()))
+        })
+        .max_by_key(|(_, _, photos)| *photos)
+        .map(|(year, month, _)| (year, month));
+    let name = "IMG".to_owned();
+    let mut group = c.benchmark_group("search");
+    group.bench_function("name", |b| {
+        b.iter(|| {
+            IC.search_image_by_name(&name, &None, 0, SEARCH_LIMIT, false, false, false, &folding)
+        })
+    });
+    if let Some((year, month)) = busiest_month {
+        group.bench_function("year_month", |b| {
+            b.iter(|| {
+                IC.search_image_by_year_month(year, month, 0, SEARCH_LIMIT, false, false, false)
+            })
+        });
+    }
+    if let Some(camera) = vocabulary.cameras.first() {
+        let (tag, operator) = ("Model".to_owned(), "==".to_owned());
+        group.bench_function("exif", |b| {
+            b.iter(|| {
+                IC.search_image_by_exif_tags(
+                    &tag,
+                    camera,
+                    &operator,
+                    0,
+                    SEARCH_LIMIT,
+                    false,
+                    false,
+                    false,
+                )
+            })
+        });
+    }
+    if let Some(object) = vocabulary.objects.first() {
+        group.bench_function("object", |b| {
+            b.iter(|| {
+                IC.search_image_by_object(
+                    object,
+                    &None,
+                    &filter,
+                    0,
+                    SEARCH_LIMIT,
+                    false,
+                    false,
+                    false,
+                )
+            })
+        });
+    }
+    if let Some(label) = vocabulary.labels.first() {
+        group.bench_function("label", |b| {
+            b.iter(|| {
+                IC.search_image_by_label(
+                    label,
+                    &None,
+                    &filter,
+                    0,
+                    SEARCH_LIMIT,
+                    false,
+                    false,
+                    false,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+// Extraction, decode and inference throughput on a sample of the collection
+fn throughput(c: &mut Criterion) {
+    let samples = sample(&IC, env_samples());
+    let photos = IC.extract_photos(samples.clone()).unwrap();
+    if photos.is_empty() {
+        eprintln!(
+            "No photos in {}, throughput is not measured",
+            IC.image_dir()
+        );
+        return;
+    }
+    let bytes = photos.iter().map(|(_, data)| data.len() as u64).sum();
+
+    let mut group = c.benchmark_group("throughput");
+    group.sample_size(10).throughput(Throughput::Bytes(bytes));
+    group.bench_function("extraction", |b| {
+        b.iter(|| IC.extract_photos(samples.clone()).unwrap())
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            for (_, data) in photos.iter() {
+                let _ = black_box(image::load_from_memory(data));
+            }
+        })
+    });
+    group.throughput(Throughput::Elements(photos.len() as u64));
+    let stages = std::env::var("BENCH_STAGES").unwrap_or_default();
+    for name in stages.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some(stage) = ANALYZERS.get(name) else {
+            eprintln!("Unknown analysis stage: {name}");
+            continue;
+        };
+        group.bench_function(format!("inference/{name}"), |b| {
+            b.iter(|| stage.analyze_batch(&photos).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, index_load, search, throughput);
+criterion_main!(benches);
@@ -0,0 +1,292 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    config::AnalysisConfig,
+    error::PhotoInsightError,
+    features,
+    image_cache::{PhotoCache, PhotoInfo, form_file},
+    stages::{
+        document::DocumentAnalyzer, face_attributes::FaceAttributesAnalyzer,
+        landmark::LandmarkAnalyzer, night_shot::NightShotAnalyzer, occasions::OccasionAnalyzer,
+        people_count::PeopleCountAnalyzer, pet_breed::PetBreedAnalyzer, tags::TagAnalyzer,
+        weather::WeatherAnalyzer,
+    },
+    store,
+    yolo::{ModelRegistry, ObjectDetectionAnalyzer},
+};
+
+/// A single stage of the background analysis pipeline. Every stage produces a JSON value
+/// per photo which is persisted in its own sidecar (`<zip>.<name>.json`).
+pub trait Analyzer: Send + Sync {
+    /// Unique stage name, used as sidecar suffix and in the configuration
+    fn name(&self) -> String;
+
+    /// Stage version, bump it when the produced results change
+    fn version(&self) -> String;
+
+    /// Stage parameters stamped into the sidecar next to name and version
+    fn params(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// Whether the stage runs when it is not mentioned in the configuration
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    /// Whether the inputs of the stage are produced, a stage depending on results of
+    /// another one never runs without them whatever the configuration says
+    fn available(&self) -> bool {
+        true
+    }
+
+    /// Analyze a single photo
+    fn analyze(
+        &self,
+        photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError>;
+
+    /// Analyze a chunk of photos at once, stages with expensive setup (model loading)
+    /// should override this
+    fn analyze_batch(
+        &self,
+        photos: &[(PhotoInfo, Vec<u8>)],
+    ) -> Result<Vec<(PhotoInfo, serde_json::Value)>, PhotoInsightError> {
+        let mut results = Vec::new();
+        for (photo_info, photo_bytes) in photos {
+            match self.analyze(photo_info, photo_bytes) {
+                Ok(value) => results.push((photo_info.clone(), value)),
+                Err(e) => tracing::warn!("{} failed on {photo_info:?}: {e}", self.name()),
+            }
+        }
+        Ok(results)
+    }
+
+    /// Makes the results available in memory, by default in the generic analysis store
+    fn store(&self, cache: &PhotoCache, results: HashMap<PhotoInfo, serde_json::Value>) {
+        cache
+            .analysis
+            .write()
+            .unwrap()
+            .entry(self.name())
+            .or_insert_with(HashMap::new)
+            .extend(results);
+    }
+
+    /// Whether the stage processes faces, such stages skip archives with face processing
+    /// disabled by `privacy.no_face_archives`
+    fn processes_faces(&self) -> bool {
+        false
+    }
+
+    /// Sidecar suffix used by older releases for the results of this stage
+    fn legacy_suffix(&self) -> Option<String> {
+        None
+    }
+
+    fn info(&self) -> AnalyzerInfo {
+        AnalyzerInfo {
+            name: self.name(),
+            version: self.version(),
+            params: self.params(),
+        }
+    }
+}
+
+/// Searchable label produced by an analysis stage. Stages producing labels store them
+/// under the "labels" key of their per photo result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Label {
+    pub name: String,
+    pub confidence: f32,
+}
+
+pub fn labels_of(result: &serde_json::Value) -> Vec<Label> {
+    result
+        .get("labels")
+        .and_then(|labels| serde_json::from_value(labels.clone()).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AnalyzerInfo {
+    pub name: String,
+    pub version: String,
+    pub params: serde_json::Value,
+}
+
+/// Sidecar with results of one analyzer for one zip archive
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct AnalysisSidecar {
+    pub analyzer: AnalyzerInfo,
+    pub results: HashMap<String, serde_json::Value>,
+}
+
+impl AnalysisSidecar {
+    pub fn file_name(image_dir: &str, zip: &str, analyzer: &dyn Analyzer) -> String {
+        form_file(image_dir, zip, &analyzer.name())
+    }
+
+    pub fn write(&self, file_name: &str) -> Result<(), PhotoInsightError> {
+        store::write_json(file_name, self)
+    }
+
+    /// Loads the sidecar of the analyzer for the zip archive, migrating legacy sidecars
+    pub fn load(
+        image_dir: &str,
+        zip: &str,
+        analyzer: &dyn Analyzer,
+    ) -> Option<HashMap<PhotoInfo, serde_json::Value>> {
+        let file_name = Self::file_name(image_dir, zip, analyzer);
+        if !store::exists(&file_name) {
+            Self::migrate_legacy(image_dir, zip, analyzer, &file_name);
+        }
+        if !store::exists(&file_name) {
+            return None;
+        }
+        let sidecar: AnalysisSidecar = match store::read_json(&file_name) {
+            Ok(sidecar) => sidecar,
+            Err(e) => {
+                tracing::error!("can't load analysis results {file_name}: {e}");
+                return None;
+            }
+        };
+        if sidecar.analyzer != analyzer.info() {
+            tracing::warn!(
+                "Results {file_name} were produced by {:?}, current analyzer is {:?}",
+                sidecar.analyzer,
+                analyzer.info()
+            );
+        }
+        Some(
+            sidecar
+                .results
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    PhotoInfo::deserialize_from_key(key)
+                        .ok()
+                        .map(|info| (info, value))
+                })
+                .collect(),
+        )
+    }
+
+    // Sidecars written before the analysis pipeline existed only contain the results
+    fn migrate_legacy(image_dir: &str, zip: &str, analyzer: &dyn Analyzer, file_name: &str) {
+        let Some(suffix) = analyzer.legacy_suffix() else {
+            return;
+        };
+        let legacy_file_name = form_file(image_dir, zip, &suffix);
+        if !store::exists(&legacy_file_name) {
+            return;
+        }
+        tracing::info!("Migrating legacy results {legacy_file_name} to {file_name}");
+        let legacy: Result<HashMap<String, serde_json::Value>, PhotoInsightError> =
+            store::read_json(&legacy_file_name);
+        let result = legacy.and_then(|results| {
+            AnalysisSidecar {
+                analyzer: analyzer.info(),
+                results,
+            }
+            .write(file_name)
+        });
+        if let Err(e) = result {
+            tracing::error!("can't migrate legacy results {legacy_file_name}: {e}");
+        }
+    }
+}
+
+/// All analyzers known to the server, in the order they run
+pub struct AnalyzerRegistry {
+    stages: Vec<Arc<dyn Analyzer>>,
+    config: AnalysisConfig,
+}
+
+impl AnalyzerRegistry {
+    pub fn build(
+        config: &AnalysisConfig,
+        models: &ModelRegistry,
+    ) -> Result<Self, PhotoInsightError> {
+        let mut stages: Vec<Arc<dyn Analyzer>> = Vec::new();
+        for model in models.models() {
+            let enabled = models.analysis_models().contains(&model);
+            stages.push(Arc::new(ObjectDetectionAnalyzer::new(
+                model.clone(),
+                enabled,
+            )));
+        }
+        stages.push(Arc::new(PetBreedAnalyzer::from_config(
+            config.stages.get(PetBreedAnalyzer::NAME),
+            models,
+        )?));
+        stages.push(Arc::new(PeopleCountAnalyzer::from_config(
+            config.stages.get(PeopleCountAnalyzer::NAME),
+            models,
+        )?));
+        stages.push(Arc::new(FaceAttributesAnalyzer::from_config(
+            config.stages.get(FaceAttributesAnalyzer::NAME),
+        )?));
+        stages.push(Arc::new(LandmarkAnalyzer::from_config(
+            config.stages.get(LandmarkAnalyzer::NAME),
+        )?));
+        stages.push(Arc::new(TagAnalyzer::from_config(
+            config.stages.get(TagAnalyzer::NAME),
+        )?));
+        stages.push(Arc::new(DocumentAnalyzer::from_config(
+            config.stages.get(DocumentAnalyzer::NAME),
+        )?));
+        stages.push(Arc::new(NightShotAnalyzer::from_config(
+            config.stages.get(NightShotAnalyzer::NAME),
+        )?));
+        stages.push(Arc::new(WeatherAnalyzer::from_config(
+            config.stages.get(WeatherAnalyzer::NAME),
+        )?));
+        stages.push(Arc::new(OccasionAnalyzer::from_config(
+            config.stages.get(OccasionAnalyzer::NAME),
+        )?));
+        for name in config.stages.keys() {
+            if !stages.iter().any(|s| s.name() == *name) {
+                return Err(PhotoInsightError::from_message(format!(
+                    "unknown analysis stage in config: {name}"
+                )));
+            }
+        }
+        Ok(Self {
+            stages,
+            config: config.clone(),
+        })
+    }
+
+    /// All registered stages, including the disabled ones (their results are still loaded)
+    pub fn all(&self) -> &[Arc<dyn Analyzer>] {
+        &self.stages
+    }
+
+    /// Stages of features not compiled in and stages missing their inputs never run
+    pub fn is_enabled(&self, analyzer: &dyn Analyzer) -> bool {
+        if features::missing_for_stage(&analyzer.name()).is_some() || !analyzer.available() {
+            return false;
+        }
+        self.config
+            .stages
+            .get(&analyzer.name())
+            .and_then(|c| c.enabled)
+            .unwrap_or_else(|| analyzer.enabled_by_default())
+    }
+
+    /// Stages run by the background crawler
+    pub fn enabled(&self) -> Vec<Arc<dyn Analyzer>> {
+        self.stages
+            .iter()
+            .filter(|s| self.is_enabled(s.as_ref()))
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Analyzer>> {
+        self.stages.iter().find(|s| s.name() == name).cloned()
+    }
+}
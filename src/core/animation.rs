@@ -0,0 +1,47 @@
+use std::io::Cursor;
+
+use image::{
+    AnimationDecoder, ImageFormat,
+    codecs::{gif::GifDecoder, webp::WebPDecoder},
+};
+use serde::{Deserialize, Serialize};
+
+/// Animation properties of animated GIF and WebP files
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Animation {
+    pub frames: u32,
+    /// Duration of one loop in milliseconds
+    pub duration_ms: u32,
+}
+
+/// Reads frame count and duration of an animated GIF or WebP, returns None for still images
+pub(crate) fn read_animation(buf: &[u8]) -> Option<Animation> {
+    let frames = match image::guess_format(buf).ok()? {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(buf)).ok()?.into_frames(),
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(Cursor::new(buf)).ok()?;
+            if !decoder.has_animation() {
+                return None;
+            }
+            decoder.into_frames()
+        }
+        _ => return None,
+    };
+    let mut count = 0;
+    let mut duration_ms = 0.0;
+    for frame in frames {
+        let Ok(frame) = frame else {
+            break;
+        };
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        duration_ms += numer as f64 / denom.max(1) as f64;
+        count += 1;
+    }
+    if count < 2 {
+        return None;
+    }
+    Some(Animation {
+        frames: count,
+        duration_ms: duration_ms.round() as u32,
+    })
+}
@@ -0,0 +1,431 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::{
+    analyzer::AnalysisSidecar,
+    bundle::archive_sidecars,
+    config::StorageMode,
+    error::PhotoInsightError,
+    hidden::HiddenPhotos,
+    manifest::{ArchiveRegistry, Manifest},
+    overrides::{MetadataOverrides, shift_date_time},
+    privacy, store, traversal,
+};
+
+/// Default backup directory, in the image directory
+pub const DEFAULT_BACKUP: &str = "photo-mcp.backup";
+const BACKUP_INDEX: &str = "backup.json";
+const BACKUP_VERSION: u32 = 1;
+
+/// Copy of one derived store in the backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupFile {
+    name: String,
+    /// Archive the sidecar belongs to, none for the stores of the whole collection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archive: Option<String>,
+    /// Analysis stage the sidecar holds results of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stage: Option<String>,
+    size: usize,
+    /// SHA-256 of the content
+    sha256: String,
+}
+
+/// Contents of `backup.json` in the backup directory
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupIndex {
+    version: u32,
+    server_version: String,
+    /// UTC time of the backup, "YYYY-MM-DD HH:MM:SS"
+    created: String,
+    /// Archive => size when the backup was made
+    archives: HashMap<String, u64>,
+    /// Stage name => version of the stage which computed the backed up results
+    stages: HashMap<String, String>,
+    files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSummary {
+    pub path: String,
+    /// Number of stores backed up or restored
+    pub files: usize,
+    pub bytes: usize,
+    pub archives: usize,
+    /// Archives of the backup missing here or of different size, their sidecars are skipped
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_archives: Vec<String>,
+    /// Stages of a different version here than in the backup, their results are skipped
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_stages: Vec<String>,
+}
+
+fn sha256_of(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn archive_size(image_dir: &str, zip: &str) -> Option<u64> {
+    std::fs::metadata(Path::new(image_dir).join(zip))
+        .ok()
+        .map(|m| m.len())
+}
+
+fn entry_name(file_name: &str) -> String {
+    Path::new(file_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name.to_owned())
+}
+
+// Name of a file in the backup index is a plain file name, no directory or parent components
+fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none()
+}
+
+// Derived stores of the collection with the archive and stage they belong to
+fn derived_stores(
+    image_dir: &str,
+    archives: &[String],
+) -> Vec<(String, Option<String>, Option<String>)> {
+    let mut stores = [
+        Manifest::file_name(image_dir),
+        ArchiveRegistry::file_name(image_dir),
+        MetadataOverrides::file_name(image_dir),
+        HiddenPhotos::file_name(image_dir),
+        privacy::audit_file_name(image_dir),
+    ]
+    .into_iter()
+    .map(|name| (name, None, None))
+    .collect::<Vec<_>>();
+    for zip in archives {
+        let stage_sidecars = crate::ANALYZERS
+            .all()
+            .iter()
+            .map(|stage| {
+                (
+                    AnalysisSidecar::file_name(image_dir, zip, stage.as_ref()),
+                    Some(stage.name()),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        for name in archive_sidecars(image_dir, zip) {
+            let stage = stage_sidecars.get(&name).cloned().flatten();
+            stores.push((name, Some(zip.clone()), stage));
+        }
+    }
+    stores
+}
+
+// Backed up stores are written the way sidecars are, encrypted when encryption is configured
+fn backup_store() -> Result<Box<dyn store::Store>, PhotoInsightError> {
+    store::build(StorageMode::Sidecar, &crate::CONFIG.encryption)
+}
+
+/// Snapshots all derived stores (indexes, analysis results, metadata overrides, hidden
+/// photos, the manifest and the privacy audit log) into the backup directory, with a
+/// SHA-256 checksum of each of them, so weeks of analysis are not lost with the sidecars
+pub fn backup(image_dir: &str, backup_dir: &str) -> Result<BackupSummary, PhotoInsightError> {
+    let archives = traversal::list_directory_zip_files(image_dir)?;
+    std::fs::create_dir_all(backup_dir).map_err(|e| PhotoInsightError::new(e))?;
+    let target = backup_store()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let mut index = BackupIndex {
+        version: BACKUP_VERSION,
+        server_version: env!("CARGO_PKG_VERSION").to_owned(),
+        created: shift_date_time("1970-01-01 00:00:00", now)?,
+        archives: archives
+            .iter()
+            .filter_map(|zip| archive_size(image_dir, zip).map(|size| (zip.clone(), size)))
+            .collect(),
+        stages: crate::ANALYZERS
+            .all()
+            .iter()
+            .map(|stage| (stage.name(), stage.version()))
+            .collect(),
+        files: Vec::new(),
+    };
+    let mut bytes = 0;
+    for (name, archive, stage) in derived_stores(image_dir, &archives) {
+        if !store::exists(&name) {
+            continue;
+        }
+        let data = crate::STORE.read(&name)?;
+        let file = BackupFile {
+            name: entry_name(&name),
+            archive,
+            stage,
+            size: data.len(),
+            sha256: sha256_of(&data),
+        };
+        bytes += data.len();
+        let target_name = Path::new(backup_dir)
+            .join(&file.name)
+            .to_string_lossy()
+            .to_string();
+        target.write(&target_name, data)?;
+        index.files.push(file);
+    }
+    let index_json = serde_json::to_vec_pretty(&index).map_err(|e| PhotoInsightError::new(e))?;
+    std::fs::write(Path::new(backup_dir).join(BACKUP_INDEX), index_json)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    tracing::info!("Backed up {} stores into {backup_dir}", index.files.len());
+
+    Ok(BackupSummary {
+        path: backup_dir.to_owned(),
+        files: index.files.len(),
+        bytes,
+        archives: index.archives.len(),
+        skipped_archives: Vec::new(),
+        skipped_stages: Vec::new(),
+    })
+}
+
+/// Restores derived stores from a backup written by `backup`. All stores are read and their
+/// checksums verified before anything is written, a corrupted backup restores nothing.
+/// Sidecars of archives missing here or of different size and results of stages whose
+/// version changed since the backup are skipped. The server has to be restarted to load the
+/// restored data.
+pub fn restore(image_dir: &str, backup_dir: &str) -> Result<BackupSummary, PhotoInsightError> {
+    let index_json = std::fs::read(Path::new(backup_dir).join(BACKUP_INDEX))
+        .map_err(|_| PhotoInsightError::from_message(format!("{backup_dir} is not a backup")))?;
+    let index: BackupIndex =
+        serde_json::from_slice(&index_json).map_err(|e| PhotoInsightError::new(e))?;
+    if index.version != BACKUP_VERSION {
+        return Err(PhotoInsightError::from_message(format!(
+            "unsupported backup version {}",
+            index.version
+        )));
+    }
+    let mut skipped_archives = index
+        .archives
+        .iter()
+        .filter(|(zip, size)| archive_size(image_dir, zip) != Some(**size))
+        .map(|(zip, _)| zip.clone())
+        .collect::<Vec<String>>();
+    skipped_archives.sort();
+    let current_stages = crate::ANALYZERS
+        .all()
+        .iter()
+        .map(|stage| (stage.name(), stage.version()))
+        .collect::<HashMap<_, _>>();
+    let mut skipped_stages = index
+        .stages
+        .iter()
+        .filter(|(name, version)| current_stages.get(*name) != Some(*version))
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<String>>();
+    skipped_stages.sort();
+
+    // only the stores a backup of this collection consists of are restored, the index is
+    // not trusted to name files in the image directory
+    let restorable = derived_stores(image_dir, &traversal::list_directory_zip_files(image_dir)?)
+        .into_iter()
+        .map(|(name, _, _)| entry_name(&name))
+        .collect::<HashSet<String>>();
+
+    let source = backup_store()?;
+    let mut restored = Vec::new();
+    for file in index.files.iter() {
+        let skipped = file
+            .archive
+            .as_ref()
+            .is_some_and(|zip| skipped_archives.contains(zip))
+            || file
+                .stage
+                .as_ref()
+                .is_some_and(|stage| skipped_stages.contains(stage));
+        if skipped {
+            continue;
+        }
+        if !is_plain_name(&file.name) || !restorable.contains(&file.name) {
+            return Err(PhotoInsightError::from_message(format!(
+                "backup lists unexpected file {}, backup is corrupted",
+                file.name
+            )));
+        }
+        let source_name = Path::new(backup_dir)
+            .join(&file.name)
+            .to_string_lossy()
+            .to_string();
+        let data = source.read(&source_name)?;
+        if data.len() != file.size || sha256_of(&data) != file.sha256 {
+            return Err(PhotoInsightError::from_message(format!(
+                "checksum mismatch of {}, backup is corrupted",
+                file.name
+            )));
+        }
+        restored.push((file.name.clone(), data));
+    }
+
+    let bytes = restored.iter().map(|(_, data)| data.len()).sum();
+    let files = restored.len();
+    for (name, data) in restored {
+        let file_name = Path::new(image_dir)
+            .join(&name)
+            .to_string_lossy()
+            .to_string();
+        crate::STORE.write(&file_name, data)?;
+    }
+    if !skipped_archives.is_empty() || !skipped_stages.is_empty() {
+        tracing::warn!(
+            "Skipped sidecars of {} archives missing or changed here and {} changed stages",
+            skipped_archives.len(),
+            skipped_stages.len()
+        );
+    }
+    tracing::info!("Restored {files} stores from {backup_dir}");
+
+    Ok(BackupSummary {
+        path: backup_dir.to_owned(),
+        files,
+        bytes,
+        archives: index.archives.len() - skipped_archives.len(),
+        skipped_archives,
+        skipped_stages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::PathBuf};
+
+    use zip::write::SimpleFileOptions;
+
+    use crate::core::{
+        analyzer::AnalysisSidecar,
+        backup::{BACKUP_INDEX, BackupFile, BackupIndex, backup, restore, sha256_of},
+        image_cache::form_file,
+        store,
+    };
+
+    fn write_archive(path: PathBuf, entries: usize) {
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for i in 0..entries {
+            writer
+                .start_file(format!("IMG_{i}.jpg"), SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(format!("photo {i}").as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    // Image directory with archives a.zip and b.zip, their EXIF sidecars and results of a
+    // stage for a.zip, backed up into the backup directory
+    fn backed_up(name: &str) -> (PathBuf, PathBuf, String) {
+        let root = std::env::temp_dir().join(format!("backup-{name}-{}", std::process::id()));
+        let (image_dir, backup_dir) = (root.join("photos"), root.join("backup"));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        write_archive(image_dir.join("a.zip"), 1);
+        write_archive(image_dir.join("b.zip"), 1);
+        let dir = image_dir.to_str().unwrap();
+        store::write_json(&form_file(dir, "a.zip", "exif"), "a").unwrap();
+        store::write_json(&form_file(dir, "b.zip", "exif"), "b").unwrap();
+        let stage = crate::ANALYZERS.all()[0].clone();
+        let stage_sidecar = AnalysisSidecar::file_name(dir, "a.zip", stage.as_ref());
+        store::write_json(&stage_sidecar, "results").unwrap();
+        let summary = backup(dir, backup_dir.to_str().unwrap()).unwrap();
+        assert_eq!(summary.files, 3);
+        (image_dir, backup_dir, stage.name())
+    }
+
+    fn read_index(backup_dir: &PathBuf) -> BackupIndex {
+        serde_json::from_slice(&std::fs::read(backup_dir.join(BACKUP_INDEX)).unwrap()).unwrap()
+    }
+
+    fn write_index(backup_dir: &PathBuf, index: &BackupIndex) {
+        let json = serde_json::to_vec_pretty(index).unwrap();
+        std::fs::write(backup_dir.join(BACKUP_INDEX), json).unwrap();
+    }
+
+    fn read_string(file_name: &str) -> String {
+        store::read_json(file_name).unwrap()
+    }
+
+    #[test]
+    fn test_restore_skips_changed_archives_and_stages() {
+        let (image_dir, backup_dir, stage) = backed_up("skipped");
+        let dir = image_dir.to_str().unwrap();
+        // b.zip changed and the stage computing the results of a.zip got a new version
+        write_archive(image_dir.join("b.zip"), 2);
+        let mut index = read_index(&backup_dir);
+        index.stages.insert(stage.clone(), "0".to_owned());
+        write_index(&backup_dir, &index);
+        let a_exif = form_file(dir, "a.zip", "exif");
+        let b_exif = form_file(dir, "b.zip", "exif");
+        let stage_sidecar = form_file(dir, "a.zip", &stage);
+        for file_name in [&a_exif, &b_exif, &stage_sidecar] {
+            store::write_json(file_name, "changed").unwrap();
+        }
+
+        let summary = restore(dir, backup_dir.to_str().unwrap()).unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.skipped_archives, vec!["b.zip".to_owned()]);
+        assert_eq!(summary.skipped_stages, vec![stage]);
+        assert_eq!(read_string(&a_exif), "a");
+        assert_eq!(read_string(&b_exif), "changed");
+        assert_eq!(read_string(&stage_sidecar), "changed");
+
+        std::fs::remove_dir_all(image_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_restore_checksum_mismatch() {
+        let (image_dir, backup_dir, _) = backed_up("checksum");
+        let dir = image_dir.to_str().unwrap();
+        let a_exif = form_file(dir, "a.zip", "exif");
+        store::write_json(&a_exif, "changed").unwrap();
+        let backed_up_b = form_file(backup_dir.to_str().unwrap(), "b.zip", "exif");
+        store::write_json(&backed_up_b, "tampered").unwrap();
+
+        let error = restore(dir, backup_dir.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("checksum mismatch"), "{error}");
+        // nothing is restored from a corrupted backup
+        assert_eq!(read_string(&a_exif), "changed");
+
+        std::fs::remove_dir_all(image_dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_restore_untrusted_names() {
+        let (image_dir, backup_dir, _) = backed_up("untrusted");
+        let dir = image_dir.to_str().unwrap();
+        let content = b"{}".to_vec();
+        std::fs::write(backup_dir.join("photo-mcp.json"), &content).unwrap();
+        let original = read_index(&backup_dir);
+        for name in [
+            "../photos/photo-mcp.json",
+            "photo-mcp.json",
+            "/tmp/a.zip.exif.json",
+        ] {
+            let mut index = read_index(&backup_dir);
+            index.files.push(BackupFile {
+                name: name.to_owned(),
+                archive: None,
+                stage: None,
+                size: content.len(),
+                sha256: sha256_of(&content),
+            });
+            write_index(&backup_dir, &index);
+            let error = restore(dir, backup_dir.to_str().unwrap()).unwrap_err();
+            assert!(
+                error.to_string().contains("unexpected file"),
+                "{name}: {error}"
+            );
+            write_index(&backup_dir, &original);
+        }
+        assert!(!image_dir.join("photo-mcp.json").exists());
+
+        std::fs::remove_dir_all(image_dir.parent().unwrap()).unwrap();
+    }
+}
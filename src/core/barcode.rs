@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    cancel::CancellationToken, error::PhotoInsightError, external::ExternalModel,
+    image_cache::PhotoInfo,
+};
+
+/// QR code or barcode found in a photo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Barcode {
+    /// Symbology, e.g. "QR_CODE", "EAN_13", "PDF_417", "AZTEC"
+    pub format: String,
+    /// Decoded content, e.g. the URL of a QR code or the boarding pass data
+    pub payload: String,
+    /// (xmin, ymin, xmax, ymax) in pixels when the decoder reports the position
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<(f32, f32, f32, f32)>,
+}
+
+/// Barcodes decoded from one photo
+#[derive(Debug, Clone, Serialize)]
+pub struct BarcodeResult {
+    pub file: PhotoInfo,
+    pub barcodes: Vec<Barcode>,
+}
+
+/// Runs the barcode decoder (e.g. a zbar or ZXing wrapper) over the photos. The decoder
+/// receives the image on stdin and prints a JSON array of
+/// `{"format": .., "payload": .., "bbox": [xmin, ymin, xmax, ymax]}`, empty when the photo
+/// has no barcode.
+pub fn decode_barcodes(
+    photos: Vec<(PhotoInfo, Vec<u8>)>,
+    decoder: &ExternalModel,
+    cancel: &CancellationToken,
+) -> Result<Vec<BarcodeResult>, PhotoInsightError> {
+    let mut results = Vec::new();
+    for (photo_info, image_data) in photos {
+        cancel.check()?;
+        let value = decoder.run(&image_data, &[])?;
+        let barcodes: Vec<Barcode> = serde_json::from_value(value).map_err(|e| {
+            PhotoInsightError::from_message(format!(
+                "unexpected barcode decoder output of {}: {e}",
+                decoder.command
+            ))
+        })?;
+        results.push(BarcodeResult {
+            file: photo_info,
+            barcodes,
+        });
+    }
+    Ok(results)
+}
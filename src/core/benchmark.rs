@@ -0,0 +1,295 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::core::{
+    cancel::CancellationToken,
+    error::PhotoInsightError,
+    image_cache::{LabelFilter, PhotoCache, PhotoInfo},
+    manifest::Manifest,
+    name_folding::NameFolding,
+};
+
+/// Page size of the benchmarked searches
+const SEARCH_LIMIT: usize = 50;
+
+#[derive(Debug, Clone)]
+pub struct BenchmarkOptions {
+    /// Photos extracted, decoded and analyzed
+    pub samples: usize,
+    /// Runs of every search, the first one is reported separately since it fills the caches
+    pub repeat: usize,
+    /// Analysis stages whose inference throughput is measured
+    pub stages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchTiming {
+    pub query_type: String,
+    pub query: String,
+    pub results: usize,
+    /// First run, with cold query caches
+    pub first_ms: f64,
+    /// Mean of the following runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeated_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Throughput {
+    pub photos: usize,
+    pub bytes: usize,
+    pub seconds: f64,
+    pub photos_per_second: f64,
+    pub mb_per_second: f64,
+}
+
+impl Throughput {
+    fn of(photos: usize, bytes: usize, seconds: f64) -> Self {
+        let per_second = |n: f64| if seconds > 0.0 { n / seconds } else { 0.0 };
+        Self {
+            photos,
+            bytes,
+            seconds,
+            photos_per_second: per_second(photos as f64),
+            mb_per_second: per_second(bytes as f64 / 1_000_000.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StageThroughput {
+    pub stage: String,
+    pub throughput: Throughput,
+}
+
+/// Timings measured on the live collection, comparable before and after a change
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkReport {
+    pub photos: usize,
+    /// Reading and parsing the manifest, the index loaded at startup
+    pub index_load_ms: f64,
+    pub index_archives: usize,
+    pub searches: Vec<SearchTiming>,
+    /// Reading photos out of the zip archives
+    pub extraction: Throughput,
+    pub decode: Throughput,
+    pub inference: Vec<StageThroughput>,
+}
+
+fn millis(started: Instant) -> f64 {
+    started.elapsed().as_secs_f64() * 1000.0
+}
+
+// Photos spread evenly over the collection
+fn sample(cache: &PhotoCache, count: usize) -> Vec<&PhotoInfo> {
+    let step = (cache.images.len() / count.max(1)).max(1);
+    cache.images.iter().step_by(step).take(count).collect()
+}
+
+fn time_search(
+    query_type: &str,
+    query: String,
+    repeat: usize,
+    cancel: &CancellationToken,
+    search: impl Fn() -> Result<usize, PhotoInsightError>,
+) -> Result<SearchTiming, PhotoInsightError> {
+    let started = Instant::now();
+    let first = search();
+    let first_ms = millis(started);
+    let mut timing = SearchTiming {
+        query_type: query_type.to_owned(),
+        query,
+        results: 0,
+        first_ms,
+        repeated_ms: None,
+        error: None,
+    };
+    match first {
+        Ok(results) => timing.results = results,
+        Err(e) => {
+            timing.error = Some(e.to_string());
+            return Ok(timing);
+        }
+    }
+    if repeat > 1 {
+        let started = Instant::now();
+        for _ in 1..repeat {
+            cancel.check()?;
+            search()?;
+        }
+        timing.repeated_ms = Some(millis(started) / (repeat - 1) as f64);
+    }
+    Ok(timing)
+}
+
+// One search of every type, with query values taken from the collection so that they match
+fn searches(
+    cache: &PhotoCache,
+    repeat: usize,
+    cancel: &CancellationToken,
+) -> Result<Vec<SearchTiming>, PhotoInsightError> {
+    let vocabulary = cache.query_vocabulary();
+    let filter = LabelFilter::new(None, None);
+    let folding = NameFolding::configured();
+    let busiest_month = cache
+        .year_month_index()
+        .iter()
+        .flat_map(|(year, months)| {
+            months
+                .iter()
+                .map(|(month, infos)| (*year, *month, infos.len()))
+        })
+        .max_by_key(|(_, _, photos)| *photos)
+        .map(|(year, month, _)| (year, month));
+    let mut timings = Vec::new();
+
+    let name = "IMG".to_owned();
+    timings.push(time_search("name", name.clone(), repeat, cancel, || {
+        Ok(cache
+            .search_image_by_name(&name, &None, 0, SEARCH_LIMIT, false, false, false, &folding)
+            .1)
+    })?);
+    if let Some((year, month)) = busiest_month {
+        timings.push(time_search(
+            "year_month",
+            format!("{year}-{month:02}"),
+            repeat,
+            cancel,
+            || {
+                Ok(cache
+                    .search_image_by_year_month(year, month, 0, SEARCH_LIMIT, false, false, false)
+                    .1)
+            },
+        )?);
+    }
+    if let Some(camera) = vocabulary.cameras.first() {
+        let (tag, operator) = ("Model".to_owned(), "==".to_owned());
+        timings.push(time_search(
+            "exif",
+            format!("{tag} == {camera}"),
+            repeat,
+            cancel,
+            || {
+                cache
+                    .search_image_by_exif_tags(
+                        &tag,
+                        camera,
+                        &operator,
+                        0,
+                        SEARCH_LIMIT,
+                        false,
+                        false,
+                        false,
+                    )
+                    .map(|(_, total)| total)
+            },
+        )?);
+    }
+    if let Some(object) = vocabulary.objects.first() {
+        timings.push(time_search(
+            "object",
+            object.clone(),
+            repeat,
+            cancel,
+            || {
+                cache
+                    .search_image_by_object(
+                        object,
+                        &None,
+                        &filter,
+                        0,
+                        SEARCH_LIMIT,
+                        false,
+                        false,
+                        false,
+                    )
+                    .map(|(_, total)| total)
+            },
+        )?);
+    }
+    if let Some(label) = vocabulary.labels.first() {
+        timings.push(time_search("label", label.clone(), repeat, cancel, || {
+            cache
+                .search_image_by_label(label, &None, &filter, 0, SEARCH_LIMIT, false, false, false)
+                .map(|(_, total)| total)
+        })?);
+    }
+    if crate::CONFIG.full_text.enabled {
+        let text = vocabulary
+            .labels
+            .first()
+            .or(vocabulary.objects.first())
+            .cloned()
+            .unwrap_or_else(|| name.clone());
+        timings.push(time_search("text", text.clone(), repeat, cancel, || {
+            cache
+                .text_search(&text, false, 0, SEARCH_LIMIT, false, false, false)
+                .map(|(_, total)| total)
+        })?);
+    }
+    Ok(timings)
+}
+
+/// Measures index load time, search latency of every search type, extraction and decode
+/// throughput and inference throughput of the analysis stages on a sample of the collection.
+/// Results are not stored, analysis sidecars are left as they are. `cargo bench --bench
+/// collection` takes the same measurements with criterion statistics.
+pub fn run(
+    cache: &PhotoCache,
+    options: &BenchmarkOptions,
+    cancel: &CancellationToken,
+) -> Result<BenchmarkReport, PhotoInsightError> {
+    let started = Instant::now();
+    let manifest = Manifest::load(cache.image_dir());
+    let index_load_ms = millis(started);
+    let index_archives = manifest.archives.len();
+    drop(manifest);
+    cancel.check()?;
+
+    let searches = searches(cache, options.repeat.max(1), cancel)?;
+    cancel.check()?;
+
+    let samples = sample(cache, options.samples);
+    let started = Instant::now();
+    let photos = cache.extract_photos(samples)?;
+    let bytes = photos.iter().map(|(_, data)| data.len()).sum();
+    let extraction = Throughput::of(photos.len(), bytes, started.elapsed().as_secs_f64());
+    cancel.check()?;
+
+    let started = Instant::now();
+    let mut decoded = 0;
+    for (_, data) in photos.iter() {
+        cancel.check()?;
+        if image::load_from_memory(data).is_ok() {
+            decoded += 1;
+        }
+    }
+    let decode = Throughput::of(decoded, bytes, started.elapsed().as_secs_f64());
+
+    let mut inference = Vec::new();
+    for name in options.stages.iter() {
+        cancel.check()?;
+        let stage = crate::ANALYZERS.get(name).ok_or_else(|| {
+            PhotoInsightError::from_message(format!("Unknown analysis stage: {name}"))
+        })?;
+        let started = Instant::now();
+        let results = stage.analyze_batch(&photos)?;
+        inference.push(StageThroughput {
+            stage: name.clone(),
+            throughput: Throughput::of(results.len(), bytes, started.elapsed().as_secs_f64()),
+        });
+    }
+
+    Ok(BenchmarkReport {
+        photos: cache.images.len(),
+        index_load_ms,
+        index_archives,
+        searches,
+        extraction,
+        decode,
+        inference,
+    })
+}
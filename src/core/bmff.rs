@@ -0,0 +1,307 @@
+use std::io::Cursor;
+
+use exif::{Context, Field, In, Reader, Tag, Value, experimental::Writer};
+
+use crate::core::error::PhotoInsightError;
+
+/// Box of Canon CR3 metadata in moov, its CMT1, CMT2 and CMT4 boxes hold the TIFF structures
+/// of IFD0, the Exif IFD and the GPS IFD
+const CANON_UUID: [u8; 16] = [
+    0x85, 0xc0, 0xb6, 0x87, 0x82, 0x0f, 0x11, 0xe0, 0x81, 0x11, 0xf4, 0xce, 0x46, 0x2b, 0x6a, 0x48,
+];
+const CANON_METADATA: [(&[u8; 4], Context); 3] = [
+    (b"CMT1", Context::Tiff),
+    (b"CMT2", Context::Exif),
+    (b"CMT4", Context::Gps),
+];
+
+/// ISO base media file (HEIC, AVIF, CR3), starting with the file type box
+pub fn is_bmff(data: &[u8]) -> bool {
+    data.get(4..8) == Some(b"ftyp".as_slice())
+}
+
+/// Reads the EXIF of a HEIF image (the Exif item of its meta box) or a Canon CR3 raw file
+/// (the CMT boxes of its metadata box). The EXIF reader handles HEIF files declaring the
+/// brands it knows only, CR3 files not at all.
+pub fn read_exif(data: &[u8]) -> Result<exif::Exif, PhotoInsightError> {
+    let boxes = children(data);
+    let not_found = || PhotoInsightError::from_message("no EXIF in the media file");
+    if let Some(meta) = find(&boxes, b"meta") {
+        let tiff = heif_exif(meta.get(4..).ok_or_else(not_found)?, data).ok_or_else(not_found)?;
+        return Reader::new()
+            .read_raw(tiff.to_vec())
+            .map_err(|e| PhotoInsightError::new(e));
+    }
+    let canon = find(&boxes, b"moov")
+        .map(children)
+        .and_then(|moov| {
+            moov.into_iter()
+                .filter(|(kind, _)| kind == b"uuid")
+                .find_map(|(_, body)| body.strip_prefix(CANON_UUID.as_slice()))
+        })
+        .ok_or_else(not_found)?;
+    cr3_exif(canon)
+}
+
+// Boxes of the data, a box is its 32 bit size (1: a 64 bit size follows the type, 0: up to the
+// end), its type and its body
+fn children(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    while data.len() >= 8 {
+        let size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64;
+        let kind = [data[4], data[5], data[6], data[7]];
+        let (header, size) = match size {
+            0 => (8, data.len() as u64),
+            1 => match data.get(8..16) {
+                Some(large) => (16, u64::from_be_bytes(large.try_into().unwrap())),
+                None => break,
+            },
+            size => (8, size),
+        };
+        if size < header || size > data.len() as u64 {
+            break;
+        }
+        boxes.push((kind, &data[header as usize..size as usize]));
+        data = &data[size as usize..];
+    }
+    boxes
+}
+
+fn find<'a>(boxes: &[([u8; 4], &'a [u8])], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes.iter().find(|(k, _)| k == kind).map(|(_, body)| *body)
+}
+
+// Big endian unsigned integer of 0, 2, 4 or 8 bytes at the reader position
+fn read_uint(data: &[u8], pos: &mut usize, size: usize) -> Option<u64> {
+    let bytes = data.get(*pos..*pos + size)?;
+    *pos += size;
+    Some(bytes.iter().fold(0, |n, b| n << 8 | *b as u64))
+}
+
+// TIFF structure of the Exif item: the item info box names the item, the item location box
+// places it in the file. The item starts with the offset of the TIFF header.
+fn heif_exif<'a>(meta: &[u8], file: &'a [u8]) -> Option<&'a [u8]> {
+    let boxes = children(meta);
+    let item_id = exif_item_id(find(&boxes, b"iinf")?)?;
+    let (offset, length) = item_location(find(&boxes, b"iloc")?, item_id)?;
+    let item = file.get(offset..offset.checked_add(length)?)?;
+    let mut pos = 0;
+    let tiff_offset = read_uint(item, &mut pos, 4)? as usize;
+    item.get(4usize.checked_add(tiff_offset)?..)
+}
+
+fn exif_item_id(iinf: &[u8]) -> Option<u64> {
+    let version = *iinf.first()?;
+    let entries = if version == 0 { 6 } else { 8 };
+    children(iinf.get(entries..)?)
+        .into_iter()
+        .filter(|(kind, _)| kind == b"infe")
+        .find_map(|(_, infe)| {
+            // item info entries of version 2 and 3 carry the item type
+            let version = *infe.first()?;
+            let id_size = match version {
+                2 => 2,
+                3 => 4,
+                _ => return None,
+            };
+            let mut pos = 4;
+            let item_id = read_uint(infe, &mut pos, id_size)?;
+            let item_type = infe.get(pos + 2..pos + 6)?;
+            (item_type == b"Exif").then_some(item_id)
+        })
+}
+
+// File offset and length of the first extent of the item
+fn item_location(iloc: &[u8], item_id: u64) -> Option<(usize, usize)> {
+    let version = *iloc.first()?;
+    let mut pos = 4;
+    let sizes = read_uint(iloc, &mut pos, 2)?;
+    let offset_size = (sizes >> 12 & 0xf) as usize;
+    let length_size = (sizes >> 8 & 0xf) as usize;
+    let base_offset_size = (sizes >> 4 & 0xf) as usize;
+    let index_size = if version > 0 {
+        (sizes & 0xf) as usize
+    } else {
+        0
+    };
+    let id_size = if version < 2 { 2 } else { 4 };
+    let item_count = read_uint(iloc, &mut pos, id_size)?;
+    for _ in 0..item_count {
+        let id = read_uint(iloc, &mut pos, id_size)?;
+        // only items stored in the file itself (construction method 0) are read
+        let construction_method = if version > 0 {
+            read_uint(iloc, &mut pos, 2)? & 0xf
+        } else {
+            0
+        };
+        let _data_reference_index = read_uint(iloc, &mut pos, 2)?;
+        let base_offset = read_uint(iloc, &mut pos, base_offset_size)?;
+        let extent_count = read_uint(iloc, &mut pos, 2)?;
+        let mut first_extent = None;
+        for _ in 0..extent_count {
+            let _extent_index = read_uint(iloc, &mut pos, index_size)?;
+            let offset = read_uint(iloc, &mut pos, offset_size)?;
+            let length = read_uint(iloc, &mut pos, length_size)?;
+            first_extent.get_or_insert((offset, length));
+        }
+        if id == item_id && construction_method == 0 {
+            let (offset, length) = first_extent?;
+            let offset = usize::try_from(base_offset.checked_add(offset)?).ok()?;
+            return Some((offset, usize::try_from(length).ok()?));
+        }
+    }
+    None
+}
+
+// The CMT boxes are separate TIFF structures with their tags in IFD0, they are merged into one
+// TIFF structure with the tags in the IFDs of their context
+fn cr3_exif(canon: &[u8]) -> Result<exif::Exif, PhotoInsightError> {
+    let boxes = children(canon);
+    let mut fields = Vec::new();
+    for (kind, context) in CANON_METADATA {
+        let Some(tiff) = find(&boxes, kind) else {
+            continue;
+        };
+        let exif = match Reader::new().read_raw(tiff.to_vec()) {
+            Ok(exif) => exif,
+            Err(e) => {
+                tracing::debug!("can't read {} box: {e}", String::from_utf8_lossy(kind));
+                continue;
+            }
+        };
+        fields.extend(
+            exif.fields()
+                .filter(|f| f.ifd_num == In::PRIMARY && !matches!(f.value, Value::Unknown(..)))
+                .map(|f| Field {
+                    tag: Tag(context, f.tag.number()),
+                    ifd_num: In::PRIMARY,
+                    value: f.value.clone(),
+                }),
+        );
+    }
+    if !fields.iter().any(|f| f.tag.context() == Context::Tiff) {
+        return Err(PhotoInsightError::from_message("no EXIF in the CR3 file"));
+    }
+    let mut writer = Writer::new();
+    for field in fields.iter() {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    // the values are decoded, any byte order does
+    writer
+        .write(&mut tiff, true)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    Reader::new()
+        .read_raw(tiff.into_inner())
+        .map_err(|e| PhotoInsightError::new(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use exif::{Field, In, Tag, Value, experimental::Writer};
+
+    use crate::core::bmff::{CANON_UUID, children, is_bmff, item_location, read_exif};
+
+    fn bmff_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(body);
+        data
+    }
+
+    // TIFF structure with the camera model in IFD0
+    fn tiff(model: &str) -> Vec<u8> {
+        let field = Field {
+            tag: Tag::Model,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![model.as_bytes().to_vec()]),
+        };
+        let mut writer = Writer::new();
+        writer.push_field(&field);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        tiff.into_inner()
+    }
+
+    fn model_of(exif: &exif::Exif) -> String {
+        exif.get_field(Tag::Model, In::PRIMARY)
+            .unwrap()
+            .display_value()
+            .to_string()
+    }
+
+    // HEIF file with the Exif item stored after the meta box, located by its iloc entry
+    fn heif(tiff: &[u8]) -> Vec<u8> {
+        let ftyp = bmff_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        // infe version 2: item 1 of type Exif
+        let infe = bmff_box(
+            b"infe",
+            &[2, 0, 0, 0, 0, 1, 0, 0, b'E', b'x', b'i', b'f', 0],
+        );
+        let mut iinf = vec![0, 0, 0, 0, 0, 1];
+        iinf.extend(infe);
+        let iinf = bmff_box(b"iinf", &iinf);
+        let mut item = 0u32.to_be_bytes().to_vec();
+        item.extend_from_slice(tiff);
+        // iloc version 0 with 4 byte offsets and lengths, the offset is patched below
+        let mut iloc = vec![0, 0, 0, 0, 0x44, 0x00, 0, 1, 0, 1, 0, 0, 0, 1];
+        iloc.extend_from_slice(&[0; 4]);
+        iloc.extend_from_slice(&(item.len() as u32).to_be_bytes());
+        let meta_len = 8 + 4 + iinf.len() + 8 + iloc.len();
+        let offset = (ftyp.len() + meta_len) as u32;
+        iloc[14..18].copy_from_slice(&offset.to_be_bytes());
+        let mut meta = vec![0; 4];
+        meta.extend(iinf);
+        meta.extend(bmff_box(b"iloc", &iloc));
+        let mut file = ftyp;
+        file.extend(bmff_box(b"meta", &meta));
+        file.extend(item);
+        file
+    }
+
+    #[test]
+    fn test_heif_exif() {
+        let file = heif(&tiff("iPhone 15"));
+        assert!(is_bmff(&file));
+        assert_eq!(model_of(&read_exif(&file).unwrap()), "\"iPhone 15\"");
+    }
+
+    #[test]
+    fn test_cr3_exif() {
+        let mut canon = CANON_UUID.to_vec();
+        canon.extend(bmff_box(b"CMT1", &tiff("Canon EOS R5")));
+        let mut file = bmff_box(b"ftyp", b"crx \0\0\0\x01crx isom");
+        file.extend(bmff_box(b"moov", &bmff_box(b"uuid", &canon)));
+        assert_eq!(model_of(&read_exif(&file).unwrap()), "\"Canon EOS R5\"");
+    }
+
+    #[test]
+    fn test_malformed_boxes() {
+        // 64 bit size, the box runs to the end of the data
+        let mut large = vec![0, 0, 0, 1];
+        large.extend_from_slice(b"free");
+        large.extend_from_slice(&20u64.to_be_bytes());
+        large.extend_from_slice(&[7; 4]);
+        let boxes = children(&large);
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0], (*b"free", [7u8; 4].as_slice()));
+        // boxes larger than the data and truncated headers are dropped
+        let mut truncated = bmff_box(b"ftyp", b"heic");
+        truncated.extend_from_slice(&[0, 0, 1, 0, b'm', b'e', b't', b'a', 0]);
+        assert_eq!(children(&truncated).len(), 1);
+        assert!(children(&[0, 0, 0, 1, b'f', b'r', b'e', b'e', 0, 0]).is_empty());
+        assert!(children(&[0, 0, 0, 4, b'f', b'r', b'e', b'e']).is_empty());
+        assert!(read_exif(&truncated).is_err());
+        let file = heif(&tiff("iPhone 15"));
+        assert!(read_exif(&file[..file.len() / 2]).is_err());
+        // base offset and extent offset overflowing
+        let mut iloc = vec![0, 0, 0, 0, 0x88, 0x80, 0, 1, 0, 1, 0, 0];
+        iloc.extend_from_slice(&u64::MAX.to_be_bytes());
+        iloc.extend_from_slice(&[0, 1]);
+        iloc.extend_from_slice(&1u64.to_be_bytes());
+        iloc.extend_from_slice(&1u64.to_be_bytes());
+        assert_eq!(item_location(&iloc, 1), None);
+    }
+}
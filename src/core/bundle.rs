@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::AnalysisSidecar, error::PhotoInsightError, hidden::HiddenPhotos,
+    image_cache::form_file, overrides::MetadataOverrides, traversal,
+};
+
+/// Default bundle file name, not a .zip so it is not taken for a photo archive
+pub const DEFAULT_BUNDLE: &str = "photo-mcp.bundle";
+const BUNDLE_INDEX: &str = "bundle.json";
+const BUNDLE_VERSION: u32 = 1;
+
+/// Sidecars of one archive, any of them may be missing
+const ARCHIVE_SIDECARS: [&str; 3] = ["exif", "by_year_month", "sha256"];
+
+/// Contents of `bundle.json` in the bundle: archives the sidecars belong to, identified by
+/// their sizes (modification times change when archives are copied to another machine)
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleIndex {
+    version: u32,
+    archives: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleSummary {
+    pub file: String,
+    /// Number of sidecars exported or imported
+    pub sidecars: usize,
+    pub archives: usize,
+    /// Archives of the bundle missing here or of different size, their sidecars are skipped
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_archives: Vec<String>,
+}
+
+// Sidecars of the archive: EXIF, year/month index, hashes and results of all analyzers
+pub(crate) fn archive_sidecars(image_dir: &str, zip: &str) -> Vec<String> {
+    ARCHIVE_SIDECARS
+        .iter()
+        .map(|suffix| form_file(image_dir, zip, suffix))
+        .chain(
+            crate::ANALYZERS
+                .all()
+                .iter()
+                .map(|analyzer| AnalysisSidecar::file_name(image_dir, zip, analyzer.as_ref())),
+        )
+        .collect()
+}
+
+fn archive_size(image_dir: &str, zip: &str) -> Option<u64> {
+    std::fs::metadata(Path::new(image_dir).join(zip))
+        .ok()
+        .map(|m| m.len())
+}
+
+fn entry_name(file_name: &str) -> String {
+    Path::new(file_name)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name.to_owned())
+}
+
+/// Writes all sidecars (EXIF, year/month indexes, hashes, analysis results, metadata
+/// overrides and hidden photos) into one zip file, so they can be imported on another
+/// machine with the same archives instead of indexing and analyzing the photos again.
+/// The manifest is not exported, it is rebuilt from the sidecars. With encryption configured
+/// the sidecars stay encrypted in the bundle and only a server with the same key imports
+/// them, the archive names and sizes in the bundle index are not encrypted.
+pub fn export(image_dir: &str, bundle_file: &str) -> Result<BundleSummary, PhotoInsightError> {
+    let archives = traversal::list_directory_zip_files(image_dir)?;
+    let mut index = BundleIndex {
+        version: BUNDLE_VERSION,
+        archives: HashMap::new(),
+    };
+    let mut sidecars = vec![
+        MetadataOverrides::file_name(image_dir),
+        HiddenPhotos::file_name(image_dir),
+    ];
+    for zip in archives.iter() {
+        if let Some(size) = archive_size(image_dir, zip) {
+            index.archives.insert(zip.clone(), size);
+        }
+        sidecars.extend(archive_sidecars(image_dir, zip));
+    }
+
+    let file = File::create(bundle_file).map_err(|e| PhotoInsightError::new(e))?;
+    let mut bundle = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut exported = 0;
+    for sidecar in sidecars.iter().filter(|name| crate::STORE.exists(name)) {
+        let name = entry_name(sidecar);
+        let data = crate::STORE.seal(&name, crate::STORE.read(sidecar)?)?;
+        bundle
+            .start_file(name, options)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        bundle
+            .write_all(&data)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        exported += 1;
+    }
+    let index_json = serde_json::to_vec_pretty(&index).map_err(|e| PhotoInsightError::new(e))?;
+    bundle
+        .start_file(BUNDLE_INDEX, options)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    bundle
+        .write_all(&index_json)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    bundle.finish().map_err(|e| PhotoInsightError::new(e))?;
+    tracing::info!("Exported {exported} sidecars into {bundle_file}");
+
+    Ok(BundleSummary {
+        file: bundle_file.to_owned(),
+        sidecars: exported,
+        archives: index.archives.len(),
+        skipped_archives: Vec::new(),
+    })
+}
+
+/// Writes sidecars of the bundle next to the archives, existing sidecars are replaced.
+/// Sidecars of archives which are missing or differ in size are skipped. The server has to
+/// be restarted to load the imported data.
+pub fn import(image_dir: &str, bundle_file: &str) -> Result<BundleSummary, PhotoInsightError> {
+    let file = File::open(bundle_file).map_err(|e| PhotoInsightError::new(e))?;
+    let mut bundle = zip::ZipArchive::new(file).map_err(|e| PhotoInsightError::new(e))?;
+    let index: BundleIndex = {
+        let mut entry = bundle
+            .by_name(BUNDLE_INDEX)
+            .map_err(|_| PhotoInsightError::from_message("not a photo-mcp bundle"))?;
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        serde_json::from_slice(&data).map_err(|e| PhotoInsightError::new(e))?
+    };
+    if index.version != BUNDLE_VERSION {
+        return Err(PhotoInsightError::from_message(format!(
+            "unsupported bundle version {}",
+            index.version
+        )));
+    }
+    let mut skipped_archives = index
+        .archives
+        .iter()
+        .filter(|(zip, size)| archive_size(image_dir, zip) != Some(**size))
+        .map(|(zip, _)| zip.clone())
+        .collect::<Vec<String>>();
+    skipped_archives.sort();
+    let skipped = |name: &str| {
+        skipped_archives
+            .iter()
+            .any(|zip| name.starts_with(&format!("{zip}.")))
+    };
+
+    let mut imported = 0;
+    for i in 0..bundle.len() {
+        let mut entry = bundle.by_index(i).map_err(|e| PhotoInsightError::new(e))?;
+        let name = entry_name(entry.name());
+        if name == BUNDLE_INDEX || skipped(&name) {
+            continue;
+        }
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        let file_name = Path::new(image_dir)
+            .join(&name)
+            .to_string_lossy()
+            .to_string();
+        crate::STORE.write(&file_name, crate::STORE.open(&name, data)?)?;
+        imported += 1;
+    }
+    if !skipped_archives.is_empty() {
+        tracing::warn!(
+            "Skipped sidecars of {} archives missing or changed here",
+            skipped_archives.len()
+        );
+    }
+    tracing::info!("Imported {imported} sidecars from {bundle_file}");
+
+    Ok(BundleSummary {
+        file: bundle_file.to_owned(),
+        sidecars: imported,
+        archives: index.archives.len() - skipped_archives.len(),
+        skipped_archives,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::PathBuf};
+
+    use zip::write::SimpleFileOptions;
+
+    use crate::core::{
+        bundle::{export, import},
+        image_cache::form_file,
+        store,
+    };
+
+    fn write_archive(path: PathBuf, entries: usize) {
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for i in 0..entries {
+            writer
+                .start_file(format!("IMG_{i}.jpg"), SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(format!("photo {i}").as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_import_skips_changed_archives() {
+        let root = std::env::temp_dir().join(format!("bundle-import-{}", std::process::id()));
+        let (source, target) = (root.join("source"), root.join("target"));
+        for dir in [&source, &target] {
+            std::fs::create_dir_all(dir).unwrap();
+            write_archive(dir.join("a.zip"), 1);
+        }
+        write_archive(source.join("b.zip"), 1);
+        // b.zip differs here, c.zip is missing
+        write_archive(target.join("b.zip"), 2);
+        write_archive(source.join("c.zip"), 1);
+        let (source_dir, target_dir) = (source.to_str().unwrap(), target.to_str().unwrap());
+        for zip in ["a.zip", "b.zip", "c.zip"] {
+            store::write_json(&form_file(source_dir, zip, "exif"), zip).unwrap();
+        }
+        let bundle_file = root.join("photo-mcp.bundle");
+        let bundle_file = bundle_file.to_str().unwrap();
+
+        let exported = export(source_dir, bundle_file).unwrap();
+        assert_eq!((exported.sidecars, exported.archives), (3, 3));
+        let imported = import(target_dir, bundle_file).unwrap();
+        assert_eq!((imported.sidecars, imported.archives), (1, 1));
+        assert_eq!(
+            imported.skipped_archives,
+            vec!["b.zip".to_owned(), "c.zip".to_owned()]
+        );
+        let a_exif: String = store::read_json(&form_file(target_dir, "a.zip", "exif")).unwrap();
+        assert_eq!(a_exif, "a.zip");
+        assert!(!store::exists(&form_file(target_dir, "b.zip", "exif")));
+        assert!(!store::exists(&form_file(target_dir, "c.zip", "exif")));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_import_of_other_zip() {
+        let root = std::env::temp_dir().join(format!("bundle-other-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        write_archive(root.join("photos.zip"), 1);
+        let error = import(
+            root.to_str().unwrap(),
+            root.join("photos.zip").to_str().unwrap(),
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("not a photo-mcp bundle"),
+            "{error}"
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::core::error::PhotoInsightError;
+
+/// Cancellation flag and deadline of a tool call, checked between photos by long running
+/// operations. Cancelled operations fail, operations past the deadline stop and return the
+/// results gathered so far.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn with_timeout(timeout: Option<Duration>) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: timeout.map(|t| Instant::now() + t),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Checks if the call ran out of time, results are truncated then
+    pub fn timed_out(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Fails with "request cancelled" error once the token is cancelled
+    pub fn check(&self) -> Result<(), PhotoInsightError> {
+        if self.is_cancelled() {
+            Err(PhotoInsightError::from_message("request cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Tool calls in progress per session. The SDK does not pass JSON-RPC request ids to the
+/// handlers, so a cancellation notification can only be matched to its call when that call is
+/// the only one in progress of the session.
+#[derive(Debug, Default)]
+pub struct InFlightCalls {
+    calls: RwLock<HashMap<String, HashMap<u64, CancellationToken>>>,
+    next_id: AtomicU64,
+}
+
+impl InFlightCalls {
+    /// Registers a new call, returns its id and cancellation token
+    pub fn start(&self, session_id: &str, timeout: Option<Duration>) -> (u64, CancellationToken) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let token = CancellationToken::with_timeout(timeout);
+        self.calls
+            .write()
+            .unwrap()
+            .entry(session_id.to_owned())
+            .or_default()
+            .insert(id, token.clone());
+        (id, token)
+    }
+
+    pub fn finish(&self, session_id: &str, id: u64) {
+        let mut calls = self.calls.write().unwrap();
+        if let Some(session) = calls.get_mut(session_id) {
+            session.remove(&id);
+            if session.is_empty() {
+                calls.remove(session_id);
+            }
+        }
+    }
+
+    /// Cancels the call in progress of the session when there is exactly one, calls of a
+    /// session running concurrently are left to their timeout as the cancelled one can't be
+    /// told apart. Returns the number of calls in progress.
+    pub fn cancel_single(&self, session_id: &str) -> usize {
+        let calls = self.calls.read().unwrap();
+        let session = calls.get(session_id);
+        if let Some(session) = session
+            && session.len() == 1
+        {
+            session.values().for_each(|token| token.cancel());
+        }
+        session.map_or(0, |session| session.len())
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fs::File, sync::Arc};
+
+use parquet::{
+    basic::Compression,
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use serde::Serialize;
+
+use crate::core::error::PhotoInsightError;
+
+/// One photo of the metadata catalog: photo info, EXIF, location and labels
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogRow {
+    /// Stable photo ID, see `photo_id::PhotoIds`
+    pub photo_id: Option<String>,
+    pub zip_file_name: String,
+    pub photo_file_name: String,
+    pub photo_index_in_zip: usize,
+    pub sha256: Option<String>,
+    pub date_time: String,
+    pub year: u32,
+    pub month: u32,
+    pub model: String,
+    pub lens: String,
+    pub width: u32,
+    pub height: u32,
+    pub aperture: String,
+    pub shutter_speed: String,
+    pub iso: String,
+    pub focal_len: String,
+    pub focal_len_35mm: Option<f64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub description: Option<String>,
+    pub color_label: Option<String>,
+    /// Detected objects and analysis labels separated by `;`
+    pub labels: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogFormat {
+    Csv,
+    Parquet,
+}
+
+impl CatalogFormat {
+    pub fn parse(format: &str) -> Result<Self, PhotoInsightError> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(CatalogFormat::Csv),
+            "parquet" => Ok(CatalogFormat::Parquet),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid catalog format: {format}, use one of csv, parquet"
+            ))),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CatalogFormat::Csv => "csv",
+            CatalogFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// Writes the catalog into the file in the given format
+pub fn export(
+    rows: &[CatalogRow],
+    format: CatalogFormat,
+    file_name: &str,
+) -> Result<(), PhotoInsightError> {
+    match format {
+        CatalogFormat::Csv => write_csv(rows, file_name),
+        CatalogFormat::Parquet => write_parquet(rows, file_name),
+    }
+}
+
+fn write_csv(rows: &[CatalogRow], file_name: &str) -> Result<(), PhotoInsightError> {
+    let mut writer = csv::Writer::from_path(file_name).map_err(|e| PhotoInsightError::new(e))?;
+    for row in rows {
+        writer
+            .serialize(row)
+            .map_err(|e| PhotoInsightError::new(e))?;
+    }
+    writer.flush().map_err(|e| PhotoInsightError::new(e))
+}
+
+// Parquet column, all columns are optional, missing values are written as nulls
+enum Column {
+    Text(&'static str, Vec<Option<String>>),
+    Int(&'static str, Vec<Option<i64>>),
+    Float(&'static str, Vec<Option<f64>>),
+}
+
+impl Column {
+    fn schema(&self) -> String {
+        match self {
+            Column::Text(name, _) => format!("OPTIONAL BYTE_ARRAY {name} (UTF8);"),
+            Column::Int(name, _) => format!("OPTIONAL INT64 {name};"),
+            Column::Float(name, _) => format!("OPTIONAL DOUBLE {name};"),
+        }
+    }
+}
+
+fn columns(rows: &[CatalogRow]) -> Vec<Column> {
+    let text = |name, f: fn(&CatalogRow) -> Option<String>| {
+        Column::Text(name, rows.iter().map(f).collect())
+    };
+    let int = |name, f: fn(&CatalogRow) -> i64| {
+        Column::Int(name, rows.iter().map(|row| Some(f(row))).collect())
+    };
+    let float =
+        |name, f: fn(&CatalogRow) -> Option<f64>| Column::Float(name, rows.iter().map(f).collect());
+    vec![
+        text("photo_id", |r| r.photo_id.clone()),
+        text("zip_file_name", |r| Some(r.zip_file_name.clone())),
+        text("photo_file_name", |r| Some(r.photo_file_name.clone())),
+        int("photo_index_in_zip", |r| r.photo_index_in_zip as i64),
+        text("sha256", |r| r.sha256.clone()),
+        text("date_time", |r| Some(r.date_time.clone())),
+        int("year", |r| r.year as i64),
+        int("month", |r| r.month as i64),
+        text("model", |r| Some(r.model.clone())),
+        text("lens", |r| Some(r.lens.clone())),
+        int("width", |r| r.width as i64),
+        int("height", |r| r.height as i64),
+        text("aperture", |r| Some(r.aperture.clone())),
+        text("shutter_speed", |r| Some(r.shutter_speed.clone())),
+        text("iso", |r| Some(r.iso.clone())),
+        text("focal_len", |r| Some(r.focal_len.clone())),
+        float("focal_len_35mm", |r| r.focal_len_35mm),
+        float("latitude", |r| r.latitude),
+        float("longitude", |r| r.longitude),
+        text("description", |r| r.description.clone()),
+        text("color_label", |r| r.color_label.clone()),
+        text("labels", |r| Some(r.labels.clone())),
+    ]
+}
+
+// Splits optional values into definition levels and the present values
+fn levels<T: Clone>(values: &[Option<T>]) -> (Vec<i16>, Vec<T>) {
+    let def_levels = values.iter().map(|v| v.is_some() as i16).collect();
+    let present = values.iter().flatten().cloned().collect();
+    (def_levels, present)
+}
+
+fn write_parquet(rows: &[CatalogRow], file_name: &str) -> Result<(), PhotoInsightError> {
+    let columns = columns(rows);
+    let message = format!(
+        "message photo_catalog {{ {} }}",
+        columns
+            .iter()
+            .map(|c| c.schema())
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    let schema = Arc::new(parse_message_type(&message).map_err(|e| PhotoInsightError::new(e))?);
+    let props = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let file = File::create(file_name).map_err(|e| PhotoInsightError::new(e))?;
+    let mut writer =
+        SerializedFileWriter::new(file, schema, props).map_err(|e| PhotoInsightError::new(e))?;
+    let mut row_group = writer
+        .next_row_group()
+        .map_err(|e| PhotoInsightError::new(e))?;
+    for column in columns.iter() {
+        let mut column_writer = row_group
+            .next_column()
+            .map_err(|e| PhotoInsightError::new(e))?
+            .ok_or_else(|| PhotoInsightError::from_message("parquet schema mismatch"))?;
+        match column {
+            Column::Text(_, values) => {
+                let (def_levels, present) = levels(values);
+                let present = present
+                    .into_iter()
+                    .map(|s| ByteArray::from(s.into_bytes()))
+                    .collect::<Vec<ByteArray>>();
+                column_writer.typed::<ByteArrayType>().write_batch(
+                    &present,
+                    Some(&def_levels),
+                    None,
+                )
+            }
+            Column::Int(_, values) => {
+                let (def_levels, present) = levels(values);
+                column_writer
+                    .typed::<Int64Type>()
+                    .write_batch(&present, Some(&def_levels), None)
+            }
+            Column::Float(_, values) => {
+                let (def_levels, present) = levels(values);
+                column_writer
+                    .typed::<DoubleType>()
+                    .write_batch(&present, Some(&def_levels), None)
+            }
+        }
+        .map_err(|e| PhotoInsightError::new(e))?;
+        column_writer
+            .close()
+            .map_err(|e| PhotoInsightError::new(e))?;
+    }
+    row_group.close().map_err(|e| PhotoInsightError::new(e))?;
+    writer.close().map_err(|e| PhotoInsightError::new(e))?;
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet};
+
+/// Built-in aliases of the COCO classes: synonyms of a class and groups of classes. Group
+/// members may be groups themselves, e.g. "animal" includes "pet".
+const ALIASES: [(&str, &[&str]); 28] = [
+    ("people", &["person"]),
+    ("human", &["person"]),
+    ("man", &["person"]),
+    ("woman", &["person"]),
+    ("child", &["person"]),
+    ("puppy", &["dog"]),
+    ("doggy", &["dog"]),
+    ("kitten", &["cat"]),
+    ("kitty", &["cat"]),
+    ("pony", &["horse"]),
+    ("automobile", &["car"]),
+    ("lorry", &["truck"]),
+    ("bike", &["bicycle", "motorcycle"]),
+    ("motorbike", &["motorcycle"]),
+    ("plane", &["airplane"]),
+    ("ship", &["boat"]),
+    ("sofa", &["couch"]),
+    ("television", &["tv"]),
+    ("phone", &["cell phone"]),
+    ("smartphone", &["cell phone"]),
+    ("ball", &["sports ball"]),
+    ("pet", &["dog", "cat"]),
+    (
+        "animal",
+        &[
+            "pet", "bird", "horse", "sheep", "cow", "elephant", "bear", "zebra", "giraffe",
+        ],
+    ),
+    (
+        "vehicle",
+        &[
+            "car",
+            "truck",
+            "bus",
+            "motorcycle",
+            "bicycle",
+            "train",
+            "boat",
+            "airplane",
+        ],
+    ),
+    (
+        "food",
+        &[
+            "banana", "apple", "sandwich", "orange", "broccoli", "carrot", "hot dog", "pizza",
+            "donut", "cake",
+        ],
+    ),
+    ("fruit", &["banana", "apple", "orange"]),
+    (
+        "furniture",
+        &["chair", "couch", "bed", "dining table", "bench"],
+    ),
+    (
+        "electronics",
+        &["tv", "laptop", "mouse", "remote", "keyboard", "cell phone"],
+    ),
+];
+
+/// Aliases of detected object classes used by the object search: synonyms ("puppy" for
+/// "dog") and hierarchical groups ("animal" for dogs, cats, horses...). Configured aliases
+/// are added to the built-in ones, a configured alias replaces the built-in one of the
+/// same name.
+#[derive(Debug, Default)]
+pub struct ClassAliases {
+    /// Alias (lowercase) => class names or other aliases
+    aliases: HashMap<String, Vec<String>>,
+}
+
+impl ClassAliases {
+    pub fn new(configured: &HashMap<String, Vec<String>>) -> Self {
+        let lowercase = |names: &[String]| names.iter().map(|n| n.to_lowercase()).collect();
+        let mut aliases: HashMap<String, Vec<String>> = ALIASES
+            .iter()
+            .map(|(alias, names)| {
+                let names = names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+                (alias.to_string(), names)
+            })
+            .collect();
+        aliases.extend(
+            configured
+                .iter()
+                .map(|(alias, names)| (alias.to_lowercase(), lowercase(names))),
+        );
+        Self { aliases }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.aliases.keys()
+    }
+
+    /// Class names the name stands for (lowercase): the name itself and the classes of its
+    /// aliases, groups are expanded recursively. Plural names ("puppies") are accepted.
+    pub fn expand(&self, name: &str) -> HashSet<String> {
+        let name = name.to_lowercase();
+        let name = if self.aliases.contains_key(&name) {
+            name
+        } else {
+            singular(&name)
+                .filter(|singular| self.aliases.contains_key(singular))
+                .unwrap_or(name)
+        };
+        let mut classes = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            match self.aliases.get(&name) {
+                // an alias may list itself to stay a class, e.g. "dog": ["dog", "wolf"]
+                Some(names) => {
+                    for member in names {
+                        if *member == name {
+                            classes.insert(member.clone());
+                        } else {
+                            pending.push(member.clone());
+                        }
+                    }
+                }
+                None => {
+                    classes.insert(name);
+                }
+            }
+        }
+        classes
+    }
+}
+
+// English plural of the alias, "puppies" => "puppy", "ponies" => "pony"
+fn singular(name: &str) -> Option<String> {
+    if let Some(stem) = name.strip_suffix("ies") {
+        Some(format!("{stem}y"))
+    } else {
+        name.strip_suffix('s').map(str::to_owned)
+    }
+}
@@ -0,0 +1,333 @@
+use std::{collections::HashSet, path::Path};
+
+use serde::Serialize;
+
+use crate::core::{
+    analyzer::AnalysisSidecar,
+    bundle::archive_sidecars,
+    error::PhotoInsightError,
+    image_cache::{PhotoCache, PhotoInfo},
+    store, traversal, zip,
+};
+
+#[derive(Debug, Clone)]
+pub struct CleanupOptions {
+    /// Only report what would be removed
+    pub dry_run: bool,
+    /// Directory orphans are moved into instead of being deleted, stale results are removed
+    /// from a copy of their sidecar kept there
+    pub archive_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanReason {
+    /// The archive the sidecar belongs to is gone
+    MissingArchive,
+    /// Results of an analysis stage (e.g. a detection model) no longer configured
+    UnregisteredStage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanSidecar {
+    pub file: String,
+    pub archive: String,
+    pub reason: OrphanReason,
+    pub bytes: u64,
+}
+
+/// Analysis sidecar with results of photos no longer in its archive
+#[derive(Debug, Clone, Serialize)]
+pub struct PrunedSidecar {
+    pub file: String,
+    pub stage: String,
+    pub stale_results: usize,
+    pub reclaimed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<String>,
+    pub orphans: Vec<OrphanSidecar>,
+    pub pruned: Vec<PrunedSidecar>,
+    /// Bytes freed in the image directory, or to be freed on a dry run
+    pub reclaimed_bytes: u64,
+}
+
+// Archive of a `<archive>.zip.<suffix>.json` sidecar name
+fn sidecar_archive(file_name: &str) -> Option<&str> {
+    let stem = file_name.strip_suffix(".json")?;
+    Some(&stem[..stem.rfind(".zip.")? + ".zip".len()])
+}
+
+// Moves the file into the archive directory, or deletes it when there is none
+fn discard(file_name: &str, archive_dir: Option<&str>) -> Result<(), PhotoInsightError> {
+    if let Some(archive_dir) = archive_dir {
+        keep_copy(file_name, archive_dir)?;
+    }
+    crate::STORE.remove(file_name)
+}
+
+// Copies the file as stored (encrypted sidecars stay encrypted) into the archive directory
+fn keep_copy(file_name: &str, archive_dir: &str) -> Result<(), PhotoInsightError> {
+    let name = Path::new(file_name)
+        .file_name()
+        .ok_or_else(|| PhotoInsightError::from_message(format!("invalid sidecar {file_name}")))?;
+    std::fs::create_dir_all(archive_dir).map_err(|e| PhotoInsightError::new(e))?;
+    std::fs::copy(file_name, Path::new(archive_dir).join(name))
+        .map_err(|e| PhotoInsightError::new(e))?;
+    Ok(())
+}
+
+// Result keys of every entry in the archive, entries excluded from indexing included
+fn archive_keys(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<HashSet<String>, PhotoInsightError> {
+    Ok(zip::list_zip_entries(image_dir, zip_file_name)?
+        .into_iter()
+        .map(|entry| {
+            PhotoInfo::new(zip_file_name.to_owned(), entry.name, entry.index).serialize_as_key()
+        })
+        .collect())
+}
+
+/// Finds sidecars of archives no longer in the image directory and of analysis stages no
+/// longer configured, and results in analysis sidecars of photos no longer in their archive
+/// (the archive was recreated with other photos). Orphans are deleted or moved into the
+/// archive directory, stale results are removed from their sidecars. Sidecars of archives
+/// excluded from indexing are kept, as are results of entries excluded from indexing. Only
+/// sidecar files are scanned, nothing is found with in-memory storage.
+pub fn run(
+    cache: &PhotoCache,
+    options: &CleanupOptions,
+) -> Result<CleanupReport, PhotoInsightError> {
+    let image_dir = cache.image_dir();
+    let indexed = traversal::list_directory_zip_files(image_dir)?;
+    let mut present = indexed.iter().cloned().collect::<HashSet<_>>();
+    present.extend(traversal::list_excluded_zip_files(image_dir)?);
+    let known = indexed
+        .iter()
+        .flat_map(|zip| archive_sidecars(image_dir, zip))
+        .collect::<HashSet<_>>();
+    let archive_dir = options.archive_dir.as_deref();
+
+    let mut orphans = Vec::new();
+    let entries = std::fs::read_dir(image_dir).map_err(|e| PhotoInsightError::new(e))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(archive) = sidecar_archive(&name) else {
+            continue;
+        };
+        let reason = if !present.contains(archive) {
+            OrphanReason::MissingArchive
+        } else if indexed.iter().any(|zip| zip == archive)
+            && !known.contains(&format!("{image_dir}/{name}"))
+        {
+            OrphanReason::UnregisteredStage
+        } else {
+            continue;
+        };
+        orphans.push(OrphanSidecar {
+            file: format!("{image_dir}/{name}"),
+            archive: archive.to_owned(),
+            reason,
+            bytes: entry.metadata().map(|m| m.len()).unwrap_or_default(),
+        });
+    }
+    orphans.sort_by(|a, b| a.file.cmp(&b.file));
+    if !options.dry_run {
+        for orphan in orphans.iter() {
+            discard(&orphan.file, archive_dir)?;
+            tracing::info!("Removed orphan sidecar {}", orphan.file);
+        }
+    }
+
+    let mut pruned = Vec::new();
+    for zip in indexed.iter() {
+        let mut photos = None;
+        for analyzer in crate::ANALYZERS.all() {
+            let file_name = AnalysisSidecar::file_name(image_dir, zip, analyzer.as_ref());
+            if !store::exists(&file_name) {
+                continue;
+            }
+            let mut sidecar: AnalysisSidecar = match store::read_json(&file_name) {
+                Ok(sidecar) => sidecar,
+                Err(e) => {
+                    tracing::warn!("can't read {file_name}: {e}");
+                    continue;
+                }
+            };
+            let photos = match photos {
+                Some(ref photos) => photos,
+                None => photos.insert(archive_keys(image_dir, zip)?),
+            };
+            let results = sidecar.results.len();
+            sidecar.results.retain(|key, _| photos.contains(key));
+            let stale_results = results - sidecar.results.len();
+            if stale_results == 0 {
+                continue;
+            }
+            let before = crate::STORE.size(&file_name).unwrap_or_default();
+            let after = if options.dry_run {
+                serde_json::to_vec(&sidecar).map_or(before, |json| json.len() as u64)
+            } else {
+                if let Some(archive_dir) = archive_dir {
+                    keep_copy(&file_name, archive_dir)?;
+                }
+                sidecar.write(&file_name)?;
+                tracing::info!("Removed {stale_results} stale results from {file_name}");
+                crate::STORE.size(&file_name).unwrap_or_default()
+            };
+            pruned.push(PrunedSidecar {
+                file: file_name,
+                stage: analyzer.name(),
+                stale_results,
+                reclaimed_bytes: before.saturating_sub(after),
+            });
+        }
+    }
+
+    let reclaimed_bytes = orphans.iter().map(|o| o.bytes).sum::<u64>()
+        + pruned.iter().map(|p| p.reclaimed_bytes).sum::<u64>();
+    tracing::info!(
+        "Cleanup found {} orphan sidecars and {} sidecars with stale results, {} bytes",
+        orphans.len(),
+        pruned.len(),
+        reclaimed_bytes
+    );
+    Ok(CleanupReport {
+        dry_run: options.dry_run,
+        archive_dir: options.archive_dir.clone(),
+        orphans,
+        pruned,
+        reclaimed_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, io::Write};
+
+    use zip::write::SimpleFileOptions;
+
+    use crate::core::{
+        analyzer::AnalysisSidecar,
+        cleanup::{CleanupOptions, OrphanReason, run},
+        image_cache::{PhotoCache, PhotoInfo, form_file},
+        store,
+    };
+
+    #[test]
+    fn test_results_of_excluded_entries_kept() {
+        let image_dir =
+            std::env::temp_dir().join(format!("cleanup-excluded-{}", std::process::id()));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(8, 8)
+            .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+            .unwrap();
+        let zip = std::fs::File::create(image_dir.join("takeout.zip")).unwrap();
+        let mut writer = zip::ZipWriter::new(zip);
+        for name in ["IMG_0.jpg", "IMG_1.jpg"] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(jpeg.get_ref()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let image_dir_str = image_dir.to_str().unwrap();
+        let mut cache = PhotoCache::build(image_dir_str).unwrap();
+        // IMG_1.jpg is excluded from indexing, as with an `exclude_entries` pattern
+        cache
+            .images
+            .retain(|info| info.photo_file_name != "IMG_1.jpg");
+        let analyzer = crate::ANALYZERS.all()[0].clone();
+        let file_name = AnalysisSidecar::file_name(image_dir_str, "takeout.zip", analyzer.as_ref());
+        let key = |name: &str, index| {
+            PhotoInfo::new("takeout.zip".to_owned(), name.to_owned(), index).serialize_as_key()
+        };
+        let results = [key("IMG_0.jpg", 0), key("IMG_1.jpg", 1), key("Gone.jpg", 2)]
+            .into_iter()
+            .map(|key| (key, serde_json::json!([])))
+            .collect::<HashMap<_, _>>();
+        AnalysisSidecar {
+            analyzer: analyzer.info(),
+            results,
+        }
+        .write(&file_name)
+        .unwrap();
+
+        let options = CleanupOptions {
+            dry_run: false,
+            archive_dir: None,
+        };
+        let report = run(&cache, &options).unwrap();
+        assert!(report.orphans.is_empty());
+        assert_eq!(report.pruned.len(), 1);
+        assert_eq!(report.pruned[0].stale_results, 1);
+        let sidecar: AnalysisSidecar = crate::core::store::read_json(&file_name).unwrap();
+        assert!(sidecar.results.contains_key(&key("IMG_0.jpg", 0)));
+        assert!(sidecar.results.contains_key(&key("IMG_1.jpg", 1)));
+        assert!(!sidecar.results.contains_key(&key("Gone.jpg", 2)));
+
+        std::fs::remove_dir_all(image_dir).unwrap();
+    }
+
+    #[test]
+    fn test_orphan_sidecars() {
+        let root = std::env::temp_dir().join(format!("cleanup-orphans-{}", std::process::id()));
+        let (image_dir, archive_dir) = (root.join("photos"), root.join("archived"));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let zip = std::fs::File::create(image_dir.join("takeout.zip")).unwrap();
+        let mut writer = zip::ZipWriter::new(zip);
+        writer
+            .start_file("IMG_0.jpg", SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(b"photo").unwrap();
+        writer.finish().unwrap();
+        let dir = image_dir.to_str().unwrap();
+        let cache = PhotoCache::build(dir).unwrap();
+        let kept = form_file(dir, "takeout.zip", "exif");
+        let missing_archive = form_file(dir, "gone.zip", "exif");
+        let unregistered_stage = form_file(dir, "takeout.zip", "retired_stage");
+        for file_name in [&kept, &missing_archive, &unregistered_stage] {
+            store::write_json(file_name, "sidecar").unwrap();
+        }
+
+        let dry_run = CleanupOptions {
+            dry_run: true,
+            archive_dir: None,
+        };
+        let report = run(&cache, &dry_run).unwrap();
+        let orphans = report
+            .orphans
+            .iter()
+            .map(|orphan| (orphan.file.as_str(), orphan.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            orphans,
+            vec![
+                (missing_archive.as_str(), OrphanReason::MissingArchive),
+                (unregistered_stage.as_str(), OrphanReason::UnregisteredStage),
+            ]
+        );
+        assert!(store::exists(&missing_archive) && store::exists(&unregistered_stage));
+
+        let options = CleanupOptions {
+            dry_run: false,
+            archive_dir: Some(archive_dir.to_str().unwrap().to_owned()),
+        };
+        let report = run(&cache, &options).unwrap();
+        assert_eq!(report.orphans.len(), 2);
+        assert!(!store::exists(&missing_archive) && !store::exists(&unregistered_stage));
+        assert!(store::exists(&kept));
+        assert!(archive_dir.join("gone.zip.exif.json").exists());
+        assert!(archive_dir.join("takeout.zip.retired_stage.json").exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
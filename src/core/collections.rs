@@ -0,0 +1,381 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::Deref,
+    path::{Component, Path},
+    sync::OnceLock,
+};
+
+use serde::Serialize;
+
+use crate::core::{
+    config::CollectionConfig,
+    error::PhotoInsightError,
+    image_cache::{PhotoCache, PhotoResult},
+    query_cache,
+};
+
+/// Name of the experimental client capability carrying the API token, clients send
+/// `{"capabilities": {"experimental": {"photo_collection": {"token": "..."}}}}` in the
+/// initialize request. A token bound to several collections selects one of them with
+/// `"collection": "<name>"`, the first one by name is used otherwise.
+pub const TOKEN_CAPABILITY: &str = "photo_collection";
+/// Label of the default collection in federated search results
+pub const DEFAULT_COLLECTION: &str = "default";
+
+/// Collections a client may access
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    /// Collection of the client's tool calls, `None` for the default collection
+    pub collection: Option<String>,
+    /// All collections the client's token is bound to, empty for the default collection
+    pub authorized: Vec<String>,
+}
+
+/// Result of a federated search labelled with the collection it was found in
+#[derive(Debug, Clone, Serialize)]
+pub struct FederatedResult<T> {
+    pub collection: String,
+    #[serde(flatten)]
+    pub result: T,
+    /// Time taken, results of all collections are merged in time order
+    pub date_time: Option<String>,
+}
+
+thread_local! {
+    // Collection of the tool call or crawler running on this thread, `None` for the default one
+    static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Restores the previous collection of the thread when dropped, also when the scoped call panics
+struct RestoreCurrent(Option<String>);
+
+impl Drop for RestoreCurrent {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+struct Collection {
+    image_dir: String,
+    cache: OnceLock<Result<PhotoCache, String>>,
+}
+
+impl Collection {
+    fn new(image_dir: &str) -> Self {
+        Self {
+            image_dir: image_dir.to_owned(),
+            cache: OnceLock::new(),
+        }
+    }
+
+    // Indexes the collection on first use
+    fn cache(&self) -> Result<&PhotoCache, PhotoInsightError> {
+        self.cache
+            .get_or_init(|| PhotoCache::build(&self.image_dir).map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| {
+                PhotoInsightError::from_message(format!(
+                    "Failed to index photos in {}: {e}",
+                    self.image_dir
+                ))
+            })
+    }
+}
+
+/// Photo collections served by the server: the default one in `IMAGE_DIR` and the named ones
+/// of the configuration, each indexed on first use. The global photo cache dereferences to
+/// the collection of the current thread, set by `scoped` for tool calls and crawlers, so a
+/// tool call never sees photos of another collection.
+pub struct Collections {
+    default: Collection,
+    named: HashMap<String, Collection>,
+    tokens: HashMap<String, Vec<String>>,
+}
+
+impl Collections {
+    pub fn new(
+        image_dir: &str,
+        config: &HashMap<String, CollectionConfig>,
+    ) -> Result<Self, PhotoInsightError> {
+        let mut tokens: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, collection) in config {
+            if collection.image_dir.is_empty() {
+                return Err(PhotoInsightError::from_message(format!(
+                    "collection {name} has no image_dir"
+                )));
+            }
+            for token in collection.tokens.iter() {
+                tokens.entry(token.clone()).or_default().push(name.clone());
+            }
+        }
+        tokens.values_mut().for_each(|names| names.sort());
+        Ok(Self {
+            default: Collection::new(image_dir),
+            named: config
+                .iter()
+                .map(|(name, collection)| (name.clone(), Collection::new(&collection.image_dir)))
+                .collect(),
+            tokens,
+        })
+    }
+
+    /// Named collections are configured, clients have to present an API token
+    pub fn is_multi_tenant(&self) -> bool {
+        !self.named.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.named.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Collections of the client with the API token, the requested collection is used for its
+    /// tool calls when the token is bound to it. Without named collections every client uses
+    /// the default collection and no token is needed.
+    pub fn access(
+        &self,
+        token: Option<&str>,
+        requested: Option<&str>,
+    ) -> Result<Access, PhotoInsightError> {
+        if !self.is_multi_tenant() {
+            return Ok(Access::default());
+        }
+        let token = token.ok_or_else(|| {
+            PhotoInsightError::from_message(format!(
+                "API token required, send it in the {TOKEN_CAPABILITY} experimental capability"
+            ))
+        })?;
+        let authorized = self
+            .tokens
+            .get(token)
+            .ok_or_else(|| PhotoInsightError::from_message("invalid API token"))?;
+        let collection = match requested {
+            Some(requested) => authorized
+                .iter()
+                .find(|name| *name == requested)
+                .ok_or_else(|| {
+                    PhotoInsightError::from_message(format!(
+                        "API token is not bound to collection {requested}"
+                    ))
+                })?,
+            None => &authorized[0],
+        };
+        Ok(Access {
+            collection: Some(collection.clone()),
+            authorized: authorized.clone(),
+        })
+    }
+
+    fn collection(&self, name: Option<&str>) -> Result<&Collection, PhotoInsightError> {
+        match name {
+            None => Ok(&self.default),
+            Some(name) => self.named.get(name).ok_or_else(|| {
+                PhotoInsightError::from_message(format!("unknown collection: {name}"))
+            }),
+        }
+    }
+
+    /// Server file or directory named by a client for a tool call on the current collection.
+    /// Without named collections the path is used as it is. With named collections it has to
+    /// be a relative path, it is resolved in the collection's directory and may not lead into
+    /// the directory of another collection, so that a tool call can't read or write data of
+    /// another tenant.
+    pub fn client_path(&self, path: &str) -> Result<String, PhotoInsightError> {
+        let name = CURRENT.with(|current| current.borrow().clone());
+        self.client_path_of(name.as_deref(), path)
+    }
+
+    fn client_path_of(&self, name: Option<&str>, path: &str) -> Result<String, PhotoInsightError> {
+        if !self.is_multi_tenant() {
+            return Ok(path.to_owned());
+        }
+        let relative = Path::new(path);
+        if path.is_empty()
+            || !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(PhotoInsightError::from_message(format!(
+                "{path}: only paths relative to the collection directory are allowed, without .."
+            )));
+        }
+        let image_dir = Path::new(&self.collection(name)?.image_dir);
+        let resolved = image_dir.join(relative);
+        let others = self
+            .named
+            .iter()
+            .filter(|(other, _)| Some(other.as_str()) != name)
+            .map(|(_, collection)| collection)
+            .chain(name.is_some().then_some(&self.default));
+        for other in others {
+            let other_dir = Path::new(&other.image_dir);
+            // a collection nested in this one is out of reach, a parent directory is not
+            if resolved.starts_with(other_dir) && !image_dir.starts_with(other_dir) {
+                return Err(PhotoInsightError::from_message(format!(
+                    "{path} is in the directory of another collection"
+                )));
+            }
+        }
+        Ok(resolved.to_string_lossy().to_string())
+    }
+
+    /// Photo cache of the collection, `None` for the default collection
+    pub fn get(&self, name: Option<&str>) -> Result<&PhotoCache, PhotoInsightError> {
+        self.collection(name)?.cache()
+    }
+
+    /// Runs `f` with the collection as the current one of this thread, the collection is
+    /// indexed first so that `f` can't fail on it
+    pub fn scoped<R>(
+        &self,
+        name: Option<&str>,
+        f: impl FnOnce() -> R,
+    ) -> Result<R, PhotoInsightError> {
+        self.get(name)?;
+        let _restore =
+            RestoreCurrent(CURRENT.with(|current| current.replace(name.map(str::to_owned))));
+        Ok(f())
+    }
+
+    /// Runs the search in every authorized collection (the default collection when none) and
+    /// merges the results in time order, undated photos last. Returns the page at offset and
+    /// the number of all results.
+    pub fn federated_search<T: PhotoResult + Clone>(
+        &self,
+        authorized: &[String],
+        offset: usize,
+        limit: usize,
+        search: impl Fn() -> Result<Vec<T>, PhotoInsightError>,
+    ) -> Result<(Vec<FederatedResult<T>>, usize), PhotoInsightError> {
+        let collections = if authorized.is_empty() {
+            vec![None]
+        } else {
+            authorized.iter().map(|name| Some(name.as_str())).collect()
+        };
+        let mut merged = Vec::new();
+        for name in collections {
+            let results = self.scoped(name, || {
+                search().map(|results| {
+                    results
+                        .into_iter()
+                        .map(|result| FederatedResult {
+                            collection: name.unwrap_or(DEFAULT_COLLECTION).to_owned(),
+                            date_time: self.date_taken(result.photo_info()),
+                            result,
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })??;
+            tracing::info!(
+                "Federated search found {} photos in {name:?}",
+                results.len()
+            );
+            merged.extend(results);
+        }
+        // stable sort keeps the order of each collection's search among undated photos
+        merged.sort_by(|a, b| match (&a.date_time, &b.date_time) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        Ok(query_cache::page(&merged, offset, limit))
+    }
+}
+
+impl Deref for Collections {
+    type Target = PhotoCache;
+
+    fn deref(&self) -> &PhotoCache {
+        CURRENT
+            .with(|current| self.get(current.borrow().as_deref()))
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::core::{collections::Collections, config::CollectionConfig};
+
+    fn collections(root: &std::path::Path) -> Collections {
+        let config = [("a", "a"), ("b", "a/b"), ("c", "c")]
+            .into_iter()
+            .map(|(name, dir)| {
+                let image_dir = root.join(dir);
+                std::fs::create_dir_all(&image_dir).unwrap();
+                let collection = CollectionConfig {
+                    image_dir: image_dir.to_string_lossy().to_string(),
+                    tokens: vec![format!("token-{name}")],
+                };
+                (name.to_owned(), collection)
+            })
+            .collect::<HashMap<_, _>>();
+        let default_dir = root.join("default");
+        std::fs::create_dir_all(&default_dir).unwrap();
+        Collections::new(default_dir.to_str().unwrap(), &config).unwrap()
+    }
+
+    #[test]
+    fn test_token_bound_to_collection() {
+        let root = std::env::temp_dir().join(format!("collections-token-{}", std::process::id()));
+        let collections = collections(&root);
+        let access = collections.access(Some("token-a"), None).unwrap();
+        assert_eq!(access.collection.as_deref(), Some("a"));
+        assert_eq!(access.authorized, vec!["a"]);
+        assert!(collections.access(Some("token-a"), Some("b")).is_err());
+        assert!(collections.access(Some("token-x"), None).is_err());
+        assert!(collections.access(None, None).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_client_paths_stay_in_collection() {
+        let root = std::env::temp_dir().join(format!("collections-paths-{}", std::process::id()));
+        let collections = collections(&root);
+        let a_dir = root.join("a");
+        let paths = collections
+            .scoped(Some("a"), || {
+                [
+                    "exports/catalog.csv".to_owned(),
+                    root.join("c").to_string_lossy().to_string(),
+                    root.join("a/photo-mcp.backup")
+                        .to_string_lossy()
+                        .to_string(),
+                    "../c/photo-mcp.backup".to_owned(),
+                    "./photo-mcp.bundle".to_owned(),
+                    "b".to_owned(),
+                    "b/photo-mcp.backup".to_owned(),
+                    String::new(),
+                ]
+                .map(|path| collections.client_path(&path).ok())
+            })
+            .unwrap();
+        assert_eq!(
+            paths[0].as_deref(),
+            Some(a_dir.join("exports/catalog.csv").to_str().unwrap())
+        );
+        // absolute paths, .. and collections nested in this one are rejected
+        assert!(paths[1..].iter().all(Option::is_none), "{paths:?}");
+        // the collection nested in another one may use its own directory
+        let nested = collections
+            .scoped(Some("b"), || collections.client_path("photo-mcp.backup"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(nested, a_dir.join("b/photo-mcp.backup").to_string_lossy());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_client_paths_single_collection() {
+        let collections = Collections::new("/photos", &HashMap::new()).unwrap();
+        assert_eq!(
+            collections.client_path_of(None, "/mnt/backup").unwrap(),
+            "/mnt/backup"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use moxcms::{ColorProfile, Layout, ProfileText, TransformOptions};
+
+use crate::core::error::PhotoInsightError;
+
+/// Decodes the image together with its embedded ICC profile (if any)
+pub(crate) fn decode_with_icc(
+    buf: &[u8],
+) -> Result<(DynamicImage, Option<Vec<u8>>), PhotoInsightError> {
+    let mut decoder = ImageReader::new(Cursor::new(buf))
+        .with_guessed_format()
+        .map_err(|e| PhotoInsightError::new(e))?
+        .into_decoder()
+        .map_err(|e| PhotoInsightError::new(e))?;
+    let icc = decoder.icc_profile().unwrap_or(None);
+    let img = DynamicImage::from_decoder(decoder).map_err(|e| PhotoInsightError::new(e))?;
+    Ok((img, icc))
+}
+
+/// Image properties read from the header
+pub(crate) struct ImageHeader {
+    /// Embedded ICC profile
+    pub icc: Option<Vec<u8>>,
+    /// Bits per color channel
+    pub bit_depth: u16,
+}
+
+/// Reads the embedded ICC profile and bit depth without decoding the pixel data
+pub(crate) fn read_header(buf: &[u8]) -> Option<ImageHeader> {
+    let mut decoder = ImageReader::new(Cursor::new(buf))
+        .with_guessed_format()
+        .ok()?
+        .into_decoder()
+        .ok()?;
+    let color_type = decoder.color_type();
+    let bit_depth = color_type.bits_per_pixel() / color_type.channel_count() as u16;
+    let icc = decoder.icc_profile().unwrap_or(None);
+    Some(ImageHeader { icc, bit_depth })
+}
+
+/// Converts high bit depth images to 8 bits per channel. The values are stretched between
+/// the 0.1% and 99.9% percentiles, scans rarely use the whole 16 bit range and plain
+/// truncation makes them dark and flat.
+pub(crate) fn tone_map_8bit(img: DynamicImage) -> DynamicImage {
+    match img {
+        DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) => {
+            let luma = img.to_luma16();
+            let (lo, hi) = percentiles(luma.as_raw());
+            let pixels = luma.as_raw().iter().map(|v| stretch(*v, lo, hi)).collect();
+            image::GrayImage::from_raw(luma.width(), luma.height(), pixels)
+                .map(DynamicImage::ImageLuma8)
+                .unwrap_or_else(|| DynamicImage::ImageLuma8(img.to_luma8()))
+        }
+        DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_)
+        | DynamicImage::ImageRgb32F(_)
+        | DynamicImage::ImageRgba32F(_) => {
+            let rgb = img.to_rgb16();
+            let (lo, hi) = percentiles(rgb.as_raw());
+            let pixels = rgb.as_raw().iter().map(|v| stretch(*v, lo, hi)).collect();
+            image::RgbImage::from_raw(rgb.width(), rgb.height(), pixels)
+                .map(DynamicImage::ImageRgb8)
+                .unwrap_or_else(|| DynamicImage::ImageRgb8(img.to_rgb8()))
+        }
+        _ => img,
+    }
+}
+
+fn percentiles(values: &[u16]) -> (u16, u16) {
+    let mut histogram = vec![0usize; 65536];
+    for v in values {
+        histogram[*v as usize] += 1;
+    }
+    let cut = values.len() / 1000;
+    let mut lo = 0;
+    let mut seen = 0;
+    for (v, count) in histogram.iter().enumerate() {
+        seen += count;
+        if seen > cut {
+            lo = v as u16;
+            break;
+        }
+    }
+    let mut hi = u16::MAX;
+    seen = 0;
+    for (v, count) in histogram.iter().enumerate().rev() {
+        seen += count;
+        if seen > cut {
+            hi = v as u16;
+            break;
+        }
+    }
+    if hi <= lo { (0, u16::MAX) } else { (lo, hi) }
+}
+
+fn stretch(v: u16, lo: u16, hi: u16) -> u8 {
+    let v = v.clamp(lo, hi) - lo;
+    (v as u32 * 255 / (hi - lo) as u32) as u8
+}
+
+/// Human readable name of the color space described by the ICC profile, e.g. "Display P3"
+pub(crate) fn color_space_name(icc: &[u8]) -> Option<String> {
+    let profile = ColorProfile::new_from_slice(icc).ok()?;
+    let name = match profile.description? {
+        ProfileText::PlainString(s) => s,
+        ProfileText::Localizable(l) => l.into_iter().next()?.value,
+        ProfileText::Description(d) => d.ascii_string,
+    };
+    let name = name.trim_matches(char::from(0)).trim().to_owned();
+    if name.is_empty() { None } else { Some(name) }
+}
+
+/// Checks if the ICC profile describes sRGB, so no conversion is needed
+pub(crate) fn is_srgb_profile(icc: &[u8]) -> bool {
+    color_space_name(icc).is_some_and(|name| {
+        let lower = name.to_lowercase();
+        lower.starts_with("srgb")
+            || lower.contains("iec61966-2.1")
+            || lower.contains("iec 61966-2-1")
+    })
+}
+
+/// Converts the image into sRGB using its ICC profile, sRGB (or unparsable) profiles are left
+/// untouched and only the pixel values are kept
+pub(crate) fn to_srgb(img: DynamicImage, icc: Option<&[u8]>) -> DynamicImage {
+    let Some(icc) = icc else {
+        return img;
+    };
+    if is_srgb_profile(icc) {
+        return img;
+    }
+    let source = match ColorProfile::new_from_slice(icc) {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::warn!("Invalid ICC profile: {e:?}");
+            return img;
+        }
+    };
+    let transform = match source.create_transform_8bit(
+        Layout::Rgb,
+        &ColorProfile::new_srgb(),
+        Layout::Rgb,
+        TransformOptions::default(),
+    ) {
+        Ok(t) => t,
+        Err(e) => {
+            tracing::warn!("Unsupported ICC profile: {e:?}");
+            return img;
+        }
+    };
+    let src = img.to_rgb8();
+    let mut dst = image::RgbImage::new(src.width(), src.height());
+    if let Err(e) = transform.transform(src.as_raw(), &mut dst) {
+        tracing::warn!("Color conversion failed: {e:?}");
+        return img;
+    }
+    DynamicImage::ImageRgb8(dst)
+}
@@ -0,0 +1,81 @@
+use lazy_static::lazy_static;
+use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::core::error::PhotoInsightError;
+
+lazy_static! {
+    // Lightroom and Bridge write the label name, darktable a sequence of label numbers
+    static ref XMP_LABEL_RE: Regex =
+        Regex::new(r#"xmp:Label\s*=\s*"([^"]*)"|<xmp:Label>([^<]*)</xmp:Label>"#).unwrap();
+    static ref DARKTABLE_LABELS_RE: Regex =
+        Regex::new(r"(?s)<darktable:colorlabels>.*?<rdf:li>(\d)</rdf:li>").unwrap();
+}
+
+/// XMP packets are stored near the start of JPEG, PNG and WebP files
+const XMP_SEARCH_BYTES: usize = 512 * 1024;
+
+/// Color label of desktop photo tools (Lightroom, Bridge, darktable, macOS Finder colors)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorLabel {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    /// Set by photo_set_metadata to remove a label read from the photo's XMP
+    None,
+}
+
+impl ColorLabel {
+    pub const NAMES: [&str; 6] = ["red", "yellow", "green", "blue", "purple", "none"];
+    const ALL: [ColorLabel; 6] = [
+        ColorLabel::Red,
+        ColorLabel::Yellow,
+        ColorLabel::Green,
+        ColorLabel::Blue,
+        ColorLabel::Purple,
+        ColorLabel::None,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+
+    /// Label as Lightroom writes it into xmp:Label
+    pub fn xmp_name(&self) -> &'static str {
+        ["Red", "Yellow", "Green", "Blue", "Purple", ""][*self as usize]
+    }
+
+    pub fn parse(name: &str) -> Result<Self, PhotoInsightError> {
+        let name = name.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|label| label.name() == name)
+            .ok_or_else(|| {
+                PhotoInsightError::from_message(format!(
+                    "Invalid color label: {name}, use one of {}",
+                    Self::NAMES.join(", ")
+                ))
+            })
+    }
+
+    /// Label of the photo's embedded XMP packet, labels other than the five colors (custom
+    /// Lightroom label sets) are ignored
+    pub fn read_xmp(image_data: &[u8]) -> Option<Self> {
+        let data = &image_data[..image_data.len().min(XMP_SEARCH_BYTES)];
+        if let Some(caps) = XMP_LABEL_RE.captures(data) {
+            let name = caps.get(1).or_else(|| caps.get(2))?;
+            return Self::parse(&String::from_utf8_lossy(name.as_bytes()))
+                .ok()
+                .filter(|label| *label != ColorLabel::None);
+        }
+        let caps = DARKTABLE_LABELS_RE.captures(data)?;
+        let number = String::from_utf8_lossy(&caps[1]).parse::<usize>().ok()?;
+        Self::ALL
+            .get(number)
+            .copied()
+            .filter(|label| *label != ColorLabel::None)
+    }
+}
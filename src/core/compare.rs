@@ -0,0 +1,149 @@
+use serde::Serialize;
+
+use crate::core::{
+    error::PhotoInsightError, exif::ExifInfo, image, image_cache::PhotoInfo, insights::unquote,
+};
+
+/// Height of the photos in the side by side preview
+const PREVIEW_HEIGHT: u32 = 512;
+/// Clipped pixels are darker or brighter than this
+const CLIP_LEVEL: u8 = 5;
+
+/// EXIF field with different values in the compared photos
+#[derive(Debug, Clone, Serialize)]
+pub struct ExifDifference {
+    pub field: String,
+    pub first: serde_json::Value,
+    pub second: serde_json::Value,
+}
+
+/// Quality metrics computed from the (resized) image data
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityMetrics {
+    pub megapixels: f64,
+    /// Variance of the Laplacian, higher is sharper
+    pub sharpness: Option<f64>,
+    /// Mean luma, 0 (black) to 1 (white)
+    pub brightness: f64,
+    /// Standard deviation of luma, 0 to 1
+    pub contrast: f64,
+    /// Share of pixels clipped to black
+    pub clipped_shadows: f64,
+    /// Share of pixels clipped to white
+    pub clipped_highlights: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhotoComparison {
+    pub first: PhotoInfo,
+    pub second: PhotoInfo,
+    pub exif_differences: Vec<ExifDifference>,
+    /// Visual similarity from 0 (unrelated) to 1 (same picture), by difference hash
+    pub similarity: f64,
+    pub identical_content: bool,
+    pub first_quality: QualityMetrics,
+    pub second_quality: QualityMetrics,
+    /// Which photo looks better by resolution and sharpness, "first" or "second"
+    pub suggestion: String,
+}
+
+fn exif_differences(first: &ExifInfo, second: &ExifInfo) -> Vec<ExifDifference> {
+    let as_map = |exif: &ExifInfo| match serde_json::to_value(exif) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => serde_json::Map::new(),
+    };
+    let (first, second) = (as_map(first), as_map(second));
+    let mut fields = first
+        .keys()
+        .chain(second.keys())
+        .cloned()
+        .collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let a = first.get(&field).cloned().unwrap_or_default();
+            let b = second.get(&field).cloned().unwrap_or_default();
+            let same = match (&a, &b) {
+                (serde_json::Value::String(a), serde_json::Value::String(b)) => {
+                    unquote(a) == unquote(b)
+                }
+                _ => a == b,
+            };
+            (!same).then_some(ExifDifference {
+                field,
+                first: a,
+                second: b,
+            })
+        })
+        .collect()
+}
+
+fn quality(exif: &ExifInfo, img: &::image::DynamicImage) -> QualityMetrics {
+    let luma = img.to_luma8();
+    let pixels = luma.pixels().map(|p| p.0[0]).collect::<Vec<u8>>();
+    let count = pixels.len().max(1) as f64;
+    let mean = pixels.iter().map(|v| *v as f64).sum::<f64>() / count;
+    let variance = pixels
+        .iter()
+        .map(|v| (*v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    QualityMetrics {
+        megapixels: exif.width as f64 * exif.height as f64 / 1_000_000.0,
+        sharpness: image::sharpness_of(img),
+        brightness: mean / 255.0,
+        contrast: variance.sqrt() / 255.0,
+        clipped_shadows: pixels.iter().filter(|v| **v <= CLIP_LEVEL).count() as f64 / count,
+        clipped_highlights: pixels.iter().filter(|v| **v >= 255 - CLIP_LEVEL).count() as f64
+            / count,
+    }
+}
+
+// Sharpness is only comparable at the same size, both images are scaled to the preview height
+fn normalized(img: ::image::DynamicImage) -> ::image::DynamicImage {
+    img.resize(
+        PREVIEW_HEIGHT * 4,
+        PREVIEW_HEIGHT,
+        ::image::imageops::FilterType::Triangle,
+    )
+}
+
+/// Compares two photos given with their EXIF and image data, returns the comparison and
+/// a side by side JPEG preview
+pub fn compare(
+    first: (PhotoInfo, ExifInfo, &[u8]),
+    second: (PhotoInfo, ExifInfo, &[u8]),
+) -> Result<(PhotoComparison, Vec<u8>), PhotoInsightError> {
+    let decode = |data: &[u8]| {
+        ::image::load_from_memory(data)
+            .map(normalized)
+            .map_err(|e| PhotoInsightError::new(e))
+    };
+    let (first_img, second_img) = (decode(first.2)?, decode(second.2)?);
+    let distance =
+        (image::difference_hash(&first_img) ^ image::difference_hash(&second_img)).count_ones();
+    let first_quality = quality(&first.1, &first_img);
+    let second_quality = quality(&second.1, &second_img);
+    let score = |q: &QualityMetrics| (q.megapixels, q.sharpness.unwrap_or_default());
+    let suggestion = if score(&second_quality) > score(&first_quality) {
+        "second"
+    } else {
+        "first"
+    };
+    let preview = image::side_by_side_jpeg(&[&first_img, &second_img], PREVIEW_HEIGHT)?;
+    Ok((
+        PhotoComparison {
+            exif_differences: exif_differences(&first.1, &second.1),
+            similarity: 1.0 - distance as f64 / 64.0,
+            identical_content: first.0.sha256.is_some() && first.0.sha256 == second.0.sha256,
+            first: first.0,
+            second: second.0,
+            first_quality,
+            second_quality,
+            suggestion: suggestion.to_owned(),
+        },
+        preview,
+    ))
+}
@@ -0,0 +1,521 @@
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::core::{error::PhotoInsightError, external::ExternalModel, traversal, yolo::ModelSpec};
+
+/// Server configuration, loaded from the JSON file given by `PHOTO_MCP_CONFIG`
+/// (defaults to `photo-mcp.json` in the working directory). Every section is optional,
+/// a missing file yields the default configuration.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub detection: DetectionConfig,
+    /// Model weights downloaded on first use
+    pub models: ModelsConfig,
+    pub analysis: AnalysisConfig,
+    /// Notifications about content found by the background analysis
+    pub watchers: Vec<WatcherConfig>,
+    pub motion: MotionConfig,
+    /// Where indexes and analysis results are kept
+    pub storage: StorageMode,
+    pub cache: CacheConfig,
+    /// Work done at startup so that the first tool calls are fast
+    pub warm_up: WarmUpConfig,
+    pub tools: ToolsConfig,
+    /// Archives and archive entries left out of indexing
+    pub index: IndexConfig,
+    pub full_text: FullTextConfig,
+    pub search: SearchConfig,
+    /// Language of detected object class names and labels in tool responses
+    pub i18n: I18nConfig,
+    /// Named photo collections, e.g. of family members, served to clients with their tokens
+    pub collections: HashMap<String, CollectionConfig>,
+    /// Text or logo drawn into images returned to clients
+    pub watermark: WatermarkConfig,
+    pub thumbnails: ThumbnailConfig,
+    pub privacy: PrivacyConfig,
+    /// Encryption of the sidecars, none unless a key is configured
+    pub encryption: EncryptionConfig,
+}
+
+/// Photo collection in its own directory, isolated from the other collections. When any
+/// collection is configured, clients have to present an API token and see only the collection
+/// the token is bound to.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CollectionConfig {
+    /// Directory with the zip archives of the collection
+    pub image_dir: String,
+    /// API tokens of the clients allowed to use the collection
+    pub tokens: Vec<String>,
+}
+
+/// Patterns of archives and entries which are not indexed. A pattern may contain `*` (any
+/// characters) and `?` (single character) wildcards and is matched against the whole name,
+/// a pattern without wildcards matches any name containing it. Matching is case-insensitive.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    /// Archive file names, e.g. `"takeout-*-003.zip"`
+    pub exclude_archives: Vec<String>,
+    /// Entry paths inside archives, e.g. `"Screenshots/"`, `"*edited*"`
+    pub exclude_entries: Vec<String>,
+    /// When not empty, only entries matching one of the patterns are indexed
+    pub include_entries: Vec<String>,
+    /// Regular expressions of dates in file names, used for photos without EXIF date in
+    /// addition to the built-in ones. Named groups `year` and `month` are required, `day`,
+    /// `hour`, `minute` and `second` are optional, e.g. `"^scan_(?<year>\\d{4})(?<month>\\d{2})"`
+    pub file_name_date_patterns: Vec<String>,
+    /// Encoding of entry names not flagged as UTF-8, e.g. `"shift_jis"` or `"gbk"`. When not
+    /// set, UTF-8 and Shift-JIS names are detected and other names are read as CP437.
+    pub entry_name_encoding: Option<String>,
+    /// Crop factors of camera models which don't record the 35mm equivalent focal length,
+    /// keyed by model pattern, e.g. `{"EOS 40D": 1.6}`. The longest matching pattern wins,
+    /// the built-in table is used for models matching none.
+    pub crop_factors: HashMap<String, f32>,
+}
+
+impl IndexConfig {
+    pub fn archive_excluded(&self, zip_file_name: &str) -> bool {
+        self.exclude_archives
+            .iter()
+            .any(|pattern| traversal::pattern_matches(pattern, zip_file_name))
+    }
+
+    pub fn entry_excluded(&self, entry_name: &str) -> bool {
+        let matches = |pattern: &String| traversal::pattern_matches(pattern, entry_name);
+        self.exclude_entries.iter().any(matches)
+            || (!self.include_entries.is_empty() && !self.include_entries.iter().any(matches))
+    }
+}
+
+/// Tools offered to clients and time limits of tool calls, long running tools (photo
+/// extraction, detection) return the results gathered so far with `truncated` flag set when
+/// the limit is exceeded
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ToolsConfig {
+    /// Timeout of every tool call in seconds
+    pub timeout_secs: Option<u64>,
+    /// Timeouts of specific tools keyed by tool name, e.g. `{"photo_object_detection": 120}`
+    pub timeouts: HashMap<String, u64>,
+    /// Tools not offered to clients, names or patterns (as in `index.exclude_archives`), e.g.
+    /// `["photo_view_*", "photo_object_detection"]`
+    pub disabled: Vec<String>,
+    /// Name searches without an archive matching more photos ask the user (on clients with
+    /// elicitation support) which archive to search, 0 never asks
+    pub elicit_above: usize,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: None,
+            timeouts: HashMap::new(),
+            disabled: Vec::new(),
+            elicit_above: 200,
+        }
+    }
+}
+
+impl ToolsConfig {
+    pub fn timeout(&self, tool_name: &str) -> Option<Duration> {
+        self.timeouts
+            .get(tool_name)
+            .copied()
+            .or(self.timeout_secs)
+            .map(Duration::from_secs)
+    }
+
+    pub fn disabled(&self, tool_name: &str) -> bool {
+        self.disabled
+            .iter()
+            .any(|pattern| traversal::pattern_matches(pattern, tool_name))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Only the photo listing is loaded at startup, EXIF and year/month index of an archive
+    /// are loaded on first use
+    pub lazy_exif: bool,
+    /// Memory budget of the loaded EXIF in megabytes, EXIF of least recently used archives
+    /// is evicted (and loaded from sidecars again on next use) when exceeded. It is a soft
+    /// limit: archives being read are not evicted, searches over the whole collection load
+    /// all of them for their duration.
+    pub memory_budget_mb: Option<usize>,
+    /// Number of searches whose full results are kept for the next pages, 0 disables the
+    /// query cache
+    pub query_cache_entries: usize,
+    /// Seconds the cached search results are reused, they are dropped earlier on index updates
+    pub query_cache_ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            lazy_exif: false,
+            memory_budget_mb: None,
+            query_cache_entries: 32,
+            query_cache_ttl_secs: 300,
+        }
+    }
+}
+
+/// Warm-up tasks run at startup before the server accepts connections, their outcome is
+/// reported by the index health tool. The photo listing is always loaded.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WarmUpConfig {
+    /// Loads EXIF of the first N archives (in name order) when EXIF is loaded lazily
+    pub preload_archives: usize,
+    /// Loads EXIF of all archives and builds the year/month index
+    pub month_indexes: bool,
+    /// Loads the YOLO detection model, checking that it is usable
+    pub detection_model: bool,
+}
+
+/// Full-text index over file names, descriptions, labels and camera fields, built in memory
+/// on first text search
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FullTextConfig {
+    pub enabled: bool,
+}
+
+/// Matching of photo and archive names in name searches (names are always compared in
+/// Unicode normalized form and case-insensitive) and of object classes in object searches
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Ignore accents by default, "café" matches "cafe"
+    pub fold_accents: bool,
+    /// Language of the case folding, e.g. `"tr"` for Turkish dotted and dotless i
+    pub locale: Option<String>,
+    /// Aliases of detected object classes in addition to the built-in ones, alias => class
+    /// names or other aliases, e.g. `{"doggo": ["dog"], "wildlife": ["bird", "bear"]}`
+    pub class_aliases: HashMap<String, Vec<String>>,
+}
+
+/// Response language of detected object class names and analysis labels. German has a
+/// built-in table of the COCO classes, `translations` add names of other classes and
+/// labels or other languages.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct I18nConfig {
+    /// Language code, e.g. `"de"`, names are returned in English when not set
+    pub language: Option<String>,
+    /// Language => English name => translated name, e.g. `{"de": {"beagle": "Beagle"}}`
+    pub translations: HashMap<String, HashMap<String, String>>,
+}
+
+/// `"sidecar"` keeps indexes in JSON files next to the archives, `"memory"` builds them in
+/// memory on every start without reading or writing any files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageMode {
+    #[default]
+    Sidecar,
+    Memory,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DetectionConfig {
+    /// Additional detection models registered next to the built-in YOLOv8 model, variants of
+    /// its weights with other thresholds (the detector loads the built-in weights only)
+    pub models: Vec<ModelSpec>,
+    /// Model id used when a tool call does not name a model explicitly
+    pub default_model: Option<String>,
+    /// Model ids run by the background crawler, defaults to the default model only
+    pub analysis_models: Vec<String>,
+    /// Open-vocabulary detector used by photo_detect_custom tool
+    pub open_vocabulary: Option<ExternalModel>,
+    /// QR code and barcode decoder used by photo_decode_barcodes tool
+    pub barcode_decoder: Option<ExternalModel>,
+}
+
+/// Weights of the models, downloaded into the cache directory on first use and verified by
+/// their checksum, e.g. `{"files": [{"model": "yolov8-default", "file": "yolov8m.safetensors",
+/// "url": "https://..", "sha256": ".."}]}`. Models without files are expected to be installed.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ModelsConfig {
+    /// Directory the weights are cached in, defaults to `YOLO_V8_MODEL_PATH` the YOLO
+    /// weights are loaded from
+    pub cache_dir: Option<String>,
+    /// Only cached weights are used, nothing is downloaded
+    pub offline: bool,
+    /// Base URL the files are downloaded from instead of their URLs, the file name is appended
+    pub mirror: Option<String>,
+    pub files: Vec<ModelFileConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ModelFileConfig {
+    /// Model id the file belongs to, e.g. `"yolov8-default"`
+    pub model: String,
+    /// File name in the cache directory
+    pub file: String,
+    pub url: String,
+    /// SHA-256 of the file content (hex)
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AnalysisConfig {
+    /// Per stage settings keyed by analyzer name (e.g. "object_detection.yolov8-default")
+    pub stages: HashMap<String, StageConfig>,
+    /// Number of photos extracted from an archive and analyzed at once
+    pub chunk_size: usize,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            stages: HashMap::new(),
+            chunk_size: 100,
+        }
+    }
+}
+
+/// Live photo support, frames of the motion part are extracted by ffmpeg
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MotionConfig {
+    /// ffmpeg executable
+    pub ffmpeg: String,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self {
+            ffmpeg: String::from("ffmpeg"),
+        }
+    }
+}
+
+/// Sidecars are encrypted with AES-256-GCM with the key read from the environment variable or
+/// the key file. The key is 32 random bytes encoded as base64 or hex, e.g. the output of
+/// `openssl rand -base64 32`, passphrases are refused. Unencrypted sidecars are read only with
+/// `migrate_plaintext` set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    /// Environment variable holding the secret, e.g. `"PHOTO_MCP_KEY"`
+    pub key_env: Option<String>,
+    /// File holding the secret, used when `key_env` is not set
+    pub key_file: Option<String>,
+    /// Reads sidecars written before encryption was configured and encrypts them in place.
+    /// Meant for one start after the key is configured, otherwise unencrypted sidecars are
+    /// refused as they may have been planted.
+    pub migrate_plaintext: bool,
+}
+
+impl EncryptionConfig {
+    /// Encoded key as configured, none when encryption is not configured
+    pub fn secret(&self) -> Result<Option<Vec<u8>>, PhotoInsightError> {
+        let secret = if let Some(key_env) = &self.key_env {
+            std::env::var(key_env)
+                .map_err(|e| PhotoInsightError::from_message(format!("{key_env}: {e}")))?
+                .into_bytes()
+        } else if let Some(key_file) = &self.key_file {
+            std::fs::read(key_file)
+                .map_err(|e| PhotoInsightError::from_message(format!("{key_file}: {e}")))?
+        } else {
+            return Ok(None);
+        };
+        let secret = secret.trim_ascii().to_vec();
+        if secret.is_empty() {
+            return Err(PhotoInsightError::from_message(
+                "empty encryption secret".to_owned(),
+            ));
+        }
+        Ok(Some(secret))
+    }
+}
+
+/// Face data controls. Removals of face data are recorded in
+/// `<image_dir>/photo-mcp.privacy-audit.jsonl`. Controls are per archive: face stages store
+/// no embeddings or identities, so there are no persons to delete or exclude from search.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// Archive patterns (as in `index.exclude_archives`) whose photos are never processed by
+    /// face stages, face data already stored for them is deleted at startup
+    pub no_face_archives: Vec<String>,
+}
+
+impl PrivacyConfig {
+    pub fn faces_disabled(&self, zip_file_name: &str) -> bool {
+        self.no_face_archives
+            .iter()
+            .any(|pattern| traversal::pattern_matches(pattern, zip_file_name))
+    }
+}
+
+/// Thumbnails of photos returned by the view tools and photo resources
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailConfig {
+    /// Crops thumbnails to fill them, centered on detected faces (or detected objects when
+    /// there are no faces), instead of fitting the whole photo. Photos without detections
+    /// keep the fitted thumbnail.
+    pub smart_crop: bool,
+    /// Photos extracted ahead of the thumbnail being made, the next photos are read from the
+    /// archive while the previous ones are decoded and encoded. 0 extracts all photos of an
+    /// archive first.
+    pub read_ahead: usize,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            smart_crop: false,
+            read_ahead: 4,
+        }
+    }
+}
+
+/// Corner of the image the watermark is drawn into
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// Watermark of images served by the view tools and photo resources, e.g.
+/// `{"text": "(c) Smith family", "corner": "bottom_left", "opacity": 0.4}`. Disabled unless
+/// a text or a logo is set, analysis always works on the originals.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatermarkConfig {
+    pub text: Option<String>,
+    /// PNG (or other image) file of the logo, drawn above the text
+    pub logo: Option<String>,
+    pub corner: Corner,
+    /// From 0 (invisible) to 1 (opaque)
+    pub opacity: f32,
+    /// Width of the watermark as a share of the image width
+    pub scale: f32,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            text: None,
+            logo: None,
+            corner: Corner::default(),
+            opacity: 0.5,
+            scale: 0.25,
+        }
+    }
+}
+
+/// Watches newly analyzed photos for detected classes or labels, e.g.
+/// `{"name": "documents", "labels": ["passport"], "classes": ["book"], "webhook": "https://.."}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WatcherConfig {
+    pub name: String,
+    /// Detected object classes (exact match, case insensitive)
+    pub classes: Vec<String>,
+    /// Labels of analysis stages (partial match, case insensitive)
+    pub labels: Vec<String>,
+    /// Findings with lower confidence are ignored
+    pub min_confidence: f32,
+    /// URL the matches are POSTed to as JSON
+    pub webhook: Option<String>,
+    /// File the matches are appended to as JSON lines
+    pub report: Option<String>,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            classes: Vec::new(),
+            labels: Vec::new(),
+            min_confidence: 0.5,
+            webhook: None,
+            report: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StageConfig {
+    /// Enables or disables the stage, stage default is used when not set
+    pub enabled: Option<bool>,
+    /// Stage specific options
+    #[serde(flatten)]
+    pub options: serde_json::Map<String, serde_json::Value>,
+}
+
+impl StageConfig {
+    /// Parses stage specific options of the stage, defaults are used when the stage is not configured
+    pub fn parse_options<T: DeserializeOwned + Default>(
+        config: Option<&StageConfig>,
+        stage: &str,
+    ) -> Result<T, PhotoInsightError> {
+        match config {
+            Some(c) => serde_json::from_value(serde_json::Value::Object(c.options.clone()))
+                .map_err(|e| {
+                    PhotoInsightError::from_message(format!("invalid {stage} options: {e}"))
+                }),
+            None => Ok(T::default()),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self, PhotoInsightError> {
+        if !Path::new(path).exists() {
+            tracing::info!("Config file {path} not found, using defaults");
+            return Ok(Config::default());
+        }
+        let file = std::fs::File::open(path).map_err(|e| PhotoInsightError::new(e))?;
+        let config: Config = serde_json::from_reader(file).map_err(|e| {
+            PhotoInsightError::from_message(format!("invalid config file {path}: {e}"))
+        })?;
+        tracing::info!("Loaded config from {path}");
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::config::IndexConfig;
+
+    #[test]
+    fn test_index_exclusion() {
+        let index = IndexConfig {
+            exclude_archives: vec!["takeout-*-003.zip".to_owned()],
+            exclude_entries: vec!["Screenshots/".to_owned(), "*edited*".to_owned()],
+            ..IndexConfig::default()
+        };
+        assert!(index.archive_excluded("takeout-20240101-003.zip"));
+        assert!(!index.archive_excluded("takeout-20240101-001.zip"));
+        assert!(index.entry_excluded("Takeout/Screenshots/IMG_1.png"));
+        assert!(index.entry_excluded("Takeout/Photos/IMG_1-EDITED.jpg"));
+        assert!(!index.entry_excluded("Takeout/Photos/IMG_1.jpg"));
+
+        // exclusion wins over inclusion, entries matching no included pattern are left out
+        let index = IndexConfig {
+            include_entries: vec!["*/Photos from 20*".to_owned()],
+            ..index
+        };
+        assert!(!index.entry_excluded("Takeout/Photos from 2021/IMG_1.jpg"));
+        assert!(index.entry_excluded("Takeout/Photos from 2021/IMG_1-edited.jpg"));
+        assert!(index.entry_excluded("Takeout/Archive/IMG_1.jpg"));
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use crate::core::{image_cache::PhotoInfo, stages::face_attributes::FaceSummary};
+
+/// Resolution counting as full quality, larger photos do not score higher
+const FULL_QUALITY_MEGAPIXELS: f64 = 12.0;
+/// Face count scoring the most, crowds do not make better covers
+const MAX_COVER_FACES: usize = 3;
+const QUALITY_WEIGHT: f64 = 1.0;
+const FACE_WEIGHT: f64 = 0.5;
+const DIVERSITY_WEIGHT: f64 = 0.5;
+/// Number of previously picked covers the next cover should differ from
+const RECENT_COVERS: usize = 3;
+
+/// Photo competing for a cover with what is known about it without decoding the image
+#[derive(Debug, Clone)]
+pub struct CoverCandidate {
+    pub info: PhotoInfo,
+    pub megapixels: f64,
+    /// People found by the face attributes or people count stage
+    pub face_count: usize,
+    /// Face attributes of the photo, portraits with closed eyes make poor covers
+    pub faces: Option<FaceSummary>,
+    pub labels: Vec<String>,
+}
+
+impl CoverCandidate {
+    /// Quality from 0 to 1, resolution saturating at 12 megapixels
+    pub fn quality(&self) -> f64 {
+        (self.megapixels / FULL_QUALITY_MEGAPIXELS).min(1.0)
+    }
+
+    /// Photos with a few (smiling, eyes open) people score higher than landscapes, from 0 to 1
+    pub fn face_score(&self) -> f64 {
+        let faces = self.face_count.min(MAX_COVER_FACES) as f64 / MAX_COVER_FACES as f64;
+        let portrait = self.faces.as_ref().map_or(1.0, |f| f.score() as f64);
+        faces * portrait
+    }
+
+    /// Share of the labels already shown on the recent covers (Jaccard similarity)
+    pub fn overlap(&self, recent: &HashSet<&str>) -> f64 {
+        let labels = self
+            .labels
+            .iter()
+            .map(String::as_str)
+            .collect::<HashSet<&str>>();
+        let union = labels.union(recent).count();
+        if union == 0 {
+            return 0.0;
+        }
+        labels.intersection(recent).count() as f64 / union as f64
+    }
+
+    fn score(&self, recent: &HashSet<&str>) -> f64 {
+        QUALITY_WEIGHT * self.quality() + FACE_WEIGHT * self.face_score()
+            - DIVERSITY_WEIGHT * self.overlap(recent)
+    }
+
+    fn fingerprint(&self, hasher: &mut DefaultHasher) {
+        self.info.hash(hasher);
+        self.megapixels.to_bits().hash(hasher);
+        self.face_count.hash(hasher);
+        self.faces
+            .as_ref()
+            .map(|f| f.score().to_bits())
+            .hash(hasher);
+        self.labels.hash(hasher);
+    }
+}
+
+/// Cover photos of archives, months and events picked by quality, face count and diversity.
+/// Picks are cached per group and reused as long as the candidates and the covers picked
+/// before do not change.
+#[derive(Debug, Clone, Default)]
+pub struct CoverCache {
+    covers: HashMap<String, (u64, PhotoInfo)>,
+}
+
+impl CoverCache {
+    /// Picks the cover of every group, groups are given in display order, each cover should
+    /// differ from the covers of the preceding groups
+    pub fn covers(
+        &mut self,
+        groups: Vec<(String, Vec<CoverCandidate>)>,
+    ) -> HashMap<String, PhotoInfo> {
+        let mut picked = HashMap::new();
+        let mut recent: VecDeque<Vec<String>> = VecDeque::new();
+        let mut used = HashSet::new();
+        for (key, candidates) in groups {
+            let mut hasher = DefaultHasher::new();
+            candidates.iter().for_each(|c| c.fingerprint(&mut hasher));
+            recent.hash(&mut hasher);
+            let fingerprint = hasher.finish();
+            let cover = match self.covers.get(&key) {
+                Some((cached, cover)) if *cached == fingerprint => {
+                    candidates.iter().find(|c| &c.info == cover)
+                }
+                _ => {
+                    let labels = recent
+                        .iter()
+                        .flatten()
+                        .map(String::as_str)
+                        .collect::<HashSet<&str>>();
+                    // a photo is the cover of one group only while there are other candidates
+                    let fresh = candidates
+                        .iter()
+                        .filter(|c| !used.contains(&c.info))
+                        .collect::<Vec<_>>();
+                    let pool = if fresh.is_empty() {
+                        candidates.iter().collect()
+                    } else {
+                        fresh
+                    };
+                    pool.into_iter()
+                        .max_by(|a, b| a.score(&labels).total_cmp(&b.score(&labels)))
+                }
+            };
+            let Some(cover) = cover else {
+                continue;
+            };
+            self.covers
+                .insert(key.clone(), (fingerprint, cover.info.clone()));
+            used.insert(cover.info.clone());
+            recent.push_back(cover.labels.clone());
+            if recent.len() > RECENT_COVERS {
+                recent.pop_front();
+            }
+            picked.insert(key, cover.info.clone());
+        }
+        picked
+    }
+}
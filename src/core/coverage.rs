@@ -0,0 +1,141 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::core::{error::PhotoInsightError, image_cache::PhotoInfo};
+
+/// Columns of a CSV manifest holding the file name, the first column is used otherwise
+const NAME_COLUMNS: [&str; 6] = [
+    "filename",
+    "file_name",
+    "file name",
+    "name",
+    "title",
+    "photo_file_name",
+];
+
+/// Indexed photos compared with the manifest
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestCoverage {
+    pub expected: usize,
+    pub found: usize,
+    /// Found by the name without extension only
+    pub found_by_stem: usize,
+    pub missing_count: usize,
+    /// Expected photos missing from the archives in manifest order, a page of them in tool
+    /// results
+    pub missing: Vec<String>,
+}
+
+/// Reads expected file names of the manifest: a JSON album export (Google Photos
+/// `mediaItems` with `filename`, or an array of names or objects with `filename` or `title`),
+/// a CSV with a file name column or a plain list with one name per line. Directories are
+/// stripped and duplicate names are listed once.
+pub fn read_manifest(manifest_file: &str) -> Result<Vec<String>, PhotoInsightError> {
+    let content = std::fs::read_to_string(manifest_file).map_err(|e| {
+        PhotoInsightError::from_message(format!("Failed to read manifest {manifest_file}: {e}"))
+    })?;
+    let names = if content.trim_start().starts_with(['[', '{']) {
+        json_names(&content)?
+    } else {
+        csv_names(&content)?
+    };
+    let mut seen = HashSet::new();
+    Ok(names
+        .into_iter()
+        .map(|name| base_name(name.trim()).to_owned())
+        .filter(|name| !name.is_empty() && seen.insert(name.to_lowercase()))
+        .collect())
+}
+
+fn json_names(content: &str) -> Result<Vec<String>, PhotoInsightError> {
+    let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| PhotoInsightError::from_message(format!("Invalid JSON manifest: {e}")))?;
+    let items = match &value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(object) => object
+            .get("mediaItems")
+            .and_then(|items| items.as_array())
+            .ok_or_else(|| {
+                PhotoInsightError::from_message("JSON manifest has no mediaItems array")
+            })?,
+        _ => return Err(PhotoInsightError::from_message("Invalid JSON manifest")),
+    };
+    Ok(items
+        .iter()
+        .filter_map(|item| match item {
+            serde_json::Value::String(name) => Some(name.clone()),
+            serde_json::Value::Object(object) => ["filename", "file_name", "title"]
+                .iter()
+                .find_map(|key| object.get(*key)?.as_str())
+                .map(str::to_owned),
+            _ => None,
+        })
+        .collect())
+}
+
+fn csv_names(content: &str) -> Result<Vec<String>, PhotoInsightError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut rows = reader.records();
+    let Some(first) = rows.next() else {
+        return Ok(Vec::new());
+    };
+    let first = first.map_err(PhotoInsightError::new)?;
+    // a header row names the file name column, a manifest without one starts with a name
+    let header = first
+        .iter()
+        .position(|column| NAME_COLUMNS.contains(&column.trim().to_lowercase().as_str()));
+    let column = header.unwrap_or(0);
+    let mut names = Vec::new();
+    if header.is_none() {
+        names.extend(first.get(column).map(str::to_owned));
+    }
+    for row in rows {
+        let row = row.map_err(PhotoInsightError::new)?;
+        names.extend(row.get(column).map(str::to_owned));
+    }
+    Ok(names)
+}
+
+fn base_name(name: &str) -> &str {
+    name.rsplit(['/', '\\']).next().unwrap_or(name)
+}
+
+fn stem(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+/// Finds the expected photos among the indexed ones by file name (case insensitive), then by
+/// the name without extension
+pub fn compare(expected: &[String], photos: &[PhotoInfo]) -> ManifestCoverage {
+    let mut names = HashSet::new();
+    let mut stems = HashSet::new();
+    for photo in photos {
+        let name = base_name(&photo.photo_file_name).to_lowercase();
+        stems.insert(stem(&name).to_owned());
+        names.insert(name);
+    }
+    let mut found_by_stem = 0;
+    let mut missing = Vec::new();
+    for name in expected {
+        let name_lower = name.to_lowercase();
+        if names.contains(&name_lower) {
+            continue;
+        }
+        if stems.contains(stem(&name_lower)) {
+            found_by_stem += 1;
+        } else {
+            missing.push(name.clone());
+        }
+    }
+    ManifestCoverage {
+        expected: expected.len(),
+        found: expected.len() - missing.len(),
+        found_by_stem,
+        missing_count: missing.len(),
+        missing,
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::core::{
+    exif::{ExifInfo, GeoLocation},
+    image_cache::PhotoInfo,
+    insights::{ValueCount, top_values, unquote},
+};
+
+/// Photos taken less than this apart belong to the same event
+const EVENT_GAP_SECONDS: i64 = 4 * 3600;
+/// Smallest number of photos of a notable event
+const MIN_EVENT_PHOTOS: usize = 5;
+/// Number of notable events reported, largest first
+const TOP_EVENTS: usize = 10;
+/// Locations are grouped by 0.01 degree (about 1 km)
+const LOCATION_GRID: f64 = 100.0;
+
+/// Photo of a period with its EXIF and labels of detected objects and analysis stages
+#[derive(Debug, Clone)]
+pub struct DigestPhoto {
+    pub info: PhotoInfo,
+    pub exif: ExifInfo,
+    /// Seconds since 1970-01-01 of the time taken
+    pub timestamp: i64,
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthCount {
+    pub year: u32,
+    pub month: u32,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationCount {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: usize,
+}
+
+/// Photos taken in a burst, e.g. a trip or a party
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub start: String,
+    pub end: String,
+    pub photo_count: usize,
+    pub top_labels: Vec<ValueCount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoLocation>,
+    /// Photo with the highest resolution, or the cover picked by quality, faces and diversity
+    pub representative: PhotoInfo,
+    /// Photos of the event, cover candidates
+    #[serde(skip)]
+    pub photos: Vec<PhotoInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeriodSummary {
+    pub date_from: String,
+    pub date_to: String,
+    pub photo_count: usize,
+    pub by_month: Vec<MonthCount>,
+    /// Most frequent detected objects and analysis labels
+    pub top_labels: Vec<ValueCount>,
+    pub cameras: Vec<ValueCount>,
+    /// Places photos were taken at, only photos with a location are counted
+    pub locations: Vec<LocationCount>,
+    pub events: Vec<Event>,
+}
+
+fn best_photo<'a>(photos: &'a [&DigestPhoto]) -> &'a DigestPhoto {
+    photos
+        .iter()
+        .max_by_key(|p| p.exif.width as u64 * p.exif.height as u64)
+        .unwrap()
+}
+
+fn top_labels(photos: &[&DigestPhoto]) -> Vec<ValueCount> {
+    top_values(
+        photos
+            .iter()
+            .flat_map(|p| p.labels.iter().map(String::as_str)),
+    )
+}
+
+fn locations(photos: &[&DigestPhoto]) -> Vec<LocationCount> {
+    let mut counts: HashMap<(i64, i64), usize> = HashMap::new();
+    for location in photos.iter().filter_map(|p| p.exif.location.as_ref()) {
+        let cell = (
+            (location.latitude * LOCATION_GRID).round() as i64,
+            (location.longitude * LOCATION_GRID).round() as i64,
+        );
+        *counts.entry(cell).or_insert(0) += 1;
+    }
+    let mut locations = counts
+        .into_iter()
+        .map(|((latitude, longitude), count)| LocationCount {
+            latitude: latitude as f64 / LOCATION_GRID,
+            longitude: longitude as f64 / LOCATION_GRID,
+            count,
+        })
+        .collect::<Vec<_>>();
+    locations.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.latitude.total_cmp(&b.latitude))
+            .then_with(|| a.longitude.total_cmp(&b.longitude))
+    });
+    locations
+}
+
+// Splits photos ordered by time at gaps longer than 4 hours
+fn clusters(photos: &[DigestPhoto]) -> Vec<Vec<&DigestPhoto>> {
+    let mut clusters: Vec<Vec<&DigestPhoto>> = Vec::new();
+    for photo in photos {
+        match clusters.last_mut() {
+            Some(cluster)
+                if photo.timestamp - cluster.last().unwrap().timestamp <= EVENT_GAP_SECONDS =>
+            {
+                cluster.push(photo)
+            }
+            _ => clusters.push(vec![photo]),
+        }
+    }
+    clusters
+}
+
+/// Splits photos ordered by time into events at gaps longer than 4 hours, events with at
+/// least 5 photos are returned, largest first
+pub fn events(photos: &[DigestPhoto]) -> Vec<Event> {
+    let mut events = clusters(photos)
+        .into_iter()
+        .filter(|cluster| cluster.len() >= MIN_EVENT_PHOTOS)
+        .map(|cluster| Event {
+            start: unquote(&cluster.first().unwrap().exif.date_time).to_owned(),
+            end: unquote(&cluster.last().unwrap().exif.date_time).to_owned(),
+            photo_count: cluster.len(),
+            top_labels: top_labels(&cluster),
+            location: locations(&cluster).first().map(|l| GeoLocation {
+                latitude: l.latitude,
+                longitude: l.longitude,
+            }),
+            representative: best_photo(&cluster).info.clone(),
+            photos: cluster.iter().map(|p| p.info.clone()).collect(),
+        })
+        .collect::<Vec<_>>();
+    events.sort_by(|a, b| b.photo_count.cmp(&a.photo_count));
+    events.truncate(TOP_EVENTS);
+    events
+}
+
+/// Summarizes photos of the period ordered by time taken
+pub fn period_summary(date_from: &str, date_to: &str, photos: &[DigestPhoto]) -> PeriodSummary {
+    let all = photos.iter().collect::<Vec<&DigestPhoto>>();
+    let mut by_month: HashMap<(u32, u32), usize> = HashMap::new();
+    for photo in photos {
+        *by_month
+            .entry((photo.exif.year, photo.exif.month))
+            .or_insert(0) += 1;
+    }
+    let mut by_month = by_month
+        .into_iter()
+        .map(|((year, month), count)| MonthCount { year, month, count })
+        .collect::<Vec<_>>();
+    by_month.sort_by_key(|m| (m.year, m.month));
+
+    PeriodSummary {
+        date_from: date_from.to_owned(),
+        date_to: date_to.to_owned(),
+        photo_count: photos.len(),
+        by_month,
+        top_labels: top_labels(&all),
+        cameras: top_values(
+            photos
+                .iter()
+                .map(|p| unquote(&p.exif.model))
+                .filter(|m| !m.is_empty() && *m != "unknown"),
+        ),
+        locations: locations(&all),
+        events: events(photos),
+    }
+}
+
+/// Thumbnails of the summary: representatives of the events, topped up with photos evenly
+/// spread over the period
+pub fn representatives<'a>(
+    summary: &'a PeriodSummary,
+    photos: &'a [DigestPhoto],
+    count: usize,
+) -> Vec<&'a PhotoInfo> {
+    let mut picked = summary
+        .events
+        .iter()
+        .map(|e| &e.representative)
+        .take(count)
+        .collect::<Vec<&PhotoInfo>>();
+    let missing = count.saturating_sub(picked.len());
+    if missing > 0 && !photos.is_empty() {
+        let step = (photos.len() / missing).max(1);
+        for photo in photos.iter().step_by(step) {
+            if picked.len() >= count {
+                break;
+            }
+            if !picked.contains(&&photo.info) {
+                picked.push(&photo.info);
+            }
+        }
+    }
+    picked
+}
+
+/// Best photo of a month of the year in review
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthHighlight {
+    pub month: u32,
+    pub photo_count: usize,
+    /// Sharpest of the highest resolution photos of the month
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best: Option<PhotoInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sharpness: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct YearInReview {
+    pub year: u32,
+    pub photo_count: usize,
+    pub months: Vec<MonthHighlight>,
+    /// Largest events of the year
+    pub events: Vec<Event>,
+    /// Most photographed subjects from detected objects and analysis labels
+    pub top_subjects: Vec<ValueCount>,
+    pub cameras: Vec<ValueCount>,
+}
+
+/// Reviews photos of the year ordered by time taken, best photos of the months are picked
+/// later from `month_candidates` by sharpness
+pub fn year_in_review(year: u32, photos: &[DigestPhoto], events: usize) -> YearInReview {
+    let summary = period_summary(&format!("{year}-01-01"), &format!("{year}-12-31"), photos);
+    YearInReview {
+        year,
+        photo_count: summary.photo_count,
+        months: summary
+            .by_month
+            .iter()
+            .filter(|m| m.year == year)
+            .map(|m| MonthHighlight {
+                month: m.month,
+                photo_count: m.count,
+                best: None,
+                sharpness: None,
+            })
+            .collect(),
+        events: summary.events.into_iter().take(events).collect(),
+        top_subjects: summary.top_labels,
+        cameras: summary.cameras,
+    }
+}
+
+/// Highest resolution photos of the month, there is no rating of photos so resolution
+/// pre-selects the candidates for the best photo
+pub fn month_candidates(photos: &[DigestPhoto], month: u32, count: usize) -> Vec<&PhotoInfo> {
+    let mut candidates = photos
+        .iter()
+        .filter(|p| p.exif.month == month)
+        .collect::<Vec<&DigestPhoto>>();
+    candidates.sort_by_key(|p| std::cmp::Reverse(p.exif.width as u64 * p.exif.height as u64));
+    candidates
+        .into_iter()
+        .take(count)
+        .map(|p| &p.info)
+        .collect()
+}
+
+/// Photo of a story with what is known about it
+#[derive(Debug, Clone, Serialize)]
+pub struct StoryPhoto {
+    pub photo: PhotoInfo,
+    pub date_time: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoLocation>,
+    /// Detected objects and analysis labels
+    pub subjects: Vec<String>,
+    /// Description set by photo_set_metadata or read from EXIF
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+/// Photos taken in one burst, a story has a chapter per event
+#[derive(Debug, Clone, Serialize)]
+pub struct StoryChapter {
+    pub start: String,
+    pub end: String,
+    /// All photos of the chapter, only a sample is listed when the story is long
+    pub photo_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoLocation>,
+    pub top_subjects: Vec<ValueCount>,
+    pub photos: Vec<StoryPhoto>,
+}
+
+/// Narrative skeleton of a date range for writing a story from
+#[derive(Debug, Clone, Serialize)]
+pub struct StoryPayload {
+    pub date_from: String,
+    pub date_to: String,
+    pub photo_count: usize,
+    pub top_subjects: Vec<ValueCount>,
+    pub chapters: Vec<StoryChapter>,
+}
+
+/// Orders photos of the range into chapters split at gaps longer than 4 hours. At most
+/// `max_photos` photos evenly spread over the range are listed, every chapter keeps its first
+/// photo.
+pub fn story_payload(
+    date_from: &str,
+    date_to: &str,
+    photos: &[DigestPhoto],
+    max_photos: usize,
+) -> StoryPayload {
+    let all = photos.iter().collect::<Vec<&DigestPhoto>>();
+    let chapters = clusters(photos);
+    let step = photos.len().div_ceil(max_photos.max(1)).max(1);
+    let mut index = 0;
+    let chapters = chapters
+        .into_iter()
+        .map(|cluster| {
+            let listed = cluster
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i == 0 || (index + i) % step == 0)
+                .map(|(_, photo)| StoryPhoto {
+                    photo: photo.info.clone(),
+                    date_time: unquote(&photo.exif.date_time).to_owned(),
+                    location: photo.exif.location.clone(),
+                    subjects: photo.labels.clone(),
+                    caption: photo
+                        .exif
+                        .description
+                        .as_deref()
+                        .map(|d| unquote(d).trim().to_owned())
+                        .filter(|d| !d.is_empty()),
+                })
+                .collect();
+            index += cluster.len();
+            StoryChapter {
+                start: unquote(&cluster.first().unwrap().exif.date_time).to_owned(),
+                end: unquote(&cluster.last().unwrap().exif.date_time).to_owned(),
+                photo_count: cluster.len(),
+                location: locations(&cluster).first().map(|l| GeoLocation {
+                    latitude: l.latitude,
+                    longitude: l.longitude,
+                }),
+                top_subjects: top_labels(&cluster),
+                photos: listed,
+            }
+        })
+        .collect();
+    StoryPayload {
+        date_from: date_from.to_owned(),
+        date_to: date_to.to_owned(),
+        photo_count: photos.len(),
+        top_subjects: top_labels(&all),
+        chapters,
+    }
+}
+
+/// Period without photos between two photos taken in a row
+#[derive(Debug, Clone, Serialize)]
+pub struct Gap {
+    /// Last photo before the gap
+    pub from: String,
+    /// First photo after the gap
+    pub to: String,
+    pub days: i64,
+}
+
+/// Photos of a year and the gaps starting in it
+#[derive(Debug, Clone, Serialize)]
+pub struct YearGaps {
+    pub year: u32,
+    pub photo_count: usize,
+    pub gaps: Vec<Gap>,
+}
+
+// Year of the time taken, photos are ordered by it
+fn year_taken(photo: &DigestPhoto) -> u32 {
+    unquote(&photo.exif.date_time)
+        .get(..4)
+        .and_then(|year| year.parse().ok())
+        .unwrap_or(photo.exif.year)
+}
+
+/// Gaps of at least `min_days` between photos ordered by time, reported per year from the
+/// first to the last photo. A gap is listed in the year it starts, years without photos are
+/// listed with no gaps.
+pub fn gaps(photos: &[DigestPhoto], min_days: i64) -> Vec<YearGaps> {
+    let (Some(first), Some(last)) = (photos.first(), photos.last()) else {
+        return Vec::new();
+    };
+    let mut years = (year_taken(first)..=year_taken(last))
+        .map(|year| YearGaps {
+            year,
+            photo_count: 0,
+            gaps: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    let index = |photo: &DigestPhoto| (year_taken(photo) - year_taken(first)) as usize;
+    for photo in photos {
+        years[index(photo)].photo_count += 1;
+    }
+    for pair in photos.windows(2) {
+        let days = (pair[1].timestamp - pair[0].timestamp) / 86400;
+        if days >= min_days {
+            years[index(&pair[0])].gaps.push(Gap {
+                from: unquote(&pair[0].exif.date_time).to_owned(),
+                to: unquote(&pair[1].exif.date_time).to_owned(),
+                days,
+            });
+        }
+    }
+    years
+}
@@ -4,10 +4,93 @@ use std::{collections::HashMap, io::Read, path::Path};
 
 use lazy_static::lazy_static;
 
-use crate::core::{error::PhotoInsightError, image_cache::PhotoInfo, zip::is_image_file};
+use crate::core::{
+    animation::{Animation, read_animation},
+    bmff,
+    color::{
+        color_space_name, decode_with_icc, is_srgb_profile, read_header, to_srgb, tone_map_8bit,
+    },
+    color_label::ColorLabel,
+    error::PhotoInsightError,
+    image_cache::PhotoInfo,
+    insights::unquote,
+    maker_note::{self, MakerNoteInfo},
+    overrides::normalize_date_time,
+    time_of_day::{self, TimeOfDay},
+    timings, traversal,
+    zip::{entry_names, is_image_file, open_archive},
+};
+
+/// Crop factors of common camera bodies matched against the EXIF model like index patterns,
+/// the first match wins. Phones record the 35mm equivalent focal length and need none.
+const CROP_FACTORS: [(&str, f32); 30] = [
+    ("EOS 5D", 1.0),
+    ("EOS 6D", 1.0),
+    ("EOS-1D X", 1.0),
+    ("EOS R7", 1.6),
+    ("EOS R10", 1.6),
+    ("EOS R50", 1.6),
+    ("EOS R100", 1.6),
+    ("EOS R", 1.0),
+    ("EOS", 1.6),
+    ("NIKON Z 50", 1.5),
+    ("NIKON Z 30", 1.5),
+    ("NIKON Z fc", 1.5),
+    ("NIKON Z", 1.0),
+    ("*NIKON D3?00*", 1.5),
+    ("*NIKON D5?00*", 1.5),
+    ("*NIKON D7?00*", 1.5),
+    ("NIKON D500", 1.5),
+    ("ILCE-6", 1.5),
+    ("NEX-", 1.5),
+    ("ILCE-", 1.0),
+    ("GFX", 0.79),
+    ("X-T", 1.5),
+    ("X-E", 1.5),
+    ("X-S", 1.5),
+    ("X-H", 1.5),
+    ("X-Pro", 1.5),
+    ("X100", 1.5),
+    ("E-M", 2.0),
+    ("DC-G", 2.0),
+    ("DMC-G", 2.0),
+];
 
 lazy_static! {
     static ref RE: Regex = Regex::new(r"^.?(\d\d\d\d)-(\d\d)").unwrap();
+    /// Dates encoded in file names: built-in patterns (IMG_20230906_142745, PXL_20230906_...,
+    /// IMG-20230906-WA0001, 2023-09-06 14.27.45) followed by the configured ones
+    static ref FILE_NAME_DATE_RE: Vec<Regex> = [
+        r"(?:^|\D)(?<year>(?:19|20)\d\d)(?<month>\d\d)(?<day>\d\d)[_-]?(?<hour>\d\d)(?<minute>\d\d)(?<second>\d\d)(?:\d{3})?(?:\D|$)",
+        r"(?:^|\D)(?<year>(?:19|20)\d\d)-(?<month>\d\d)-(?<day>\d\d)[ _.-](?<hour>\d\d)[.:-](?<minute>\d\d)[.:-](?<second>\d\d)(?:\D|$)",
+        r"(?:^|\D)(?<year>(?:19|20)\d\d)[_-]?(?<month>\d\d)[_-]?(?<day>\d\d)(?:\D|$)",
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).unwrap())
+    .chain(
+        crate::CONFIG
+            .index
+            .file_name_date_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(re) if re.capture_names().flatten().any(|n| n == "year")
+                    && re.capture_names().flatten().any(|n| n == "month") =>
+                {
+                    Some(re)
+                }
+                Ok(_) => {
+                    tracing::warn!(
+                        "Ignoring file name date pattern {pattern}: needs year and month groups"
+                    );
+                    None
+                }
+                Err(e) => {
+                    tracing::warn!("Ignoring invalid file name date pattern {pattern}: {e}");
+                    None
+                }
+            }),
+    )
+    .collect();
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -21,10 +104,299 @@ pub struct ExifInfo {
     pub aperture: String,
     pub shutter_speed: String,
     pub iso: String,
+    /// Focal length of the lens in millimeters
     pub focal_len: String,
+    /// 35mm equivalent focal length, recorded by the camera or computed from the crop factor of
+    /// the model when the photos are loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focal_len_35mm: Option<f32>,
     pub lens: String,
+    /// Location set by a metadata override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoLocation>,
+    /// Description set by a metadata override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Original color space, taken from the embedded ICC profile or the EXIF ColorSpace tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_space: Option<String>,
+    /// Bits per color channel, reported for high bit depth (e.g. 16-bit TIFF or PNG) images only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit_depth: Option<u16>,
+    /// Frame count and duration of animated GIF and WebP files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<Animation>,
+    /// Live photo with a motion part (video) stored next to the still
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub has_motion: bool,
+    /// Shutter actuations of the body when taken, read from the MakerNote of makes recording it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutter_count: Option<u32>,
+    /// Serial number telling apart bodies of the same model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_serial: Option<String>,
+    /// Whether the flash fired, none when the camera did not record it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flash: Option<bool>,
+    /// Exposure program, e.g. manual or aperture_priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exposure_program: Option<String>,
+    /// Metering mode, e.g. pattern or spot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metering_mode: Option<String>,
+    /// Color label read from the XMP of the photo or set by a metadata override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_label: Option<ColorLabel>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GeoLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Paper sizes of the ISO 216 A series in millimeters, largest first
+const PAPER_SIZES: [(&str, u32, u32); 7] = [
+    ("A0", 841, 1189),
+    ("A1", 594, 841),
+    ("A2", 420, 594),
+    ("A3", 297, 420),
+    ("A4", 210, 297),
+    ("A5", 148, 210),
+    ("A6", 105, 148),
+];
+/// Resolutions of the print advisory: photo quality and acceptable for posters viewed from afar
+pub const PRINT_DPI: [u32; 2] = [300, 150];
+const MM_PER_INCH: f64 = 25.4;
+
+/// Largest print of a photo at the resolution
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PrintSize {
+    pub dpi: u32,
+    pub width_cm: f64,
+    pub height_cm: f64,
+    /// Largest A series paper the photo fills at the resolution (cropped to the paper's aspect
+    /// ratio), none when it is smaller than A6
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub largest_paper: Option<String>,
+}
+
+/// Operators of numeric, text and enumerated tags of photo_exif_search_tags
+pub const NUMERIC_OPERATORS: &[&str] = &["==", ">", "<", ">=", "<=", "!="];
+pub const STRING_OPERATORS: &[&str] = &["==", "!=", "contains", "starts_with", "ends_with"];
+pub const EQUALITY_OPERATORS: &[&str] = &["==", "!="];
+
+/// Tag searchable by photo_exif_search_tags
+#[derive(Debug, Clone, Copy)]
+pub struct SearchTag {
+    pub name: &'static str,
+    /// Integer, Float, String or Paper
+    pub tag_type: &'static str,
+    pub operators: &'static [&'static str],
+    /// Values the tag takes when they are not evident from the examples
+    pub values: Option<&'static str>,
+    /// Value of the tag in the EXIF information, none for tags derived in `matches_query`
+    /// and tags not of the EXIF information
+    value: Option<TagValue>,
+}
+
+type TagValue = fn(&ExifInfo) -> Result<ExifTagValue, PhotoInsightError>;
+
+const fn tag(
+    name: &'static str,
+    tag_type: &'static str,
+    operators: &'static [&'static str],
+    values: Option<&'static str>,
+) -> SearchTag {
+    SearchTag {
+        name,
+        tag_type,
+        operators,
+        values,
+        value: None,
+    }
+}
+
+const fn exif_tag(
+    name: &'static str,
+    tag_type: &'static str,
+    operators: &'static [&'static str],
+    values: Option<&'static str>,
+    value: TagValue,
+) -> SearchTag {
+    SearchTag {
+        value: Some(value),
+        ..tag(name, tag_type, operators, values)
+    }
+}
+
+fn float_of(value: &str) -> Result<ExifTagValue, PhotoInsightError> {
+    let f: f32 = value
+        .parse()
+        .map_err(|_| PhotoInsightError::from_message("Invalid float value"))?;
+    Ok(ExifTagValue::Float(f))
+}
+
+/// Tags of ExifInfo matched by `matches_query`, in listing order
+pub const SEARCH_TAGS: [SearchTag; 21] = [
+    exif_tag("width", "Integer", NUMERIC_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::Number(exif.width))
+    }),
+    exif_tag("height", "Integer", NUMERIC_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::Number(exif.height))
+    }),
+    exif_tag("month", "Integer", NUMERIC_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::Number(exif.month))
+    }),
+    exif_tag("year", "Integer", NUMERIC_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::Number(exif.year))
+    }),
+    exif_tag("aperture", "Float", NUMERIC_OPERATORS, None, |exif| {
+        float_of(&exif.aperture)
+    }),
+    exif_tag("focal_len", "Float", NUMERIC_OPERATORS, None, |exif| {
+        float_of(&exif.focal_len)
+    }),
+    exif_tag("focal_len_35mm", "Float", NUMERIC_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::Float(exif.focal_len_35mm.unwrap_or_default()))
+    }),
+    exif_tag("iso", "Float", NUMERIC_OPERATORS, None, |exif| {
+        float_of(&exif.iso)
+    }),
+    exif_tag(
+        "shutter_speed",
+        "Float",
+        NUMERIC_OPERATORS,
+        Some("exposure time in seconds"),
+        |exif| float_of(&exif.shutter_speed),
+    ),
+    exif_tag("lens", "String", STRING_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::String(exif.lens.clone()))
+    }),
+    exif_tag("model", "String", STRING_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::String(exif.model.clone()))
+    }),
+    exif_tag(
+        "description",
+        "String",
+        STRING_OPERATORS,
+        Some("description set by photo_set_metadata"),
+        |exif| {
+            Ok(ExifTagValue::String(
+                exif.description.clone().unwrap_or_default(),
+            ))
+        },
+    ),
+    exif_tag(
+        "animated",
+        "String",
+        EQUALITY_OPERATORS,
+        Some("true or false"),
+        |exif| Ok(ExifTagValue::String(exif.animation.is_some().to_string())),
+    ),
+    exif_tag(
+        "has_motion",
+        "String",
+        EQUALITY_OPERATORS,
+        Some("true or false"),
+        |exif| Ok(ExifTagValue::String(exif.has_motion.to_string())),
+    ),
+    exif_tag("frames", "Integer", NUMERIC_OPERATORS, None, |exif| {
+        Ok(ExifTagValue::Number(
+            exif.animation.as_ref().map(|a| a.frames).unwrap_or(1),
+        ))
+    }),
+    exif_tag(
+        "color_label",
+        "String",
+        EQUALITY_OPERATORS,
+        Some("red, yellow, green, blue or purple, from the XMP of the photo or photo_set_metadata"),
+        |exif| {
+            Ok(ExifTagValue::String(
+                exif.color_label
+                    .map(|label| label.name().to_owned())
+                    .unwrap_or_default(),
+            ))
+        },
+    ),
+    exif_tag(
+        "flash",
+        "String",
+        EQUALITY_OPERATORS,
+        Some("true when the flash fired"),
+        |exif| {
+            Ok(ExifTagValue::String(
+                exif.flash
+                    .map(|fired| fired.to_string())
+                    .unwrap_or_default(),
+            ))
+        },
+    ),
+    exif_tag(
+        "exposure_program",
+        "String",
+        EQUALITY_OPERATORS,
+        Some(
+            "manual, program, aperture_priority, shutter_priority, creative, action, portrait or landscape",
+        ),
+        |exif| {
+            Ok(ExifTagValue::String(
+                exif.exposure_program.clone().unwrap_or_default(),
+            ))
+        },
+    ),
+    exif_tag(
+        "metering_mode",
+        "String",
+        EQUALITY_OPERATORS,
+        Some("average, center_weighted, spot, multi_spot, pattern, partial or other"),
+        |exif| {
+            Ok(ExifTagValue::String(
+                exif.metering_mode.clone().unwrap_or_default(),
+            ))
+        },
+    ),
+    tag(
+        "time_of_day",
+        "String",
+        EQUALITY_OPERATORS,
+        Some(
+            "morning, golden_hour, midday, evening or night, from the sun position when the location is known",
+        ),
+    ),
+    tag(
+        "printable",
+        "Paper",
+        EQUALITY_OPERATORS,
+        Some("A0 to A6 filled at 300 DPI, or at another resolution as \"A2@150\""),
+    ),
+];
+
+/// Tags of analysis results and metadata overrides, searchable and listed once photos of the
+/// collection have them, sorted by name
+pub const DYNAMIC_TAGS: [SearchTag; 3] = [
+    tag(
+        "label",
+        "String",
+        STRING_OPERATORS,
+        Some(
+            "labels of analysis stages, a photo matches when any of its labels does (!= when none does)",
+        ),
+    ),
+    tag(
+        "rating",
+        "Integer",
+        NUMERIC_OPERATORS,
+        Some("star rating from 0 to 5 set by photo_set_metadata"),
+    ),
+    tag(
+        "temperature",
+        "Float",
+        NUMERIC_OPERATORS,
+        Some("degrees Celsius when taken, of photos enriched by the weather analysis stage"),
+    ),
+];
+
 // Enum to represent different types of EXIF tag values
 enum ExifTagValue {
     String(String),
@@ -32,7 +404,214 @@ enum ExifTagValue {
     Float(f32),
 }
 
+/// Compares a number with the given value using EXIF query operators, also used for numeric
+/// analysis results (e.g. people count)
+pub(crate) fn match_number(
+    n: u32,
+    tag_value: &str,
+    operator: &str,
+) -> Result<bool, PhotoInsightError> {
+    let tag_value: u32 = tag_value
+        .parse()
+        .map_err(|_| PhotoInsightError::from_message("Invalid number value for comparison"))?;
+    match operator {
+        "==" => Ok(n == tag_value),
+        "!=" => Ok(n != tag_value),
+        ">" => Ok(n > tag_value),
+        "<" => Ok(n < tag_value),
+        ">=" => Ok(n >= tag_value),
+        "<=" => Ok(n <= tag_value),
+        _ => Err(PhotoInsightError::from_message(format!(
+            "Invalid operator for number: {}",
+            operator
+        ))),
+    }
+}
+
+/// Compares a text case insensitively with the given value using EXIF query operators, also
+/// used for labels of analysis results
+pub(crate) fn match_string(
+    s: &str,
+    tag_value: &str,
+    operator: &str,
+) -> Result<bool, PhotoInsightError> {
+    let (s, tag_value) = (s.to_lowercase(), tag_value.to_lowercase());
+    match operator {
+        "==" => Ok(s == tag_value),
+        "!=" => Ok(s != tag_value),
+        "contains" => Ok(s.contains(&tag_value)),
+        "starts_with" => Ok(s.starts_with(&tag_value)),
+        "ends_with" => Ok(s.ends_with(&tag_value)),
+        _ => Err(PhotoInsightError::from_message(format!(
+            "Invalid operator for string: {}",
+            operator
+        ))),
+    }
+}
+
+/// Compares a float with the given value using EXIF query operators, also used for numeric
+/// analysis results (e.g. temperature)
+pub(crate) fn match_float(
+    f: f32,
+    tag_value: &str,
+    operator: &str,
+) -> Result<bool, PhotoInsightError> {
+    let tag_value: f32 = tag_value
+        .parse()
+        .map_err(|_| PhotoInsightError::from_message("Invalid float value for comparison"))?;
+    match operator {
+        "==" => Ok((f - tag_value).abs() < std::f32::EPSILON),
+        "!=" => Ok((f - tag_value).abs() >= std::f32::EPSILON),
+        ">" => Ok(f > tag_value),
+        "<" => Ok(f < tag_value),
+        ">=" => Ok(f >= tag_value),
+        "<=" => Ok(f <= tag_value),
+        _ => Err(PhotoInsightError::from_message(format!(
+            "Invalid operator for float: {}",
+            operator,
+        ))),
+    }
+}
+
 impl ExifInfo {
+    /// EXIF info of a photo without EXIF data, all tags unknown
+    pub fn unknown() -> Self {
+        let unknown = String::from("\"unknown\"");
+        ExifInfo {
+            year: 0,
+            month: 0,
+            model: unknown.clone(),
+            width: 0,
+            height: 0,
+            date_time: unknown.clone(),
+            aperture: String::from("0"),
+            shutter_speed: unknown.clone(),
+            iso: String::from("0"),
+            focal_len: String::from("0"),
+            focal_len_35mm: None,
+            lens: unknown,
+            location: None,
+            description: None,
+            color_space: None,
+            bit_depth: None,
+            animation: None,
+            has_motion: false,
+            shutter_count: None,
+            body_serial: None,
+            flash: None,
+            exposure_program: None,
+            metering_mode: None,
+            color_label: None,
+        }
+    }
+
+    /// Crop factor of the camera model, configured factors take precedence over the built-in
+    /// ones
+    pub fn crop_factor(&self) -> Option<f32> {
+        let model = self.model.trim_matches('"');
+        crate::CONFIG
+            .index
+            .crop_factors
+            .iter()
+            .filter(|(pattern, _)| traversal::pattern_matches(pattern, model))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, factor)| *factor)
+            .or_else(|| {
+                CROP_FACTORS
+                    .iter()
+                    .find(|(pattern, _)| traversal::pattern_matches(pattern, model))
+                    .map(|(_, factor)| *factor)
+            })
+    }
+
+    /// Fills in the 35mm equivalent focal length from the crop factor when the camera didn't
+    /// record it, so that focal lengths of phones, APS-C and full-frame bodies are comparable
+    pub fn normalize_focal_len(&mut self) {
+        if self.focal_len_35mm.is_some() {
+            return;
+        }
+        let focal_len = self.focal_len.parse::<f32>().unwrap_or_default();
+        if focal_len > 0.0 {
+            self.focal_len_35mm = self
+                .crop_factor()
+                .map(|factor| (focal_len * factor).round());
+        }
+    }
+
+    /// Part of the day the photo was taken in, none when the time taken is unknown
+    pub fn time_of_day(&self) -> Option<TimeOfDay> {
+        time_of_day::time_of_day(&self.date_time, self.location.as_ref())
+    }
+
+    /// Maximum recommended print sizes at 300 and 150 DPI, none when the dimensions are unknown
+    pub fn print_sizes(&self) -> Vec<PrintSize> {
+        if self.width == 0 || self.height == 0 {
+            return Vec::new();
+        }
+        // millimeters rounded, reported in centimeters
+        let to_cm =
+            |pixels: u32, dpi: u32| (pixels as f64 / dpi as f64 * MM_PER_INCH).round() / 10.0;
+        PRINT_DPI
+            .iter()
+            .map(|&dpi| PrintSize {
+                dpi,
+                width_cm: to_cm(self.width, dpi),
+                height_cm: to_cm(self.height, dpi),
+                largest_paper: PAPER_SIZES
+                    .iter()
+                    .find(|(_, short, long)| self.fills_paper(*short, *long, dpi))
+                    .map(|(name, ..)| name.to_string()),
+            })
+            .collect()
+    }
+
+    // The photo printed at the resolution covers the paper in either orientation
+    fn fills_paper(&self, short_mm: u32, long_mm: u32, dpi: u32) -> bool {
+        let mm = |pixels: u32| pixels as f64 / dpi as f64 * MM_PER_INCH;
+        let (short, long) = if self.width <= self.height {
+            (self.width, self.height)
+        } else {
+            (self.height, self.width)
+        };
+        mm(short) >= short_mm as f64 && mm(long) >= long_mm as f64
+    }
+
+    // Matches the "printable" tag, the value is an A series paper optionally followed by the
+    // resolution, e.g. "A3" (at 300 DPI) or "A2@150"
+    fn printable(&self, tag_value: &str, operator: &str) -> Result<bool, PhotoInsightError> {
+        let (paper, dpi) = match tag_value.split_once('@') {
+            Some((paper, dpi)) => (
+                paper,
+                dpi.trim()
+                    .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|dpi| *dpi > 0)
+                    .ok_or_else(|| {
+                        PhotoInsightError::from_message(format!("Invalid print resolution: {dpi}"))
+                    })?,
+            ),
+            None => (tag_value, PRINT_DPI[0]),
+        };
+        let (_, short, long) = PAPER_SIZES
+            .iter()
+            .find(|(name, ..)| name.eq_ignore_ascii_case(paper.trim()))
+            .ok_or_else(|| {
+                PhotoInsightError::from_message(format!(
+                    "Unknown paper size: {paper}, expected A0 to A6"
+                ))
+            })?;
+        let fills = self.fills_paper(*short, *long, dpi);
+        match operator {
+            "==" => Ok(fills),
+            "!=" => Ok(!fills),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid operator for printable: {}",
+                operator
+            ))),
+        }
+    }
+
     /// Checks if the EXIF information matches the given query parameters.
     pub fn matches_query(
         &self,
@@ -40,10 +619,39 @@ impl ExifInfo {
         tag_value: &String,
         operator: &String,
     ) -> Result<bool, PhotoInsightError> {
+        if tag_name == "printable" {
+            return self.printable(tag_value, operator);
+        }
+        if tag_name == "time_of_day" {
+            let taken_in = self.time_of_day() == Some(TimeOfDay::parse(tag_value)?);
+            return match operator.as_str() {
+                "==" => Ok(taken_in),
+                "!=" => Ok(!taken_in),
+                _ => Err(PhotoInsightError::from_message(format!(
+                    "Invalid operator for time_of_day: {}",
+                    operator
+                ))),
+            };
+        }
         let exif_tag_value = self.extract_tag_value(tag_name.as_str())?;
         ExifInfo::match_exif_tag_value(exif_tag_value, tag_value.as_str(), operator.as_str())
     }
 
+    /// Value of a searchable tag as listed by photo_exif_tags, `None` when the photo has no
+    /// known value (e.g. no lens recorded). Printable paper sizes are derived and have none.
+    pub fn tag_text(&self, tag_name: &str) -> Option<String> {
+        if tag_name == "time_of_day" {
+            return self.time_of_day().map(|t| t.name().to_owned());
+        }
+        match self.extract_tag_value(tag_name).ok()? {
+            ExifTagValue::String(s) => {
+                Some(unquote(&s).to_owned()).filter(|s| !s.is_empty() && s != "unknown")
+            }
+            ExifTagValue::Number(n) => Some(n).filter(|n| *n > 0).map(|n| n.to_string()),
+            ExifTagValue::Float(f) => Some(f).filter(|f| *f > 0.0).map(|f| f.to_string()),
+        }
+    }
+
     // Function to compare an ExifTagValue with a given tag value and operator (type aware)
     fn match_exif_tag_value(
         value: ExifTagValue,
@@ -51,93 +659,118 @@ impl ExifInfo {
         operator: &str,
     ) -> Result<bool, PhotoInsightError> {
         match value {
-            ExifTagValue::String(s) => match operator {
-                "==" => Ok(s.to_lowercase() == tag_value.to_lowercase()),
-                "!=" => Ok(s.to_lowercase() != tag_value.to_lowercase()),
-                "contains" => Ok(s.to_lowercase().contains(&tag_value.to_lowercase())),
-                "starts_with" => Ok(s.to_lowercase().starts_with(&tag_value.to_lowercase())),
-                "ends_with" => Ok(s.to_lowercase().ends_with(&tag_value.to_lowercase())),
-                _ => Err(PhotoInsightError::from_message(format!(
-                    "Invalid operator for string: {}",
-                    operator
-                ))),
-            },
-            ExifTagValue::Number(n) => {
-                let tag_value: u32 = tag_value.parse().map_err(|_| {
-                    PhotoInsightError::from_message("Invalid number value for comparison")
-                })?;
-                match operator {
-                    "==" => Ok(n == tag_value),
-                    "!=" => Ok(n != tag_value),
-                    ">" => Ok(n > tag_value),
-                    "<" => Ok(n < tag_value),
-                    ">=" => Ok(n >= tag_value),
-                    "<=" => Ok(n <= tag_value),
-                    _ => Err(PhotoInsightError::from_message(format!(
-                        "Invalid operator for number: {}",
-                        operator
-                    ))),
-                }
-            }
-            ExifTagValue::Float(f) => {
-                let tag_value: f32 = tag_value.parse().map_err(|_| {
-                    PhotoInsightError::from_message("Invalid float value for comparison")
-                })?;
-                match operator {
-                    "==" => Ok((f - tag_value).abs() < std::f32::EPSILON),
-                    "!=" => Ok((f - tag_value).abs() >= std::f32::EPSILON),
-                    ">" => Ok(f > tag_value),
-                    "<" => Ok(f < tag_value),
-                    ">=" => Ok(f >= tag_value),
-                    "<=" => Ok(f <= tag_value),
-                    _ => Err(PhotoInsightError::from_message(format!(
-                        "Invalid operator for float: {}",
-                        operator,
-                    ))),
-                }
-            }
+            // camera and lens names are stored quoted as EXIF displays them
+            ExifTagValue::String(s) => match_string(unquote(&s), tag_value, operator),
+            ExifTagValue::Number(n) => match_number(n, tag_value, operator),
+            ExifTagValue::Float(f) => match_float(f, tag_value, operator),
         }
     }
 
     // Extracts the value of a specified EXIF tag and returns it as an ExifTagValue enum
     fn extract_tag_value(&self, tag_name: &str) -> Result<ExifTagValue, PhotoInsightError> {
-        match tag_name {
-            "model" | "lens" => match tag_name {
-                "model" => Ok(ExifTagValue::String(self.model.clone())),
-                "lens" => Ok(ExifTagValue::String(self.lens.clone())),
-                _ => Err(PhotoInsightError::from_message("Invalid tag name")),
-            },
-            "aperture" | "shutter_speed" | "iso" | "focal_len" => {
-                let val = match tag_name {
-                    "aperture" => &self.aperture,
-                    "shutter_speed" => &self.shutter_speed,
-                    "iso" => &self.iso,
-                    "focal_len" => &self.focal_len,
-                    _ => "",
-                };
-                let f: f32 = val
-                    .parse()
-                    .map_err(|_| PhotoInsightError::from_message("Invalid float value"))?;
-                Ok(ExifTagValue::Float(f))
-            }
-            "width" | "height" | "year" | "month" => {
-                let val = match tag_name {
-                    "width" => self.width,
-                    "height" => self.height,
-                    "year" => self.year,
-                    "month" => self.month,
-                    _ => 0,
-                };
-                Ok(ExifTagValue::Number(val))
-            }
-            _ => Err(PhotoInsightError::from_message(format!(
-                "Invalid tag name: {}",
-                tag_name
-            ))),
-        }
+        let value = SEARCH_TAGS
+            .iter()
+            .find(|tag| tag.name == tag_name)
+            .and_then(|tag| tag.value)
+            .ok_or_else(|| {
+                PhotoInsightError::from_message(format!("Invalid tag name: {}", tag_name))
+            })?;
+        value(self)
     }
 }
 
+/// Date and time encoded in the file name (the directory part is ignored), normalized to
+/// "YYYY-MM-DD HH:MM:SS". Missing day or time is taken as the first day or midnight.
+pub fn date_from_file_name(file_name: &str) -> Option<String> {
+    let base_name = file_name.rsplit('/').next().unwrap_or(file_name);
+    FILE_NAME_DATE_RE.iter().find_map(|re| {
+        let caps = re.captures(base_name)?;
+        let part = |name: &str, default: &str| {
+            caps.name(name)
+                .map(|m| m.as_str().to_owned())
+                .unwrap_or_else(|| default.to_owned())
+        };
+        let date_time = format!(
+            "{}-{:0>2}-{:0>2} {:0>2}:{:0>2}:{:0>2}",
+            part("year", ""),
+            part("month", ""),
+            part("day", "01"),
+            part("hour", "00"),
+            part("minute", "00"),
+            part("second", "00")
+        );
+        normalize_date_time(&date_time).ok()
+    })
+}
+
+/// Info of an image without readable EXIF: dimensions from the image header, all tags unknown
+fn unknown_exif_info(image_data: &[u8]) -> ExifInfo {
+    let (width, height) = image::ImageReader::new(std::io::Cursor::new(image_data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+        .unwrap_or_default();
+    ExifInfo {
+        width,
+        height,
+        ..ExifInfo::unknown()
+    }
+}
+
+/// Exposure of a photo read from its EXIF, missing tags are none
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Exposure {
+    pub seconds: Option<f64>,
+    pub iso: Option<u32>,
+    /// Hour of the time taken
+    pub hour: Option<u32>,
+}
+
+/// Exposure time in seconds (`shutter_speed` keeps the denominator only), ISO and hour taken
+pub(crate) fn exposure_of(image_data: &[u8]) -> Exposure {
+    let mut cursor = std::io::Cursor::new(image_data);
+    let Ok(exif) = exif::Reader::new().read_from_container(&mut cursor) else {
+        return Exposure::default();
+    };
+    let field = |tag: exif::Tag| exif.get_field(tag, exif::In::PRIMARY);
+    let seconds = field(exif::Tag::ExposureTime).and_then(|f| match &f.value {
+        exif::Value::Rational(r) if !r.is_empty() && r[0].denom != 0 => Some(r[0].to_f64()),
+        _ => None,
+    });
+    let iso = field(exif::Tag::PhotographicSensitivity).and_then(|f| f.value.get_uint(0));
+    let hour = [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .into_iter()
+        .find_map(field)
+        .and_then(|f| match &f.value {
+            exif::Value::Ascii(v) if !v.is_empty() => exif::DateTime::from_ascii(&v[0]).ok(),
+            _ => None,
+        })
+        .map(|date_time| date_time.hour as u32);
+    Exposure { seconds, iso, hour }
+}
+
+/// GPS position recorded by the camera, none when the photo has no GPS tags
+pub(crate) fn gps_of(image_data: &[u8]) -> Option<GeoLocation> {
+    let mut cursor = std::io::Cursor::new(image_data);
+    let exif = exif::Reader::new().read_from_container(&mut cursor).ok()?;
+    let coordinate = |tag: exif::Tag, reference: exif::Tag, negative: &str| {
+        let degrees = match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Rational(r) if r.len() >= 3 && r.iter().all(|v| v.denom != 0) => {
+                r[0].to_f64() + r[1].to_f64() / 60.0 + r[2].to_f64() / 3600.0
+            }
+            _ => return None,
+        };
+        let negated = exif
+            .get_field(reference, exif::In::PRIMARY)
+            .is_some_and(|f| f.display_value().to_string().trim_matches('"') == negative);
+        Some(if negated { -degrees } else { degrees })
+    };
+    Some(GeoLocation {
+        latitude: coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, "S")?,
+        longitude: coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, "W")?,
+    })
+}
+
 pub fn extract_all_exifs_from_zip_archive(
     image_dir: &str,
     zip_file_name: &str,
@@ -146,31 +779,31 @@ pub fn extract_all_exifs_from_zip_archive(
     let mut files = HashMap::new();
 
     if zip_path.is_file() {
-        let file = std::fs::File::open(&zip_path).map_err(|e| PhotoInsightError::new(e))?;
-        let mut archive = zip::ZipArchive::new(file).map_err(|e| PhotoInsightError::new(e))?;
+        let mut archive = open_archive(&zip_path)?;
+        let names = entry_names(&mut archive)?;
 
-        for i in 0..archive.len() {
+        for (i, entry) in names.into_iter().enumerate() {
             let mut file = archive.by_index(i).map_err(|e| PhotoInsightError::new(e))?;
-            let file_name = file.name().to_string();
+            let file_name = entry.name;
 
             if is_image_file(&file_name) {
                 let mut image_data = Vec::new();
                 file.read_to_end(&mut image_data)
                     .map_err(|e| PhotoInsightError::new(e))?;
-                let exif = extract_exif_info(&image_data, false);
-                if exif.is_err() {
-                    tracing::warn!(
-                        "Failed to extract exif from image {} in zip {}: {}",
-                        file_name,
-                        zip_file_name,
-                        exif.err().unwrap()
-                    );
-                    continue;
-                }
-                files.insert(
-                    PhotoInfo::new(zip_file_name.to_owned(), file_name, i),
-                    exif.unwrap().0,
-                );
+                let exif = match extract_exif_info(&image_data, false) {
+                    Ok((exif, _)) => exif,
+                    Err(e) => {
+                        // indexed anyway, dated by the file name on load if possible
+                        tracing::warn!(
+                            "Failed to extract exif from image {} in zip {}: {}",
+                            file_name,
+                            zip_file_name,
+                            e
+                        );
+                        unknown_exif_info(&image_data)
+                    }
+                };
+                files.insert(PhotoInfo::new(zip_file_name.to_owned(), file_name, i), exif);
             }
         }
     } else {
@@ -187,9 +820,18 @@ pub fn extract_exif_info(
 ) -> Result<(ExifInfo, Vec<u8>), PhotoInsightError> {
     let mut cursor = std::io::Cursor::new(image_data);
     let exifreader = exif::Reader::new();
-    let exif = exifreader
-        .read_from_container(&mut cursor)
-        .map_err(|e| PhotoInsightError::new(e))?;
+    let exif = match exifreader.read_from_container(&mut cursor) {
+        Ok(exif) => exif,
+        // HEIF without the brands the reader recognizes and Canon CR3 raw files
+        Err(_) if bmff::is_bmff(image_data) => bmff::read_exif(image_data)?,
+        Err(e) => {
+            // animated files rarely carry EXIF, index them with unknown tags anyway
+            return match read_animation(image_data) {
+                Some(animation) => animation_info(image_data, animation, thumbnail),
+                None => Err(PhotoInsightError::new(e)),
+            };
+        }
+    };
 
     let model = extract_tag(&exif, vec![exif::Tag::Model], false);
 
@@ -260,11 +902,11 @@ pub fn extract_exif_info(
         vec![exif::Tag::ISOSpeed, exif::Tag::PhotographicSensitivity],
         true,
     );
-    let focal_len = extract_tag(
-        &exif,
-        vec![exif::Tag::FocalLengthIn35mmFilm, exif::Tag::FocalLength],
-        true,
-    );
+    let focal_len = extract_tag(&exif, vec![exif::Tag::FocalLength], true);
+    let focal_len_35mm = extract_tag(&exif, vec![exif::Tag::FocalLengthIn35mmFilm], true)
+        .parse::<f32>()
+        .ok()
+        .filter(|f| *f > 0.0);
     let lens = extract_tag(
         &exif,
         vec![
@@ -275,8 +917,33 @@ pub fn extract_exif_info(
         false,
     );
 
-    // let maker_notes = extract_tag(&exif, vec![exif::Tag::MakerNote], false);
-    // println!("maker_notes={maker_notes}");
+    let header = read_header(image_data);
+    let bit_depth = header.as_ref().map(|h| h.bit_depth).filter(|d| *d > 8);
+    let icc = header.and_then(|h| h.icc);
+    let color_space = icc
+        .as_deref()
+        .and_then(color_space_name)
+        .or_else(|| extract_color_space(&exif));
+
+    let make = extract_tag(&exif, vec![exif::Tag::Make], false);
+    let maker_note = match exif.get_field(exif::Tag::MakerNote, exif::In::PRIMARY) {
+        Some(exif::Field {
+            value: exif::Value::Undefined(data, _),
+            ..
+        }) => maker_note::parse(&make, data),
+        _ => MakerNoteInfo::default(),
+    };
+    let body_serial = exif
+        .get_field(exif::Tag::BodySerialNumber, exif::In::PRIMARY)
+        .map(|f| {
+            f.display_value()
+                .to_string()
+                .trim_matches('"')
+                .trim()
+                .to_owned()
+        })
+        .filter(|serial| !serial.is_empty())
+        .or(maker_note.serial);
 
     // println!("model={}", model.replace("\"", "").replace(",", ""));
 
@@ -292,17 +959,109 @@ pub fn extract_exif_info(
             shutter_speed,
             iso,
             focal_len,
+            focal_len_35mm,
             lens,
+            location: None,
+            description: None,
+            color_space,
+            bit_depth,
+            animation: read_animation(image_data),
+            has_motion: false,
+            shutter_count: maker_note.shutter_count,
+            body_serial,
+            flash: extract_uint(&exif, exif::Tag::Flash).map(|flash| flash & 1 == 1),
+            exposure_program: extract_uint(&exif, exif::Tag::ExposureProgram)
+                .and_then(exposure_program_name)
+                .map(str::to_owned),
+            metering_mode: extract_uint(&exif, exif::Tag::MeteringMode)
+                .and_then(metering_mode_name)
+                .map(str::to_owned),
+            color_label: ColorLabel::read_xmp(image_data),
         },
-        if thumbnail {
-            extract_thm(image_data, &exif)
+        // HEVC and raw images are not decoded, they have no thumbnail
+        if thumbnail && !bmff::is_bmff(image_data) {
+            extract_thm(image_data, &exif, icc.as_deref())?
         } else {
             Vec::new()
         },
     ))
 }
 
-fn extract_thm(image_data: &Vec<u8>, exif: &exif::Exif) -> Vec<u8> {
+fn animation_info(
+    image_data: &Vec<u8>,
+    animation: Animation,
+    thumbnail: bool,
+) -> Result<(ExifInfo, Vec<u8>), PhotoInsightError> {
+    let (width, height) = image::ImageReader::new(std::io::Cursor::new(image_data))
+        .with_guessed_format()
+        .map_err(|e| PhotoInsightError::new(e))?
+        .into_dimensions()
+        .map_err(|e| PhotoInsightError::new(e))?;
+    let exif = ExifInfo {
+        width,
+        height,
+        animation: Some(animation),
+        ..ExifInfo::unknown()
+    };
+    // the first frame is decoded for the thumbnail
+    let thm = if thumbnail {
+        resize(image_data, width, height)?
+    } else {
+        Vec::new()
+    };
+    Ok((exif, thm))
+}
+
+fn extract_uint(exif: &exif::Exif, tag: exif::Tag) -> Option<u32> {
+    exif.get_field(tag, exif::In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+}
+
+// Values of the EXIF ExposureProgram tag, 0 is not defined
+fn exposure_program_name(program: u32) -> Option<&'static str> {
+    match program {
+        1 => Some("manual"),
+        2 => Some("program"),
+        3 => Some("aperture_priority"),
+        4 => Some("shutter_priority"),
+        5 => Some("creative"),
+        6 => Some("action"),
+        7 => Some("portrait"),
+        8 => Some("landscape"),
+        _ => None,
+    }
+}
+
+// Values of the EXIF MeteringMode tag, 0 is unknown
+fn metering_mode_name(mode: u32) -> Option<&'static str> {
+    match mode {
+        1 => Some("average"),
+        2 => Some("center_weighted"),
+        3 => Some("spot"),
+        4 => Some("multi_spot"),
+        5 => Some("pattern"),
+        6 => Some("partial"),
+        255 => Some("other"),
+        _ => None,
+    }
+}
+
+fn extract_color_space(exif: &exif::Exif) -> Option<String> {
+    let v = exif
+        .get_field(exif::Tag::ColorSpace, exif::In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))?;
+    match v {
+        1 => Some(String::from("sRGB")),
+        0xffff => Some(String::from("Uncalibrated")),
+        _ => None,
+    }
+}
+
+fn extract_thm(
+    image_data: &Vec<u8>,
+    exif: &exif::Exif,
+    icc: Option<&[u8]>,
+) -> Result<Vec<u8>, PhotoInsightError> {
     //let buf = fs::read(path).expect("read input file");
     let buf = exif.buf();
     let off = exif
@@ -319,7 +1078,13 @@ fn extract_thm(image_data: &Vec<u8>, exif: &exif::Exif) -> Vec<u8> {
         let end = start + len.unwrap() as usize;
         let res = &buf[start..end];
         // println!("start={} end={}", start, end);
-        res.to_vec()
+        Ok(match icc {
+            // embedded thumbnail shares the color space of the photo
+            Some(icc) if !is_srgb_profile(icc) => {
+                thumbnail_to_srgb(res, icc).unwrap_or_else(|| res.to_vec())
+            }
+            _ => res.to_vec(),
+        })
     } else {
         // fallback to canvas resize if we are unable to extract the thumbnail from the exif tags
         let w = extract_tag(
@@ -333,13 +1098,22 @@ fn extract_thm(image_data: &Vec<u8>, exif: &exif::Exif) -> Vec<u8> {
             true,
         );
 
-        let orig_w: u32 = w.parse().unwrap();
-        let orig_h: u32 = h.parse().unwrap();
+        // 0 takes the size of the decoded image
+        let orig_w: u32 = w.parse().unwrap_or(0);
+        let orig_h: u32 = h.parse().unwrap_or(0);
 
         resize(image_data, orig_w, orig_h)
     }
 }
 
+fn thumbnail_to_srgb(thm: &[u8], icc: &[u8]) -> Option<Vec<u8>> {
+    let img = image::load_from_memory(thm).ok()?;
+    let img = to_srgb(img, Some(icc));
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buf, image::ImageFormat::Jpeg).ok()?;
+    Some(buf.into_inner())
+}
+
 fn extract_tag(exif: &exif::Exif, tags: Vec<exif::Tag>, numeric: bool) -> String {
     for t in tags.iter() {
         let v = exif.get_field(*t, exif::In::PRIMARY);
@@ -367,9 +1141,13 @@ fn extract_tag(exif: &exif::Exif, tags: Vec<exif::Tag>, numeric: bool) -> String
     return String::from(if numeric { "0" } else { "\"unknown\"" });
 }
 
-pub(crate) fn resize(buf: &Vec<u8>, orig_w: u32, orig_h: u32) -> Vec<u8> {
-    // load the image
-    let img = image::load_from_memory(&buf).expect("image decoded");
+pub(crate) fn resize(
+    buf: &Vec<u8>,
+    orig_w: u32,
+    orig_h: u32,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    // load the image together with its color profile
+    let (img, icc) = timings::timed("decode", || decode_with_icc(&buf))?;
 
     let width = if orig_w == 0 { img.width() } else { orig_w };
     let height = if orig_h == 0 { img.height() } else { orig_h };
@@ -381,16 +1159,81 @@ pub(crate) fn resize(buf: &Vec<u8>, orig_w: u32, orig_h: u32) -> Vec<u8> {
         nh = 160;
     }
     tracing::info!("Resizing image {width}x{height} -> {nw}x{nh}");
-    let sc_img = img.resize(nw, nh, image::imageops::FilterType::Lanczos3);
-    // sc_img.as_bytes().to_vec()
-    sc_img.save("/tmp/x.jpg").expect("resize save failed");
-    let result = std::fs::read("/tmp/x.jpg").expect("read resized file");
-    result
+    let sc_img = timings::timed("resize", || {
+        img.resize(nw, nh, image::imageops::FilterType::Lanczos3)
+    });
+    // JPEG thumbnails are 8-bit only
+    let sc_img = tone_map_8bit(sc_img);
+    // the profile is not written to the thumbnail, convert the pixels to sRGB instead
+    let sc_img = to_srgb(sc_img, icc.as_deref());
+    let mut result = std::io::Cursor::new(Vec::new());
+    timings::timed("encode", || {
+        image::DynamicImage::ImageRgb8(sc_img.to_rgb8())
+            .write_to(&mut result, image::ImageFormat::Jpeg)
+    })
+    .map_err(|e| PhotoInsightError::new(e))?;
+    Ok(result.into_inner())
+}
+
+/// Thumbnail of the size `resize` makes, filled by a crop of the photo centered on the
+/// subjects (xmin, ymin, xmax, ymax in pixels) instead of fitting the whole photo
+pub(crate) fn smart_crop(
+    buf: &[u8],
+    subjects: &[(f32, f32, f32, f32)],
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let (img, icc) = timings::timed("decode", || decode_with_icc(buf))?;
+    let (width, height) = (img.width(), img.height());
+    let (nw, nh) = if height > width {
+        (100, 160)
+    } else {
+        (160, 100)
+    };
+    // largest crop of the thumbnail aspect ratio
+    let crop_w = width
+        .min((height as u64 * nw as u64 / nh as u64) as u32)
+        .max(1);
+    let crop_h = height
+        .min((width as u64 * nh as u64 / nw as u64) as u32)
+        .max(1);
+    let xmin = subjects.iter().map(|b| b.0).fold(f32::INFINITY, f32::min);
+    let ymin = subjects.iter().map(|b| b.1).fold(f32::INFINITY, f32::min);
+    let xmax = subjects
+        .iter()
+        .map(|b| b.2)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let ymax = subjects
+        .iter()
+        .map(|b| b.3)
+        .fold(f32::NEG_INFINITY, f32::max);
+    if subjects.is_empty() || xmax <= xmin || ymax <= ymin {
+        return Err(PhotoInsightError::from_message("no subjects to crop to"));
+    }
+    let center = |min: f32, max: f32, crop: u32, size: u32| {
+        ((min + max) / 2.0 - crop as f32 / 2.0).clamp(0.0, (size - crop) as f32) as u32
+    };
+    let x = center(xmin, xmax, crop_w, width);
+    let y = center(ymin, ymax, crop_h, height);
+    tracing::info!("Smart cropping image {width}x{height} at {x},{y} -> {nw}x{nh}");
+    let sc_img = timings::timed("resize", || {
+        img.crop_imm(x, y, crop_w, crop_h).resize_exact(
+            nw,
+            nh,
+            image::imageops::FilterType::Lanczos3,
+        )
+    });
+    let sc_img = to_srgb(tone_map_8bit(sc_img), icc.as_deref());
+    let mut result = std::io::Cursor::new(Vec::new());
+    timings::timed("encode", || {
+        image::DynamicImage::ImageRgb8(sc_img.to_rgb8())
+            .write_to(&mut result, image::ImageFormat::Jpeg)
+    })
+    .map_err(|e| PhotoInsightError::new(e))?;
+    Ok(result.into_inner())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::core::exif::extract_exif_info;
+    use crate::core::exif::{ExifInfo, ExifTagValue, SEARCH_TAGS, extract_exif_info, resize};
 
     #[test]
     fn test_exif_info() {
@@ -398,4 +1241,106 @@ mod tests {
         let exif = extract_exif_info(&img, false).expect("can't extract exif");
         println!("{exif:#?}");
     }
+
+    #[test]
+    fn test_search_tags_searchable() {
+        let exif = ExifInfo {
+            aperture: "2.8".to_owned(),
+            shutter_speed: "0.01".to_owned(),
+            iso: "100".to_owned(),
+            focal_len: "50".to_owned(),
+            ..ExifInfo::unknown()
+        };
+        for tag in SEARCH_TAGS.iter() {
+            let example = match tag.tag_type {
+                "Integer" | "Float" => "1",
+                "Paper" => "A4",
+                _ if tag.name == "time_of_day" => "night",
+                _ => "true",
+            };
+            let matched = exif.matches_query(&tag.name.into(), &example.into(), &"==".into());
+            assert!(matched.is_ok(), "{}: {matched:?}", tag.name);
+            let Some(value) = tag.value else {
+                continue;
+            };
+            let tag_type = match value(&exif).unwrap() {
+                ExifTagValue::String(_) => "String",
+                ExifTagValue::Number(_) => "Integer",
+                ExifTagValue::Float(_) => "Float",
+            };
+            assert_eq!(tag_type, tag.tag_type, "{}", tag.name);
+        }
+        assert!(
+            exif.matches_query(&"exposure".into(), &"1".into(), &"==".into())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_print_sizes() {
+        let exif = ExifInfo {
+            width: 6000,
+            height: 4000,
+            ..ExifInfo::unknown()
+        };
+        let sizes = exif.print_sizes();
+        assert_eq!(sizes[0].dpi, 300);
+        assert_eq!((sizes[0].width_cm, sizes[0].height_cm), (50.8, 33.9));
+        assert_eq!(sizes[0].largest_paper.as_deref(), Some("A3"));
+        assert_eq!(sizes[1].largest_paper.as_deref(), Some("A1"));
+        let query = |value: &str| {
+            exif.matches_query(&"printable".into(), &value.into(), &"==".into())
+                .unwrap()
+        };
+        assert!(query("a3"));
+        assert!(!query("A2"));
+        assert!(query("A2@150dpi"));
+        assert!(ExifInfo::unknown().print_sizes().is_empty());
+    }
+
+    #[test]
+    fn test_normalize_focal_len() {
+        let mut exif = ExifInfo {
+            model: "\"Canon EOS 40D\"".to_owned(),
+            focal_len: "50".to_owned(),
+            ..ExifInfo::unknown()
+        };
+        exif.normalize_focal_len();
+        assert_eq!(exif.focal_len_35mm, Some(80.0));
+        let mut exif = ExifInfo {
+            model: "\"Canon EOS 5D Mark IV\"".to_owned(),
+            focal_len: "50".to_owned(),
+            ..ExifInfo::unknown()
+        };
+        exif.normalize_focal_len();
+        assert_eq!(exif.focal_len_35mm, Some(50.0));
+        let mut phone = ExifInfo {
+            model: "\"Pixel 7\"".to_owned(),
+            focal_len: "6.81".to_owned(),
+            focal_len_35mm: Some(25.0),
+            ..ExifInfo::unknown()
+        };
+        phone.normalize_focal_len();
+        assert_eq!(phone.focal_len_35mm, Some(25.0));
+    }
+
+    #[test]
+    fn test_resize() {
+        let thumbnails =
+            [(300, 200, [255, 0, 0]), (200, 300, [0, 0, 255])].map(|(width, height, color)| {
+                let photo = image::RgbImage::from_pixel(width, height, image::Rgb(color));
+                let mut png = std::io::Cursor::new(Vec::new());
+                photo.write_to(&mut png, image::ImageFormat::Png).unwrap();
+                let thumbnail = resize(&png.into_inner(), 0, 0).unwrap();
+                image::load_from_memory_with_format(&thumbnail, image::ImageFormat::Jpeg)
+                    .unwrap()
+                    .to_rgb8()
+            });
+        assert_eq!(thumbnails[0].dimensions(), (150, 100));
+        assert_eq!(thumbnails[1].dimensions(), (100, 150));
+        // each call encodes its own thumbnail
+        assert!(thumbnails[0].get_pixel(50, 50)[0] > 200);
+        assert!(thumbnails[1].get_pixel(50, 50)[2] > 200);
+        assert!(resize(&b"not an image".to_vec(), 0, 0).is_err());
+    }
 }
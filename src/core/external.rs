@@ -0,0 +1,82 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{error::PhotoInsightError, timings};
+
+/// Model running as an external process (e.g. a python script wrapping an on-device model).
+/// Image bytes are written to its stdin, the result is read from stdout as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExternalModel {
+    /// Executable to run
+    pub command: String,
+    /// Arguments passed before any call specific arguments
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl ExternalModel {
+    /// Reads `command` and `args` from stage options, `None` if no command is configured
+    pub fn from_options(options: &serde_json::Map<String, serde_json::Value>) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::Object(options.clone())).ok()
+    }
+
+    pub fn run(
+        &self,
+        input: &[u8],
+        extra_args: &[String],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .args(extra_args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                PhotoInsightError::from_message(format!("can't run {}: {e}", self.command))
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input)
+                .map_err(|e| PhotoInsightError::new(e))?;
+        }
+        let output = timings::timed("inference", || child.wait_with_output())
+            .map_err(|e| PhotoInsightError::new(e))?;
+        if !output.status.success() {
+            return Err(PhotoInsightError::from_message(format!(
+                "{} failed with {}: {}",
+                self.command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        serde_json::from_slice(&output.stdout).map_err(|e| {
+            PhotoInsightError::from_message(format!("invalid output of {}: {e}", self.command))
+        })
+    }
+
+    /// Runs a classifier, expected output is a JSON array of `{"label": .., "confidence": ..}`
+    pub fn classify(
+        &self,
+        input: &[u8],
+        extra_args: &[String],
+    ) -> Result<Vec<Classification>, PhotoInsightError> {
+        let value = self.run(input, extra_args)?;
+        serde_json::from_value(value).map_err(|e| {
+            PhotoInsightError::from_message(format!(
+                "unexpected classifier output of {}: {e}",
+                self.command
+            ))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Classification {
+    pub label: String,
+    pub confidence: f32,
+}
@@ -0,0 +1,56 @@
+/// Optional parts of the server compiled in by the cargo features of the same name. Slim
+/// builds without them still search results stored by full builds, the tools and analysis
+/// stages running the models are left out.
+pub const DETECTION: &str = "detection";
+pub const FACES: &str = "faces";
+pub const EMBEDDINGS: &str = "embeddings";
+pub const OCR: &str = "ocr";
+
+/// Tools running a model of an optional feature
+const FEATURE_TOOLS: [(&str, &str); 2] = [
+    ("photo_object_detection", DETECTION),
+    ("photo_search_portraits", FACES),
+];
+
+/// Analysis stages (by name prefix) running a model of an optional feature
+const FEATURE_STAGES: [(&str, &str); 5] = [
+    ("object_detection.", DETECTION),
+    ("people_count", DETECTION),
+    ("face_attributes", FACES),
+    ("tags", EMBEDDINGS),
+    ("document", OCR),
+];
+
+pub fn compiled(feature: &str) -> bool {
+    match feature {
+        DETECTION => cfg!(feature = "detection"),
+        FACES => cfg!(feature = "faces"),
+        EMBEDDINGS => cfg!(feature = "embeddings"),
+        OCR => cfg!(feature = "ocr"),
+        _ => false,
+    }
+}
+
+/// Features compiled into this binary
+pub fn compiled_features() -> Vec<&'static str> {
+    [DETECTION, FACES, EMBEDDINGS, OCR]
+        .into_iter()
+        .filter(|feature| compiled(feature))
+        .collect()
+}
+
+/// Feature the tool needs which is not compiled in
+pub fn missing_for_tool(tool_name: &str) -> Option<&'static str> {
+    FEATURE_TOOLS
+        .iter()
+        .find(|(tool, feature)| *tool == tool_name && !compiled(feature))
+        .map(|(_, feature)| *feature)
+}
+
+/// Feature the analysis stage needs which is not compiled in
+pub fn missing_for_stage(stage_name: &str) -> Option<&'static str> {
+    FEATURE_STAGES
+        .iter()
+        .find(|(prefix, feature)| stage_name.starts_with(prefix) && !compiled(feature))
+        .map(|(_, feature)| *feature)
+}
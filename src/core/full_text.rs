@@ -0,0 +1,194 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use serde::Serialize;
+use tantivy::{
+    Index, IndexReader, TantivyDocument,
+    collector::{Count, TopDocs},
+    doc,
+    query::QueryParser,
+    schema::{Field, INDEXED, STORED, STRING, Schema, TEXT, Value},
+};
+
+use crate::core::{
+    catalog::CatalogRow,
+    error::PhotoInsightError,
+    image_cache::{PhotoInfo, PhotoResult},
+};
+
+/// Memory budget of the index writer, the minimum tantivy accepts is 15 MB
+const WRITER_MEMORY: usize = 50_000_000;
+/// Terms of fuzzy queries match words differing in one character
+const FUZZY_DISTANCE: u8 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextSearchResult {
+    file: PhotoInfo,
+    /// Relevance, higher is better
+    score: f32,
+}
+
+impl TextSearchResult {
+    pub fn new(file: PhotoInfo, score: f32) -> Self {
+        Self { file, score }
+    }
+}
+
+impl PhotoResult for TextSearchResult {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.file
+    }
+}
+
+struct Fields {
+    zip_file_name: Field,
+    photo_index_in_zip: Field,
+    photo_file_name: Field,
+    description: Field,
+    labels: Field,
+    camera: Field,
+    date_time: Field,
+}
+
+/// Full-text index over the metadata catalog: file names, descriptions, detected objects
+/// and analysis labels, camera model and lens, and date. Kept in memory and rebuilt when the
+/// catalog changes.
+pub struct FullTextIndex {
+    index: Index,
+    reader: IndexReader,
+    fields: Fields,
+    /// Hash of the indexed catalog text, a different hash means the index is stale
+    fingerprint: u64,
+}
+
+/// Hash of the searchable text of the catalog
+pub fn fingerprint(rows: &[CatalogRow]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for row in rows {
+        row.zip_file_name.hash(&mut hasher);
+        row.photo_index_in_zip.hash(&mut hasher);
+        row.description.hash(&mut hasher);
+        row.labels.hash(&mut hasher);
+        row.date_time.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// Values read from EXIF are quoted, the unknown ones are left out of the index
+fn text(value: &str) -> String {
+    let value = value.trim_matches('"');
+    if value == "unknown" {
+        String::new()
+    } else {
+        value.to_owned()
+    }
+}
+
+impl FullTextIndex {
+    pub fn build(rows: &[CatalogRow]) -> Result<Self, PhotoInsightError> {
+        let mut schema = Schema::builder();
+        let fields = Fields {
+            zip_file_name: schema.add_text_field("zip_file_name", STRING | STORED),
+            photo_index_in_zip: schema.add_u64_field("photo_index_in_zip", INDEXED | STORED),
+            photo_file_name: schema.add_text_field("file_name", TEXT | STORED),
+            description: schema.add_text_field("description", TEXT),
+            labels: schema.add_text_field("labels", TEXT),
+            camera: schema.add_text_field("camera", TEXT),
+            date_time: schema.add_text_field("date", TEXT),
+        };
+        let index = Index::create_in_ram(schema.build());
+        let mut writer = index
+            .writer::<TantivyDocument>(WRITER_MEMORY)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        for row in rows {
+            writer
+                .add_document(doc!(
+                    fields.zip_file_name => row.zip_file_name.clone(),
+                    fields.photo_index_in_zip => row.photo_index_in_zip as u64,
+                    fields.photo_file_name => row.photo_file_name.clone(),
+                    fields.description => row.description.clone().unwrap_or_default(),
+                    fields.labels => row.labels.replace(';', " "),
+                    fields.camera => format!("{} {}", text(&row.model), text(&row.lens)),
+                    fields.date_time => text(&row.date_time),
+                ))
+                .map_err(|e| PhotoInsightError::new(e))?;
+        }
+        writer.commit().map_err(|e| PhotoInsightError::new(e))?;
+        let reader = index.reader().map_err(|e| PhotoInsightError::new(e))?;
+        tracing::info!("Built full-text index of {} photos", rows.len());
+        Ok(Self {
+            index,
+            reader,
+            fields,
+            fingerprint: fingerprint(rows),
+        })
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Searches the index ranked by relevance (BM25). The query supports `AND`, `OR`, `-term`,
+    /// `"phrases"` and `field:term` (file_name, description, labels, camera, date). With
+    /// `fuzzy` every term also matches words differing in one character. Returns all matching
+    /// photos with their scores, best first.
+    pub fn search(
+        &self,
+        query: &str,
+        fuzzy: bool,
+    ) -> Result<Vec<(PhotoInfo, f32)>, PhotoInsightError> {
+        let text_fields = [
+            self.fields.photo_file_name,
+            self.fields.description,
+            self.fields.labels,
+            self.fields.camera,
+            self.fields.date_time,
+        ];
+        let mut parser = QueryParser::for_index(&self.index, text_fields.to_vec());
+        parser.set_conjunction_by_default();
+        parser.set_field_boost(self.fields.description, 2.0);
+        parser.set_field_boost(self.fields.labels, 1.5);
+        if fuzzy {
+            for field in text_fields {
+                parser.set_field_fuzzy(field, false, FUZZY_DISTANCE, true);
+            }
+        }
+        let (query, errors) = parser.parse_query_lenient(query);
+        if !errors.is_empty() {
+            tracing::warn!("Full-text query errors: {errors:?}");
+        }
+        let searcher = self.reader.searcher();
+        let total = searcher
+            .search(&query, &Count)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        if total == 0 {
+            return Ok(Vec::new());
+        }
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(total))
+            .map_err(|e| PhotoInsightError::new(e))?;
+        let mut results = Vec::new();
+        for (score, address) in top_docs {
+            let doc: TantivyDocument = searcher
+                .doc(address)
+                .map_err(|e| PhotoInsightError::new(e))?;
+            let zip_file_name = doc
+                .get_first(self.fields.zip_file_name)
+                .and_then(|v| v.as_str().map(str::to_owned));
+            let photo_file_name = doc
+                .get_first(self.fields.photo_file_name)
+                .and_then(|v| v.as_str().map(str::to_owned));
+            let index = doc
+                .get_first(self.fields.photo_index_in_zip)
+                .and_then(|v| v.as_u64());
+            if let (Some(zip_file_name), Some(photo_file_name), Some(index)) =
+                (zip_file_name, photo_file_name, index)
+            {
+                results.push((
+                    PhotoInfo::new(zip_file_name, photo_file_name, index as usize),
+                    score,
+                ));
+            }
+        }
+        Ok(results)
+    }
+}
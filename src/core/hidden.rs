@@ -0,0 +1,64 @@
+use std::{collections::HashSet, path::Path};
+
+use crate::core::{error::PhotoInsightError, image_cache::PhotoInfo, photo_id::PhotoIds, store};
+
+const HIDDEN_FILE: &str = "hidden_photos.json";
+
+/// Photos hidden by the user, persisted in `<image_dir>/hidden_photos.json`. The original
+/// archives are never touched, hidden photos are only excluded from searches. Photos are
+/// stored by their stable ID.
+#[derive(Debug, Clone, Default)]
+pub struct HiddenPhotos {
+    photos: HashSet<PhotoInfo>,
+}
+
+impl HiddenPhotos {
+    pub(crate) fn file_name(image_dir: &str) -> String {
+        Path::new(image_dir)
+            .join(HIDDEN_FILE)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn load(image_dir: &str, ids: &PhotoIds) -> Result<Self, PhotoInsightError> {
+        let file_name = Self::file_name(image_dir);
+        if !store::exists(&file_name) {
+            return Ok(Self::default());
+        }
+        let serialized: Vec<String> = store::read_json(&file_name)?;
+        let photos = serialized
+            .into_iter()
+            .filter_map(|key| ids.resolve(key))
+            .collect::<HashSet<_>>();
+        tracing::info!("Loaded {} hidden photos", photos.len());
+        Ok(Self { photos })
+    }
+
+    pub fn save(&self, image_dir: &str, ids: &PhotoIds) -> Result<(), PhotoInsightError> {
+        let mut serialized = self
+            .photos
+            .iter()
+            .map(|info| ids.key_of(info))
+            .collect::<Vec<String>>();
+        serialized.sort();
+        store::write_json(&Self::file_name(image_dir), &serialized)
+    }
+
+    pub fn contains(&self, photo_info: &PhotoInfo) -> bool {
+        self.photos.contains(photo_info)
+    }
+
+    /// Hides the photo, returns false if it was already hidden
+    pub fn hide(&mut self, photo_info: PhotoInfo) -> bool {
+        self.photos.insert(photo_info)
+    }
+
+    /// Shows the photo again, returns false if it was not hidden
+    pub fn unhide(&mut self, photo_info: &PhotoInfo) -> bool {
+        self.photos.remove(photo_info)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PhotoInfo> {
+        self.photos.iter()
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::core::{cover::CoverCandidate, image_cache::PhotoInfo};
+
+const QUALITY_WEIGHT: f64 = 1.0;
+const FACE_WEIGHT: f64 = 0.5;
+const RATING_WEIGHT: f64 = 1.0;
+const DIVERSITY_WEIGHT: f64 = 0.5;
+const TIME_WEIGHT: f64 = 0.5;
+/// Photos taken closer to an already picked highlight are penalized, one moment is shown once
+const MOMENT_SECONDS: i64 = 10 * 60;
+/// Highest star rating of the rating override
+pub const MAX_RATING: u8 = 5;
+
+/// Photo competing for the highlight reel
+#[derive(Debug, Clone)]
+pub struct HighlightCandidate {
+    pub candidate: CoverCandidate,
+    /// Seconds since 1970-01-01 of the time taken
+    pub timestamp: i64,
+    pub date_time: String,
+    /// Star rating set by photo_set_metadata, 0 rejects the photo
+    pub rating: Option<u8>,
+}
+
+impl HighlightCandidate {
+    fn rating_score(&self) -> f64 {
+        self.rating
+            .map_or(0.0, |r| r.min(MAX_RATING) as f64 / MAX_RATING as f64)
+    }
+
+    fn base_score(&self) -> f64 {
+        QUALITY_WEIGHT * self.candidate.quality()
+            + FACE_WEIGHT * self.candidate.face_score()
+            + RATING_WEIGHT * self.rating_score()
+    }
+}
+
+/// Why a photo was picked, the score is the weighted sum of the parts minus the penalties
+#[derive(Debug, Clone, Serialize)]
+pub struct Justification {
+    /// Resolution from 0 to 1
+    pub quality: f64,
+    /// People in the photo and how well they look, from 0 to 1
+    pub faces: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Share of the labels already shown by higher ranked highlights
+    pub label_overlap: f64,
+    /// Taken within minutes of a higher ranked highlight
+    pub same_moment: bool,
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Highlight {
+    pub rank: usize,
+    pub score: f64,
+    pub date_time: String,
+    pub photo: PhotoInfo,
+    pub justification: Justification,
+}
+
+/// Picks the `count` best photos one by one, each pick penalizes the remaining photos showing
+/// the same things or the same moment so that the reel covers the whole range. Rejected
+/// photos (rating 0) are never picked. Highlights are returned in rank order.
+pub fn highlights(candidates: Vec<HighlightCandidate>, count: usize) -> Vec<Highlight> {
+    let mut remaining = candidates
+        .into_iter()
+        .filter(|c| c.rating != Some(0))
+        .collect::<Vec<_>>();
+    let mut shown: HashSet<String> = HashSet::new();
+    let mut picked_times: Vec<i64> = Vec::new();
+    let mut picked = Vec::new();
+    while picked.len() < count && !remaining.is_empty() {
+        let shown_labels = shown.iter().map(String::as_str).collect::<HashSet<&str>>();
+        let scored = remaining
+            .iter()
+            .map(|c| {
+                let overlap = c.candidate.overlap(&shown_labels);
+                let same_moment = picked_times
+                    .iter()
+                    .any(|t| (c.timestamp - t).abs() < MOMENT_SECONDS);
+                let score = c.base_score()
+                    - DIVERSITY_WEIGHT * overlap
+                    - if same_moment { TIME_WEIGHT } else { 0.0 };
+                (score, overlap, same_moment)
+            })
+            .collect::<Vec<_>>();
+        let Some(best) = (0..remaining.len()).max_by(|a, b| scored[*a].0.total_cmp(&scored[*b].0))
+        else {
+            break;
+        };
+        let (score, label_overlap, same_moment) = scored[best];
+        let candidate = remaining.swap_remove(best);
+        shown.extend(candidate.candidate.labels.iter().cloned());
+        picked_times.push(candidate.timestamp);
+        picked.push(Highlight {
+            rank: picked.len() + 1,
+            score,
+            justification: Justification {
+                quality: candidate.candidate.quality(),
+                faces: candidate.candidate.face_score(),
+                rating: candidate.rating,
+                label_overlap,
+                same_moment,
+                labels: candidate.candidate.labels.clone(),
+            },
+            date_time: candidate.date_time,
+            photo: candidate.candidate.info,
+        });
+    }
+    picked
+}
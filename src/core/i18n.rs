@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::core::config::I18nConfig;
+
+/// German names of the COCO classes detected by the default YOLOv8 models
+const GERMAN: [(&str, &str); 80] = [
+    ("person", "Person"),
+    ("bicycle", "Fahrrad"),
+    ("car", "Auto"),
+    ("motorcycle", "Motorrad"),
+    ("airplane", "Flugzeug"),
+    ("bus", "Bus"),
+    ("train", "Zug"),
+    ("truck", "Lastwagen"),
+    ("boat", "Boot"),
+    ("traffic light", "Ampel"),
+    ("fire hydrant", "Hydrant"),
+    ("stop sign", "Stoppschild"),
+    ("parking meter", "Parkuhr"),
+    ("bench", "Bank"),
+    ("bird", "Vogel"),
+    ("cat", "Katze"),
+    ("dog", "Hund"),
+    ("horse", "Pferd"),
+    ("sheep", "Schaf"),
+    ("cow", "Kuh"),
+    ("elephant", "Elefant"),
+    ("bear", "Bär"),
+    ("zebra", "Zebra"),
+    ("giraffe", "Giraffe"),
+    ("backpack", "Rucksack"),
+    ("umbrella", "Regenschirm"),
+    ("handbag", "Handtasche"),
+    ("tie", "Krawatte"),
+    ("suitcase", "Koffer"),
+    ("frisbee", "Frisbee"),
+    ("skis", "Ski"),
+    ("snowboard", "Snowboard"),
+    ("sports ball", "Ball"),
+    ("kite", "Drachen"),
+    ("baseball bat", "Baseballschläger"),
+    ("baseball glove", "Baseballhandschuh"),
+    ("skateboard", "Skateboard"),
+    ("surfboard", "Surfbrett"),
+    ("tennis racket", "Tennisschläger"),
+    ("bottle", "Flasche"),
+    ("wine glass", "Weinglas"),
+    ("cup", "Tasse"),
+    ("fork", "Gabel"),
+    ("knife", "Messer"),
+    ("spoon", "Löffel"),
+    ("bowl", "Schüssel"),
+    ("banana", "Banane"),
+    ("apple", "Apfel"),
+    ("sandwich", "Sandwich"),
+    ("orange", "Orange"),
+    ("broccoli", "Brokkoli"),
+    ("carrot", "Karotte"),
+    ("hot dog", "Hotdog"),
+    ("pizza", "Pizza"),
+    ("donut", "Donut"),
+    ("cake", "Kuchen"),
+    ("chair", "Stuhl"),
+    ("couch", "Sofa"),
+    ("potted plant", "Topfpflanze"),
+    ("bed", "Bett"),
+    ("dining table", "Esstisch"),
+    ("toilet", "Toilette"),
+    ("tv", "Fernseher"),
+    ("laptop", "Laptop"),
+    ("mouse", "Maus"),
+    ("remote", "Fernbedienung"),
+    ("keyboard", "Tastatur"),
+    ("cell phone", "Handy"),
+    ("microwave", "Mikrowelle"),
+    ("oven", "Backofen"),
+    ("toaster", "Toaster"),
+    ("sink", "Spüle"),
+    ("refrigerator", "Kühlschrank"),
+    ("book", "Buch"),
+    ("clock", "Uhr"),
+    ("vase", "Vase"),
+    ("scissors", "Schere"),
+    ("teddy bear", "Teddybär"),
+    ("hair drier", "Föhn"),
+    ("toothbrush", "Zahnbürste"),
+];
+
+/// Members of tool responses holding a class name or label, either a string, an array of
+/// strings, or an array of objects with the name in `name` or `value`
+const TRANSLATED_MEMBERS: [&str; 6] = [
+    "class_name",
+    "classes",
+    "labels",
+    "objects",
+    "top_labels",
+    "top_subjects",
+];
+
+/// Translation of detected object class names and analysis labels into the response
+/// language. Class names and labels are stored in English, tool responses carry the
+/// translated names and names in tool arguments are translated back.
+#[derive(Debug, Default)]
+pub struct Translations {
+    /// English name (lowercase) => translated name
+    to: HashMap<String, String>,
+    /// Translated name (lowercase) => English name
+    from: HashMap<String, String>,
+}
+
+impl Translations {
+    /// Built-in table of the configured language (German only) extended and overridden by
+    /// the configured translations of the language
+    pub fn from_config(config: &I18nConfig) -> Self {
+        let Some(language) = config.language.as_deref().map(str::to_lowercase) else {
+            return Self::default();
+        };
+        let mut to: HashMap<String, String> = HashMap::new();
+        if language == "de" || language.starts_with("de-") {
+            to.extend(
+                GERMAN
+                    .iter()
+                    .map(|(en, de)| (en.to_string(), de.to_string())),
+            );
+        }
+        let configured = config
+            .translations
+            .iter()
+            .filter(|(table, _)| table.to_lowercase() == language)
+            .flat_map(|(_, table)| table.iter());
+        to.extend(configured.map(|(en, translated)| (en.to_lowercase(), translated.clone())));
+        let from = to
+            .iter()
+            .map(|(en, translated)| (translated.to_lowercase(), en.clone()))
+            .collect();
+        Self { to, from }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.to.is_empty()
+    }
+
+    /// Name in the response language, names without translation are returned unchanged
+    pub fn translate(&self, name: &str) -> String {
+        self.to
+            .get(&name.to_lowercase())
+            .cloned()
+            .unwrap_or_else(|| name.to_owned())
+    }
+
+    /// English name of a translated name (case insensitive), other names are returned
+    /// unchanged
+    pub fn untranslate(&self, name: &str) -> String {
+        self.from
+            .get(&name.to_lowercase())
+            .cloned()
+            .unwrap_or_else(|| name.to_owned())
+    }
+
+    /// Translates class names and labels anywhere in the tool response
+    pub fn translate_response(&self, response: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        match response {
+            Value::Object(members) => {
+                for (key, value) in members.iter_mut() {
+                    if TRANSLATED_MEMBERS.contains(&key.as_str()) {
+                        self.translate_names(value);
+                    } else {
+                        self.translate_response(value);
+                    }
+                }
+            }
+            Value::Array(items) => items
+                .iter_mut()
+                .for_each(|item| self.translate_response(item)),
+            _ => {}
+        }
+    }
+
+    fn translate_names(&self, value: &mut Value) {
+        match value {
+            Value::String(name) => *name = self.translate(name),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.translate_names(item)),
+            Value::Object(members) => {
+                for (key, value) in members.iter_mut() {
+                    match (key.as_str(), value) {
+                        ("name" | "value", Value::String(name)) => *name = self.translate(name),
+                        (_, value) => self.translate_response(value),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::iter;
 
+use serde::Serialize;
+
 use crate::core::error::PhotoInsightError;
 
 pub(crate) fn guess_format(buffer: &[u8]) -> Result<ImageFormat, PhotoInsightError> {
@@ -24,6 +26,243 @@ pub(crate) fn guess_format(buffer: &[u8]) -> Result<ImageFormat, PhotoInsightErr
     ))
 }
 
+/// Crops the bounding box (xmin, ymin, xmax, ymax in pixels, clamped to the image)
+/// and encodes the crop as JPEG
+pub(crate) fn crop_to_jpeg(
+    img: &image::DynamicImage,
+    bbox: (f32, f32, f32, f32),
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let (w, h) = (img.width() as f32, img.height() as f32);
+    let xmin = bbox.0.clamp(0.0, w);
+    let ymin = bbox.1.clamp(0.0, h);
+    let xmax = bbox.2.clamp(xmin, w);
+    let ymax = bbox.3.clamp(ymin, h);
+    if xmax - xmin < 1.0 || ymax - ymin < 1.0 {
+        return Err(PhotoInsightError::from_message(format!(
+            "empty crop {bbox:?} of {w}x{h} image"
+        )));
+    }
+    let crop = img.crop_imm(
+        xmin as u32,
+        ymin as u32,
+        (xmax - xmin) as u32,
+        (ymax - ymin) as u32,
+    );
+    let mut buf = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(crop.to_rgb8())
+        .write_to(&mut buf, image::ImageFormat::Jpeg)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    Ok(buf.into_inner())
+}
+
+/// Sharpness of the image as variance of the Laplacian of its luma, higher is sharper.
+/// Only comparable between images of similar size.
+pub(crate) fn sharpness(data: &[u8]) -> Option<f64> {
+    sharpness_of(&image::load_from_memory(data).ok()?)
+}
+
+pub(crate) fn sharpness_of(img: &image::DynamicImage) -> Option<f64> {
+    let luma = img.to_luma8();
+    let (w, h) = luma.dimensions();
+    if w < 3 || h < 3 {
+        return None;
+    }
+    let px = |x: u32, y: u32| luma.get_pixel(x, y).0[0] as f64;
+    let mut values = Vec::with_capacity(((w - 2) * (h - 2)) as usize);
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            values.push(px(x - 1, y) + px(x + 1, y) + px(x, y - 1) + px(x, y + 1) - 4.0 * px(x, y));
+        }
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    Some(values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64)
+}
+
+/// Tiles the images into a grid of square cells (images are cropped to fill the cell)
+/// and encodes the collage as JPEG, images which cannot be decoded are skipped
+pub(crate) fn collage_to_jpeg(
+    images: &[&[u8]],
+    columns: u32,
+    cell: u32,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let decoded = images
+        .iter()
+        .filter_map(|data| image::load_from_memory(data).ok())
+        .collect::<Vec<_>>();
+    if decoded.is_empty() {
+        return Err(PhotoInsightError::from_message("no images for collage"));
+    }
+    let columns = columns.clamp(1, decoded.len() as u32);
+    let rows = (decoded.len() as u32).div_ceil(columns);
+    let mut collage = image::RgbImage::new(columns * cell, rows * cell);
+    for (i, img) in decoded.iter().enumerate() {
+        let tile = img
+            .resize_to_fill(cell, cell, image::imageops::FilterType::Triangle)
+            .to_rgb8();
+        let (x, y) = (i as u32 % columns, i as u32 / columns);
+        image::imageops::replace(&mut collage, &tile, (x * cell) as i64, (y * cell) as i64);
+    }
+    let mut buf = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(collage)
+        .write_to(&mut buf, image::ImageFormat::Jpeg)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    Ok(buf.into_inner())
+}
+
+/// Difference hash of the image: 64 bits comparing neighbouring pixels of a 9x8 grayscale
+/// thumbnail, similar images differ in few bits
+pub(crate) fn difference_hash(img: &image::DynamicImage) -> u64 {
+    let small = img
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Places the images next to each other scaled to the same height, encoded as JPEG
+pub(crate) fn side_by_side_jpeg(
+    images: &[&image::DynamicImage],
+    height: u32,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let scaled = images
+        .iter()
+        .map(|img| {
+            let width = (img.width() as u64 * height as u64 / img.height().max(1) as u64) as u32;
+            img.resize_exact(width.max(1), height, image::imageops::FilterType::Triangle)
+                .to_rgb8()
+        })
+        .collect::<Vec<_>>();
+    let width = scaled.iter().map(|img| img.width()).sum::<u32>();
+    let mut canvas = image::RgbImage::new(width.max(1), height);
+    let mut x = 0;
+    for img in scaled.iter() {
+        image::imageops::replace(&mut canvas, img, x as i64, 0);
+        x += img.width();
+    }
+    let mut buf = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgb8(canvas)
+        .write_to(&mut buf, image::ImageFormat::Jpeg)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    Ok(buf.into_inner())
+}
+
+/// Transform of a photo preview, applied to a copy of the photo
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transform {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    /// Stretches the luminance so that the darkest and brightest 0.5% of pixels clip
+    AutoContrast,
+    /// Gray world white balance, the average color becomes neutral gray
+    AutoWhiteBalance,
+}
+
+impl Transform {
+    pub const NAMES: [&str; 7] = [
+        "rotate_90",
+        "rotate_180",
+        "rotate_270",
+        "flip_horizontal",
+        "flip_vertical",
+        "auto_contrast",
+        "auto_white_balance",
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, PhotoInsightError> {
+        match name.to_lowercase().as_str() {
+            "rotate_90" => Ok(Transform::Rotate90),
+            "rotate_180" => Ok(Transform::Rotate180),
+            "rotate_270" => Ok(Transform::Rotate270),
+            "flip_horizontal" => Ok(Transform::FlipHorizontal),
+            "flip_vertical" => Ok(Transform::FlipVertical),
+            "auto_contrast" => Ok(Transform::AutoContrast),
+            "auto_white_balance" => Ok(Transform::AutoWhiteBalance),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid transform: {name}, use one of {}",
+                Self::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+/// Applies the transforms in order, rotations are clockwise
+pub(crate) fn transform(img: image::DynamicImage, transforms: &[Transform]) -> image::DynamicImage {
+    transforms
+        .iter()
+        .fold(img, |img, transform| match transform {
+            Transform::Rotate90 => img.rotate90(),
+            Transform::Rotate180 => img.rotate180(),
+            Transform::Rotate270 => img.rotate270(),
+            Transform::FlipHorizontal => img.fliph(),
+            Transform::FlipVertical => img.flipv(),
+            Transform::AutoContrast => auto_contrast(img.to_rgb8()).into(),
+            Transform::AutoWhiteBalance => auto_white_balance(img.to_rgb8()).into(),
+        })
+}
+
+fn auto_contrast(mut img: image::RgbImage) -> image::RgbImage {
+    let mut histogram = [0usize; 256];
+    for p in img.pixels() {
+        let [r, g, b] = p.0;
+        let luma = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round() as usize;
+        histogram[luma.min(255)] += 1;
+    }
+    let clip = img.pixels().len() / 200;
+    let percentile = |levels: Vec<usize>| {
+        let mut count = 0;
+        levels
+            .into_iter()
+            .find(|level| {
+                count += histogram[*level];
+                count > clip
+            })
+            .unwrap_or_default() as f32
+    };
+    let low = percentile((0..256).collect());
+    let high = percentile((0..256).rev().collect());
+    if high - low < 1.0 {
+        return img;
+    }
+    let scale = 255.0 / (high - low);
+    img.pixels_mut().for_each(|p| {
+        p.0 =
+            p.0.map(|c| ((c as f32 - low) * scale).round().clamp(0.0, 255.0) as u8)
+    });
+    img
+}
+
+fn auto_white_balance(mut img: image::RgbImage) -> image::RgbImage {
+    let mut sums = [0f64; 3];
+    for p in img.pixels() {
+        for (sum, c) in sums.iter_mut().zip(p.0) {
+            *sum += c as f64;
+        }
+    }
+    let gray = sums.iter().sum::<f64>() / 3.0;
+    if sums.iter().any(|sum| *sum < 1.0) {
+        return img;
+    }
+    let gains = sums.map(|sum| (gray / sum) as f32);
+    img.pixels_mut().for_each(|p| {
+        for (c, gain) in p.0.iter_mut().zip(gains) {
+            *c = (*c as f32 * gain).round().clamp(0.0, 255.0) as u8;
+        }
+    });
+    img
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ImageFormat {
     /// An Image in PNG Format
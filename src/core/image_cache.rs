@@ -1,28 +1,100 @@
 use serde::{Deserialize, Serialize};
 
-use crate::{
-    IC,
-    core::{
-        error::PhotoInsightError,
-        exif, traversal,
-        yolo::{AnalysisResult, DetectedObject},
-        zip,
+use crate::core::{
+    analyzer::{AnalysisSidecar, Label, labels_of},
+    barcode::{self, BarcodeResult},
+    bmff, bundle,
+    cancel::CancellationToken,
+    catalog::CatalogRow,
+    config::IndexConfig,
+    cover::{CoverCache, CoverCandidate},
+    digest::{DigestPhoto, Event},
+    error::PhotoInsightError,
+    exif,
+    full_text::{self, FullTextIndex, TextSearchResult},
+    hidden::HiddenPhotos,
+    highlights::{self, Highlight, HighlightCandidate},
+    insights::unquote,
+    manifest::{ArchiveFingerprint, ArchiveIndex, ArchiveRegistry, Manifest, RelinkedArchive},
+    motion,
+    name_folding::{FUZZY_MIN_SCORE, NameFolding, fuzzy_score},
+    overrides::{
+        self, MetadataOverride, MetadataOverrides, normalize_date_time, normalize_date_to,
+        parse_date_time, shift_date_time, timestamp_of,
     },
+    photo_id::{PhotoIds, photo_id_of},
+    privacy,
+    query_cache::{self, QueryCache},
+    query_plan::Vocabulary,
+    stages::{
+        face_attributes::{FaceAttributesAnalyzer, FaceSummary},
+        people_count::PeopleCountAnalyzer,
+        weather::WeatherAnalyzer,
+    },
+    store, timings, traversal,
+    warm_up::WarmUpStep,
+    watcher::Watchers,
+    xmp::XmpMetadata,
+    yolo::{AnalysisResult, DetectedObject, ModelSpec},
+    zip::{self, EntryLocation},
 };
 use std::{
-    collections::{HashMap, HashSet},
-    path::Path,
-    time::Instant,
+    collections::{BTreeMap, HashMap, HashSet, hash_map::RandomState},
+    hash::BuildHasher,
+    ops::Deref,
+    sync::{
+        Arc, Mutex, RwLock, RwLockReadGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// Photos taken by the same camera at most this apart belong to the same burst
+const BURST_GAP_SECONDS: i64 = 2;
+/// Detected objects a smart cropped thumbnail is centered on
+const SUBJECT_MIN_CONFIDENCE: f32 = 0.5;
+/// Most common values listed as examples of a searchable tag
+const TAG_EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhotoInfo {
     /// Zip file name in the filesystem
     pub zip_file_name: String,
     /// Image file name inside the zip file
     pub photo_file_name: String,
-    /// Image index inside the zip file, useful for extraction
+    /// Image index inside the zip file, useful for extraction. It changes when the archive is
+    /// recreated, refer to photos by `photo_id` outside of the archive.
     pub photo_index_in_zip: usize,
+    /// SHA-256 of the photo content (lowercase hex), not part of the photo identity
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Stable photo ID derived from the content, see `photo_id::PhotoIds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub photo_id: Option<String>,
+    /// Entry name as stored in the archive when `photo_file_name` differs from it (name
+    /// transcoded to UTF-8 or made unique), bytes other than printable ASCII are
+    /// percent-encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_name: Option<String>,
+}
+
+// The photo is identified by its archive and position, the content hash is informational
+impl PartialEq for PhotoInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.zip_file_name == other.zip_file_name
+            && self.photo_file_name == other.photo_file_name
+            && self.photo_index_in_zip == other.photo_index_in_zip
+    }
+}
+
+impl Eq for PhotoInfo {}
+
+impl std::hash::Hash for PhotoInfo {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.zip_file_name.hash(state);
+        self.photo_file_name.hash(state);
+        self.photo_index_in_zip.hash(state);
+    }
 }
 
 impl PhotoInfo {
@@ -31,17 +103,26 @@ impl PhotoInfo {
             zip_file_name: zip_file,
             photo_file_name: image,
             photo_index_in_zip: index,
+            sha256: None,
+            photo_id: None,
+            original_name: None,
         }
     }
 
-    fn serialize_as_key(&self) -> String {
+    // Fills in the content hash and the photo ID derived from it
+    fn set_hash(&mut self, sha256: Option<String>) {
+        self.photo_id = sha256.as_deref().map(photo_id_of);
+        self.sha256 = sha256;
+    }
+
+    pub(crate) fn serialize_as_key(&self) -> String {
         format!(
             "{}|{}|{}",
             self.zip_file_name, self.photo_file_name, self.photo_index_in_zip
         )
     }
 
-    fn deserialize_from_key(key: String) -> Result<Self, PhotoInsightError> {
+    pub(crate) fn deserialize_from_key(key: String) -> Result<Self, PhotoInsightError> {
         let parts: Vec<&str> = key.split('|').collect();
         if parts.len() == 3 {
             let zip_file = parts[0].to_string();
@@ -58,407 +139,3578 @@ impl PhotoInfo {
     }
 }
 
+/// Photo found by fuzzy name search
+#[derive(Debug, Clone, Serialize)]
+pub struct NameMatch {
+    file: PhotoInfo,
+    /// Similarity of the name and the query, 1 for names containing the query
+    score: f32,
+}
+
+impl PhotoResult for NameMatch {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.file
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ExifResult {
     file: PhotoInfo,
     exif: exif::ExifInfo,
+    /// Maximum recommended print sizes computed from the pixel dimensions
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    print_sizes: Vec<exif::PrintSize>,
+    /// Other copies of the same photo
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    duplicates: Vec<PhotoInfo>,
 }
 
 impl ExifResult {
-    fn new(file: PhotoInfo, exif: exif::ExifInfo) -> Self {
-        Self { file, exif }
+    fn new(file: PhotoInfo, exif: exif::ExifInfo, duplicates: Vec<PhotoInfo>) -> Self {
+        Self {
+            file,
+            print_sizes: exif.print_sizes(),
+            exif,
+            duplicates,
+        }
     }
-}
-
-// year => month => photo_info(s)
-pub type ByYearMonth = HashMap<u32, HashMap<u32, Vec<PhotoInfo>>>;
 
-// photo_info => exif_info
-pub type ExifCache = HashMap<PhotoInfo, exif::ExifInfo>;
-pub type ExifCacheSerialized = HashMap<String, exif::ExifInfo>;
+    pub fn into_parts(self) -> (PhotoInfo, exif::ExifInfo) {
+        (self.file, self.exif)
+    }
+}
 
-// photo_info => object_detecion
-pub type ObjectDetectionCache = HashMap<PhotoInfo, Vec<DetectedObject>>;
+/// Search result of a photo, see `PhotoCache::preview_sample`
+pub trait PhotoResult {
+    fn photo_info(&self) -> &PhotoInfo;
+}
 
-pub struct PhotoCache {
-    image_dir: String,
-    // Map image file name to zip file name
-    pub images: Vec<PhotoInfo>,
-    pub exif_cache: ExifCache,
-    pub by_year_month: ByYearMonth,
-    pub object_detection: Option<ObjectDetectionCache>,
+impl PhotoResult for PhotoInfo {
+    fn photo_info(&self) -> &PhotoInfo {
+        self
+    }
 }
 
-impl PhotoCache {
-    pub fn build(image_dir: &str) -> Result<Self, PhotoInsightError> {
-        let mut exif_cache: ExifCache = HashMap::new();
-        let mut by_year_month: ByYearMonth = HashMap::new();
-        let mut zip_infos = HashSet::new();
-        let zip_files = traversal::list_directory_zip_files(image_dir)?;
-        for zip in &zip_files {
-            let images = zip::list_zip_archive(image_dir, zip)?;
-            tracing::info!("Found zip file: {} with {} images", zip, images.len());
-            for (index, image) in &images {
-                zip_infos.insert(PhotoInfo::new(zip.clone(), image.clone(), *index));
-            }
+impl PhotoResult for &PhotoInfo {
+    fn photo_info(&self) -> &PhotoInfo {
+        self
+    }
+}
 
-            // Extract and cache exif data
-            if !std::path::Path::new(&form_file(image_dir, zip, "exif")).exists() {
-                tracing::info!(
-                    "Exif file does not exists for zip {}, creating  exif data",
-                    zip
-                );
+impl PhotoResult for ExifResult {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.file
+    }
+}
 
-                let extract_exif_raw: HashMap<PhotoInfo, exif::ExifInfo> =
-                    crate::core::exif::extract_all_exifs_from_zip_archive(image_dir, zip)?;
-                let exif_count = extract_exif_raw.len();
-                tracing::info!("Extracted exif from {} images in zip {}", exif_count, zip);
+impl PhotoResult for AnalysisResult {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.photo_info
+    }
+}
 
-                // Convert ZipInfo to String for serialization
-                let extract_exif: ExifCacheSerialized = extract_exif_raw
-                    .into_iter()
-                    .map(|(zip_info, exif)| (zip_info.serialize_as_key(), exif))
-                    .collect();
+impl PhotoResult for LabelResult {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.file
+    }
+}
 
-                serde_json::to_writer_pretty(
-                    std::fs::File::create(form_file(image_dir, zip, "exif"))
-                        .map_err(|e| PhotoInsightError::new(e))?,
-                    &extract_exif,
-                )
-                .map_err(|e| PhotoInsightError::new(e))?;
-            } else {
-                tracing::info!(
-                    "Exif file already exists for zip {}, skipping exif extraction",
-                    zip
-                );
-            }
-            let extract_exif_serialized: ExifCacheSerialized = serde_json::from_reader(
-                std::fs::File::open(form_file(image_dir, zip, "exif"))
-                    .map_err(|e| PhotoInsightError::new(e))?,
-            )
-            .map_err(|e| PhotoInsightError::new(e))?;
+impl PhotoResult for PeopleCountResult {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.file
+    }
+}
 
-            // Convert String back to ZipInfo
-            let extract_exif: ExifCache = extract_exif_serialized
-                .into_iter()
-                .filter_map(|(key, exif)| {
-                    if let Some(photo_info) = PhotoInfo::deserialize_from_key(key).ok() {
-                        Some((photo_info, exif))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+impl PhotoResult for PortraitResult {
+    fn photo_info(&self) -> &PhotoInfo {
+        &self.file
+    }
+}
 
-            // merge extract_exif into exif_cache
-            exif_cache.extend(extract_exif.clone());
+/// Sample of a large result set spread evenly over time, returned instead of the first page
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewSample {
+    /// Number of sampled photos
+    pub sampled: usize,
+    /// Number of all matching photos
+    pub total: usize,
+    /// Time span of the matching photos with known date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_to: Option<String>,
+    /// Matching photos without date, sampled in proportion to their count
+    pub undated: usize,
+}
 
-            // Extract and cache by year month data
-            if !std::path::Path::new(&form_file(image_dir, zip, "by_year_month")).exists() {
-                tracing::info!(
-                    "By year month file does not exists for zip {}, creating by year month data",
-                    zip
-                );
-                let by_year_month: ByYearMonth =
-                    extract_exif
-                        .iter()
-                        .fold(HashMap::new(), |mut acc, (zip_info, exif)| {
-                            let year = exif.year;
-                            let month = exif.month;
-                            acc.entry(year)
-                                .or_insert_with(HashMap::new)
-                                .entry(month)
-                                .or_insert_with(Vec::new)
-                                .push(zip_info.clone());
-                            acc
-                        });
-                serde_json::to_writer_pretty(
-                    std::fs::File::create(form_file(image_dir, zip, "by_year_month"))
-                        .map_err(|e| PhotoInsightError::new(e))?,
-                    &by_year_month,
-                )
-                .map_err(|e| PhotoInsightError::new(e))?;
-            } else {
-                tracing::info!(
-                    "By year month file already exists for zip {}, skipping by year month creation",
-                    zip
-                );
-            }
-            let partial_by_year_month: ByYearMonth = serde_json::from_reader(
-                std::fs::File::open(form_file(image_dir, zip, "by_year_month"))
-                    .map_err(|e| PhotoInsightError::new(e))?,
-            )
-            .map_err(|e| PhotoInsightError::new(e))?;
+/// Time bucket of search results aggregated by `PhotoCache::group_by_time`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeGroup {
+    Day,
+    /// ISO 8601 week starting on Monday
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
 
-            // merge partial_by_year_month into by_year_month
-            for (year, month_map) in partial_by_year_month {
-                let mut updates: Vec<(u32, u32, Vec<PhotoInfo>)> = Vec::new();
-                for (month, infos) in month_map {
-                    updates.push((year, month, infos));
-                }
-                for (year, month, infos) in updates {
-                    by_year_month
-                        .entry(year)
-                        .or_insert_with(HashMap::new)
-                        .entry(month)
-                        .or_insert_with(Vec::new)
-                        .extend(infos);
-                }
-            }
+impl TimeGroup {
+    pub fn parse(group_by: &str) -> Result<Self, PhotoInsightError> {
+        match group_by.to_lowercase().as_str() {
+            "day" => Ok(TimeGroup::Day),
+            "week" => Ok(TimeGroup::Week),
+            "month" => Ok(TimeGroup::Month),
+            "quarter" => Ok(TimeGroup::Quarter),
+            "year" => Ok(TimeGroup::Year),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid group_by: {group_by}, use one of day, week, month, quarter, year"
+            ))),
         }
-        Ok(Self {
-            images: zip_infos.into_iter().collect(),
-            image_dir: image_dir.to_string(),
-            exif_cache,
-            by_year_month,
-            object_detection: None,
-        })
-    }
-
-    // List all images in the cache
-    pub fn list_all_images(&self, offset: usize, limit: usize) -> (Vec<&PhotoInfo>, usize) {
-        let total_images = self.images.len();
-        tracing::info!("Total images in cache: {}", total_images);
-        let start = offset.min(total_images);
-        let end = (offset + limit).min(total_images);
-        tracing::info!("Returning images from {} to {}", start, end);
-        (self.images[start..end].iter().collect(), total_images)
     }
 
-    // Crawl images and perform AI analysis
-    pub fn crawl_and_analyse(&self) {
-        let mut by_zip_archive = HashMap::new();
-        for info in self.images.iter() {
-            by_zip_archive
-                .entry(info.zip_file_name.clone())
-                .or_insert(Vec::new())
-                .push(info);
-        }
-        for (archive, photos) in by_zip_archive.iter() {
-            let result_file_name = form_file(&self.image_dir, &archive, "object_detection");
-            if Path::new(&result_file_name).exists() {
-                tracing::info!("Already found {result_file_name}, skipping creation");
-                continue;
-            }
-            let mut per_archive_object_detection = HashMap::new();
-            tracing::info!("Analysis of  photo archive {archive} to perform object detection");
-            let archive_start = Instant::now();
-            for photo_chunks in photos.chunks(100) {
-                tracing::info!(
-                    "Performing object detecion on  photo chunk with {} items",
-                    photo_chunks.len()
-                );
-                let chunk_start = Instant::now();
-                let r = self.yolo_v8_analysis(photo_chunks.to_vec());
-                let elapsed = chunk_start.elapsed();
-                if let Ok(image_detections) = r {
-                    for image_detection in image_detections {
-                        per_archive_object_detection.insert(
-                            image_detection.photo_info.serialize_as_key(),
-                            image_detection.object_detection,
-                        );
-                    }
-                } else {
-                    tracing::error!("object detection error: {:?}", r.err().unwrap());
-                }
-                tracing::info!("Analysis of chunk finished in {elapsed:?}");
-            }
-            tracing::info!(
-                "Processing of archive {archive} finished in {:?}",
-                archive_start.elapsed()
-            );
-            let writer_attempt = std::fs::File::create(result_file_name);
-            if let Ok(writer) = writer_attempt {
-                if let Err(e) = serde_json::to_writer_pretty(writer, &per_archive_object_detection)
-                {
-                    tracing::error!(
-                        "can't serialize object detection results for {archive} due to error {e:?}"
-                    );
-                }
-            } else {
-                tracing::error!(
-                    "can't serialize object detection results for {archive} due to error {:?}",
-                    writer_attempt.err()
-                );
+    // Bucket of the normalized date time, e.g. 2024-05-17, 2024-W20, 2024-05, 2024-Q2, 2024
+    pub(crate) fn bucket(&self, date_time: &str) -> Option<String> {
+        match self {
+            TimeGroup::Day => date_time.get(..10).map(str::to_owned),
+            TimeGroup::Week => overrides::iso_week(date_time)
+                .ok()
+                .map(|(year, week)| format!("{year:04}-W{week:02}")),
+            TimeGroup::Month => date_time.get(..7).map(str::to_owned),
+            TimeGroup::Quarter => {
+                let month = date_time.get(5..7)?.parse::<u32>().ok()?;
+                Some(format!("{}-Q{}", date_time.get(..4)?, (month + 2) / 3))
             }
+            TimeGroup::Year => date_time.get(..4).map(str::to_owned),
         }
     }
+}
 
-    // Search for image by partial name (case insensitive)
-    // returns vector exif info and thumbnail image data
-    pub fn search_image_by_name(
-        &self,
-        file_name: &String,
-        zip_file_name: &Option<String>,
-        offset: usize,
-        limit: usize,
-    ) -> (Vec<&PhotoInfo>, usize) {
-        let image_name_lower = file_name.to_lowercase();
-        let zip_infos: Vec<&PhotoInfo> = self
-            .images
-            .iter()
-            .filter(|info| {
-                let file_condition = info
-                    .photo_file_name
-                    .to_lowercase()
-                    .contains(&image_name_lower);
-                if let Some(zip_file) = &zip_file_name {
-                    file_condition
-                        && info
-                            .zip_file_name
-                            .to_lowercase()
-                            .contains(&zip_file.to_lowercase())
-                } else {
-                    file_condition
-                }
-            })
-            .collect();
-        let total_found = zip_infos.len();
-        tracing::info!("Found {} matching images", total_found);
-        let start = offset.min(zip_infos.len());
-        let end = (offset + limit).min(zip_infos.len());
-        tracing::info!("Returning images from {} to {}", start, end);
+/// Groups of search results collapsed to one representative by `PhotoCache::collapse`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collapse {
+    /// Photos taken by the same camera in quick succession
+    Bursts,
+    /// Copies of the same photo stored more than once
+    Duplicates,
+}
 
-        (zip_infos[start..end].to_vec(), total_found)
+impl Collapse {
+    pub fn parse(collapse: &str) -> Result<Self, PhotoInsightError> {
+        match collapse.to_lowercase().as_str() {
+            "bursts" => Ok(Collapse::Bursts),
+            "duplicates" => Ok(Collapse::Duplicates),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid collapse: {collapse}, use one of bursts, duplicates"
+            ))),
+        }
     }
+}
 
-    pub fn search_image_by_year_month(
-        &self,
-        year: u32,
-        month: u32,
-        offset: usize,
-        limit: usize,
-    ) -> (Vec<&PhotoInfo>, usize) {
-        let r = IC.by_year_month.get(&year);
-        if r.is_none() {
-            return (Vec::new(), 0);
-        }
-        let month_map = r.unwrap();
-        let r = month_map.get(&month);
-        if r.is_none() {
-            return (Vec::new(), 0);
-        }
+/// Search result, the representative of its group when the results are collapsed
+#[derive(Debug, Clone, Serialize)]
+pub struct Collapsed<T> {
+    #[serde(flatten)]
+    pub result: T,
+    /// Number of results in the burst or duplicate group
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_size: Option<usize>,
+}
 
-        let zip_infos: &Vec<PhotoInfo> = r.unwrap();
-        let total_found = zip_infos.len();
-        tracing::info!("Found {} matching images", total_found);
-        let start = offset.min(zip_infos.len());
-        let end = (offset + limit).min(zip_infos.len());
-        tracing::info!("Returning images from {} to {}", start, end);
+impl<T: PhotoResult> PhotoResult for Collapsed<T> {
+    fn photo_info(&self) -> &PhotoInfo {
+        self.result.photo_info()
+    }
+}
 
-        let slice = zip_infos[start..end].iter().collect::<Vec<&PhotoInfo>>();
+/// Number of matching photos taken in the time bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeBucket {
+    /// Day (2024-05-17), ISO week (2024-W20), month (2024-05) or year (2024), `unknown` for
+    /// photos without date
+    pub bucket: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_to: Option<String>,
+    /// Photo taken in the middle of the bucket
+    pub representative: Option<PhotoInfo>,
+}
 
-        (slice, total_found)
+// Picks `count` items from the middle of equally sized consecutive strata
+fn stratified<T>(items: Vec<T>, count: usize) -> Vec<T> {
+    if count == 0 {
+        return Vec::new();
     }
+    if items.len() <= count {
+        return items;
+    }
+    let len = items.len();
+    let picked = (0..count)
+        .map(|i| (2 * i + 1) * len / (2 * count))
+        .collect::<HashSet<usize>>();
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picked.contains(i))
+        .map(|(_, item)| item)
+        .collect()
+}
 
-    pub fn search_image_by_exif_tags(
-        &self,
-        tag_name: &String,
-        tag_value: &String,
-        operator: &String,
-        offset: usize,
-        limit: usize,
-    ) -> Result<(Vec<ExifResult>, usize), PhotoInsightError> {
-        tracing::info!("search image by EXIF tag : offset: {offset} Limiting results to {limit}");
-        let mut results = Vec::new();
-        IC.exif_cache.iter().for_each(|(zip_info, exif)| {
-            let matched = exif
-                .matches_query(tag_name, tag_value, operator)
-                .map_err(|e| e)
-                .unwrap_or(false);
+/// Label of a photo with its confidence: detected object or label of an analysis stage
+pub trait ScoredLabel {
+    fn label(&self) -> &str;
+    fn confidence(&self) -> f32;
+}
 
-            if matched {
-                results.push(ExifResult::new(zip_info.clone(), exif.clone()));
-            }
-        });
+impl ScoredLabel for DetectedObject {
+    fn label(&self) -> &str {
+        &self.class_name
+    }
 
-        let total_found = results.len();
-        tracing::info!("Found {} matching images", total_found);
-        let start = offset.min(results.len());
-        let end = (offset + limit).min(results.len());
-        tracing::info!("Returning images from {} to {}", start, end);
+    fn confidence(&self) -> f32 {
+        self.confidence
+    }
+}
 
-        let slice = results[start..end].to_vec();
+impl ScoredLabel for Label {
+    fn label(&self) -> &str {
+        &self.name
+    }
 
-        Ok((slice, total_found))
+    fn confidence(&self) -> f32 {
+        self.confidence
     }
+}
 
-    pub fn exif_info(
-        &self,
-        image_infos: Vec<&PhotoInfo>,
-    ) -> Result<Vec<ExifResult>, PhotoInsightError> {
-        let mut exif_infos = Vec::new();
-        for img in image_infos {
-            if let Some(exif) = self.exif_cache.get(img) {
-                exif_infos.push(ExifResult::new(img.clone(), exif.clone()));
-            }
+/// Noise controls shared by the label searches (detected objects, labels and tags of
+/// analysis stages)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LabelFilter {
+    /// Labels with lower confidence are ignored
+    pub min_confidence: f32,
+    /// Only the photo's k most confident distinct labels are searched
+    pub top_k_per_photo: Option<usize>,
+}
+
+impl LabelFilter {
+    pub fn new(min_confidence: Option<f32>, top_k_per_photo: Option<u32>) -> Self {
+        Self {
+            min_confidence: min_confidence.unwrap_or(0.0),
+            top_k_per_photo: top_k_per_photo.map(|k| k as usize),
         }
-        Ok(exif_infos)
     }
 
-    pub fn image_data(
+    /// Labels of a photo passing the filter and matching the searched label
+    pub fn select<T: ScoredLabel + Clone>(
         &self,
-        image_infos: Vec<&PhotoInfo>,
-    ) -> Result<Vec<(PhotoInfo, String, Vec<u8>)>, PhotoInsightError> {
-        let mut arxives = HashMap::new();
-        for info in image_infos {
-            let arxive = info.zip_file_name.clone();
-            let index = info.photo_index_in_zip;
-            arxives.entry(arxive).or_insert_with(Vec::new).push(index);
-        }
-        let mut images = Vec::new();
-        for (zip_file, indices) in arxives {
-            let unpacked = zip::extract_zip_archive(&self.image_dir, &zip_file, indices)?;
-            for (photo_info, image_data) in unpacked {
-                let exif = crate::core::exif::extract_exif_info(&image_data, true);
-                if exif.is_err() {
-                    tracing::warn!(
-                        "Failed to extract exif from image {:?} in zip {}: {}",
-                        photo_info,
-                        zip_file,
-                        exif.err().unwrap()
-                    );
-                    // let mime = mime_from_image(&image_data);
-                    let resized_image = exif::resize(&image_data, 0, 0);
-                    let mime = mime_from_image(&resized_image);
-                    images.push((photo_info, mime, resized_image));
-                } else {
-                    let image_data = exif.unwrap().1;
-                    let mime = mime_from_image(&image_data);
-                    images.push((photo_info, mime, image_data));
+        labels: &[T],
+        matches: impl Fn(&T) -> bool,
+    ) -> Vec<T> {
+        let confident = labels
+            .iter()
+            .filter(|l| l.confidence() >= self.min_confidence)
+            .collect::<Vec<_>>();
+        let top = self.top_k_per_photo.map(|k| {
+            let mut ranked = confident.clone();
+            ranked.sort_by(|a, b| b.confidence().total_cmp(&a.confidence()));
+            let mut top = Vec::new();
+            for label in ranked {
+                if top.len() < k && !top.contains(&label.label()) {
+                    top.push(label.label());
                 }
             }
-        }
-        Ok(images)
+            top
+        });
+        confident
+            .iter()
+            .filter(|l| top.as_ref().is_none_or(|top| top.contains(&l.label())))
+            .filter(|l| matches(l))
+            .map(|l| (*l).clone())
+            .collect()
     }
+}
 
-    pub fn yolo_v8_analysis(
-        &self,
-        image_infos: Vec<&PhotoInfo>,
-    ) -> Result<Vec<AnalysisResult>, PhotoInsightError> {
-        let mut arxives = HashMap::new();
-        for info in image_infos {
-            let arxive = info.zip_file_name.clone();
-            let index = info.photo_index_in_zip;
-            arxives.entry(arxive).or_insert_with(Vec::new).push(index);
-        }
-        let mut analysis_results = Vec::new();
-        for (zip_file, indices) in arxives {
-            let unpacked = zip::extract_zip_archive(&self.image_dir, &zip_file, indices)?;
-            let yolo_results = crate::core::yolo::analyze_images_using_yolo(unpacked)?;
-            analysis_results.extend(yolo_results);
-        }
-        Ok(analysis_results)
-    }
+/// Searchable tag with the values it takes in the collection
+#[derive(Debug, Clone, Serialize)]
+pub struct TagListing {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub tag_type: &'static str,
+    pub allowed_operators: &'static [&'static str],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<&'static str>,
+    /// Photos with a known value of the tag
+    pub photos: usize,
+    /// Most common values with their photo counts
+    pub examples: Vec<TagExample>,
+    /// Lowest and highest value of numeric tags
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<[f64; 2]>,
 }
 
-fn form_file(image_dir: &str, zip_file: &str, suffix: &str) -> String {
-    format!("{}/{}.{}.json", image_dir, zip_file, suffix)
+#[derive(Debug, Clone, Serialize)]
+pub struct TagExample {
+    pub value: String,
+    pub count: usize,
 }
 
-fn mime_from_image(image_data: &Vec<u8>) -> String {
-    match crate::core::image::guess_format(image_data) {
-        Ok(format) => match format {
+impl TagListing {
+    fn new(tag: &exif::SearchTag, photos: usize, counts: HashMap<String, usize>) -> Self {
+        let range = matches!(tag.tag_type, "Integer" | "Float")
+            .then(|| {
+                counts
+                    .keys()
+                    .filter_map(|value| value.parse::<f64>().ok())
+                    .fold(None, |range: Option<[f64; 2]>, v| {
+                        Some(range.map_or([v, v], |[min, max]| [min.min(v), max.max(v)]))
+                    })
+            })
+            .flatten();
+        let mut examples = counts
+            .into_iter()
+            .map(|(value, count)| TagExample { value, count })
+            .collect::<Vec<_>>();
+        examples.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        examples.truncate(TAG_EXAMPLES);
+        Self {
+            name: tag.name,
+            tag_type: tag.tag_type,
+            allowed_operators: tag.operators,
+            values: tag.values,
+            photos,
+            examples,
+            range,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelResult {
+    file: PhotoInfo,
+    stage: String,
+    labels: Vec<Label>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeopleCountResult {
+    file: PhotoInfo,
+    people_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortraitResult {
+    file: PhotoInfo,
+    score: f32,
+    faces: FaceSummary,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageUsage {
+    files: usize,
+    compressed_size: u64,
+    size: u64,
+}
+
+impl StorageUsage {
+    fn add(&mut self, entry: &zip::ZipEntry) {
+        self.files += 1;
+        self.compressed_size += entry.compressed_size;
+        self.size += entry.size;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageReport {
+    total: StorageUsage,
+    by_archive: HashMap<String, StorageUsage>,
+    by_year: HashMap<u32, StorageUsage>,
+    by_media_type: HashMap<String, StorageUsage>,
+}
+
+/// How the representative photo of a calendar month is picked
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Representative {
+    /// First photo taken in the month
+    First,
+    Random,
+    /// Photo with the highest resolution
+    Best,
+    /// Photo picked by quality, faces and diversity from the covers of the preceding months
+    Cover,
+}
+
+impl Representative {
+    pub fn parse(pick: &str) -> Result<Self, PhotoInsightError> {
+        match pick.to_lowercase().as_str() {
+            "first" => Ok(Representative::First),
+            "random" => Ok(Representative::Random),
+            "best" => Ok(Representative::Best),
+            "cover" => Ok(Representative::Cover),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid representative: {pick}, use one of first, random, best, cover"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CalendarMonth {
+    pub year: u32,
+    pub month: u32,
+    pub count: usize,
+    pub representative: Option<PhotoInfo>,
+}
+
+/// Photo of a subject representing one period of its timeline
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEntry {
+    /// Month or quarter, e.g. 2024-05 or 2024-Q2
+    pub period: String,
+    /// Photos of the subject taken in the period
+    pub photo_count: usize,
+    pub date_time: Option<String>,
+    pub photo: PhotoInfo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveCover {
+    pub zip_file_name: String,
+    pub photo_count: usize,
+    pub cover: Option<PhotoInfo>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ArchiveUsage {
+    bytes: usize,
+    last_used: u64,
+}
+
+/// Archives whose EXIF is being read, they are not evicted until the pin is dropped. Archives
+/// no longer read by anyone may be evicted when the last pin is dropped.
+pub struct ExifPin<'a> {
+    cache: &'a PhotoCache,
+    archives: Vec<String>,
+}
+
+impl Drop for ExifPin<'_> {
+    fn drop(&mut self) {
+        let mut released = false;
+        {
+            let mut pins = self.cache.exif_pins.lock().unwrap();
+            for zip in self.archives.iter() {
+                if let Some(count) = pins.get_mut(zip) {
+                    *count -= 1;
+                    if *count == 0 {
+                        pins.remove(zip);
+                        released = true;
+                    }
+                }
+            }
+        }
+        if released {
+            self.cache.evict_exif();
+        }
+    }
+}
+
+/// Read access to the EXIF index, the archives are pinned while it is held
+pub struct ExifRead<'a, T> {
+    // the guard is dropped before the pin
+    guard: RwLockReadGuard<'a, T>,
+    _pin: ExifPin<'a>,
+}
+
+impl<T> Deref for ExifRead<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheUsage {
+    entries: usize,
+    estimated_bytes: usize,
+}
+
+impl CacheUsage {
+    fn of(sizes: impl Iterator<Item = usize>) -> Self {
+        sizes.fold(Self::default(), |mut usage, size| {
+            usage.entries += 1;
+            usage.estimated_bytes += size;
+            usage
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionSummary {
+    pub photos: usize,
+    pub archives: usize,
+    pub first_year: Option<u32>,
+    pub last_year: Option<u32>,
+    /// Analysis stages run by the background crawler
+    pub stages: Vec<String>,
+    /// Archives analyzed by all the stages
+    pub analyzed_archives: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    images: CacheUsage,
+    /// EXIF as read from the archives
+    raw_exif: CacheUsage,
+    /// EXIF with metadata overrides applied
+    exif: CacheUsage,
+    by_year_month: CacheUsage,
+    object_detection: HashMap<String, CacheUsage>,
+    analysis: HashMap<String, CacheUsage>,
+    loaded_archives: usize,
+    unloaded_archives: usize,
+    memory_budget_bytes: Option<usize>,
+    /// Searches with cached results
+    query_cache_entries: usize,
+    /// Time it took to read the manifest at startup, none with lazy EXIF loading
+    manifest_load_ms: Option<f64>,
+    archives: Vec<ArchiveStats>,
+    /// Sidecar kind (exif, by_year_month, sha256 or stage name) => bytes of all archives
+    sidecar_bytes: HashMap<String, u64>,
+    cardinality: IndexCardinality,
+}
+
+/// Load time and size of the index of one archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveStats {
+    archive: String,
+    photos: usize,
+    /// Whether its EXIF is in memory
+    loaded: bool,
+    /// Time the last load of its index took, from the manifest, the sidecars or the archive
+    load_ms: Option<f64>,
+    exif_estimated_bytes: usize,
+    sidecar_bytes: u64,
+}
+
+/// Distinct values of the EXIF fields of the loaded archives, searches over fields with many
+/// values return fewer photos per value
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexCardinality {
+    models: usize,
+    lenses: usize,
+    years: usize,
+    months: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexHealth {
+    indexed_archives: usize,
+    indexed_photos: usize,
+    excluded_archives: Vec<String>,
+    /// Archive => number of image entries left out by the entry patterns
+    excluded_entries: HashMap<String, usize>,
+    patterns: IndexConfig,
+    /// Archives renamed since the last start, their sidecars were re-linked
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    relinked_archives: Vec<RelinkedArchive>,
+    /// Startup warm-up tasks with their timing
+    warm_up: Vec<WarmUpStep>,
+    /// Photos in the Takeout trash and archive folders
+    takeout_folders: HashMap<TakeoutFolder, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeShift {
+    file: PhotoInfo,
+    from: String,
+    to: String,
+}
+
+// year => month => photo_info(s)
+pub type ByYearMonth = HashMap<u32, HashMap<u32, Vec<PhotoInfo>>>;
+
+// photo_info => exif_info
+pub type ExifCache = HashMap<PhotoInfo, exif::ExifInfo>;
+pub type ExifCacheSerialized = HashMap<String, exif::ExifInfo>;
+
+// photo_info => object_detecion
+pub type ObjectDetectionCache = HashMap<PhotoInfo, Vec<DetectedObject>>;
+
+// model id => photo_info => object_detection
+pub type ObjectDetectionByModel = HashMap<String, ObjectDetectionCache>;
+
+// analyzer name => photo_info => analysis result
+pub type AnalysisStore = HashMap<String, HashMap<PhotoInfo, serde_json::Value>>;
+
+pub struct PhotoCache {
+    image_dir: String,
+    // Map image file name to zip file name
+    pub images: Vec<PhotoInfo>,
+    // EXIF and year/month index as read from the archives
+    raw_exif_cache: RwLock<ExifCache>,
+    raw_by_year_month: RwLock<ByYearMonth>,
+    // Archives whose EXIF was not loaded yet (lazy EXIF loading) or was evicted
+    unloaded: RwLock<HashSet<String>>,
+    // Estimated memory and last use of the loaded EXIF per archive
+    exif_usage: RwLock<HashMap<String, ArchiveUsage>>,
+    // Number of reads in progress per archive, pinned archives are not evicted
+    exif_pins: Mutex<HashMap<String, usize>>,
+    // Bytes of loaded EXIF above which archives are evicted, none for no limit
+    exif_budget: Option<usize>,
+    // Milliseconds the last load of the archive index took
+    load_times: RwLock<HashMap<String, f64>>,
+    manifest_load_ms: Option<f64>,
+    tick: AtomicU64,
+    // EXIF and year/month index with metadata overrides applied, use exif_index() and
+    // year_month_index() to access them
+    exif_cache: RwLock<ExifCache>,
+    by_year_month: RwLock<ByYearMonth>,
+    // Taken after the EXIF indexes and never held while EXIF is loaded, which applies them
+    pub overrides: RwLock<MetadataOverrides>,
+    pub hidden: RwLock<HiddenPhotos>,
+    // Live photo still => its motion part
+    pub motion: HashMap<PhotoInfo, PhotoInfo>,
+    // Photo => SHA-256 of its content
+    sha256: HashMap<PhotoInfo, String>,
+    // Photo stored under a different entry name => the name as stored
+    original_names: HashMap<PhotoInfo, String>,
+    // Archive => entry index => position of the entry, photos are read by seeking to it
+    locations: HashMap<String, HashMap<usize, EntryLocation>>,
+    // Stable photo IDs, used as keys of persisted stores
+    pub ids: PhotoIds,
+    // Archives renamed since the last start
+    pub relinked: Vec<RelinkedArchive>,
+    // Copy of a photo stored more than once => canonical photo
+    pub duplicates: HashMap<PhotoInfo, PhotoInfo>,
+    // Canonical photo => its other copies
+    pub alternates: HashMap<PhotoInfo, Vec<PhotoInfo>>,
+    // Photos in the Takeout trash or archive folders
+    pub takeout_folders: HashMap<PhotoInfo, TakeoutFolder>,
+    pub object_detection: RwLock<ObjectDetectionByModel>,
+    pub analysis: RwLock<AnalysisStore>,
+    // Full-text index of the catalog, built on first text search
+    full_text: RwLock<Option<FullTextIndex>>,
+    // Cover photos of archives, months and events
+    covers: RwLock<CoverCache>,
+    // Full results of recent searches, pages are cut from them
+    queries: QueryCache,
+}
+
+impl PhotoCache {
+    pub fn build(image_dir: &str) -> Result<Self, PhotoInsightError> {
+        let mut exif_cache: ExifCache = HashMap::new();
+        let mut by_year_month: ByYearMonth = HashMap::new();
+        let mut zip_infos = HashSet::new();
+        let mut sha256 = HashMap::new();
+        let mut original_names = HashMap::new();
+        let mut locations = HashMap::new();
+        let mut motion = HashMap::new();
+        let mut by_hash: HashMap<String, Vec<PhotoInfo>> = HashMap::new();
+        let zip_files = traversal::list_directory_zip_files(image_dir)?;
+        // sidecars of renamed archives are moved to the new name before indexing
+        let relinked = ArchiveRegistry::track(image_dir, &zip_files);
+        let lazy = crate::CONFIG.cache.lazy_exif;
+        let mut unloaded = HashSet::new();
+        let mut exif_usage = HashMap::new();
+        let mut load_times = HashMap::new();
+        // the manifest holds EXIF of all archives, it is not used with lazy EXIF loading
+        let manifest_start = Instant::now();
+        let mut manifest = if lazy {
+            Manifest::default()
+        } else {
+            Manifest::load(image_dir)
+        };
+        let manifest_load_ms = (!lazy).then(|| millis_since(manifest_start));
+        manifest.relink(&relinked);
+        let mut stale = manifest.archives.len() != zip_files.len() || !relinked.is_empty();
+        let mut archives = HashMap::new();
+        for zip in &zip_files {
+            let archive_start = Instant::now();
+            let (images, zip_motion, hashes, originals) = if lazy {
+                unloaded.insert(zip.clone());
+                (
+                    zip::list_zip_archive(image_dir, zip)?,
+                    zip::list_motion_pairs(image_dir, zip)?,
+                    Self::load_hash_sidecar(image_dir, zip)?,
+                    zip::list_original_names(image_dir, zip)?,
+                )
+            } else {
+                let fingerprint = ArchiveFingerprint::of(image_dir, zip)?;
+                let index = match manifest.take_fresh(zip, fingerprint) {
+                    Some(index) => index,
+                    None => {
+                        tracing::info!("Manifest is stale for zip {zip}, indexing the archive");
+                        stale = true;
+                        Self::index_archive(image_dir, zip, fingerprint)?
+                    }
+                };
+                let bytes = merge_exif(
+                    &mut exif_cache,
+                    &mut by_year_month,
+                    &index.exif,
+                    &index.by_year_month,
+                );
+                exif_usage.insert(
+                    zip.clone(),
+                    ArchiveUsage {
+                        bytes,
+                        last_used: 0,
+                    },
+                );
+                let listing = (
+                    index.images.clone(),
+                    index.motion.clone(),
+                    index.sha256.clone(),
+                    index.original_names.clone(),
+                );
+                locations.insert(zip.clone(), index.locations.clone());
+                archives.insert(zip.clone(), index);
+                listing
+            };
+            load_times.insert(zip.clone(), millis_since(archive_start));
+            tracing::info!("Found zip file: {} with {} images", zip, images.len());
+            for (index, image) in &images {
+                let mut info = PhotoInfo::new(zip.clone(), image.clone(), *index);
+                info.set_hash(hashes.get(index).cloned());
+                if let Some(original) = originals.get(index) {
+                    original_names.insert(info.clone(), original.clone());
+                }
+                if let Some(hash) = &info.sha256 {
+                    by_hash.entry(hash.clone()).or_default().push(info.clone());
+                    sha256.insert(info.clone(), hash.clone());
+                }
+                zip_infos.insert(info);
+            }
+
+            // Pair live photo stills with their motion part
+            let names: HashMap<usize, &String> = images.iter().map(|(i, n)| (*i, n)).collect();
+            for (index, video) in zip_motion {
+                if let Some(name) = names.get(&index) {
+                    motion.insert(
+                        PhotoInfo::new(zip.clone(), (*name).clone(), index),
+                        PhotoInfo::new(zip.clone(), video.name, video.index),
+                    );
+                }
+            }
+        }
+        if stale && !lazy {
+            if let Err(e) = Manifest::new(archives).save(image_dir) {
+                tracing::error!("can't write manifest: {e}");
+            }
+        }
+
+        for still in motion.keys() {
+            if let Some(exif) = exif_cache.get_mut(still) {
+                exif.has_motion = true;
+            }
+        }
+
+        // The same photo stored more than once (e.g. in several Takeout parts) is linked to the copy in
+        // the first archive, the other copies are left out of searches unless asked for
+        let mut duplicates = HashMap::new();
+        let mut alternates = HashMap::new();
+        for mut copies in by_hash.into_values().filter(|copies| copies.len() > 1) {
+            copies.sort_by(|a, b| {
+                (&a.zip_file_name, a.photo_index_in_zip)
+                    .cmp(&(&b.zip_file_name, b.photo_index_in_zip))
+            });
+            let canonical = copies.remove(0);
+            for copy in copies.iter() {
+                duplicates.insert(copy.clone(), canonical.clone());
+            }
+            alternates.insert(canonical, copies);
+        }
+        tracing::info!("Found {} duplicate photos", duplicates.len());
+        let ids = PhotoIds::build(&sha256, &duplicates);
+        let takeout_folders = zip_infos
+            .iter()
+            .filter_map(|info| Some((info.clone(), TakeoutFolder::of(&info.photo_file_name)?)))
+            .collect::<HashMap<_, _>>();
+        tracing::info!(
+            "Found {} photos in Takeout trash or archive",
+            takeout_folders.len()
+        );
+
+        let cache = Self {
+            images: zip_infos.into_iter().collect(),
+            image_dir: image_dir.to_string(),
+            raw_exif_cache: RwLock::new(exif_cache),
+            raw_by_year_month: RwLock::new(by_year_month),
+            unloaded: RwLock::new(unloaded),
+            exif_usage: RwLock::new(exif_usage),
+            exif_pins: Mutex::new(HashMap::new()),
+            exif_budget: crate::CONFIG
+                .cache
+                .memory_budget_mb
+                .map(|mb| mb * 1024 * 1024),
+            load_times: RwLock::new(load_times),
+            manifest_load_ms,
+            tick: AtomicU64::new(0),
+            exif_cache: RwLock::new(HashMap::new()),
+            by_year_month: RwLock::new(HashMap::new()),
+            overrides: RwLock::new(MetadataOverrides::load(image_dir, &ids)?),
+            hidden: RwLock::new(HiddenPhotos::load(image_dir, &ids)?),
+            motion,
+            sha256,
+            original_names,
+            locations,
+            ids,
+            relinked,
+            duplicates,
+            alternates,
+            takeout_folders,
+            object_detection: RwLock::new(HashMap::new()),
+            analysis: RwLock::new(HashMap::new()),
+            full_text: RwLock::new(None),
+            covers: RwLock::new(CoverCache::default()),
+            queries: QueryCache::new(
+                crate::CONFIG.cache.query_cache_entries,
+                Duration::from_secs(crate::CONFIG.cache.query_cache_ttl_secs),
+            ),
+        };
+
+        cache.evict_exif();
+        cache.apply_overrides();
+
+        privacy::delete_disabled_face_data(image_dir, &zip_files);
+
+        // Load results of all registered analyzers
+        for analyzer in crate::ANALYZERS.all() {
+            for zip in &zip_files {
+                if let Some(results) = AnalysisSidecar::load(image_dir, zip, analyzer.as_ref()) {
+                    analyzer.store(&cache, results);
+                }
+            }
+        }
+
+        Ok(cache)
+    }
+
+    // Indexes one archive from its per-zip sidecars, missing sidecars are created
+    fn index_archive(
+        image_dir: &str,
+        zip: &String,
+        fingerprint: ArchiveFingerprint,
+    ) -> Result<ArchiveIndex, PhotoInsightError> {
+        let _span = tracing::info_span!("index_archive", archive = %zip).entered();
+        let images = zip::list_zip_archive(image_dir, zip)?;
+        let motion = zip::list_motion_pairs(image_dir, zip)?;
+        let sha256 = Self::load_hash_sidecar(image_dir, zip)?;
+        let original_names = zip::list_original_names(image_dir, zip)?;
+        let locations = zip::list_entry_locations(image_dir, zip)?;
+        let (exif, by_year_month) = Self::load_exif_sidecars(image_dir, zip)?;
+        Ok(ArchiveIndex {
+            fingerprint,
+            images,
+            sha256,
+            original_names,
+            locations,
+            motion,
+            exif,
+            by_year_month,
+        })
+    }
+
+    // Loads SHA-256 hashes of the photos of the archive, the sidecar is created when missing
+    fn load_hash_sidecar(
+        image_dir: &str,
+        zip: &String,
+    ) -> Result<HashMap<usize, String>, PhotoInsightError> {
+        let file_name = form_file(image_dir, zip, "sha256");
+        if store::exists(&file_name) {
+            return store::read_json(&file_name);
+        }
+        tracing::info!("Hashing photos of zip {}", zip);
+        let hashes = zip::hash_zip_archive(image_dir, zip)?;
+        store::write_json(&file_name, &hashes)?;
+        Ok(hashes)
+    }
+
+    // Loads EXIF and year/month index of the archive from its sidecars, missing sidecars
+    // are created
+    fn load_exif_sidecars(
+        image_dir: &str,
+        zip: &String,
+    ) -> Result<(ExifCacheSerialized, ByYearMonth), PhotoInsightError> {
+        // Extract and cache exif data
+        if !store::exists(&form_file(image_dir, zip, "exif")) {
+            tracing::info!(
+                "Exif file does not exists for zip {}, creating  exif data",
+                zip
+            );
+
+            let extract_exif_raw: HashMap<PhotoInfo, exif::ExifInfo> =
+                crate::core::exif::extract_all_exifs_from_zip_archive(image_dir, zip)?;
+            let exif_count = extract_exif_raw.len();
+            tracing::info!("Extracted exif from {} images in zip {}", exif_count, zip);
+
+            // Convert ZipInfo to String for serialization
+            let extract_exif: ExifCacheSerialized = extract_exif_raw
+                .into_iter()
+                .map(|(zip_info, exif)| (zip_info.serialize_as_key(), exif))
+                .collect();
+
+            store::write_json(&form_file(image_dir, zip, "exif"), &extract_exif)?;
+        } else {
+            tracing::info!(
+                "Exif file already exists for zip {}, skipping exif extraction",
+                zip
+            );
+        }
+        let mut exif: ExifCacheSerialized = store::read_json(&form_file(image_dir, zip, "exif"))?;
+
+        // Extract and cache by year month data
+        if !store::exists(&form_file(image_dir, zip, "by_year_month")) {
+            tracing::info!(
+                "By year month file does not exists for zip {}, creating by year month data",
+                zip
+            );
+            let by_year_month: ByYearMonth =
+                exif.iter().fold(HashMap::new(), |mut acc, (key, exif)| {
+                    if let Ok(zip_info) = PhotoInfo::deserialize_from_key(key.clone()) {
+                        acc.entry(exif.year)
+                            .or_insert_with(HashMap::new)
+                            .entry(exif.month)
+                            .or_insert_with(Vec::new)
+                            .push(zip_info);
+                    }
+                    acc
+                });
+            store::write_json(&form_file(image_dir, zip, "by_year_month"), &by_year_month)?;
+        } else {
+            tracing::info!(
+                "By year month file already exists for zip {}, skipping by year month creation",
+                zip
+            );
+        }
+        let mut by_year_month: ByYearMonth =
+            store::read_json(&form_file(image_dir, zip, "by_year_month"))?;
+        // sidecars hold all photos of the archive, excluded entries are dropped on load
+        let index = &crate::CONFIG.index;
+        exif.retain(|key, _| {
+            PhotoInfo::deserialize_from_key(key.clone())
+                .is_ok_and(|info| !index.entry_excluded(&info.photo_file_name))
+        });
+        by_year_month.values_mut().for_each(|months| {
+            months
+                .values_mut()
+                .for_each(|infos| infos.retain(|info| !index.entry_excluded(&info.photo_file_name)))
+        });
+        date_from_file_names(&mut exif, &mut by_year_month);
+        Ok((exif, by_year_month))
+    }
+
+    // Loads EXIF of archives not loaded yet (lazy EXIF loading), either of the given archive
+    // or of all archives
+    fn load_exif(&self, zip_file_name: Option<&str>) -> ExifPin<'_> {
+        let requested: HashSet<String> = match zip_file_name {
+            Some(zip) => HashSet::from([zip.to_owned()]),
+            None => self
+                .images
+                .iter()
+                .map(|i| i.zip_file_name.clone())
+                .collect(),
+        };
+        self.load_archives_exif(&requested)
+    }
+
+    // Loads EXIF of the requested archives not loaded yet. Least recently used archives not
+    // pinned by a read in progress are evicted when the memory budget is exceeded. The
+    // requested archives stay pinned until the returned pin is dropped.
+    fn load_archives_exif(&self, requested: &HashSet<String>) -> ExifPin<'_> {
+        let tick = self.tick.fetch_add(1, Ordering::Relaxed) + 1;
+        let pin = self.pin_exif(requested);
+        for usage in self
+            .exif_usage
+            .write()
+            .unwrap()
+            .iter_mut()
+            .filter(|(zip, _)| requested.contains(*zip))
+        {
+            usage.1.last_used = tick;
+        }
+        let pending: Vec<String> = self
+            .unloaded
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|zip| requested.contains(*zip))
+            .cloned()
+            .collect();
+        if pending.is_empty() {
+            return pin;
+        }
+        {
+            let mut unloaded = self.unloaded.write().unwrap();
+            let mut exif_cache = self.raw_exif_cache.write().unwrap();
+            let mut by_year_month = self.raw_by_year_month.write().unwrap();
+            let mut exif_usage = self.exif_usage.write().unwrap();
+            for zip in pending {
+                // loaded by another thread meanwhile
+                if !unloaded.remove(&zip) {
+                    continue;
+                }
+                let start = Instant::now();
+                match Self::load_exif_sidecars(&self.image_dir, &zip) {
+                    Ok((exif, partial_by_year_month)) => {
+                        let bytes = merge_exif(
+                            &mut exif_cache,
+                            &mut by_year_month,
+                            &exif,
+                            &partial_by_year_month,
+                        );
+                        exif_usage.insert(
+                            zip.clone(),
+                            ArchiveUsage {
+                                bytes,
+                                last_used: tick,
+                            },
+                        );
+                        tracing::info!("Loaded exif of zip {zip} in {:?}", start.elapsed());
+                        self.load_times
+                            .write()
+                            .unwrap()
+                            .insert(zip.clone(), millis_since(start));
+                    }
+                    Err(e) => tracing::error!("can't load exif of zip {zip}: {e}"),
+                }
+            }
+            for still in self.motion.keys() {
+                if let Some(exif) = exif_cache.get_mut(still) {
+                    exif.has_motion = true;
+                }
+            }
+        }
+        self.apply_overrides();
+        self.evict_exif();
+        pin
+    }
+
+    fn pin_exif(&self, archives: &HashSet<String>) -> ExifPin<'_> {
+        let mut pins = self.exif_pins.lock().unwrap();
+        for zip in archives {
+            *pins.entry(zip.clone()).or_default() += 1;
+        }
+        ExifPin {
+            cache: self,
+            archives: archives.iter().cloned().collect(),
+        }
+    }
+
+    // Drops EXIF of least recently used archives until the loaded EXIF fits into the memory
+    // budget, evicted archives are loaded from their sidecars again on next use. Archives
+    // pinned by reads in progress are kept, so the budget is exceeded while they last.
+    // Nothing is evicted while another read holds the indexes, the next load or released
+    // pin tries again, so eviction never waits for a lock the calling thread may hold.
+    fn evict_exif(&self) {
+        let Some(budget) = self.exif_budget else {
+            return;
+        };
+        if self
+            .exif_usage
+            .read()
+            .unwrap()
+            .values()
+            .map(|u| u.bytes)
+            .sum::<usize>()
+            <= budget
+        {
+            return;
+        }
+        // same lock order as in load_exif and apply_overrides
+        let (
+            Ok(mut unloaded),
+            Ok(mut exif_cache),
+            Ok(mut by_year_month),
+            Ok(mut exif_usage),
+            Ok(mut applied_exif),
+            Ok(mut applied_by_year_month),
+        ) = (
+            self.unloaded.try_write(),
+            self.raw_exif_cache.try_write(),
+            self.raw_by_year_month.try_write(),
+            self.exif_usage.try_write(),
+            self.exif_cache.try_write(),
+            self.by_year_month.try_write(),
+        )
+        else {
+            return;
+        };
+        let mut total: usize = exif_usage.values().map(|u| u.bytes).sum();
+        let pins = self.exif_pins.lock().unwrap();
+        let mut candidates = exif_usage
+            .iter()
+            .filter(|(zip, _)| !pins.contains_key(*zip))
+            .map(|(zip, usage)| (usage.last_used, zip.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        let mut evicted = HashSet::new();
+        for (_, zip) in candidates {
+            if total <= budget {
+                break;
+            }
+            if let Some(usage) = exif_usage.remove(&zip) {
+                total -= usage.bytes;
+            }
+            evicted.insert(zip);
+        }
+        if evicted.is_empty() {
+            return;
+        }
+        tracing::info!("Evicting exif of {} archives: {:?}", evicted.len(), evicted);
+        for (exif_cache, by_year_month) in [
+            (&mut *exif_cache, &mut *by_year_month),
+            (&mut *applied_exif, &mut *applied_by_year_month),
+        ] {
+            exif_cache.retain(|info, _| !evicted.contains(&info.zip_file_name));
+            for by_month in by_year_month.values_mut() {
+                for infos in by_month.values_mut() {
+                    infos.retain(|info| !evicted.contains(&info.zip_file_name));
+                }
+                by_month.retain(|_, infos| !infos.is_empty());
+            }
+            by_year_month.retain(|_, by_month| !by_month.is_empty());
+        }
+        unloaded.extend(evicted);
+    }
+
+    pub fn image_dir(&self) -> &str {
+        &self.image_dir
+    }
+
+    /// Number of entries and estimated memory consumption of the caches
+    pub fn cache_stats(&self) -> CacheStats {
+        let images = CacheUsage::of(self.images.iter().map(photo_info_size));
+        let raw_exif = CacheUsage::of(
+            self.raw_exif_cache
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(info, exif)| photo_info_size(info) + exif_size(exif)),
+        );
+        let exif = CacheUsage::of(
+            self.exif_cache
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(info, exif)| photo_info_size(info) + exif_size(exif)),
+        );
+        let by_year_month = CacheUsage::of(
+            self.by_year_month
+                .read()
+                .unwrap()
+                .values()
+                .flat_map(|by_month| by_month.values())
+                .flatten()
+                .map(photo_info_size),
+        );
+        let object_detection = self
+            .object_detection
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(model, cache)| {
+                let usage = CacheUsage::of(cache.iter().map(|(info, objects)| {
+                    photo_info_size(info)
+                        + objects
+                            .iter()
+                            .map(|o| size_of::<DetectedObject>() + o.class_name.capacity())
+                            .sum::<usize>()
+                }));
+                (model.clone(), usage)
+            })
+            .collect();
+        let analysis = self
+            .analysis
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(stage, results)| {
+                // JSON length is a good enough estimate of the value size
+                let usage = CacheUsage::of(
+                    results
+                        .iter()
+                        .map(|(info, value)| photo_info_size(info) + value.to_string().len()),
+                );
+                (stage.clone(), usage)
+            })
+            .collect();
+        let mut photos_by_archive: HashMap<&str, usize> = HashMap::new();
+        for info in self.images.iter() {
+            *photos_by_archive.entry(&info.zip_file_name).or_default() += 1;
+        }
+        let exif_usage = self.exif_usage.read().unwrap();
+        let load_times = self.load_times.read().unwrap();
+        let mut sidecar_bytes: HashMap<String, u64> = HashMap::new();
+        let mut archives = Vec::new();
+        for zip in traversal::list_directory_zip_files(&self.image_dir).unwrap_or_default() {
+            let mut archive_sidecar_bytes = 0;
+            for name in bundle::archive_sidecars(&self.image_dir, &zip) {
+                let Some(size) = crate::STORE.size(&name) else {
+                    continue;
+                };
+                let kind = name
+                    .strip_prefix(&format!("{}/{zip}.", self.image_dir))
+                    .unwrap_or(&name)
+                    .trim_end_matches(".json")
+                    .to_owned();
+                *sidecar_bytes.entry(kind).or_default() += size;
+                archive_sidecar_bytes += size;
+            }
+            archives.push(ArchiveStats {
+                photos: photos_by_archive.get(zip.as_str()).copied().unwrap_or(0),
+                loaded: exif_usage.contains_key(&zip),
+                load_ms: load_times.get(&zip).copied(),
+                exif_estimated_bytes: exif_usage.get(&zip).map_or(0, |usage| usage.bytes),
+                sidecar_bytes: archive_sidecar_bytes,
+                archive: zip,
+            });
+        }
+        let loaded_archives = exif_usage.len();
+        drop(exif_usage);
+        drop(load_times);
+        CacheStats {
+            images,
+            raw_exif,
+            exif,
+            by_year_month,
+            object_detection,
+            analysis,
+            loaded_archives,
+            unloaded_archives: self.unloaded.read().unwrap().len(),
+            memory_budget_bytes: self.exif_budget,
+            query_cache_entries: self.queries.len(),
+            manifest_load_ms: self.manifest_load_ms,
+            archives,
+            sidecar_bytes,
+            cardinality: self.cardinality(),
+        }
+    }
+
+    fn cardinality(&self) -> IndexCardinality {
+        let exif_cache = self.exif_cache.read().unwrap();
+        let distinct = |field: fn(&exif::ExifInfo) -> String| {
+            exif_cache
+                .values()
+                .map(field)
+                .filter(|value| !value.is_empty() && value != "\"unknown\"")
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        let by_year_month = self.by_year_month.read().unwrap();
+        IndexCardinality {
+            models: distinct(|exif| exif.model.clone()),
+            lenses: distinct(|exif| exif.lens.clone()),
+            years: by_year_month.keys().filter(|year| **year > 0).count(),
+            months: by_year_month
+                .iter()
+                .filter(|(year, _)| **year > 0)
+                .map(|(_, by_month)| by_month.len())
+                .sum(),
+        }
+    }
+
+    /// Size, time span and analysis progress of the collection, EXIF of archives not loaded
+    /// yet (lazy loading) is not loaded for the time span
+    pub fn summary(&self) -> CollectionSummary {
+        let years = self
+            .by_year_month
+            .read()
+            .unwrap()
+            .keys()
+            .copied()
+            .filter(|year| *year > 0)
+            .collect::<Vec<_>>();
+        let archives = self
+            .images
+            .iter()
+            .map(|info| info.zip_file_name.as_str())
+            .collect::<HashSet<_>>();
+        let stages = crate::ANALYZERS.enabled();
+        let analyzed_archives = archives
+            .iter()
+            .filter(|archive| {
+                stages.iter().all(|stage| {
+                    store::exists(&AnalysisSidecar::file_name(
+                        &self.image_dir,
+                        archive,
+                        stage.as_ref(),
+                    ))
+                })
+            })
+            .count();
+        CollectionSummary {
+            photos: self.images.len(),
+            archives: archives.len(),
+            first_year: years.iter().min().copied(),
+            last_year: years.iter().max().copied(),
+            stages: stages.iter().map(|stage| stage.name()).collect(),
+            analyzed_archives,
+        }
+    }
+
+    /// EXIF of all photos with metadata overrides applied
+    pub fn exif_index(&self) -> ExifRead<'_, ExifCache> {
+        let pin = self.load_exif(None);
+        ExifRead {
+            guard: self.exif_cache.read().unwrap(),
+            _pin: pin,
+        }
+    }
+
+    /// EXIF of the photos with metadata overrides applied, only archives of the photos are
+    /// loaded and pinned
+    pub fn exif_of<'a>(
+        &self,
+        infos: impl IntoIterator<Item = &'a PhotoInfo>,
+    ) -> ExifRead<'_, ExifCache> {
+        let archives = infos
+            .into_iter()
+            .map(|info| info.zip_file_name.clone())
+            .collect::<HashSet<_>>();
+        let pin = self.load_archives_exif(&archives);
+        ExifRead {
+            guard: self.exif_cache.read().unwrap(),
+            _pin: pin,
+        }
+    }
+
+    // EXIF of the photos of the archive, only the archive is loaded and pinned
+    fn archive_exif(&self, zip_file_name: &str) -> ExifRead<'_, ExifCache> {
+        let pin = self.load_exif(Some(zip_file_name));
+        ExifRead {
+            guard: self.exif_cache.read().unwrap(),
+            _pin: pin,
+        }
+    }
+
+    /// Visits all photos with their EXIF (metadata overrides applied) archive by archive,
+    /// only the archive being visited is pinned, so that scans of the whole collection keep
+    /// within the memory budget
+    pub fn for_each_exif(&self, mut f: impl FnMut(&PhotoInfo, Option<&exif::ExifInfo>)) {
+        let mut archives: BTreeMap<&str, Vec<&PhotoInfo>> = BTreeMap::new();
+        for info in self.images.iter() {
+            archives.entry(&info.zip_file_name).or_default().push(info);
+        }
+        for infos in archives.into_values() {
+            let exif_cache = self.exif_of(infos.iter().copied());
+            for info in infos {
+                f(info, exif_cache.get(info));
+            }
+        }
+    }
+
+    /// Year/month index of all photos with metadata overrides applied
+    pub fn year_month_index(&self) -> ExifRead<'_, ByYearMonth> {
+        let pin = self.load_exif(None);
+        ExifRead {
+            guard: self.by_year_month.read().unwrap(),
+            _pin: pin,
+        }
+    }
+
+    /// Photo is in the Takeout trash
+    pub fn in_trash(&self, photo_info: &PhotoInfo) -> bool {
+        self.takeout_folders.get(photo_info) == Some(&TakeoutFolder::Trash)
+    }
+
+    // Loads EXIF of the first `count` archives in name order, returns the archives
+    pub fn preload_archives(&self, count: usize) -> Vec<String> {
+        let mut archives = self
+            .images
+            .iter()
+            .map(|info| info.zip_file_name.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<String>>();
+        archives.sort();
+        archives.truncate(count);
+        for zip_file in archives.iter() {
+            self.load_exif(Some(zip_file));
+        }
+        archives
+    }
+
+    // List all images in the cache
+    pub fn list_all_images(
+        &self,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> (Vec<&PhotoInfo>, usize) {
+        let hidden = self.hidden.read().unwrap();
+        let images = self
+            .images
+            .iter()
+            .filter(|info| include_hidden || !hidden.contains(info))
+            .filter(|info| include_duplicates || !self.duplicates.contains_key(info))
+            .filter(|info| include_trash || !self.in_trash(info))
+            .collect::<Vec<&PhotoInfo>>();
+        let total_images = images.len();
+        tracing::info!("Total images in cache: {}", total_images);
+        let start = offset.min(total_images);
+        let end = (offset + limit).min(total_images);
+        tracing::info!("Returning images from {} to {}", start, end);
+        (images[start..end].to_vec(), total_images)
+    }
+
+    // Stratified sample of the results for preview of large result sets: `count` results
+    // spread evenly over the time taken, in time order. Results are returned as they are
+    // when preview is off.
+    pub fn preview_sample<T: PhotoResult>(
+        &self,
+        results: Vec<T>,
+        count: usize,
+        preview: bool,
+    ) -> (Vec<T>, Option<PreviewSample>) {
+        if !preview {
+            return (results, None);
+        }
+        let total = results.len();
+        let exif_cache = self.exif_of(results.iter().map(PhotoResult::photo_info));
+        let (mut dated, undated): (Vec<_>, Vec<_>) = results
+            .into_iter()
+            .map(|result| {
+                let date_time = exif_cache
+                    .get(result.photo_info())
+                    .filter(|exif| exif.year > 0)
+                    .map(|exif| unquote(&exif.date_time).to_owned());
+                let timestamp = date_time.as_deref().and_then(|d| timestamp_of(d).ok());
+                (timestamp, date_time, result)
+            })
+            .partition(|(timestamp, _, _)| timestamp.is_some());
+        drop(exif_cache);
+        dated.sort_by_key(|(timestamp, _, _)| *timestamp);
+        let date_from = dated.first().and_then(|(_, d, _)| d.clone());
+        let date_to = dated.last().and_then(|(_, d, _)| d.clone());
+        let undated_count = undated.len();
+        let undated_share = if total == 0 {
+            0
+        } else {
+            (count * undated_count).div_ceil(total)
+        };
+        let mut sample = stratified(dated, count.saturating_sub(undated_share.min(count)));
+        sample.extend(stratified(undated, count.saturating_sub(sample.len())));
+        let sample = sample
+            .into_iter()
+            .map(|(_, _, result)| result)
+            .collect::<Vec<T>>();
+        let info = PreviewSample {
+            sampled: sample.len(),
+            total,
+            date_from,
+            date_to,
+            undated: undated_count,
+        };
+        (sample, Some(info))
+    }
+
+    // Collapses bursts or copies of the same photo in the results to their first result with
+    // the group size, and takes the page at offset of the groups. Returns the page and number
+    // of groups, results are returned as they are when collapse is off.
+    pub fn collapse<T: PhotoResult>(
+        &self,
+        results: Vec<T>,
+        total: usize,
+        collapse: Option<Collapse>,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<Collapsed<T>>, usize) {
+        let Some(collapse) = collapse else {
+            let results = results
+                .into_iter()
+                .map(|result| Collapsed {
+                    result,
+                    group_size: None,
+                })
+                .collect();
+            return (results, total);
+        };
+        let group_of = match collapse {
+            Collapse::Bursts => self.bursts(&results),
+            Collapse::Duplicates => {
+                let mut groups = HashMap::new();
+                results
+                    .iter()
+                    .map(|result| {
+                        let info = result.photo_info();
+                        let canonical = self.duplicates.get(info).unwrap_or(info);
+                        let next = groups.len();
+                        *groups.entry(canonical.clone()).or_insert(next)
+                    })
+                    .collect::<Vec<usize>>()
+            }
+        };
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for group in group_of.iter() {
+            *sizes.entry(*group).or_insert(0) += 1;
+        }
+        let mut seen = HashSet::new();
+        let representatives = results
+            .into_iter()
+            .zip(group_of)
+            .filter(|(_, group)| seen.insert(*group))
+            .map(|(result, group)| Collapsed {
+                result,
+                group_size: sizes.get(&group).copied(),
+            })
+            .collect::<Vec<_>>();
+        let total = representatives.len();
+        let page = representatives
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect();
+        (page, total)
+    }
+
+    // Burst of every result: results taken by the same camera at most 2 seconds after the
+    // previous one belong to its burst, results without date are bursts of their own
+    fn bursts<T: PhotoResult>(&self, results: &[T]) -> Vec<usize> {
+        let exif_cache = self.exif_of(results.iter().map(PhotoResult::photo_info));
+        let mut dated = results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, result)| {
+                let exif = exif_cache
+                    .get(result.photo_info())
+                    .filter(|exif| exif.year > 0)?;
+                let timestamp = timestamp_of(unquote(&exif.date_time)).ok()?;
+                Some((unquote(&exif.model).to_owned(), timestamp, i))
+            })
+            .collect::<Vec<_>>();
+        dated.sort();
+        let mut group_of = (0..results.len()).collect::<Vec<usize>>();
+        for pair in dated.windows(2) {
+            let ((model, previous, first), (next_model, timestamp, i)) = (&pair[0], &pair[1]);
+            if model == next_model && timestamp - previous <= BURST_GAP_SECONDS {
+                group_of[*i] = group_of[*first];
+            }
+        }
+        group_of
+    }
+
+    /// Meta of an image returned by a tool: the photo (`name`, kept for older clients), its ID
+    /// and archive position, capture date, dimensions and counts of detected objects, so
+    /// clients can correlate images with other results without extra calls
+    pub fn image_meta(
+        &self,
+        photo_info: &PhotoInfo,
+        image_data: &[u8],
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let exif = self.exif_of([photo_info]).get(photo_info).cloned();
+        let (width, height) = exif
+            .as_ref()
+            .filter(|exif| exif.width > 0 && exif.height > 0)
+            .map(|exif| (exif.width, exif.height))
+            .or_else(|| {
+                ::image::ImageReader::new(std::io::Cursor::new(image_data))
+                    .with_guessed_format()
+                    .ok()?
+                    .into_dimensions()
+                    .ok()
+            })
+            .unzip();
+        let date_time = exif
+            .filter(|exif| exif.year > 0)
+            .map(|exif| unquote(&exif.date_time).to_owned());
+        let mut objects: HashMap<String, usize> = HashMap::new();
+        for detections in self.object_detection.read().unwrap().values() {
+            for object in detections.get(photo_info).into_iter().flatten() {
+                *objects.entry(object.class_name.clone()).or_default() += 1;
+            }
+        }
+        let meta = serde_json::json!({
+            "name": self.with_hash(photo_info),
+            "photo_id": self.ids.id_of(photo_info),
+            "zip_file_name": photo_info.zip_file_name,
+            "photo_index_in_zip": photo_info.photo_index_in_zip,
+            "date_time": date_time,
+            "width": width,
+            "height": height,
+            "objects": objects,
+            "takeout_folder": self.takeout_folders.get(photo_info),
+        });
+        meta.as_object().cloned().unwrap_or_default()
+    }
+
+    /// Normalized time the photo was taken, `None` when the EXIF data has no date
+    pub fn date_taken(&self, photo_info: &PhotoInfo) -> Option<String> {
+        self.exif_of([photo_info])
+            .get(photo_info)
+            .filter(|exif| exif.year > 0)
+            .and_then(|exif| normalize_date_time(unquote(&exif.date_time)).ok())
+    }
+
+    // Counts the results per time bucket, buckets are in time order followed by the bucket of
+    // photos without date
+    pub fn group_by_time<T: PhotoResult>(
+        &self,
+        results: &[T],
+        group: TimeGroup,
+    ) -> Vec<TimeBucket> {
+        let exif_cache = self.exif_of(results.iter().map(PhotoResult::photo_info));
+        let mut dated = Vec::new();
+        let mut undated = Vec::new();
+        for result in results {
+            let info = result.photo_info();
+            let date_time = exif_cache
+                .get(info)
+                .filter(|exif| exif.year > 0)
+                .and_then(|exif| normalize_date_time(unquote(&exif.date_time)).ok());
+            match date_time {
+                Some(date_time) => dated.push((date_time, info)),
+                None => undated.push(info),
+            }
+        }
+        drop(exif_cache);
+        dated.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut buckets: Vec<(String, Vec<(String, &PhotoInfo)>)> = Vec::new();
+        for (date_time, info) in dated {
+            let Some(bucket) = group.bucket(&date_time) else {
+                undated.push(info);
+                continue;
+            };
+            match buckets.last_mut() {
+                Some((last, photos)) if *last == bucket => photos.push((date_time, info)),
+                _ => buckets.push((bucket, vec![(date_time, info)])),
+            }
+        }
+        let mut grouped = buckets
+            .into_iter()
+            .map(|(bucket, photos)| TimeBucket {
+                bucket,
+                count: photos.len(),
+                date_from: photos.first().map(|(d, _)| d.clone()),
+                date_to: photos.last().map(|(d, _)| d.clone()),
+                representative: Some(photos[photos.len() / 2].1.clone()),
+            })
+            .collect::<Vec<_>>();
+        if !undated.is_empty() {
+            grouped.push(TimeBucket {
+                bucket: "unknown".to_owned(),
+                count: undated.len(),
+                date_from: None,
+                date_to: None,
+                representative: undated.first().map(|info| (*info).clone()),
+            });
+        }
+        grouped
+    }
+
+    // Rebuilds EXIF cache and year/month index from the original EXIF and metadata overrides
+    fn apply_overrides(&self) {
+        let overrides = self.overrides.read().unwrap();
+        let raw_exif_cache = self.raw_exif_cache.read().unwrap();
+        let mut exif_cache = raw_exif_cache.clone();
+        let mut by_year_month = self.raw_by_year_month.read().unwrap().clone();
+        for (photo_info, value) in overrides.iter() {
+            let original = raw_exif_cache.get(photo_info);
+            let exif = value.apply(original.cloned().unwrap_or_else(exif::ExifInfo::unknown));
+            if value.date_time.is_some() {
+                if let Some(original) = original {
+                    if let Some(infos) = by_year_month
+                        .get_mut(&original.year)
+                        .and_then(|by_month| by_month.get_mut(&original.month))
+                    {
+                        infos.retain(|info| info != photo_info);
+                    }
+                }
+                by_year_month
+                    .entry(exif.year)
+                    .or_insert_with(HashMap::new)
+                    .entry(exif.month)
+                    .or_insert_with(Vec::new)
+                    .push(photo_info.clone());
+            }
+            exif_cache.insert(photo_info.clone(), exif);
+        }
+        // drop months and years emptied by moved photos
+        for by_month in by_year_month.values_mut() {
+            by_month.retain(|_, infos| !infos.is_empty());
+        }
+        by_year_month.retain(|_, by_month| !by_month.is_empty());
+
+        *self.exif_cache.write().unwrap() = exif_cache;
+        *self.by_year_month.write().unwrap() = by_year_month;
+        self.queries.invalidate();
+    }
+
+    // Finds the photo by its zip archive and file name (exact match)
+    pub fn find_photo(
+        &self,
+        zip_file_name: &str,
+        file_name: &str,
+    ) -> Result<PhotoInfo, PhotoInsightError> {
+        self.images
+            .iter()
+            .find(|info| info.zip_file_name == zip_file_name && info.photo_file_name == file_name)
+            .cloned()
+            .ok_or_else(|| {
+                PhotoInsightError::from_message(format!(
+                    "Photo {file_name} not found in {zip_file_name}"
+                ))
+            })
+    }
+
+    // Photos by photo ID or archive position key (`zip|file name|index`), in the given order
+    pub fn resolve_photos(&self, keys: &[String]) -> Result<Vec<PhotoInfo>, PhotoInsightError> {
+        keys.iter()
+            .map(|key| {
+                self.ids
+                    .resolve(key.clone())
+                    .filter(|info| self.ids.id_of(info).is_some() || self.images.contains(info))
+                    .map(|info| self.with_hash(&info))
+                    .ok_or_else(|| {
+                        PhotoInsightError::from_message(format!("Photo {key} not found"))
+                    })
+            })
+            .collect()
+    }
+
+    // Stores metadata correction of the photo (or removes all its corrections) and applies it
+    // everywhere, returns the resulting EXIF info
+    pub fn set_metadata(
+        &self,
+        photo_info: &PhotoInfo,
+        value: Option<MetadataOverride>,
+    ) -> Result<ExifResult, PhotoInsightError> {
+        {
+            let mut overrides = self.overrides.write().unwrap();
+            match value {
+                Some(value) => overrides.set(photo_info.clone(), value),
+                None => {
+                    overrides.clear(photo_info);
+                }
+            }
+            overrides.save(&self.image_dir, &self.ids)?;
+        }
+        let _pin = self.load_exif(Some(&photo_info.zip_file_name));
+        self.apply_overrides();
+        let exif = self
+            .exif_cache
+            .read()
+            .unwrap()
+            .get(photo_info)
+            .cloned()
+            .unwrap_or_else(exif::ExifInfo::unknown);
+        Ok(ExifResult::new(
+            self.with_hash(photo_info),
+            exif,
+            self.copies_of(photo_info),
+        ))
+    }
+
+    // Shifts date time of all photos taken with matching camera model (case insensitive, partial
+    // match) in the zip archive and date range by the given number of seconds. The shift is
+    // recorded in the metadata overrides, nothing is stored when dry_run is set
+    pub fn shift_time(
+        &self,
+        camera_model: &Option<String>,
+        zip_file_name: &Option<String>,
+        date_from: &Option<String>,
+        date_to: &Option<String>,
+        seconds: i64,
+        dry_run: bool,
+    ) -> Result<Vec<TimeShift>, PhotoInsightError> {
+        let date_from = date_from.as_deref().map(normalize_date_time).transpose()?;
+        let date_to = date_to.as_deref().map(normalize_date_to).transpose()?;
+        let mut shifts = Vec::new();
+        let mut shifted = Ok(());
+        self.for_each_exif(|photo_info, exif| {
+            let Some(exif) = exif.filter(|_| shifted.is_ok()) else {
+                return;
+            };
+            let Ok(from) = normalize_date_time(exif.date_time.trim_matches('"')) else {
+                return;
+            };
+            let matched = camera_model
+                .as_ref()
+                .is_none_or(|m| exif.model.to_lowercase().contains(&m.to_lowercase()))
+                && zip_file_name.as_ref().is_none_or(|z| {
+                    photo_info
+                        .zip_file_name
+                        .to_lowercase()
+                        .contains(&z.to_lowercase())
+                })
+                && date_from.as_ref().is_none_or(|d| from >= *d)
+                && date_to.as_ref().is_none_or(|d| from <= *d);
+            if matched {
+                match shift_date_time(&from, seconds) {
+                    Ok(to) => shifts.push(TimeShift {
+                        file: photo_info.clone(),
+                        from,
+                        to,
+                    }),
+                    Err(e) => shifted = Err(e),
+                }
+            }
+        });
+        shifted?;
+        shifts.sort_by(|a, b| a.file.serialize_as_key().cmp(&b.file.serialize_as_key()));
+        if !dry_run && !shifts.is_empty() {
+            {
+                let mut overrides = self.overrides.write().unwrap();
+                for shift in shifts.iter() {
+                    overrides.set(
+                        shift.file.clone(),
+                        MetadataOverride {
+                            date_time: Some(shift.to.clone()),
+                            ..Default::default()
+                        },
+                    );
+                }
+                overrides.save(&self.image_dir, &self.ids)?;
+            }
+            self.apply_overrides();
+        }
+        Ok(shifts)
+    }
+
+    // Hides the photos (or shows them again), returns number of photos changed
+    pub fn hide_photos(
+        &self,
+        photo_infos: Vec<PhotoInfo>,
+        hide: bool,
+    ) -> Result<usize, PhotoInsightError> {
+        let mut hidden = self.hidden.write().unwrap();
+        let mut changed = 0;
+        for photo_info in photo_infos {
+            let updated = if hide {
+                hidden.hide(photo_info)
+            } else {
+                hidden.unhide(&photo_info)
+            };
+            if updated {
+                changed += 1;
+            }
+        }
+        hidden.save(&self.image_dir, &self.ids)?;
+        self.queries.invalidate();
+        Ok(changed)
+    }
+
+    pub fn list_hidden(&self, offset: usize, limit: usize) -> (Vec<PhotoInfo>, usize) {
+        let mut photos = self
+            .hidden
+            .read()
+            .unwrap()
+            .iter()
+            .map(|info| self.with_hash(info))
+            .collect::<Vec<PhotoInfo>>();
+        photos.sort_by_key(|info| info.serialize_as_key());
+        let total_found = photos.len();
+        let start = offset.min(total_found);
+        let end = (offset + limit).min(total_found);
+        tracing::info!("Returning hidden images from {} to {}", start, end);
+        (photos.drain(start..end).collect(), total_found)
+    }
+
+    // Crawl images and perform AI analysis
+    pub fn crawl_and_analyse(&self) {
+        let stages = crate::ANALYZERS.enabled();
+        let watchers = Watchers::new(&crate::CONFIG.watchers);
+        let mut by_zip_archive = HashMap::new();
+        for info in self.images.iter() {
+            by_zip_archive
+                .entry(info.zip_file_name.clone())
+                .or_insert(Vec::new())
+                .push(info);
+        }
+        for (archive, photos) in by_zip_archive.iter() {
+            let _span = tracing::info_span!("crawl", archive = %archive).entered();
+            let pending = stages
+                .iter()
+                .filter(|stage| {
+                    if stage.processes_faces() && crate::CONFIG.privacy.faces_disabled(archive) {
+                        tracing::info!("Face processing of {archive} is disabled");
+                        return false;
+                    }
+                    let file_name =
+                        AnalysisSidecar::file_name(&self.image_dir, archive, stage.as_ref());
+                    if store::exists(&file_name) {
+                        tracing::info!("Already found {file_name}, skipping creation");
+                        false
+                    } else {
+                        true
+                    }
+                })
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                continue;
+            }
+            let mut per_archive_results: HashMap<String, HashMap<String, serde_json::Value>> =
+                HashMap::new();
+            // stages which missed a chunk, their sidecars are not written so that the next
+            // crawl analyses the archive again
+            let mut incomplete = HashSet::new();
+            tracing::info!(
+                "Analysis of  photo archive {archive} using {:?}",
+                pending.iter().map(|s| s.name()).collect::<Vec<_>>()
+            );
+            let archive_start = Instant::now();
+            for photo_chunks in photos.chunks(crate::CONFIG.analysis.chunk_size.max(1)) {
+                tracing::info!(
+                    "Performing analysis on  photo chunk with {} items",
+                    photo_chunks.len()
+                );
+                let unpacked = match self.extract_photos(photo_chunks.to_vec()) {
+                    Ok(unpacked) => unpacked,
+                    Err(e) => {
+                        tracing::error!("photo extraction error: {e:?}");
+                        incomplete.extend(pending.iter().map(|stage| stage.name()));
+                        continue;
+                    }
+                };
+                for stage in pending.iter() {
+                    let chunk_start = Instant::now();
+                    let r = stage.analyze_batch(&unpacked);
+                    let elapsed = chunk_start.elapsed();
+                    match r {
+                        Ok(results) => {
+                            watchers.check(&stage.name(), &results);
+                            let stage_results =
+                                per_archive_results.entry(stage.name()).or_default();
+                            for (photo_info, value) in results.iter() {
+                                stage_results.insert(photo_info.serialize_as_key(), value.clone());
+                            }
+                            // make results visible to later stages of the same chunk
+                            stage.store(self, results.into_iter().collect());
+                            self.queries.invalidate();
+                        }
+                        Err(e) => {
+                            tracing::error!("{} analysis error: {e:?}", stage.name());
+                            incomplete.insert(stage.name());
+                        }
+                    }
+                    tracing::info!(
+                        "Analysis of chunk by {} finished in {elapsed:?}",
+                        stage.name()
+                    );
+                }
+            }
+            tracing::info!(
+                "Processing of archive {archive} finished in {:?}",
+                archive_start.elapsed()
+            );
+            for stage in pending.iter() {
+                if incomplete.contains(&stage.name()) {
+                    tracing::warn!(
+                        "{} results of {archive} are incomplete, the next crawl analyses it again",
+                        stage.name()
+                    );
+                    continue;
+                }
+                let results = per_archive_results
+                    .remove(&stage.name())
+                    .unwrap_or_default();
+                let sidecar = AnalysisSidecar {
+                    analyzer: stage.info(),
+                    results,
+                };
+                let file_name =
+                    AnalysisSidecar::file_name(&self.image_dir, archive, stage.as_ref());
+                if let Err(e) = sidecar.write(&file_name) {
+                    tracing::error!(
+                        "can't serialize {} results for {archive} due to error {e:?}",
+                        stage.name()
+                    );
+                }
+            }
+        }
+    }
+
+    // Search for image by partial name (case insensitive, see NameFolding)
+    // returns vector exif info and thumbnail image data
+    pub fn search_image_by_name(
+        &self,
+        file_name: &String,
+        zip_file_name: &Option<String>,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+        folding: &NameFolding,
+    ) -> (Vec<&PhotoInfo>, usize) {
+        let hidden = self.hidden.read().unwrap();
+        let image_name_folded = folding.fold(file_name);
+        let zip_name_folded = zip_file_name.as_deref().map(|zip| folding.fold(zip));
+        let zip_infos: Vec<&PhotoInfo> = self
+            .images
+            .iter()
+            .filter(|info| include_hidden || !hidden.contains(info))
+            .filter(|info| include_duplicates || !self.duplicates.contains_key(info))
+            .filter(|info| include_trash || !self.in_trash(info))
+            .filter(|info| {
+                let file_condition = folding
+                    .fold(&info.photo_file_name)
+                    .contains(&image_name_folded);
+                if let Some(zip_file) = &zip_name_folded {
+                    file_condition && folding.fold(&info.zip_file_name).contains(zip_file)
+                } else {
+                    file_condition
+                }
+            })
+            .collect();
+        let total_found = zip_infos.len();
+        tracing::info!("Found {} matching images", total_found);
+        let start = offset.min(zip_infos.len());
+        let end = (offset + limit).min(zip_infos.len());
+        tracing::info!("Returning images from {} to {}", start, end);
+
+        (zip_infos[start..end].to_vec(), total_found)
+    }
+
+    // Search for image by name with typos tolerated, best matches first
+    pub fn search_image_by_name_fuzzy(
+        &self,
+        file_name: &str,
+        zip_file_name: &Option<String>,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+        folding: &NameFolding,
+    ) -> (Vec<NameMatch>, usize) {
+        let hidden = self.hidden.read().unwrap();
+        let query = folding.fold(file_name);
+        let zip_name_folded = zip_file_name.as_deref().map(|zip| folding.fold(zip));
+        let mut matches: Vec<NameMatch> = self
+            .images
+            .iter()
+            .filter(|info| include_hidden || !hidden.contains(info))
+            .filter(|info| include_duplicates || !self.duplicates.contains_key(info))
+            .filter(|info| include_trash || !self.in_trash(info))
+            .filter(|info| {
+                zip_name_folded
+                    .as_ref()
+                    .is_none_or(|zip| folding.fold(&info.zip_file_name).contains(zip))
+            })
+            .filter_map(|info| {
+                let score = fuzzy_score(&query, &folding.fold(&info.photo_file_name));
+                (score >= FUZZY_MIN_SCORE).then(|| NameMatch {
+                    file: info.clone(),
+                    score,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.file.photo_file_name.cmp(&b.file.photo_file_name))
+                .then_with(|| a.file.zip_file_name.cmp(&b.file.zip_file_name))
+                .then_with(|| a.file.photo_index_in_zip.cmp(&b.file.photo_index_in_zip))
+        });
+        let total_found = matches.len();
+        tracing::info!("Found {} fuzzy matching images", total_found);
+        let start = offset.min(matches.len());
+        let end = offset.saturating_add(limit).min(matches.len());
+
+        (matches.drain(start..end).collect(), total_found)
+    }
+
+    pub fn search_image_by_year_month(
+        &self,
+        year: u32,
+        month: u32,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> (Vec<PhotoInfo>, usize) {
+        let key = format!(
+            "year_month|{year}|{month}|{include_hidden}|{include_duplicates}|{include_trash}"
+        );
+        let zip_infos = self.queries.get_or_compute(key, || {
+            let by_year_month = self.year_month_index();
+            let Some(infos) = by_year_month.get(&year).and_then(|m| m.get(&month)) else {
+                return Ok::<_, PhotoInsightError>(Vec::new());
+            };
+            let hidden = self.hidden.read().unwrap();
+            Ok(infos
+                .iter()
+                .filter(|info| include_hidden || !hidden.contains(info))
+                .filter(|info| include_duplicates || !self.duplicates.contains_key(info))
+                .filter(|info| include_trash || !self.in_trash(info))
+                .map(|info| self.with_hash(info))
+                .collect())
+        });
+        // computing the listing does not fail
+        zip_infos
+            .map(|infos| query_cache::page(&infos, offset, limit))
+            .unwrap_or_default()
+    }
+
+    pub fn search_image_by_exif_tags(
+        &self,
+        tag_name: &String,
+        tag_value: &String,
+        operator: &String,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> Result<(Vec<ExifResult>, usize), PhotoInsightError> {
+        tracing::info!("search image by EXIF tag : offset: {offset} Limiting results to {limit}");
+        let key = format!(
+            "exif_tags|{}|{}|{}|{include_hidden}|{include_duplicates}|{include_trash}",
+            tag_name.trim(),
+            operator.trim(),
+            tag_value.trim()
+        );
+        let results = self.queries.get_or_compute(key, || {
+            let hidden = self.hidden.read().unwrap();
+            let analysis = self.analysis.read().unwrap();
+            // temperature is not part of EXIF, it is looked up by the weather stage
+            let weather = analysis.get(WeatherAnalyzer::NAME);
+            let mut results = Vec::new();
+            self.for_each_exif(|zip_info, exif| {
+                let Some(exif) = exif else {
+                    return;
+                };
+                let matched = match tag_name.as_str() {
+                    "temperature" => weather
+                        .and_then(|results| results.get(zip_info))
+                        .and_then(WeatherAnalyzer::temperature_of)
+                        .is_some_and(|t| {
+                            exif::match_float(t, tag_value, operator).unwrap_or(false)
+                        }),
+                    // overrides are read only while the archive's EXIF is held, loading EXIF
+                    // applies the overrides and would wait for a queued writer otherwise
+                    "rating" => self
+                        .overrides
+                        .read()
+                        .unwrap()
+                        .get(zip_info)
+                        .and_then(|o| o.rating)
+                        .is_some_and(|r| {
+                            exif::match_number(r as u32, tag_value, operator).unwrap_or(false)
+                        }),
+                    "label" => {
+                        let labels = analysis
+                            .values()
+                            .filter_map(|results| results.get(zip_info))
+                            .flat_map(labels_of)
+                            .collect::<Vec<_>>();
+                        let matches = |label: &Label, operator: &str| {
+                            exif::match_string(&label.name, tag_value, operator).unwrap_or(false)
+                        };
+                        if operator == "!=" {
+                            !labels.is_empty() && labels.iter().all(|l| matches(l, "!="))
+                        } else {
+                            labels.iter().any(|l| matches(l, operator))
+                        }
+                    }
+                    _ => exif
+                        .matches_query(tag_name, tag_value, operator)
+                        .unwrap_or(false),
+                };
+
+                if matched
+                    && (include_hidden || !hidden.contains(zip_info))
+                    && (include_duplicates || !self.duplicates.contains_key(zip_info))
+                    && (include_trash || !self.in_trash(zip_info))
+                {
+                    results.push(ExifResult::new(
+                        self.with_hash(zip_info),
+                        exif.clone(),
+                        self.copies_of(zip_info),
+                    ));
+                }
+            });
+            Ok::<_, PhotoInsightError>(results)
+        })?;
+        Ok(query_cache::page(&results, offset, limit))
+    }
+
+    /// Tags searchable by photo_exif_search_tags with their most common values: the EXIF tags
+    /// in schema order followed by the tags of analysis results and overrides the collection
+    /// has. The order does not depend on the values, a listing is paged by the last tag name.
+    pub fn exif_tags(&self) -> Result<Arc<Vec<TagListing>>, PhotoInsightError> {
+        self.queries.get_or_compute("exif_tags".to_owned(), || {
+            let mut values = vec![(0, HashMap::new()); exif::SEARCH_TAGS.len()];
+            self.for_each_exif(|_, exif| {
+                let Some(exif) = exif else {
+                    return;
+                };
+                for (tag, (photos, counts)) in exif::SEARCH_TAGS.iter().zip(values.iter_mut()) {
+                    if let Some(value) = exif.tag_text(tag.name) {
+                        *photos += 1;
+                        *counts.entry(value).or_insert(0) += 1;
+                    }
+                }
+            });
+            let mut listing = exif::SEARCH_TAGS
+                .iter()
+                .zip(values)
+                .map(|(tag, (photos, counts))| TagListing::new(tag, photos, counts))
+                .collect::<Vec<_>>();
+
+            let analysis = self.analysis.read().unwrap();
+            let overrides = self.overrides.read().unwrap();
+            for tag in exif::DYNAMIC_TAGS.iter() {
+                let mut counts = HashMap::new();
+                let photos = match tag.name {
+                    "label" => {
+                        let mut labeled = HashSet::new();
+                        for (info, result) in analysis.values().flat_map(|results| results.iter()) {
+                            let labels = labels_of(result);
+                            if !labels.is_empty() {
+                                labeled.insert(info);
+                            }
+                            for label in labels {
+                                *counts.entry(label.name).or_insert(0) += 1;
+                            }
+                        }
+                        labeled.len()
+                    }
+                    "rating" => overrides
+                        .iter()
+                        .filter_map(|(_, o)| o.rating)
+                        .map(|rating| *counts.entry(rating.to_string()).or_insert(0) += 1)
+                        .count(),
+                    "temperature" => analysis
+                        .get(WeatherAnalyzer::NAME)
+                        .into_iter()
+                        .flat_map(|results| results.values())
+                        .filter_map(WeatherAnalyzer::temperature_of)
+                        .map(|t| *counts.entry(format!("{t:.0}")).or_insert(0) += 1)
+                        .count(),
+                    _ => 0,
+                };
+                if photos > 0 {
+                    listing.push(TagListing::new(tag, photos, counts));
+                }
+            }
+            Ok(listing)
+        })
+    }
+
+    // The photo with its content hash, ID and original entry name filled in
+    fn with_hash(&self, photo_info: &PhotoInfo) -> PhotoInfo {
+        let mut info = photo_info.clone();
+        info.set_hash(self.sha256.get(photo_info).cloned());
+        info.original_name = self.original_names.get(photo_info).cloned();
+        info
+    }
+
+    // Other copies of the photo: the canonical photo and its copies, without the photo itself
+    pub fn copies_of(&self, photo_info: &PhotoInfo) -> Vec<PhotoInfo> {
+        let canonical = self.duplicates.get(photo_info).unwrap_or(photo_info);
+        let Some(alternates) = self.alternates.get(canonical) else {
+            return Vec::new();
+        };
+        std::iter::once(canonical)
+            .chain(alternates.iter())
+            .filter(|info| *info != photo_info)
+            .map(|info| self.with_hash(info))
+            .collect()
+    }
+
+    pub fn exif_info(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+    ) -> Result<Vec<ExifResult>, PhotoInsightError> {
+        let _pins = image_infos
+            .iter()
+            .map(|info| &info.zip_file_name)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|zip| self.load_exif(Some(zip)))
+            .collect::<Vec<_>>();
+        let exif_cache = self.exif_cache.read().unwrap();
+        let mut exif_infos = Vec::new();
+        for img in image_infos {
+            if let Some(exif) = exif_cache.get(img) {
+                exif_infos.push(ExifResult::new(
+                    self.with_hash(img),
+                    exif.clone(),
+                    self.copies_of(img),
+                ));
+            }
+        }
+        Ok(exif_infos)
+    }
+
+    pub fn image_data(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<(PhotoInfo, String, Vec<u8>)>, PhotoInsightError> {
+        let mut images = Vec::new();
+        let read_ahead = crate::CONFIG.thumbnails.read_ahead;
+        for (zip_file, infos) in by_archive(image_infos) {
+            let _span = tracing::info_span!("archive", archive = %zip_file).entered();
+            cancel.check()?;
+            let finished = if read_ahead > 0 {
+                self.thumbnails_pipelined(&zip_file, infos, read_ahead, &mut images, cancel)?
+            } else {
+                let mut unpacked = Vec::new();
+                self.extract_entries(&zip_file, infos, |info, data| {
+                    unpacked.push((info, data));
+                    true
+                })?;
+                let mut finished = true;
+                for (photo_info, image_data) in unpacked {
+                    cancel.check()?;
+                    if cancel.timed_out() {
+                        finished = false;
+                        break;
+                    }
+                    images.push(self.thumbnail(photo_info, &image_data));
+                }
+                finished
+            };
+            if !finished {
+                tracing::warn!("Timed out, returning {} photos", images.len());
+                break;
+            }
+        }
+        Ok(images)
+    }
+
+    // Thumbnails of the photos of one archive, the photos are extracted by another thread up
+    // to `read_ahead` photos ahead of the thumbnail being made. Returns false when the call
+    // ran out of time.
+    fn thumbnails_pipelined(
+        &self,
+        zip_file: &str,
+        infos: Vec<PhotoInfo>,
+        read_ahead: usize,
+        images: &mut Vec<(PhotoInfo, String, Vec<u8>)>,
+        cancel: &CancellationToken,
+    ) -> Result<bool, PhotoInsightError> {
+        let collect_timings = timings::enabled();
+        std::thread::scope(|scope| {
+            let (sender, receiver) = std::sync::mpsc::sync_channel(read_ahead);
+            let extractor = scope.spawn(move || {
+                let collector = timings::start(collect_timings);
+                // stops once the receiver is gone
+                let extracted = self.extract_entries(zip_file, infos, |info, data| {
+                    sender.send((info, data)).is_ok()
+                });
+                (extracted, collector.finish())
+            });
+            let mut finished = true;
+            let mut made = Ok(());
+            for (photo_info, image_data) in receiver.iter() {
+                if let Err(e) = cancel.check() {
+                    made = Err(e);
+                    break;
+                }
+                if cancel.timed_out() {
+                    finished = false;
+                    break;
+                }
+                images.push(self.thumbnail(photo_info, &image_data));
+            }
+            drop(receiver);
+            let (extracted, extractor_timings) = extractor
+                .join()
+                .map_err(|_| PhotoInsightError::from_message("photo extraction failed"))?;
+            if let Some(extractor_timings) = extractor_timings {
+                timings::add(extractor_timings);
+            }
+            made?;
+            extracted?;
+            Ok(finished)
+        })
+    }
+
+    // Thumbnail of the photo, smart cropped when enabled, else the embedded EXIF thumbnail
+    // or the resized photo
+    fn thumbnail(
+        &self,
+        photo_info: PhotoInfo,
+        image_data: &Vec<u8>,
+    ) -> (PhotoInfo, String, Vec<u8>) {
+        let subjects = if crate::CONFIG.thumbnails.smart_crop {
+            self.subjects(&photo_info)
+        } else {
+            Vec::new()
+        };
+        if !subjects.is_empty() {
+            match exif::smart_crop(image_data, &subjects) {
+                Ok(thumbnail) => {
+                    let mime = mime_from_image(&thumbnail);
+                    return (photo_info, mime, thumbnail);
+                }
+                Err(e) => tracing::warn!("Failed to smart crop {:?}: {}", photo_info, e),
+            }
+        }
+        let exif = crate::core::exif::extract_exif_info(image_data, true);
+        if exif.is_err() {
+            tracing::warn!(
+                "Failed to extract exif from image {:?} in zip {}: {}",
+                photo_info,
+                photo_info.zip_file_name,
+                exif.err().unwrap()
+            );
+            // let mime = mime_from_image(&image_data);
+            // HEVC and raw images can't be decoded either
+            let resized_image = if bmff::is_bmff(image_data) {
+                Vec::new()
+            } else {
+                exif::resize(image_data, 0, 0).unwrap_or_else(|e| {
+                    tracing::warn!("Failed to resize {:?}: {}", photo_info, e);
+                    Vec::new()
+                })
+            };
+            let mime = mime_from_image(&resized_image);
+            (photo_info, mime, resized_image)
+        } else {
+            let image_data = exif.unwrap().1;
+            let mime = mime_from_image(&image_data);
+            (photo_info, mime, image_data)
+        }
+    }
+
+    // Bounding boxes of the faces in the photo, of confidently detected objects when no face
+    // was found
+    fn subjects(&self, photo_info: &PhotoInfo) -> Vec<(f32, f32, f32, f32)> {
+        let faces = self
+            .analysis
+            .read()
+            .unwrap()
+            .get(FaceAttributesAnalyzer::NAME)
+            .and_then(|results| results.get(photo_info))
+            .map(FaceAttributesAnalyzer::face_boxes_of)
+            .unwrap_or_default();
+        if !faces.is_empty() {
+            return faces;
+        }
+        self.object_detection
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|detections| detections.get(photo_info))
+            .flatten()
+            .filter(|o| o.confidence >= SUBJECT_MIN_CONFIDENCE)
+            .map(|o| o.bbox)
+            .collect()
+    }
+
+    /// Frames of the motion part of live photos at the given second, stills are returned for
+    /// photos without motion part
+    pub fn motion_frames(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+        at_seconds: f32,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<(PhotoInfo, String, Vec<u8>)>, PhotoInsightError> {
+        let mut images = Vec::new();
+        let mut stills = Vec::new();
+        for info in image_infos {
+            let Some(video) = self.motion.get(info) else {
+                stills.push(info);
+                continue;
+            };
+            let _span = tracing::info_span!("archive", archive = %video.zip_file_name).entered();
+            cancel.check()?;
+            if cancel.timed_out() {
+                return Ok(images);
+            }
+            let unpacked = zip::extract_zip_archive(
+                &self.image_dir,
+                &video.zip_file_name,
+                vec![video.photo_index_in_zip],
+            )?;
+            for (_, video_data) in unpacked {
+                let frame =
+                    motion::extract_frame(&crate::CONFIG.motion.ffmpeg, &video_data, at_seconds)?;
+                let resized_image = exif::resize(&frame, 0, 0)?;
+                let mime = mime_from_image(&resized_image);
+                images.push((info.clone(), mime, resized_image));
+            }
+        }
+        images.extend(self.image_data(stills, cancel)?);
+        Ok(images)
+    }
+
+    // Search photos by detected object class name (case insensitive) or its alias (see
+    // ClassAliases), either in results of a single model or of all models side by side
+    pub fn search_image_by_object(
+        &self,
+        class_name: &String,
+        model: &Option<String>,
+        filter: &LabelFilter,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> Result<(Vec<AnalysisResult>, usize), PhotoInsightError> {
+        if let Some(model) = model {
+            crate::MODELS.get(model)?;
+        }
+        let key = format!(
+            "object|{}|{model:?}|{filter:?}|{include_hidden}|{include_duplicates}|{include_trash}",
+            class_name.trim().to_lowercase()
+        );
+        let results = self.queries.get_or_compute(key, || {
+            let hidden = self.hidden.read().unwrap();
+            let class_names = crate::CLASS_ALIASES.expand(class_name);
+            let object_detection = self.object_detection.read().unwrap();
+            let mut results = Vec::new();
+            for (model_id, detections) in object_detection.iter() {
+                if model.as_ref().is_some_and(|m| m != model_id) {
+                    continue;
+                }
+                for (photo_info, objects) in detections {
+                    if (!include_hidden && hidden.contains(photo_info))
+                        || (!include_duplicates && self.duplicates.contains_key(photo_info))
+                        || (!include_trash && self.in_trash(photo_info))
+                    {
+                        continue;
+                    }
+                    let matching = filter.select(objects, |o| {
+                        class_names.contains(&o.class_name.to_lowercase())
+                    });
+                    if !matching.is_empty() {
+                        results.push(AnalysisResult::new(
+                            self.with_hash(photo_info),
+                            model_id.clone(),
+                            matching,
+                        ));
+                    }
+                }
+            }
+            // stable order for pagination
+            results.sort_by(|a, b| {
+                (a.photo_info.serialize_as_key(), &a.model)
+                    .cmp(&(b.photo_info.serialize_as_key(), &b.model))
+            });
+            Ok::<_, PhotoInsightError>(results)
+        })?;
+        Ok(query_cache::page(&results, offset, limit))
+    }
+
+    // Search photos by label produced by analysis stages (case insensitive, partial match),
+    // either in results of a single stage or of all stages
+    pub fn search_image_by_label(
+        &self,
+        label: &String,
+        stage: &Option<String>,
+        filter: &LabelFilter,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> Result<(Vec<LabelResult>, usize), PhotoInsightError> {
+        if let Some(stage) = stage {
+            if crate::ANALYZERS.get(stage).is_none() {
+                return Err(PhotoInsightError::from_message(format!(
+                    "unknown analysis stage: {stage}"
+                )));
+            }
+        }
+        let label_lower = label.to_lowercase();
+        let key = format!(
+            "label|{}|{stage:?}|{filter:?}|{include_hidden}|{include_duplicates}|{include_trash}",
+            label_lower.trim()
+        );
+        let results = self.queries.get_or_compute(key, || {
+            let hidden = self.hidden.read().unwrap();
+            let analysis = self.analysis.read().unwrap();
+            let mut results = Vec::new();
+            for (stage_name, stage_results) in analysis.iter() {
+                if stage.as_ref().is_some_and(|s| s != stage_name) {
+                    continue;
+                }
+                for (photo_info, value) in stage_results {
+                    if (!include_hidden && hidden.contains(photo_info))
+                        || (!include_duplicates && self.duplicates.contains_key(photo_info))
+                        || (!include_trash && self.in_trash(photo_info))
+                    {
+                        continue;
+                    }
+                    let labels = filter.select(&labels_of(value), |l| {
+                        l.name.to_lowercase().contains(&label_lower)
+                    });
+                    if !labels.is_empty() {
+                        results.push(LabelResult {
+                            file: self.with_hash(photo_info),
+                            stage: stage_name.clone(),
+                            labels,
+                        });
+                    }
+                }
+            }
+            // stable order for pagination
+            results.sort_by(|a, b| {
+                (a.file.serialize_as_key(), &a.stage).cmp(&(b.file.serialize_as_key(), &b.stage))
+            });
+            Ok::<_, PhotoInsightError>(results)
+        })?;
+        Ok(query_cache::page(&results, offset, limit))
+    }
+
+    // Search photos by number of people, operators are the same as for numeric EXIF tags
+    pub fn search_image_by_people_count(
+        &self,
+        value: &String,
+        operator: &String,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> Result<(Vec<PeopleCountResult>, usize), PhotoInsightError> {
+        // validate the query upfront, matching errors are not reported per photo
+        exif::match_number(0, value, operator)?;
+        let key = format!(
+            "people_count|{}|{}|{include_hidden}|{include_duplicates}|{include_trash}",
+            operator.trim(),
+            value.trim()
+        );
+        let results = self.queries.get_or_compute(key, || {
+            let hidden = self.hidden.read().unwrap();
+            let analysis = self.analysis.read().unwrap();
+            let mut results = Vec::new();
+            if let Some(stage_results) = analysis.get(PeopleCountAnalyzer::NAME) {
+                for (photo_info, result) in stage_results {
+                    if (!include_hidden && hidden.contains(photo_info))
+                        || (!include_duplicates && self.duplicates.contains_key(photo_info))
+                        || (!include_trash && self.in_trash(photo_info))
+                    {
+                        continue;
+                    }
+                    let Some(people_count) = PeopleCountAnalyzer::people_count_of(result) else {
+                        continue;
+                    };
+                    if exif::match_number(people_count, value, operator)? {
+                        results.push(PeopleCountResult {
+                            file: self.with_hash(photo_info),
+                            people_count,
+                        });
+                    }
+                }
+            }
+            // stable order for pagination
+            results.sort_by(|a, b| a.file.serialize_as_key().cmp(&b.file.serialize_as_key()));
+            Ok(results)
+        })?;
+        Ok(query_cache::page(&results, offset, limit))
+    }
+
+    // Search portraits where every face smiles and has open eyes at least with given scores,
+    // best shots (by the worst face) come first
+    pub fn search_portraits(
+        &self,
+        min_faces: usize,
+        min_smile: f32,
+        min_eyes_open: f32,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> Result<(Vec<PortraitResult>, usize), PhotoInsightError> {
+        let key = format!(
+            "portraits|{min_faces}|{min_smile}|{min_eyes_open}|{include_hidden}|{include_duplicates}|{include_trash}"
+        );
+        let results = self.queries.get_or_compute(key, || {
+            let hidden = self.hidden.read().unwrap();
+            let analysis = self.analysis.read().unwrap();
+            let mut results = Vec::new();
+            if let Some(stage_results) = analysis.get(FaceAttributesAnalyzer::NAME) {
+                for (photo_info, result) in stage_results {
+                    if (!include_hidden && hidden.contains(photo_info))
+                        || (!include_duplicates && self.duplicates.contains_key(photo_info))
+                        || (!include_trash && self.in_trash(photo_info))
+                    {
+                        continue;
+                    }
+                    let Some(faces) = FaceAttributesAnalyzer::summary_of(result) else {
+                        continue;
+                    };
+                    if faces.face_count >= min_faces.max(1)
+                        && faces.min_smile >= min_smile
+                        && faces.min_eyes_open >= min_eyes_open
+                    {
+                        results.push(PortraitResult {
+                            file: self.with_hash(photo_info),
+                            score: faces.score(),
+                            faces,
+                        });
+                    }
+                }
+            }
+            // best first, stable order for pagination
+            results.sort_by(|a, b| {
+                b.score
+                    .total_cmp(&a.score)
+                    .then_with(|| a.file.serialize_as_key().cmp(&b.file.serialize_as_key()))
+            });
+            Ok::<_, PhotoInsightError>(results)
+        })?;
+        Ok(query_cache::page(&results, offset, limit))
+    }
+
+    // Photo count and representative photo of every year/month with photos, in calendar order
+    pub fn calendar(
+        &self,
+        year_start: u32,
+        year_end: u32,
+        pick: Representative,
+        include_hidden: bool,
+    ) -> Vec<CalendarMonth> {
+        let random_state = RandomState::new();
+        let hidden = self.hidden.read().unwrap();
+        let exif_cache = self.exif_index();
+        let mut calendar = Vec::new();
+        let mut groups = Vec::new();
+        for (year, by_month) in self.year_month_index().iter() {
+            if *year < year_start || *year > year_end || *year == 0 {
+                continue;
+            }
+            for (month, infos) in by_month.iter() {
+                let infos = infos
+                    .iter()
+                    .filter(|info| include_hidden || !hidden.contains(info))
+                    .filter(|info| !self.in_trash(info))
+                    .collect::<Vec<&PhotoInfo>>();
+                if infos.is_empty() {
+                    continue;
+                }
+                let exif_of = |info: &&&PhotoInfo| exif_cache.get(**info);
+                let representative = match pick {
+                    Representative::First => infos.iter().min_by_key(|info| {
+                        (
+                            exif_of(info).map(|exif| exif.date_time.clone()),
+                            info.serialize_as_key(),
+                        )
+                    }),
+                    Representative::Random => infos
+                        .iter()
+                        .min_by_key(|info| random_state.hash_one(info.serialize_as_key())),
+                    Representative::Best => infos.iter().max_by_key(|info| {
+                        exif_of(info)
+                            .map(|exif| exif.width as u64 * exif.height as u64)
+                            .unwrap_or(0)
+                    }),
+                    Representative::Cover => {
+                        let infos = infos.iter().map(|info| (*info).clone()).collect();
+                        groups.push((month_key(*year, *month), infos));
+                        None
+                    }
+                };
+                calendar.push(CalendarMonth {
+                    year: *year,
+                    month: *month,
+                    count: infos.len(),
+                    representative: representative.map(|info| (*info).clone()),
+                });
+            }
+        }
+        calendar.sort_by_key(|m| (m.year, m.month));
+        drop(exif_cache);
+        drop(hidden);
+        if pick == Representative::Cover {
+            groups.sort_by(|a, b| a.0.cmp(&b.0));
+            let covers = self.covers(groups);
+            for month in calendar.iter_mut() {
+                month.representative = covers.get(&month_key(month.year, month.month)).cloned();
+            }
+        }
+        calendar
+    }
+
+    // Cover candidate of the photo with what the indexes know about it
+    fn cover_candidate(
+        &self,
+        photo_info: &PhotoInfo,
+        exif_cache: &ExifCache,
+        object_detection: &ObjectDetectionByModel,
+        analysis: &AnalysisStore,
+    ) -> CoverCandidate {
+        let result_of = |stage: &str| analysis.get(stage).and_then(|r| r.get(photo_info));
+        let faces =
+            result_of(FaceAttributesAnalyzer::NAME).and_then(FaceAttributesAnalyzer::summary_of);
+        let face_count = faces.as_ref().map(|f| f.face_count).or_else(|| {
+            result_of(PeopleCountAnalyzer::NAME)
+                .and_then(PeopleCountAnalyzer::people_count_of)
+                .map(|count| count as usize)
+        });
+        CoverCandidate {
+            info: self.with_hash(photo_info),
+            megapixels: exif_cache
+                .get(photo_info)
+                .map(|exif| exif.width as f64 * exif.height as f64 / 1_000_000.0)
+                .unwrap_or(0.0),
+            face_count: face_count.unwrap_or(0),
+            faces,
+            labels: photo_labels(object_detection, analysis, photo_info),
+        }
+    }
+
+    // Cover photos of the groups (archives, months or events) given in display order, picked
+    // by quality, face count and diversity, hidden photos, copies and trash are never picked
+    pub fn covers(&self, groups: Vec<(String, Vec<PhotoInfo>)>) -> HashMap<String, PhotoInfo> {
+        let exif_cache = self.exif_of(groups.iter().flat_map(|(_, infos)| infos));
+        let hidden = self.hidden.read().unwrap();
+        let object_detection = self.object_detection.read().unwrap();
+        let analysis = self.analysis.read().unwrap();
+        let groups = groups
+            .into_iter()
+            .map(|(key, infos)| {
+                let candidates = infos
+                    .iter()
+                    .filter(|info| {
+                        !hidden.contains(info)
+                            && !self.duplicates.contains_key(info)
+                            && !self.in_trash(info)
+                    })
+                    .map(|info| {
+                        self.cover_candidate(info, &exif_cache, &object_detection, &analysis)
+                    })
+                    .collect();
+                (key, candidates)
+            })
+            .collect();
+        self.covers.write().unwrap().covers(groups)
+    }
+
+    // Cover photo of every archive in the archive name order
+    pub fn archive_covers(&self) -> Vec<ArchiveCover> {
+        let mut by_archive: HashMap<&String, Vec<PhotoInfo>> = HashMap::new();
+        for info in self.images.iter() {
+            by_archive
+                .entry(&info.zip_file_name)
+                .or_default()
+                .push(info.clone());
+        }
+        let mut archives = by_archive.into_iter().collect::<Vec<_>>();
+        archives.sort_by(|a, b| a.0.cmp(b.0));
+        let counts = archives
+            .iter()
+            .map(|(zip_file, infos)| ((*zip_file).clone(), infos.len()))
+            .collect::<Vec<_>>();
+        let covers = self.covers(
+            archives
+                .into_iter()
+                .map(|(zip_file, infos)| (format!("archive:{zip_file}"), infos))
+                .collect(),
+        );
+        counts
+            .into_iter()
+            .map(|(zip_file_name, photo_count)| ArchiveCover {
+                cover: covers.get(&format!("archive:{zip_file_name}")).cloned(),
+                zip_file_name,
+                photo_count,
+            })
+            .collect()
+    }
+
+    // One photo of the subject per period in time order, the period's cover (quality, faces
+    // and diversity from the neighbouring periods). Undated photos are left out.
+    pub fn subject_timeline(
+        &self,
+        subject: &str,
+        photos: Vec<PhotoInfo>,
+        group: TimeGroup,
+    ) -> Vec<TimelineEntry> {
+        let mut periods: Vec<(String, Vec<PhotoInfo>)> = Vec::new();
+        let mut dated = photos
+            .into_iter()
+            .filter_map(|info| Some((self.date_taken(&info)?, info)))
+            .collect::<Vec<_>>();
+        dated.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut dates = HashMap::new();
+        for (date_time, info) in dated {
+            let Some(period) = group.bucket(&date_time) else {
+                continue;
+            };
+            dates.insert(info.clone(), date_time);
+            match periods.last_mut() {
+                Some((last, infos)) if *last == period => infos.push(info),
+                _ => periods.push((period, vec![info])),
+            }
+        }
+        let counts = periods
+            .iter()
+            .map(|(period, infos)| (period.clone(), infos.len()))
+            .collect::<Vec<_>>();
+        let key = |period: &str| format!("timeline:{subject}:{period}");
+        let covers = self.covers(
+            periods
+                .into_iter()
+                .map(|(period, infos)| (key(&period), infos))
+                .collect(),
+        );
+        counts
+            .into_iter()
+            .filter_map(|(period, photo_count)| {
+                let photo = covers.get(&key(&period))?.clone();
+                Some(TimelineEntry {
+                    date_time: dates.get(&photo).cloned(),
+                    photo: self.with_hash(&photo),
+                    period,
+                    photo_count,
+                })
+            })
+            .collect()
+    }
+
+    // Replaces the representatives of the events (highest resolution) with their covers
+    pub fn event_covers(&self, events: &mut [Event]) {
+        let mut order = events.iter().collect::<Vec<&Event>>();
+        order.sort_by(|a, b| a.start.cmp(&b.start));
+        let covers = self.covers(
+            order
+                .into_iter()
+                .map(|event| (format!("event:{}", event.start), event.photos.clone()))
+                .collect(),
+        );
+        for event in events.iter_mut() {
+            if let Some(cover) = covers.get(&format!("event:{}", event.start)) {
+                event.representative = cover.clone();
+            }
+        }
+    }
+
+    // Reports what is indexed and what was left out by the index configuration, entries are
+    // counted from the zip central directories
+    pub fn index_health(&self) -> Result<IndexHealth, PhotoInsightError> {
+        let archives = traversal::list_directory_zip_files(&self.image_dir)?;
+        let mut excluded_entries = HashMap::new();
+        for zip_file in archives.iter() {
+            let excluded = zip::list_excluded_entries(&self.image_dir, zip_file)?;
+            if !excluded.is_empty() {
+                excluded_entries.insert(zip_file.clone(), excluded.len());
+            }
+        }
+        Ok(IndexHealth {
+            indexed_archives: archives.len(),
+            indexed_photos: self.images.len(),
+            excluded_archives: traversal::list_excluded_zip_files(&self.image_dir)?,
+            excluded_entries,
+            patterns: crate::CONFIG.index.clone(),
+            relinked_archives: self.relinked.clone(),
+            warm_up: crate::WARM_UP.steps(),
+            takeout_folders: self.takeout_folders.values().fold(
+                HashMap::new(),
+                |mut counts, folder| {
+                    *counts.entry(*folder).or_default() += 1;
+                    counts
+                },
+            ),
+        })
+    }
+
+    // Photos taken in the date range with EXIF and labels, ordered by the time taken. Copies of
+    // photos stored more than once and photos in the Takeout trash are left out.
+    pub fn digest_photos(
+        &self,
+        date_from: &str,
+        date_to: &str,
+        include_hidden: bool,
+    ) -> Result<Vec<DigestPhoto>, PhotoInsightError> {
+        let date_from = normalize_date_time(date_from)?;
+        let date_to = normalize_date_to(date_to)?;
+        let hidden = self.hidden.read().unwrap();
+        let object_detection = self.object_detection.read().unwrap();
+        let analysis = self.analysis.read().unwrap();
+        let mut photos = Vec::new();
+        self.for_each_exif(|photo_info, exif| {
+            let Some(exif) = exif else {
+                return;
+            };
+            if (!include_hidden && hidden.contains(photo_info))
+                || self.duplicates.contains_key(photo_info)
+                || self.in_trash(photo_info)
+            {
+                return;
+            }
+            let Ok(date_time) = normalize_date_time(exif.date_time.trim_matches('"')) else {
+                return;
+            };
+            if date_time < date_from || date_time > date_to {
+                return;
+            }
+            // normalized dates always have a timestamp
+            let Ok(timestamp) = timestamp_of(&date_time) else {
+                return;
+            };
+            photos.push(DigestPhoto {
+                info: self.with_hash(photo_info),
+                exif: exif.clone(),
+                timestamp,
+                labels: photo_labels(&object_detection, &analysis, photo_info),
+            });
+        });
+        photos.sort_by(|a, b| {
+            (a.timestamp, a.info.serialize_as_key()).cmp(&(b.timestamp, b.info.serialize_as_key()))
+        });
+        Ok(photos)
+    }
+
+    // Top photos of the date range by quality, faces, rating overrides and diversity
+    pub fn highlights(
+        &self,
+        date_from: &str,
+        date_to: &str,
+        count: usize,
+        include_hidden: bool,
+    ) -> Result<Vec<Highlight>, PhotoInsightError> {
+        let photos = self.digest_photos(date_from, date_to, include_hidden)?;
+        let exif_cache = self.exif_of(photos.iter().map(|photo| &photo.info));
+        let object_detection = self.object_detection.read().unwrap();
+        let analysis = self.analysis.read().unwrap();
+        let overrides = self.overrides.read().unwrap();
+        let candidates = photos
+            .iter()
+            .map(|photo| HighlightCandidate {
+                candidate: self.cover_candidate(
+                    &photo.info,
+                    &exif_cache,
+                    &object_detection,
+                    &analysis,
+                ),
+                timestamp: photo.timestamp,
+                date_time: unquote(&photo.exif.date_time).to_owned(),
+                rating: overrides.get(&photo.info).and_then(|o| o.rating),
+            })
+            .collect();
+        Ok(highlights::highlights(candidates, count))
+    }
+
+    // Metadata catalog of all photos ordered by archive and index: EXIF, location and labels
+    // of detected objects and analysis stages
+    pub fn catalog(&self, include_hidden: bool) -> Vec<CatalogRow> {
+        let hidden = self.hidden.read().unwrap();
+        let object_detection = self.object_detection.read().unwrap();
+        let analysis = self.analysis.read().unwrap();
+        let mut rows = Vec::new();
+        self.for_each_exif(|info, exif| {
+            if !include_hidden && hidden.contains(info) {
+                return;
+            }
+            let exif = exif.cloned().unwrap_or_else(exif::ExifInfo::unknown);
+            let labels = photo_labels(&object_detection, &analysis, info);
+            rows.push(CatalogRow {
+                zip_file_name: info.zip_file_name.clone(),
+                photo_file_name: info.photo_file_name.clone(),
+                photo_index_in_zip: info.photo_index_in_zip,
+                photo_id: info.photo_id.clone(),
+                sha256: info.sha256.clone(),
+                date_time: exif.date_time,
+                year: exif.year,
+                month: exif.month,
+                model: exif.model,
+                lens: exif.lens,
+                width: exif.width,
+                height: exif.height,
+                aperture: exif.aperture,
+                shutter_speed: exif.shutter_speed,
+                iso: exif.iso,
+                focal_len: exif.focal_len,
+                focal_len_35mm: exif.focal_len_35mm.map(f64::from),
+                latitude: exif.location.as_ref().map(|l| l.latitude),
+                longitude: exif.location.as_ref().map(|l| l.longitude),
+                description: exif.description,
+                color_label: exif.color_label.map(|label| label.name().to_owned()),
+                labels: labels.join(";"),
+            });
+        });
+        rows.sort_by(|a, b| {
+            (&a.zip_file_name, a.photo_index_in_zip).cmp(&(&b.zip_file_name, b.photo_index_in_zip))
+        });
+        rows
+    }
+
+    // Metadata of the photos written into XMP sidecars: rating, color label, caption, date and
+    // location with overrides applied, labels of detected objects and analysis as keywords
+    pub fn xmp_metadata(&self, infos: &[PhotoInfo]) -> Vec<XmpMetadata> {
+        let exif_cache = self.exif_of(infos);
+        let overrides = self.overrides.read().unwrap();
+        let object_detection = self.object_detection.read().unwrap();
+        let analysis = self.analysis.read().unwrap();
+        infos
+            .iter()
+            .map(|info| {
+                let exif = exif_cache.get(info);
+                XmpMetadata {
+                    rating: overrides.get(info).and_then(|o| o.rating),
+                    color_label: exif.and_then(|exif| exif.color_label),
+                    keywords: photo_labels(&object_detection, &analysis, info),
+                    description: exif
+                        .and_then(|exif| exif.description.as_deref())
+                        .map(|d| unquote(d).trim().to_owned())
+                        .filter(|d| !d.is_empty()),
+                    date_time: exif.map(|exif| exif.date_time.clone()),
+                    location: exif.and_then(|exif| exif.location.clone()),
+                }
+            })
+            .collect()
+    }
+
+    // Camera models, detected object classes and analysis labels of the collection
+    pub fn query_vocabulary(&self) -> Vocabulary {
+        let mut cameras = Vec::new();
+        self.for_each_exif(|_, exif| {
+            let model = exif.map_or("", |exif| exif.model.trim_matches('"'));
+            if !model.is_empty() && model != "unknown" {
+                cameras.push(model.to_owned());
+            }
+        });
+        let mut objects = self
+            .object_detection
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|detections| detections.values().flatten())
+            .map(|object| object.class_name.clone())
+            .collect::<Vec<String>>();
+        let mut labels = self
+            .analysis
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|results| results.values().flat_map(labels_of))
+            .map(|label| label.name)
+            .collect::<Vec<String>>();
+        for values in [&mut cameras, &mut objects, &mut labels] {
+            values.sort();
+            values.dedup();
+        }
+        Vocabulary {
+            cameras,
+            objects,
+            labels,
+        }
+    }
+
+    // Full-text search over file names, descriptions, labels and camera fields ranked by
+    // relevance. The index is rebuilt when the catalog changed since the last search.
+    pub fn text_search(
+        &self,
+        query: &str,
+        fuzzy: bool,
+        offset: usize,
+        limit: usize,
+        include_hidden: bool,
+        include_duplicates: bool,
+        include_trash: bool,
+    ) -> Result<(Vec<TextSearchResult>, usize), PhotoInsightError> {
+        if !crate::CONFIG.full_text.enabled {
+            return Err(PhotoInsightError::from_message(
+                "Full-text index is disabled, set full_text.enabled in the configuration",
+            ));
+        }
+        let rows = self.catalog(true);
+        let fingerprint = full_text::fingerprint(&rows);
+        let stale = self
+            .full_text
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|index| index.fingerprint() != fingerprint);
+        if stale {
+            *self.full_text.write().unwrap() = Some(FullTextIndex::build(&rows)?);
+        }
+        let matches = self
+            .full_text
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|index| timings::timed("search", || index.search(query, fuzzy)))
+            .transpose()?
+            .unwrap_or_default();
+        let hidden = self.hidden.read().unwrap();
+        let matches = matches
+            .into_iter()
+            .filter(|(info, _)| include_hidden || !hidden.contains(info))
+            .filter(|(info, _)| include_duplicates || !self.duplicates.contains_key(info))
+            .filter(|(info, _)| include_trash || !self.in_trash(info))
+            .collect::<Vec<_>>();
+        let total = matches.len();
+        let results = matches
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(info, score)| TextSearchResult::new(self.with_hash(&info), score))
+            .collect();
+        Ok((results, total))
+    }
+
+    // Sums sizes of all zip entries by archive, year and media type. The year comes from EXIF,
+    // or from the Takeout folder name ("Photos from 2019"), 0 when unknown
+    pub fn storage_report(&self) -> Result<StorageReport, PhotoInsightError> {
+        let mut report = StorageReport::default();
+        for zip_file in traversal::list_directory_zip_files(&self.image_dir)? {
+            let exif_cache = self.archive_exif(&zip_file);
+            for entry in zip::list_zip_entries(&self.image_dir, &zip_file)? {
+                let photo_info = PhotoInfo::new(zip_file.clone(), entry.name.clone(), entry.index);
+                let year = exif_cache
+                    .get(&photo_info)
+                    .map(|exif| exif.year)
+                    .filter(|year| *year > 0)
+                    .or_else(|| takeout_year(&entry.name))
+                    .unwrap_or(0);
+                report.total.add(&entry);
+                report
+                    .by_archive
+                    .entry(zip_file.clone())
+                    .or_default()
+                    .add(&entry);
+                report.by_year.entry(year).or_default().add(&entry);
+                report
+                    .by_media_type
+                    .entry(zip::media_type(&entry.name).to_owned())
+                    .or_default()
+                    .add(&entry);
+            }
+        }
+        Ok(report)
+    }
+
+    // Extracts original photo bytes from the zip archives
+    pub fn extract_photos(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+    ) -> Result<Vec<(PhotoInfo, Vec<u8>)>, PhotoInsightError> {
+        let mut photos = Vec::new();
+        for (zip_file, infos) in by_archive(image_infos) {
+            let _span = tracing::info_span!("archive", archive = %zip_file).entered();
+            self.extract_entries(&zip_file, infos, |info, data| {
+                photos.push((info, data));
+                true
+            })?;
+        }
+        Ok(photos)
+    }
+
+    // Extracts the photos of one archive, passing each to `each` as soon as it is read until
+    // `each` returns false. Photos are read at the entry locations persisted in the manifest
+    // when all of them are known, the archive is opened and walked when a location is missing
+    // or stale.
+    fn extract_entries(
+        &self,
+        zip_file: &str,
+        infos: Vec<PhotoInfo>,
+        mut each: impl FnMut(PhotoInfo, Vec<u8>) -> bool,
+    ) -> Result<(), PhotoInsightError> {
+        let indices = infos
+            .iter()
+            .map(|info| info.photo_index_in_zip)
+            .collect::<Vec<_>>();
+        let located = self.locations.get(zip_file).and_then(|locations| {
+            infos
+                .into_iter()
+                .map(|info| {
+                    let location = *locations.get(&info.photo_index_in_zip)?;
+                    let mut photo_info = PhotoInfo::new(
+                        info.zip_file_name,
+                        info.photo_file_name,
+                        info.photo_index_in_zip,
+                    );
+                    photo_info.original_name = self.original_names.get(&photo_info).cloned();
+                    Some((photo_info, location))
+                })
+                .collect::<Option<Vec<_>>>()
+        });
+        let Some(located) = located else {
+            return zip::extract_zip_entries(&self.image_dir, zip_file, indices, each);
+        };
+        let mut delivered = 0;
+        let read = zip::read_located_entries(&self.image_dir, zip_file, located, |info, data| {
+            delivered += 1;
+            each(info, data)
+        });
+        match read {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                tracing::warn!("Entry locations of {zip_file} are stale, extracting: {e}");
+                let rest = indices[delivered..].to_vec();
+                zip::extract_zip_entries(&self.image_dir, zip_file, rest, each)
+            }
+        }
+    }
+
+    // Original bytes of one photo, as stored in the archive
+    pub fn original_data(&self, photo_info: &PhotoInfo) -> Result<Vec<u8>, PhotoInsightError> {
+        let located = self
+            .locations
+            .get(&photo_info.zip_file_name)
+            .is_some_and(|locations| locations.contains_key(&photo_info.photo_index_in_zip));
+        if located {
+            let mut original = None;
+            self.extract_entries(
+                &photo_info.zip_file_name,
+                vec![photo_info.clone()],
+                |_, data| {
+                    original = Some(data);
+                    false
+                },
+            )?;
+            return original.ok_or_else(|| PhotoInsightError::from_message("entry not found"));
+        }
+        zip::read_original(
+            &self.image_dir,
+            &photo_info.zip_file_name,
+            photo_info.photo_index_in_zip,
+        )
+    }
+
+    pub fn open_vocabulary_analysis(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+        classes: &[String],
+        min_confidence: f32,
+    ) -> Result<Vec<AnalysisResult>, PhotoInsightError> {
+        let detector = crate::CONFIG
+            .detection
+            .open_vocabulary
+            .as_ref()
+            .ok_or_else(|| {
+                PhotoInsightError::from_message("no open-vocabulary detector configured")
+            })?;
+        let photos = self.extract_photos(image_infos)?;
+        crate::core::open_vocabulary::detect_open_vocabulary(
+            photos,
+            classes,
+            detector,
+            min_confidence,
+        )
+    }
+
+    pub fn barcode_analysis(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<BarcodeResult>, PhotoInsightError> {
+        let decoder = crate::CONFIG
+            .detection
+            .barcode_decoder
+            .as_ref()
+            .ok_or_else(|| PhotoInsightError::from_message("no barcode decoder configured"))?;
+        let photos = self.extract_photos(image_infos)?;
+        barcode::decode_barcodes(photos, decoder, cancel)
+    }
+
+    pub fn yolo_v8_analysis(
+        &self,
+        image_infos: Vec<&PhotoInfo>,
+        model: &ModelSpec,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<AnalysisResult>, PhotoInsightError> {
+        let mut arxives = HashMap::new();
+        for info in image_infos {
+            let arxive = info.zip_file_name.clone();
+            let index = info.photo_index_in_zip;
+            arxives.entry(arxive).or_insert_with(Vec::new).push(index);
+        }
+        let mut analysis_results = Vec::new();
+        for (zip_file, indices) in arxives {
+            let _span = tracing::info_span!("archive", archive = %zip_file).entered();
+            cancel.check()?;
+            if cancel.timed_out() {
+                break;
+            }
+            let unpacked = zip::extract_zip_archive(&self.image_dir, &zip_file, indices)?;
+            let yolo_results =
+                crate::core::yolo::analyze_images_using_yolo(unpacked, model, cancel)?;
+            analysis_results.extend(yolo_results);
+        }
+        Ok(analysis_results)
+    }
+}
+
+// Merges EXIF and year/month index of one archive, returns estimated memory of its EXIF. 35mm
+// equivalent focal lengths are filled in here so that changed crop factors apply on restart.
+fn merge_exif(
+    exif_cache: &mut ExifCache,
+    by_year_month: &mut ByYearMonth,
+    exif: &ExifCacheSerialized,
+    partial_by_year_month: &ByYearMonth,
+) -> usize {
+    let mut bytes = 0;
+    // Convert String back to ZipInfo
+    exif_cache.extend(exif.iter().filter_map(|(key, exif)| {
+        PhotoInfo::deserialize_from_key(key.clone())
+            .ok()
+            .map(|photo_info| {
+                bytes += photo_info_size(&photo_info) + exif_size(exif);
+                let mut exif = exif.clone();
+                exif.normalize_focal_len();
+                (photo_info, exif)
+            })
+    }));
+    for (year, month_map) in partial_by_year_month {
+        for (month, infos) in month_map {
+            by_year_month
+                .entry(*year)
+                .or_insert_with(HashMap::new)
+                .entry(*month)
+                .or_insert_with(Vec::new)
+                .extend(infos.iter().cloned());
+            bytes += infos.iter().map(photo_info_size).sum::<usize>();
+        }
+    }
+    bytes
+}
+
+// Photos without EXIF date are dated by their file names (IMG_20230906_142745.jpg) and moved
+// from year 0 to the right year/month
+fn date_from_file_names(exif: &mut ExifCacheSerialized, by_year_month: &mut ByYearMonth) {
+    let mut dated = Vec::new();
+    for (key, exif) in exif.iter_mut().filter(|(_, exif)| exif.year == 0) {
+        let Ok(info) = PhotoInfo::deserialize_from_key(key.clone()) else {
+            continue;
+        };
+        let Some(date_time) = exif::date_from_file_name(&info.photo_file_name) else {
+            continue;
+        };
+        let Ok((year, month, ..)) = parse_date_time(&date_time) else {
+            continue;
+        };
+        exif.year = year;
+        exif.month = month;
+        // same format as date time read from EXIF
+        exif.date_time = format!("\"{date_time}\"");
+        dated.push((info, year, month));
+    }
+    if dated.is_empty() {
+        return;
+    }
+    if let Some(undated) = by_year_month.get_mut(&0) {
+        let moved = dated
+            .iter()
+            .map(|(info, _, _)| info)
+            .collect::<HashSet<_>>();
+        for infos in undated.values_mut() {
+            infos.retain(|info| !moved.contains(info));
+        }
+        undated.retain(|_, infos| !infos.is_empty());
+    }
+    by_year_month.retain(|_, by_month| !by_month.is_empty());
+    for (info, year, month) in dated {
+        by_year_month
+            .entry(year)
+            .or_default()
+            .entry(month)
+            .or_default()
+            .push(info);
+    }
+}
+
+// Photos grouped by their archive
+fn by_archive(image_infos: Vec<&PhotoInfo>) -> HashMap<String, Vec<PhotoInfo>> {
+    let mut archives: HashMap<String, Vec<PhotoInfo>> = HashMap::new();
+    for info in image_infos {
+        archives
+            .entry(info.zip_file_name.clone())
+            .or_default()
+            .push(info.clone());
+    }
+    archives
+}
+
+fn millis_since(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+fn photo_info_size(info: &PhotoInfo) -> usize {
+    size_of::<PhotoInfo>() + info.zip_file_name.capacity() + info.photo_file_name.capacity()
+}
+
+fn exif_size(exif: &exif::ExifInfo) -> usize {
+    size_of::<exif::ExifInfo>()
+        + [
+            &exif.model,
+            &exif.date_time,
+            &exif.aperture,
+            &exif.shutter_speed,
+            &exif.iso,
+            &exif.focal_len,
+            &exif.lens,
+        ]
+        .iter()
+        .map(|s| s.capacity())
+        .sum::<usize>()
+        + exif.description.as_ref().map(|s| s.capacity()).unwrap_or(0)
+        + exif.color_space.as_ref().map(|s| s.capacity()).unwrap_or(0)
+}
+
+/// Special Google Photos folder a Takeout entry is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TakeoutFolder {
+    /// Deleted photos, left out of searches unless asked for
+    Trash,
+    /// Photos archived in Google Photos, still searched
+    Archive,
+}
+
+impl TakeoutFolder {
+    /// Folder of the entry by its path, e.g. "Takeout/Google Photos/Trash/IMG_0001.jpg". The
+    /// trash is named "Bin" in some locales.
+    pub fn of(file_name: &str) -> Option<Self> {
+        let (folders, _) = file_name.rsplit_once('/')?;
+        folders
+            .split('/')
+            .find_map(|folder| match folder.to_lowercase().as_str() {
+                "trash" | "bin" => Some(TakeoutFolder::Trash),
+                "archive" => Some(TakeoutFolder::Archive),
+                _ => None,
+            })
+    }
+}
+
+// Google Takeout stores photos in "Takeout/Google Photos/Photos from <year>/" folders
+fn takeout_year(file_name: &str) -> Option<u32> {
+    let (_, rest) = file_name.split_once("Photos from ")?;
+    rest.get(..4)?.parse().ok()
+}
+
+// Class names of objects detected by all models and labels of all analysis stages
+fn photo_labels(
+    object_detection: &ObjectDetectionByModel,
+    analysis: &AnalysisStore,
+    photo_info: &PhotoInfo,
+) -> Vec<String> {
+    let mut labels = object_detection
+        .values()
+        .filter_map(|detections| detections.get(photo_info))
+        .flatten()
+        .map(|object| object.class_name.clone())
+        .chain(
+            analysis
+                .values()
+                .filter_map(|results| results.get(photo_info))
+                .flat_map(labels_of)
+                .map(|label| label.name),
+        )
+        .collect::<Vec<String>>();
+    labels.sort();
+    labels.dedup();
+    labels
+}
+
+// Cover cache key of the calendar month
+fn month_key(year: u32, month: u32) -> String {
+    format!("month:{year:04}-{month:02}")
+}
+
+pub(crate) fn form_file(image_dir: &str, zip_file: &str, suffix: &str) -> String {
+    format!("{}/{}.{}.json", image_dir, zip_file, suffix)
+}
+
+fn mime_from_image(image_data: &Vec<u8>) -> String {
+    match crate::core::image::guess_format(image_data) {
+        Ok(format) => match format {
             crate::core::image::ImageFormat::Png => "image/png".to_string(),
             crate::core::image::ImageFormat::Jpeg => "image/jpeg".to_string(),
             crate::core::image::ImageFormat::Gif => "image/gif".to_string(),
@@ -479,3 +3731,78 @@ fn mime_from_image(image_data: &Vec<u8>) -> String {
         Err(_) => "application/octet-stream".to_string(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+
+    use crate::core::image_cache::PhotoCache;
+
+    // Image directory with archives of small JPEG photos
+    fn image_dir(name: &str, archives: usize, photos: usize) -> std::path::PathBuf {
+        let image_dir =
+            std::env::temp_dir().join(format!("image-cache-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(8, 8)
+            .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+            .unwrap();
+        for archive in 0..archives {
+            let zip = std::fs::File::create(image_dir.join(format!("takeout-{archive}.zip")));
+            let mut writer = zip::ZipWriter::new(zip.unwrap());
+            for photo in 0..photos {
+                writer
+                    .start_file(
+                        format!("Takeout/Photos/IMG_{photo}.jpg"),
+                        SimpleFileOptions::default(),
+                    )
+                    .unwrap();
+                writer.write_all(jpeg.get_ref()).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        image_dir
+    }
+
+    fn loaded_bytes(cache: &PhotoCache) -> usize {
+        cache
+            .exif_usage
+            .read()
+            .unwrap()
+            .values()
+            .map(|u| u.bytes)
+            .sum()
+    }
+
+    #[test]
+    fn test_exif_budget_after_full_read() {
+        let image_dir = image_dir("budget", 3, 4);
+        let mut cache = PhotoCache::build(image_dir.to_str().unwrap()).unwrap();
+        let total = loaded_bytes(&cache);
+        assert!(total > 0);
+        // one archive fits, two do not
+        let budget = total / 2;
+        cache.exif_budget = Some(budget);
+
+        for _ in 0..2 {
+            let exif_cache = cache.exif_index();
+            assert_eq!(exif_cache.len(), 12);
+            drop(exif_cache);
+            assert!(loaded_bytes(&cache) <= budget);
+            assert!(!cache.unloaded.read().unwrap().is_empty());
+        }
+
+        let mut visited = 0;
+        cache.for_each_exif(|_, exif| {
+            assert!(exif.is_some());
+            assert!(loaded_bytes(&cache) <= budget);
+            visited += 1;
+        });
+        assert_eq!(visited, 12);
+        assert!(loaded_bytes(&cache) <= budget);
+
+        std::fs::remove_dir_all(image_dir).unwrap();
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::{BTreeMap, HashMap};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::core::{
+    error::PhotoInsightError,
+    exif::ExifInfo,
+    image_cache::TimeGroup,
+    overrides::{normalize_date_time, timestamp_of},
+};
+
+lazy_static! {
+    static ref TIME_RE: Regex = Regex::new(r"(\d\d):\d\d:\d\d").unwrap();
+}
+
+/// Number of most frequent values reported per setting
+const TOP_VALUES: usize = 10;
+
+/// Shooting scenario derived from the time the photo was taken
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scenario {
+    All,
+    /// 06:00 - 19:59
+    Day,
+    /// 20:00 - 05:59
+    Night,
+}
+
+impl Scenario {
+    pub fn parse(scenario: &str) -> Result<Self, PhotoInsightError> {
+        match scenario.to_lowercase().as_str() {
+            "all" | "" => Ok(Scenario::All),
+            "day" => Ok(Scenario::Day),
+            "night" => Ok(Scenario::Night),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid scenario: {scenario}, use one of all, day, night"
+            ))),
+        }
+    }
+
+    fn matches(&self, exif: &ExifInfo) -> bool {
+        let hour = TIME_RE
+            .captures(&exif.date_time)
+            .and_then(|caps| caps[1].parse::<u32>().ok());
+        match (self, hour) {
+            (Scenario::All, _) => true,
+            (Scenario::Day, Some(h)) => (6..20).contains(&h),
+            (Scenario::Night, Some(h)) => !(6..20).contains(&h),
+            (_, None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Statistics of a single setting, unknown values are not counted
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingStats {
+    pub count: usize,
+    pub min: Option<f32>,
+    pub median: Option<f32>,
+    pub max: Option<f32>,
+    /// Most frequent values, most frequent first
+    pub top_values: Vec<ValueCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IsoShutterPattern {
+    pub iso: String,
+    pub shutter_speed: String,
+    pub count: usize,
+}
+
+/// Shutter actuations of a camera body within a period
+#[derive(Debug, Clone, Serialize)]
+pub struct ActuationPeriod {
+    /// Bucket of the time taken, e.g. 2024-05 or 2024
+    pub period: String,
+    pub photos: usize,
+    /// Highest shutter count of the period
+    pub shutter_count: u32,
+    /// Actuations since the last photo of the previous period
+    pub actuations: u32,
+}
+
+/// Estimated shutter wear of a camera body, the counts include frames not kept in the archive
+#[derive(Debug, Clone, Serialize)]
+pub struct BodyActuations {
+    pub camera: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    /// Photos with a shutter count
+    pub photos: usize,
+    pub first_taken: String,
+    pub last_taken: String,
+    pub first_count: u32,
+    pub last_count: u32,
+    /// Actuations between the first and the last photo
+    pub actuations: u32,
+    /// Average over the days between the first and the last photo, none within a single day
+    pub actuations_per_day: Option<f64>,
+    pub periods: Vec<ActuationPeriod>,
+}
+
+/// Flash usage of photos, unknown when the camera did not record it
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FlashUsage {
+    pub fired: usize,
+    pub not_fired: usize,
+    pub unknown: usize,
+    /// Share of the photos with a flash record taken with the flash fired
+    pub fired_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureModes {
+    pub photo_count: usize,
+    pub flash: FlashUsage,
+    /// Most frequent exposure programs (e.g. aperture_priority) of photos recording one
+    pub exposure_programs: Vec<ValueCount>,
+    /// Most frequent metering modes (e.g. pattern, spot) of photos recording one
+    pub metering_modes: Vec<ValueCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureAnalytics {
+    #[serde(flatten)]
+    pub overall: ExposureModes,
+    /// The same summary per year taken, undated photos count in the overall summary only
+    pub by_year: BTreeMap<u32, ExposureModes>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingsInsights {
+    pub scenario: Scenario,
+    pub photo_count: usize,
+    pub focal_len: SettingStats,
+    pub aperture: SettingStats,
+    pub iso: SettingStats,
+    /// Values as recorded in the EXIF cache, denominator for fractions (e.g. "250" for 1/250s)
+    pub shutter_speed: SettingStats,
+    /// Most frequent ISO and shutter speed combinations
+    pub iso_shutter_patterns: Vec<IsoShutterPattern>,
+    pub cameras: Vec<ValueCount>,
+    pub lenses: Vec<ValueCount>,
+}
+
+pub(crate) fn unquote(value: &str) -> &str {
+    value.trim_matches('"')
+}
+
+fn matches_filter(value: &str, filter: &Option<String>) -> bool {
+    filter
+        .as_ref()
+        .is_none_or(|f| value.to_lowercase().contains(&f.to_lowercase()))
+}
+
+pub(crate) fn top_values<'a>(values: impl Iterator<Item = &'a str>) -> Vec<ValueCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    let mut counts = counts
+        .into_iter()
+        .map(|(value, count)| ValueCount {
+            value: value.to_owned(),
+            count,
+        })
+        .collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    counts.truncate(TOP_VALUES);
+    counts
+}
+
+fn setting_stats(values: Vec<&str>) -> SettingStats {
+    let known = values
+        .into_iter()
+        .filter(|v| !v.is_empty() && *v != "0" && *v != "unknown")
+        .collect::<Vec<_>>();
+    let mut numbers = known
+        .iter()
+        .filter_map(|v| v.parse::<f32>().ok())
+        .collect::<Vec<_>>();
+    numbers.sort_by(|a, b| a.total_cmp(b));
+    SettingStats {
+        count: known.len(),
+        min: numbers.first().copied(),
+        median: numbers.get(numbers.len() / 2).copied(),
+        max: numbers.last().copied(),
+        top_values: top_values(known.into_iter()),
+    }
+}
+
+/// Summarizes shooting settings of photos taken with matching camera and lens
+/// (case insensitive, partial match) in the given scenario
+pub fn settings_insights<'a>(
+    exifs: impl Iterator<Item = &'a ExifInfo>,
+    camera: &Option<String>,
+    lens: &Option<String>,
+    scenario: Scenario,
+) -> SettingsInsights {
+    let selected = exifs
+        .filter(|e| {
+            matches_filter(unquote(&e.model), camera)
+                && matches_filter(unquote(&e.lens), lens)
+                && scenario.matches(e)
+        })
+        .collect::<Vec<&ExifInfo>>();
+
+    let mut patterns: HashMap<(&str, &str), usize> = HashMap::new();
+    for exif in selected.iter() {
+        let iso = unquote(&exif.iso);
+        let shutter_speed = unquote(&exif.shutter_speed);
+        if iso != "0" && !shutter_speed.is_empty() && shutter_speed != "unknown" {
+            *patterns.entry((iso, shutter_speed)).or_insert(0) += 1;
+        }
+    }
+    let mut iso_shutter_patterns = patterns
+        .into_iter()
+        .map(|((iso, shutter_speed), count)| IsoShutterPattern {
+            iso: iso.to_owned(),
+            shutter_speed: shutter_speed.to_owned(),
+            count,
+        })
+        .collect::<Vec<_>>();
+    iso_shutter_patterns.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| (&a.iso, &a.shutter_speed).cmp(&(&b.iso, &b.shutter_speed)))
+    });
+    iso_shutter_patterns.truncate(TOP_VALUES);
+
+    SettingsInsights {
+        scenario,
+        photo_count: selected.len(),
+        focal_len: setting_stats(selected.iter().map(|e| unquote(&e.focal_len)).collect()),
+        aperture: setting_stats(selected.iter().map(|e| unquote(&e.aperture)).collect()),
+        iso: setting_stats(selected.iter().map(|e| unquote(&e.iso)).collect()),
+        shutter_speed: setting_stats(selected.iter().map(|e| unquote(&e.shutter_speed)).collect()),
+        iso_shutter_patterns,
+        cameras: top_values(selected.iter().map(|e| unquote(&e.model))),
+        lenses: top_values(selected.iter().map(|e| unquote(&e.lens))),
+    }
+}
+
+/// Estimated shutter actuations over time per camera body (model and serial number) of cameras
+/// matching the filter (case insensitive, partial match), from the shutter counts recorded in
+/// the photos. A count lower than the previous one (a replaced shutter) starts over from there.
+pub fn shutter_counts<'a>(
+    exifs: impl Iterator<Item = &'a ExifInfo>,
+    camera: &Option<String>,
+    group: TimeGroup,
+) -> Vec<BodyActuations> {
+    let mut bodies: HashMap<(&str, Option<&str>), Vec<(String, u32)>> = HashMap::new();
+    for exif in exifs.filter(|e| matches_filter(unquote(&e.model), camera)) {
+        let (Some(count), Ok(date_time)) = (
+            exif.shutter_count,
+            normalize_date_time(unquote(&exif.date_time)),
+        ) else {
+            continue;
+        };
+        bodies
+            .entry((unquote(&exif.model), exif.body_serial.as_deref()))
+            .or_default()
+            .push((date_time, count));
+    }
+    let mut actuations = bodies
+        .into_iter()
+        .map(|((camera, serial), mut samples)| {
+            samples.sort();
+            body_actuations(camera, serial, &samples, group)
+        })
+        .collect::<Vec<_>>();
+    actuations.sort_by(|a, b| (&a.camera, &a.serial).cmp(&(&b.camera, &b.serial)));
+    actuations
+}
+
+// Samples sorted by the time taken, there is at least one
+fn body_actuations(
+    camera: &str,
+    serial: Option<&str>,
+    samples: &[(String, u32)],
+    group: TimeGroup,
+) -> BodyActuations {
+    let mut periods: Vec<ActuationPeriod> = Vec::new();
+    let mut previous = samples[0].1;
+    let mut total = 0;
+    for (date_time, count) in samples {
+        let delta = count.saturating_sub(previous);
+        previous = *count;
+        total += delta;
+        let period = group.bucket(date_time).unwrap_or_default();
+        match periods.last_mut() {
+            Some(last) if last.period == period => {
+                last.photos += 1;
+                last.shutter_count = last.shutter_count.max(*count);
+                last.actuations += delta;
+            }
+            _ => periods.push(ActuationPeriod {
+                period,
+                photos: 1,
+                shutter_count: *count,
+                actuations: delta,
+            }),
+        }
+    }
+    let (first_taken, first_count) = samples[0].clone();
+    let (last_taken, last_count) = samples[samples.len() - 1].clone();
+    let days = match (timestamp_of(&first_taken), timestamp_of(&last_taken)) {
+        (Ok(first), Ok(last)) => (last - first) as f64 / 86400.0,
+        _ => 0.0,
+    };
+    BodyActuations {
+        camera: camera.to_owned(),
+        serial: serial.map(str::to_owned),
+        photos: samples.len(),
+        first_taken,
+        last_taken,
+        first_count,
+        last_count,
+        actuations: total,
+        actuations_per_day: (days >= 1.0).then(|| total as f64 / days),
+        periods,
+    }
+}
+
+fn exposure_modes(exifs: &[&ExifInfo]) -> ExposureModes {
+    let mut flash = FlashUsage::default();
+    for exif in exifs {
+        match exif.flash {
+            Some(true) => flash.fired += 1,
+            Some(false) => flash.not_fired += 1,
+            None => flash.unknown += 1,
+        }
+    }
+    let recorded = flash.fired + flash.not_fired;
+    flash.fired_ratio = (recorded > 0).then(|| flash.fired as f64 / recorded as f64);
+    ExposureModes {
+        photo_count: exifs.len(),
+        flash,
+        exposure_programs: top_values(exifs.iter().filter_map(|e| e.exposure_program.as_deref())),
+        metering_modes: top_values(exifs.iter().filter_map(|e| e.metering_mode.as_deref())),
+    }
+}
+
+/// Summarizes flash usage, exposure programs and metering modes of photos taken with matching
+/// camera (case insensitive, partial match) within the years, overall and per year
+pub fn exposure_analytics<'a>(
+    exifs: impl Iterator<Item = &'a ExifInfo>,
+    camera: &Option<String>,
+    year_start: Option<u32>,
+    year_end: Option<u32>,
+) -> ExposureAnalytics {
+    let years = (year_start.is_some() || year_end.is_some())
+        .then(|| year_start.unwrap_or(1)..=year_end.unwrap_or(u32::MAX));
+    let selected = exifs
+        .filter(|e| matches_filter(unquote(&e.model), camera))
+        .filter(|e| years.as_ref().is_none_or(|years| years.contains(&e.year)))
+        .collect::<Vec<&ExifInfo>>();
+    let mut by_year: BTreeMap<u32, Vec<&ExifInfo>> = BTreeMap::new();
+    for exif in selected.iter().filter(|e| e.year > 0) {
+        by_year.entry(exif.year).or_default().push(exif);
+    }
+    ExposureAnalytics {
+        overall: exposure_modes(&selected),
+        by_year: by_year
+            .into_iter()
+            .map(|(year, exifs)| (year, exposure_modes(&exifs)))
+            .collect(),
+    }
+}
@@ -0,0 +1,114 @@
+/// Nikon MakerNote tags
+const NIKON_SERIAL_NUMBER: u16 = 0x001d;
+const NIKON_SHUTTER_COUNT: u16 = 0x00a7;
+/// TIFF field types
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+
+/// Camera body details recorded in the vendor MakerNote
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MakerNoteInfo {
+    /// Shutter actuations of the body when the photo was taken
+    pub shutter_count: Option<u32>,
+    pub serial: Option<String>,
+}
+
+/// Reads the MakerNote of the camera make. Only Nikon records the shutter count unencrypted
+/// in every photo, MakerNotes of other makes yield nothing.
+pub fn parse(make: &str, maker_note: &[u8]) -> MakerNoteInfo {
+    let make = make.trim_matches('"').to_lowercase();
+    if make.starts_with("nikon") {
+        nikon(maker_note).unwrap_or_default()
+    } else {
+        MakerNoteInfo::default()
+    }
+}
+
+// Nikon type 3 MakerNote: "Nikon\0", a version and padding, then a TIFF structure with offsets
+// relative to its own header. Type 1 MakerNotes of old compacts have no shutter count.
+fn nikon(maker_note: &[u8]) -> Option<MakerNoteInfo> {
+    let tiff = Tiff::new(maker_note.strip_prefix(b"Nikon\0")?.get(4..)?)?;
+    Some(MakerNoteInfo {
+        shutter_count: tiff.number(NIKON_SHUTTER_COUNT),
+        serial: tiff.text(NIKON_SERIAL_NUMBER),
+    })
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    ifd: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..4)? {
+            b"MM\0*" => true,
+            b"II*\0" => false,
+            _ => return None,
+        };
+        let mut tiff = Self {
+            data,
+            big_endian,
+            ifd: 0,
+        };
+        tiff.ifd = tiff.u32(4)? as usize;
+        Some(tiff)
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    // Offset of the 12 byte entry of the tag in the first IFD
+    fn entry(&self, tag: u16) -> Option<usize> {
+        let count = self.u16(self.ifd)? as usize;
+        (0..count)
+            .map(|i| self.ifd + 2 + i * 12)
+            .find(|entry| self.u16(*entry) == Some(tag))
+    }
+
+    fn number(&self, tag: u16) -> Option<u32> {
+        let entry = self.entry(tag)?;
+        match self.u16(entry + 2)? {
+            SHORT => self.u16(entry + 8).map(u32::from),
+            LONG => self.u32(entry + 8),
+            _ => None,
+        }
+    }
+
+    fn text(&self, tag: u16) -> Option<String> {
+        let entry = self.entry(tag)?;
+        if self.u16(entry + 2)? != ASCII {
+            return None;
+        }
+        let count = self.u32(entry + 4)? as usize;
+        // values up to 4 bytes are stored in the entry itself
+        let offset = if count <= 4 {
+            entry + 8
+        } else {
+            self.u32(entry + 8)? as usize
+        };
+        let bytes = self.data.get(offset..offset.checked_add(count)?)?;
+        let text = String::from_utf8_lossy(bytes)
+            .trim_end_matches('\0')
+            .trim()
+            .to_owned();
+        Some(text).filter(|text| !text.is_empty())
+    }
+}
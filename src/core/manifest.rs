@@ -0,0 +1,330 @@
+use std::{collections::HashMap, path::Path, time::UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    bundle::archive_sidecars,
+    config::IndexConfig,
+    error::PhotoInsightError,
+    hidden::HiddenPhotos,
+    image_cache::{ByYearMonth, ExifCacheSerialized},
+    overrides::MetadataOverrides,
+    store,
+    zip::{self, EntryLocation, ZipEntry},
+};
+
+const MANIFEST_FILE: &str = "photo-mcp.manifest.json.zst";
+const ARCHIVES_FILE: &str = "photo-mcp.archives.json";
+const MANIFEST_VERSION: u32 = 5;
+
+/// Size and modification time of an archive, a changed fingerprint makes its index stale
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveFingerprint {
+    pub size: u64,
+    pub modified: u64,
+}
+
+impl ArchiveFingerprint {
+    /// Fingerprint of the archive, of all its parts for split archives
+    pub fn of(image_dir: &str, zip_file_name: &str) -> Result<Self, PhotoInsightError> {
+        let zip_path = Path::new(image_dir).join(zip_file_name);
+        let mut parts = zip::split_parts(&zip_path);
+        if parts.is_empty() {
+            parts.push(zip_path);
+        }
+        let mut fingerprint = Self {
+            size: 0,
+            modified: 0,
+        };
+        for part in parts {
+            let metadata = std::fs::metadata(part).map_err(|e| PhotoInsightError::new(e))?;
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            fingerprint.size += metadata.len();
+            fingerprint.modified = fingerprint.modified.max(modified);
+        }
+        Ok(fingerprint)
+    }
+}
+
+/// Index of one archive: photo entries, live photo pairs, content hashes, EXIF and
+/// year/month index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveIndex {
+    pub fingerprint: ArchiveFingerprint,
+    pub images: Vec<(usize, String)>,
+    /// Still index => motion part
+    pub motion: HashMap<usize, ZipEntry>,
+    /// Image index => SHA-256 of its content
+    pub sha256: HashMap<usize, String>,
+    /// Entry index => name as stored in the archive, of entries stored under another name
+    #[serde(default)]
+    pub original_names: HashMap<usize, String>,
+    /// Entry index => position of the entry in the archive file, empty for split archives
+    #[serde(default)]
+    pub locations: HashMap<usize, EntryLocation>,
+    pub exif: ExifCacheSerialized,
+    pub by_year_month: ByYearMonth,
+}
+
+/// Indexes of all archives in a single zstd compressed JSON file, loaded at startup instead
+/// of the per-zip sidecars. Analysis results are not part of the manifest.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    version: u32,
+    /// Index configuration the manifest was built with, archive indexes leave excluded
+    /// entries out
+    #[serde(default)]
+    index: IndexConfig,
+    pub archives: HashMap<String, ArchiveIndex>,
+}
+
+impl Manifest {
+    pub(crate) fn file_name(image_dir: &str) -> String {
+        Path::new(image_dir)
+            .join(MANIFEST_FILE)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Loads the manifest, an empty one is returned if it is missing or unreadable
+    pub fn load(image_dir: &str) -> Self {
+        let file_name = Self::file_name(image_dir);
+        if !store::exists(&file_name) {
+            return Self::default();
+        }
+        let manifest = crate::STORE.read(&file_name).and_then(|data| {
+            let json = zstd::decode_all(data.as_slice()).map_err(|e| PhotoInsightError::new(e))?;
+            serde_json::from_slice::<Manifest>(&json).map_err(|e| PhotoInsightError::new(e))
+        });
+        match manifest {
+            Ok(manifest) if manifest.index != crate::CONFIG.index => {
+                tracing::info!("Index configuration changed, ignoring manifest");
+                Self::default()
+            }
+            Ok(manifest) if manifest.version == MANIFEST_VERSION => {
+                tracing::info!("Loaded manifest of {} archives", manifest.archives.len());
+                manifest
+            }
+            Ok(manifest) => {
+                tracing::warn!("Ignoring manifest version {}", manifest.version);
+                Self::default()
+            }
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable manifest {file_name}: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, image_dir: &str) -> Result<(), PhotoInsightError> {
+        let json = serde_json::to_vec(self).map_err(|e| PhotoInsightError::new(e))?;
+        let data = zstd::encode_all(json.as_slice(), 0).map_err(|e| PhotoInsightError::new(e))?;
+        crate::STORE.write(&Self::file_name(image_dir), data)
+    }
+
+    /// Index of the archive if it was not modified since the manifest was written
+    pub fn take_fresh(
+        &mut self,
+        zip_file_name: &str,
+        fingerprint: ArchiveFingerprint,
+    ) -> Option<ArchiveIndex> {
+        self.archives
+            .remove(zip_file_name)
+            .filter(|index| index.fingerprint == fingerprint)
+    }
+
+    /// Moves indexes of renamed archives to their new names
+    pub fn relink(&mut self, relinked: &[RelinkedArchive]) {
+        for RelinkedArchive { from, to } in relinked {
+            let Some(index) = self.archives.remove(from) else {
+                continue;
+            };
+            let relinked_index = serde_json::to_value(&index)
+                .map(|mut value| {
+                    relink_json(&mut value, from, to);
+                    value
+                })
+                .and_then(serde_json::from_value::<ArchiveIndex>);
+            match relinked_index {
+                Ok(index) => {
+                    self.archives.insert(to.clone(), index);
+                }
+                Err(e) => tracing::warn!("can't relink index of {from} to {to}: {e}"),
+            }
+        }
+    }
+
+    pub fn new(archives: HashMap<String, ArchiveIndex>) -> Self {
+        Self {
+            version: MANIFEST_VERSION,
+            index: crate::CONFIG.index.clone(),
+            archives,
+        }
+    }
+}
+
+/// Identity of an archive independent of its file name: size and hash of the central
+/// directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveIdentity {
+    pub size: u64,
+    pub central_directory: String,
+}
+
+/// Archive renamed since the last start, its sidecars were moved to the new name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelinkedArchive {
+    pub from: String,
+    pub to: String,
+}
+
+/// Identities of the archives seen at the last start, persisted in
+/// `<image_dir>/photo-mcp.archives.json`, so renamed archives keep their sidecars
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ArchiveRegistry {
+    archives: HashMap<String, ArchiveIdentity>,
+}
+
+impl ArchiveRegistry {
+    pub(crate) fn file_name(image_dir: &str) -> String {
+        Path::new(image_dir)
+            .join(ARCHIVES_FILE)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn load(image_dir: &str) -> Self {
+        let file_name = Self::file_name(image_dir);
+        if !store::exists(&file_name) {
+            return Self::default();
+        }
+        store::read_json(&file_name).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring unreadable archive registry {file_name}: {e}");
+            Self::default()
+        })
+    }
+
+    /// Finds archives renamed since the last start: a new archive name with the identity of an
+    /// archive name gone missing. Sidecars of renamed archives (and photo references in the
+    /// metadata overrides and hidden photos) are moved to the new name instead of being
+    /// rebuilt. Identity of known archives is only computed again when their size changes.
+    pub fn track(image_dir: &str, zip_files: &[String]) -> Vec<RelinkedArchive> {
+        let known = Self::load(image_dir);
+        let mut current = HashMap::new();
+        for zip in zip_files {
+            let Ok(metadata) = std::fs::metadata(Path::new(image_dir).join(zip)) else {
+                continue;
+            };
+            let identity = match known.archives.get(zip) {
+                Some(identity) if identity.size == metadata.len() => identity.clone(),
+                _ => match zip::central_directory_hash(image_dir, zip) {
+                    Ok(central_directory) => ArchiveIdentity {
+                        size: metadata.len(),
+                        central_directory,
+                    },
+                    Err(e) => {
+                        tracing::warn!("can't fingerprint archive {zip}: {e}");
+                        continue;
+                    }
+                },
+            };
+            current.insert(zip.clone(), identity);
+        }
+
+        let mut relinked = Vec::new();
+        for (to, identity) in current.iter() {
+            if known.archives.contains_key(to) {
+                continue;
+            }
+            let renamed = known
+                .archives
+                .iter()
+                .find(|(from, known)| *known == identity && !current.contains_key(*from));
+            if let Some((from, _)) = renamed {
+                relink_sidecars(image_dir, from, to);
+                relinked.push(RelinkedArchive {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+        relinked.sort_by(|a, b| a.to.cmp(&b.to));
+        for RelinkedArchive { from, to } in relinked.iter() {
+            tracing::info!("Archive {from} was renamed to {to}, re-linked its sidecars");
+        }
+        if !relinked.is_empty() {
+            tracing::info!("Re-linked {} renamed archives", relinked.len());
+        }
+
+        if current != known.archives {
+            let registry = Self { archives: current };
+            if let Err(e) = store::write_json(&Self::file_name(image_dir), &registry) {
+                tracing::error!("can't write archive registry: {e}");
+            }
+        }
+        relinked
+    }
+}
+
+// Moves sidecars of the archive to its new name, sidecars existing under the new name are kept
+fn relink_sidecars(image_dir: &str, from: &str, to: &str) {
+    let sidecars = archive_sidecars(image_dir, from)
+        .into_iter()
+        .zip(archive_sidecars(image_dir, to))
+        .filter(|(old, new)| store::exists(old) && !store::exists(new));
+    for (old, new) in sidecars {
+        let result = relink_file(&old, &new, from, to).and_then(|_| crate::STORE.remove(&old));
+        if let Err(e) = result {
+            tracing::error!("can't relink sidecar {old}: {e}");
+        }
+    }
+    for file_name in [
+        HiddenPhotos::file_name(image_dir),
+        MetadataOverrides::file_name(image_dir),
+    ] {
+        if store::exists(&file_name) {
+            if let Err(e) = relink_file(&file_name, &file_name, from, to) {
+                tracing::error!("can't relink photos in {file_name}: {e}");
+            }
+        }
+    }
+}
+
+fn relink_file(old: &str, new: &str, from: &str, to: &str) -> Result<(), PhotoInsightError> {
+    let mut value: serde_json::Value = store::read_json(old)?;
+    relink_json(&mut value, from, to);
+    store::write_json(new, &value)
+}
+
+// Replaces the archive name in photo keys (`zip|file|index`) and zip_file_name members
+fn relink_json(value: &mut serde_json::Value, from: &str, to: &str) {
+    let prefix = format!("{from}|");
+    let relink_key = |key: &str| match key.strip_prefix(&prefix) {
+        Some(rest) => format!("{to}|{rest}"),
+        None => key.to_owned(),
+    };
+    match value {
+        serde_json::Value::Object(map) => {
+            let members = std::mem::take(map);
+            for (key, mut member) in members {
+                if key == "zip_file_name" && member == from {
+                    member = serde_json::Value::from(to);
+                }
+                relink_json(&mut member, from, to);
+                map.insert(relink_key(&key), member);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            items
+                .iter_mut()
+                .for_each(|item| relink_json(item, from, to));
+        }
+        serde_json::Value::String(text) => *text = relink_key(text),
+        _ => {}
+    }
+}
@@ -1,7 +1,55 @@
+pub mod analyzer;
+pub mod animation;
+pub mod backup;
+pub mod barcode;
+pub mod benchmark;
+pub mod bmff;
+pub mod bundle;
+pub mod cancel;
+pub mod catalog;
+pub mod class_aliases;
+pub mod cleanup;
+pub mod collections;
+pub mod color;
+pub mod color_label;
+pub mod compare;
+pub mod config;
+pub mod cover;
+pub mod coverage;
+pub mod digest;
 pub mod error;
 pub mod exif;
+pub mod external;
+pub mod features;
+pub mod full_text;
+pub mod hidden;
+pub mod highlights;
+pub mod i18n;
 pub mod image;
 pub mod image_cache;
+pub mod insights;
+pub mod maker_note;
+pub mod manifest;
+pub mod models;
+pub mod motion;
+pub mod name_folding;
+pub mod open_vocabulary;
+pub mod overrides;
+pub mod photo_id;
+pub mod privacy;
+pub mod query_cache;
+pub mod query_plan;
+pub mod selection;
+pub mod setup;
+pub mod slideshow;
+pub mod stages;
+pub mod store;
+pub mod time_of_day;
+pub mod timings;
 pub mod traversal;
+pub mod warm_up;
+pub mod watcher;
+pub mod watermark;
+pub mod xmp;
 pub mod yolo;
 pub mod zip;
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::core::{
+    config::{ModelFileConfig, ModelsConfig},
+    error::PhotoInsightError,
+};
+
+/// Weights of the models, downloaded into the cache directory on first use and verified
+/// against their SHA-256 checksum. Files are verified once per process, a file failing the
+/// check is downloaded again (or reported in offline mode).
+pub struct ModelFiles {
+    config: ModelsConfig,
+    cache_dir: Option<PathBuf>,
+    verified: Mutex<HashSet<PathBuf>>,
+    // one download at a time, concurrent first uses wait for it
+    downloading: Mutex<()>,
+}
+
+impl ModelFiles {
+    pub fn from_config(config: &ModelsConfig) -> Self {
+        // the YOLO stack loads its weights from YOLO_V8_MODEL_PATH
+        let cache_dir = config
+            .cache_dir
+            .clone()
+            .or_else(|| std::env::var("YOLO_V8_MODEL_PATH").ok())
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from);
+        Self {
+            config: config.clone(),
+            cache_dir,
+            verified: Mutex::new(HashSet::new()),
+            downloading: Mutex::new(()),
+        }
+    }
+
+    fn files_of<'a>(&'a self, model: &'a str) -> impl Iterator<Item = &'a ModelFileConfig> {
+        self.config.files.iter().filter(move |f| f.model == model)
+    }
+
+    fn path_of(&self, file: &ModelFileConfig) -> Result<PathBuf, PhotoInsightError> {
+        let cache_dir = self.cache_dir.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message(
+                "no model cache directory, set models.cache_dir or YOLO_V8_MODEL_PATH",
+            )
+        })?;
+        Ok(cache_dir.join(&file.file))
+    }
+
+    /// Makes sure the files of the model are cached and verified, missing or corrupted files
+    /// are downloaded unless offline. Returns the directory of the verified files, none for
+    /// models without configured files, they are assumed to be installed by other means.
+    pub fn ensure(&self, model: &str) -> Result<Option<PathBuf>, PhotoInsightError> {
+        let mut verified_dir = None;
+        for file in self.files_of(model) {
+            let path = self.path_of(file)?;
+            verified_dir = path.parent().map(Path::to_path_buf);
+            if self.verified.lock().unwrap().contains(&path) {
+                continue;
+            }
+            let _downloading = self.downloading.lock().unwrap();
+            if path.exists() && sha256_of(&path)? == file.sha256.to_lowercase() {
+                self.verified.lock().unwrap().insert(path);
+                continue;
+            }
+            if self.config.offline {
+                return Err(PhotoInsightError::from_message(format!(
+                    "model file {} of {model} is missing or corrupted, downloads are disabled \
+                     in offline mode",
+                    path.display()
+                )));
+            }
+            self.download(file, &path)?;
+            self.verified.lock().unwrap().insert(path);
+        }
+        Ok(verified_dir)
+    }
+
+    fn url_of(&self, file: &ModelFileConfig) -> String {
+        match &self.config.mirror {
+            Some(mirror) => format!("{}/{}", mirror.trim_end_matches('/'), file.file),
+            None => file.url.clone(),
+        }
+    }
+
+    // Downloads into a temporary file next to the cached one, it replaces the cached file only
+    // when the checksum matches
+    fn download(&self, file: &ModelFileConfig, path: &Path) -> Result<(), PhotoInsightError> {
+        let url = self.url_of(file);
+        if url.is_empty() {
+            return Err(PhotoInsightError::from_message(format!(
+                "model file {} has no download url",
+                file.file
+            )));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| PhotoInsightError::new(e))?;
+        }
+        tracing::info!("Downloading model file {} from {url}", file.file);
+        let partial = path.with_extension("download");
+        let sha256 = fetch(&url, &partial).inspect_err(|_| {
+            let _ = std::fs::remove_file(&partial);
+        })?;
+        if sha256 != file.sha256.to_lowercase() {
+            let _ = std::fs::remove_file(&partial);
+            return Err(PhotoInsightError::from_message(format!(
+                "checksum of {url} is {sha256}, expected {}",
+                file.sha256
+            )));
+        }
+        std::fs::rename(&partial, path).map_err(|e| PhotoInsightError::new(e))?;
+        tracing::info!("Model file {} verified and cached", path.display());
+        Ok(())
+    }
+}
+
+fn sha256_of(path: &Path) -> Result<String, PhotoInsightError> {
+    let mut file = std::fs::File::open(path).map_err(|e| PhotoInsightError::new(e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| PhotoInsightError::new(e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Streams the url into the file, returns the SHA-256 of the content. Models are loaded from
+// tool calls running inside the server runtime, the download runs on its own thread and
+// runtime.
+fn fetch(url: &str, target: &Path) -> Result<String, PhotoInsightError> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .map_err(|e| PhotoInsightError::new(e))?;
+                runtime.block_on(async {
+                    let mut response = reqwest::get(url)
+                        .await
+                        .and_then(|response| response.error_for_status())
+                        .map_err(|e| PhotoInsightError::new(e))?;
+                    let mut file =
+                        std::fs::File::create(target).map_err(|e| PhotoInsightError::new(e))?;
+                    let mut hasher = Sha256::new();
+                    while let Some(chunk) = response
+                        .chunk()
+                        .await
+                        .map_err(|e| PhotoInsightError::new(e))?
+                    {
+                        hasher.update(&chunk);
+                        file.write_all(&chunk)
+                            .map_err(|e| PhotoInsightError::new(e))?;
+                    }
+                    Ok(format!("{:x}", hasher.finalize()))
+                })
+            })
+            .join()
+            .map_err(|_| PhotoInsightError::from_message("model download failed"))?
+    })
+}
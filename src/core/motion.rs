@@ -0,0 +1,47 @@
+use std::{
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::core::error::PhotoInsightError;
+
+static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+
+/// Extracts a JPEG frame at the given second of the video with ffmpeg. The video is written
+/// into a temporary file first, ffmpeg can't seek in QuickTime files read from a pipe.
+pub(crate) fn extract_frame(
+    ffmpeg: &str,
+    video: &[u8],
+    at_seconds: f32,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let path = std::env::temp_dir().join(format!(
+        "photo-mcp-motion-{}-{}.mov",
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, video).map_err(|e| PhotoInsightError::new(e))?;
+    let output = Command::new(ffmpeg)
+        .args(["-v", "error", "-ss", &at_seconds.max(0.0).to_string(), "-i"])
+        .arg(&path)
+        .args([
+            "-frames:v",
+            "1",
+            "-f",
+            "image2pipe",
+            "-vcodec",
+            "mjpeg",
+            "pipe:1",
+        ])
+        .output();
+    let _ = std::fs::remove_file(&path);
+    let output =
+        output.map_err(|e| PhotoInsightError::from_message(format!("can't run {ffmpeg}: {e}")))?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(PhotoInsightError::from_message(format!(
+            "{ffmpeg} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,160 @@
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+/// Lowest similarity of a fuzzy name match
+pub const FUZZY_MIN_SCORE: f32 = 0.7;
+
+/// Folding of photo and archive names for matching: Unicode compatibility normalization (so
+/// names from phones and macOS in decomposed form, or with full-width characters, match the
+/// query typed in composed form) and full case folding, optionally with accents removed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameFolding {
+    /// Remove accents and other diacritics, "café" matches "cafe"
+    pub fold_accents: bool,
+    /// Turkish and Azerbaijani dotted and dotless i: "I" folds to "ı" and "İ" to "i"
+    pub turkic: bool,
+}
+
+impl NameFolding {
+    /// Folding of the `search` config section
+    pub fn configured() -> Self {
+        let search = &crate::CONFIG.search;
+        Self::new(search.fold_accents, search.locale.as_deref())
+    }
+
+    /// `locale` is a language tag, e.g. `"tr"` or `"de-AT"`, only its language matters
+    pub fn new(fold_accents: bool, locale: Option<&str>) -> Self {
+        let language = locale
+            .and_then(|locale| locale.split(['-', '_']).next())
+            .map(|language| language.to_ascii_lowercase());
+        Self {
+            fold_accents,
+            turkic: matches!(language.as_deref(), Some("tr" | "az")),
+        }
+    }
+
+    /// Configured folding with the tool arguments applied
+    pub fn with(fold_accents: Option<bool>, locale: &Option<String>) -> Self {
+        let configured = Self::configured();
+        match locale {
+            Some(locale) => Self::new(
+                fold_accents.unwrap_or(configured.fold_accents),
+                Some(locale),
+            ),
+            None => Self {
+                fold_accents: fold_accents.unwrap_or(configured.fold_accents),
+                ..configured
+            },
+        }
+    }
+
+    pub fn fold(&self, name: &str) -> String {
+        if name.is_ascii() && !self.turkic {
+            return name.to_ascii_lowercase();
+        }
+        // dotted capital I decomposes into I and a combining dot, it is mapped first
+        let turkic = name.chars().map(|c| match c {
+            'I' if self.turkic => 'ı',
+            'İ' if self.turkic => 'i',
+            c => c,
+        });
+        let mut folded = String::with_capacity(name.len());
+        for c in turkic.collect::<String>().nfkd() {
+            match c {
+                'ß' | 'ẞ' => folded.push_str("ss"),
+                'ς' => folded.push('σ'),
+                c => folded.extend(c.to_lowercase()),
+            }
+        }
+        if !self.fold_accents {
+            return folded.nfc().collect();
+        }
+        let mut stripped = String::with_capacity(folded.len());
+        for c in folded.nfd().filter(|c| !is_combining_mark(*c)) {
+            match without_stroke(c) {
+                Some(letters) => stripped.push_str(letters),
+                None => stripped.push(c),
+            }
+        }
+        stripped
+    }
+}
+
+/// Similarity of the folded query and the folded file name (0 to 1): the best normalized
+/// Damerau-Levenshtein similarity of the query and a part of the name of about the query's
+/// length, so both typos ("IMG_1243" finds "IMG_1234.jpg") and partial names are tolerated.
+/// The directory of the name is ignored, so is the extension unless the query has one.
+pub fn fuzzy_score(query: &str, name: &str) -> f32 {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    let name = match name.rfind('.') {
+        Some(dot) if dot > 0 && !query.contains('.') => &name[..dot],
+        _ => name,
+    };
+    if name.contains(query) {
+        return 1.0;
+    }
+    let query_len = query.chars().count();
+    let name = name.chars().collect::<Vec<char>>();
+    if name.is_empty() {
+        return 0.0;
+    }
+    let mut best = 0.0;
+    for len in query_len.saturating_sub(1).max(1)..=query_len + 1 {
+        for window in name.windows(len.min(name.len())) {
+            let window = window.iter().collect::<String>();
+            best = strsim::normalized_damerau_levenshtein(query, &window).max(best);
+        }
+    }
+    best as f32
+}
+
+// Letters with a stroke or ligatures, these have no decomposition into a base letter and
+// an accent
+fn without_stroke(c: char) -> Option<&'static str> {
+    let letters = match c {
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'ł' => "l",
+        'ħ' => "h",
+        'ı' => "i",
+        'æ' => "ae",
+        'œ' => "oe",
+        'þ' => "th",
+        _ => return None,
+    };
+    Some(letters)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::name_folding::{FUZZY_MIN_SCORE, NameFolding, fuzzy_score};
+
+    #[test]
+    fn test_fold() {
+        let exact = NameFolding::new(false, None);
+        let accents = NameFolding::new(true, None);
+        // decomposed "é" as written by macOS
+        assert_eq!(exact.fold("Cafe\u{301}.JPG"), exact.fold("café.jpg"));
+        assert_ne!(exact.fold("café.jpg"), exact.fold("cafe.jpg"));
+        assert_eq!(accents.fold("Café Łódź.jpg"), "cafe lodz.jpg");
+        assert_eq!(exact.fold("Straße"), "strasse");
+        // full-width characters
+        assert_eq!(exact.fold("ＩＭＧ_１.jpg"), "img_1.jpg");
+        let turkish = NameFolding::new(false, Some("tr-TR"));
+        assert_eq!(turkish.fold("İSTANBUL"), "istanbul");
+        assert_eq!(turkish.fold("ISPARTA"), "ısparta");
+        assert_eq!(
+            NameFolding::new(true, Some("tr")).fold("ISPARTA"),
+            "isparta"
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("img_1234", "takeout/img_1234 (1).jpg"), 1.0);
+        assert!(fuzzy_score("img_1243", "photos/img_1234.jpg") >= FUZZY_MIN_SCORE);
+        assert!(fuzzy_score("1243", "img_1234.jpg") >= FUZZY_MIN_SCORE);
+        assert!(fuzzy_score("1299", "img_1234.jpg") < FUZZY_MIN_SCORE);
+        assert!(fuzzy_score("img_1243", "img_9876.jpg") < FUZZY_MIN_SCORE);
+        assert!(fuzzy_score("vacation", "summer_vacaton_01.jpg") >= FUZZY_MIN_SCORE);
+    }
+}
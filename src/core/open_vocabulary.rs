@@ -0,0 +1,40 @@
+use crate::core::{
+    error::PhotoInsightError,
+    external::ExternalModel,
+    image_cache::PhotoInfo,
+    yolo::{AnalysisResult, DetectedObject},
+};
+
+/// Model id reported with open-vocabulary detections
+pub const OPEN_VOCABULARY_MODEL: &str = "open-vocabulary";
+
+/// Runs an open-vocabulary detector (OWL-ViT/YOLO-World style) over the images. The detector
+/// receives the image on stdin and the text classes as arguments, and prints a JSON array of
+/// `{"class_name": .., "confidence": .., "bbox": [xmin, ymin, xmax, ymax]}`.
+pub fn detect_open_vocabulary(
+    images: Vec<(PhotoInfo, Vec<u8>)>,
+    classes: &[String],
+    detector: &ExternalModel,
+    min_confidence: f32,
+) -> Result<Vec<AnalysisResult>, PhotoInsightError> {
+    let mut results = Vec::new();
+    for (photo_info, image_data) in images {
+        let value = detector.run(&image_data, classes)?;
+        let detections: Vec<DetectedObject> = serde_json::from_value(value).map_err(|e| {
+            PhotoInsightError::from_message(format!(
+                "unexpected detector output of {}: {e}",
+                detector.command
+            ))
+        })?;
+        let detections = detections
+            .into_iter()
+            .filter(|d| d.confidence >= min_confidence)
+            .collect();
+        results.push(AnalysisResult::new(
+            photo_info,
+            OPEN_VOCABULARY_MODEL.to_owned(),
+            detections,
+        ));
+    }
+    Ok(results)
+}
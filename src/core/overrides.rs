@@ -0,0 +1,283 @@
+use std::{collections::HashMap, path::Path};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    color_label::ColorLabel,
+    error::PhotoInsightError,
+    exif::{ExifInfo, GeoLocation},
+    image_cache::PhotoInfo,
+    photo_id::PhotoIds,
+    store,
+};
+
+lazy_static! {
+    static ref DATE_TIME_RE: Regex =
+        Regex::new(r"^(\d\d\d\d)[-:](\d\d)[-:](\d\d)(?:[ T](\d\d):(\d\d)(?::(\d\d))?)?$").unwrap();
+}
+
+const OVERRIDES_FILE: &str = "metadata_overrides.json";
+
+/// Correction of photo metadata applied on top of EXIF, unset fields keep the EXIF value
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetadataOverride {
+    /// Corrected date and time, normalized to "YYYY-MM-DD HH:MM:SS"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<GeoLocation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Star rating from 1 to 5, 0 rejects the photo from highlights
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    /// Color label, `ColorLabel::None` removes the label read from XMP
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_label: Option<ColorLabel>,
+}
+
+impl MetadataOverride {
+    /// Overrides fields set in `other`
+    pub fn merge(&mut self, other: MetadataOverride) {
+        if other.date_time.is_some() {
+            self.date_time = other.date_time;
+        }
+        if other.location.is_some() {
+            self.location = other.location;
+        }
+        if other.description.is_some() {
+            self.description = other.description;
+        }
+        if other.rating.is_some() {
+            self.rating = other.rating;
+        }
+        if other.color_label.is_some() {
+            self.color_label = other.color_label;
+        }
+    }
+
+    pub fn apply(&self, mut exif: ExifInfo) -> ExifInfo {
+        if let Some(date_time) = &self.date_time {
+            if let Some((year, month)) = parse_date_time(date_time)
+                .ok()
+                .map(|(year, month, ..)| (year, month))
+            {
+                exif.year = year;
+                exif.month = month;
+            }
+            // same format as date time read from EXIF
+            exif.date_time = format!("\"{date_time}\"");
+        }
+        if let Some(location) = &self.location {
+            exif.location = Some(location.clone());
+        }
+        if let Some(description) = &self.description {
+            exif.description = Some(description.clone());
+        }
+        if let Some(label) = self.color_label {
+            exif.color_label = Some(label).filter(|label| *label != ColorLabel::None);
+        }
+        exif
+    }
+}
+
+/// Parses "YYYY-MM-DD HH:MM:SS" (also EXIF "YYYY:MM:DD HH:MM:SS", "T" separator, date only
+/// or without seconds) into (year, month, day, hour, minute, second)
+pub fn parse_date_time(
+    date_time: &str,
+) -> Result<(u32, u32, u32, u32, u32, u32), PhotoInsightError> {
+    let invalid = || {
+        PhotoInsightError::from_message(format!(
+            "Invalid date time: {date_time}, expected YYYY-MM-DD HH:MM:SS"
+        ))
+    };
+    let caps = DATE_TIME_RE
+        .captures(date_time.trim())
+        .ok_or_else(invalid)?;
+    let part = |i: usize| {
+        caps.get(i)
+            .map(|m| m.as_str().parse::<u32>().unwrap_or(0))
+            .unwrap_or(0)
+    };
+    let (year, month, day, hour, minute, second) =
+        (part(1), part(2), part(3), part(4), part(5), part(6));
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+    Ok((year, month, day, hour, minute, second))
+}
+
+pub fn normalize_date_time(date_time: &str) -> Result<String, PhotoInsightError> {
+    let (year, month, day, hour, minute, second) = parse_date_time(date_time)?;
+    Ok(format!(
+        "{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}"
+    ))
+}
+
+// days since 1970-01-01 of the civil date (proleptic Gregorian calendar)
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// civil date of the days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Normalizes the end of a date range, a date only end includes the whole day
+pub fn normalize_date_to(date_to: &str) -> Result<String, PhotoInsightError> {
+    normalize_date_time(date_to).map(|n| {
+        if date_to.trim().len() == 10 {
+            n.replace(" 00:00:00", " 23:59:59")
+        } else {
+            n
+        }
+    })
+}
+
+/// Seconds since 1970-01-01 00:00:00 of the date time (time zone is not known)
+pub fn timestamp_of(date_time: &str) -> Result<i64, PhotoInsightError> {
+    let (year, month, day, hour, minute, second) = parse_date_time(date_time)?;
+    Ok(
+        days_from_civil(year as i64, month as i64, day as i64) * 86400
+            + hour as i64 * 3600
+            + minute as i64 * 60
+            + second as i64,
+    )
+}
+
+/// Shifts the date time by the given number of seconds, result is normalized
+pub fn shift_date_time(date_time: &str, seconds: i64) -> Result<String, PhotoInsightError> {
+    // years 1 to 9999
+    const TIMESTAMPS: std::ops::RangeInclusive<i64> = -62_135_596_800..=253_402_300_799;
+    let timestamp = timestamp_of(date_time)?
+        .checked_add(seconds)
+        .filter(|timestamp| TIMESTAMPS.contains(timestamp))
+        .ok_or_else(|| {
+            PhotoInsightError::from_message(format!(
+                "{date_time} shifted by {seconds}s is out of range"
+            ))
+        })?;
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(86400));
+    let time = timestamp.rem_euclid(86400);
+    Ok(format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    ))
+}
+
+/// ISO 8601 week year and week number of the date time, weeks start on Monday
+pub fn iso_week(date_time: &str) -> Result<(i64, i64), PhotoInsightError> {
+    let (year, month, day, _, _, _) = parse_date_time(date_time)?;
+    let days = days_from_civil(year as i64, month as i64, day as i64);
+    // 1970-01-01 was Thursday, the week belongs to the year of its Thursday
+    let thursday = days - (days + 3).rem_euclid(7) + 3;
+    let (week_year, _, _) = civil_from_days(thursday);
+    Ok((
+        week_year,
+        (thursday - days_from_civil(week_year, 1, 1)) / 7 + 1,
+    ))
+}
+
+/// Metadata corrections of all photos, persisted in `<image_dir>/metadata_overrides.json`
+/// separately from the original archives and EXIF sidecars, photos are stored by their stable ID
+#[derive(Debug, Clone, Default)]
+pub struct MetadataOverrides {
+    overrides: HashMap<PhotoInfo, MetadataOverride>,
+}
+
+impl MetadataOverrides {
+    pub(crate) fn file_name(image_dir: &str) -> String {
+        Path::new(image_dir)
+            .join(OVERRIDES_FILE)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn load(image_dir: &str, ids: &PhotoIds) -> Result<Self, PhotoInsightError> {
+        let file_name = Self::file_name(image_dir);
+        if !store::exists(&file_name) {
+            return Ok(Self::default());
+        }
+        let serialized: HashMap<String, MetadataOverride> = store::read_json(&file_name)?;
+        let overrides = serialized
+            .into_iter()
+            .filter_map(|(key, value)| ids.resolve(key).map(|info| (info, value)))
+            .collect::<HashMap<_, _>>();
+        tracing::info!("Loaded {} metadata overrides", overrides.len());
+        Ok(Self { overrides })
+    }
+
+    pub fn save(&self, image_dir: &str, ids: &PhotoIds) -> Result<(), PhotoInsightError> {
+        let serialized: HashMap<String, &MetadataOverride> = self
+            .overrides
+            .iter()
+            .map(|(info, value)| (ids.key_of(info), value))
+            .collect();
+        store::write_json(&Self::file_name(image_dir), &serialized)
+    }
+
+    pub fn get(&self, photo_info: &PhotoInfo) -> Option<&MetadataOverride> {
+        self.overrides.get(photo_info)
+    }
+
+    pub fn set(&mut self, photo_info: PhotoInfo, value: MetadataOverride) {
+        self.overrides.entry(photo_info).or_default().merge(value);
+    }
+
+    pub fn clear(&mut self, photo_info: &PhotoInfo) -> Option<MetadataOverride> {
+        self.overrides.remove(photo_info)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&PhotoInfo, &MetadataOverride)> {
+        self.overrides.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::overrides::{normalize_date_time, shift_date_time};
+
+    #[test]
+    fn test_shift_date_time() {
+        assert_eq!(
+            shift_date_time("2019:12:31 23:30:00", 3600).unwrap(),
+            "2020-01-01 00:30:00"
+        );
+        assert_eq!(
+            shift_date_time("2020-03-01 00:10:00", -3600).unwrap(),
+            "2020-02-29 23:10:00"
+        );
+        assert!(shift_date_time("2020-03-01 00:10:00", i64::MAX).is_err());
+        assert!(shift_date_time("2020-03-01 00:10:00", -2020 * 366 * 86400).is_err());
+        assert_eq!(
+            normalize_date_time("2019-07-14").unwrap(),
+            "2019-07-14 00:00:00"
+        );
+        assert!(normalize_date_time("2019-13-01").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::core::image_cache::PhotoInfo;
+
+/// Hex digits of the content hash making up the photo ID
+const PHOTO_ID_LEN: usize = 16;
+
+/// Stable photo ID derived from the photo content (SHA-256), it survives recreating the
+/// archive which changes the zip entry indexes
+pub fn photo_id_of(sha256: &str) -> String {
+    sha256.chars().take(PHOTO_ID_LEN).collect()
+}
+
+/// Stable IDs of all photos. Copies of the same photo (e.g. in several Takeout parts) share
+/// the ID, it resolves to the first copy.
+#[derive(Debug, Clone, Default)]
+pub struct PhotoIds {
+    by_photo: HashMap<PhotoInfo, String>,
+    by_id: HashMap<String, PhotoInfo>,
+}
+
+impl PhotoIds {
+    /// IDs of the photos with known content hash, `canonical` links copies to the first copy
+    pub fn build(
+        sha256: &HashMap<PhotoInfo, String>,
+        canonical: &HashMap<PhotoInfo, PhotoInfo>,
+    ) -> Self {
+        let mut ids = Self::default();
+        for (info, hash) in sha256 {
+            let id = photo_id_of(hash);
+            if !canonical.contains_key(info) {
+                ids.by_id.insert(id.clone(), info.clone());
+            }
+            ids.by_photo.insert(info.clone(), id);
+        }
+        ids
+    }
+
+    pub fn id_of(&self, photo_info: &PhotoInfo) -> Option<&String> {
+        self.by_photo.get(photo_info)
+    }
+
+    pub fn photo_of(&self, photo_id: &str) -> Option<&PhotoInfo> {
+        self.by_id.get(photo_id)
+    }
+
+    /// Key of the photo in persisted stores: its ID, photos without content hash fall back to
+    /// the archive position
+    pub fn key_of(&self, photo_info: &PhotoInfo) -> String {
+        self.id_of(photo_info)
+            .cloned()
+            .unwrap_or_else(|| photo_info.serialize_as_key())
+    }
+
+    /// Photo of a persisted store key, both IDs and archive positions (stores written before
+    /// photo IDs) are accepted
+    pub fn resolve(&self, key: String) -> Option<PhotoInfo> {
+        if key.contains('|') {
+            PhotoInfo::deserialize_from_key(key).ok()
+        } else {
+            self.photo_of(&key).cloned()
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::{path::Path, time::UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::AnalysisSidecar, error::PhotoInsightError, overrides::shift_date_time, store,
+};
+
+const AUDIT_FILE: &str = "photo-mcp.privacy-audit.jsonl";
+
+/// Record of an action on personal data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// UTC time of the action, "YYYY-MM-DD HH:MM:SS"
+    pub time: String,
+    pub action: String,
+    pub archive: String,
+    /// Analysis stage whose data was affected
+    pub stage: String,
+    pub photos: usize,
+}
+
+pub(crate) fn audit_file_name(image_dir: &str) -> String {
+    Path::new(image_dir)
+        .join(AUDIT_FILE)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Appends the entry to the audit log, a JSON line per action
+pub fn audit(image_dir: &str, mut entry: AuditEntry) -> Result<(), PhotoInsightError> {
+    let now = std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    entry.time = shift_date_time("1970-01-01 00:00:00", now)?;
+    let file_name = audit_file_name(image_dir);
+    let mut log = if store::exists(&file_name) {
+        crate::STORE.read(&file_name)?
+    } else {
+        Vec::new()
+    };
+    serde_json::to_writer(&mut log, &entry).map_err(|e| PhotoInsightError::new(e))?;
+    log.push(b'\n');
+    crate::STORE.write(&file_name, log)
+}
+
+/// Deletes results of face stages stored for archives with face processing disabled, every
+/// deleted sidecar is recorded in the audit log
+pub fn delete_disabled_face_data(image_dir: &str, zip_files: &[String]) {
+    let face_stages = crate::ANALYZERS
+        .all()
+        .iter()
+        .filter(|stage| stage.processes_faces())
+        .cloned()
+        .collect::<Vec<_>>();
+    for zip in zip_files
+        .iter()
+        .filter(|zip| crate::CONFIG.privacy.faces_disabled(zip))
+    {
+        for stage in face_stages.iter() {
+            let file_name = AnalysisSidecar::file_name(image_dir, zip, stage.as_ref());
+            if !store::exists(&file_name) {
+                continue;
+            }
+            let photos = AnalysisSidecar::load(image_dir, zip, stage.as_ref())
+                .map(|results| results.len())
+                .unwrap_or_default();
+            let deleted = crate::STORE.remove(&file_name).and_then(|_| {
+                audit(
+                    image_dir,
+                    AuditEntry {
+                        time: String::new(),
+                        action: "delete_face_data".to_owned(),
+                        archive: zip.clone(),
+                        stage: stage.name(),
+                        photos,
+                    },
+                )
+            });
+            match deleted {
+                Ok(()) => tracing::info!(
+                    "Deleted {} data of {photos} photos in {zip}, face processing is disabled",
+                    stage.name()
+                ),
+                Err(e) => tracing::error!("can't delete face data {file_name}: {e}"),
+            }
+        }
+    }
+}
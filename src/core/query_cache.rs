@@ -0,0 +1,166 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::core::timings;
+
+struct Entry {
+    results: Arc<dyn Any + Send + Sync>,
+    created: Instant,
+    last_used: AtomicU64,
+}
+
+/// Bounded cache of full search results keyed by the normalized query, pages of popular
+/// queries (e.g. a whole month listing) are cut from the cached results instead of searching
+/// again. Entries expire after the TTL, the least recently used entry is evicted when full and
+/// all entries are dropped when the indexes change.
+pub struct QueryCache {
+    entries: RwLock<HashMap<String, Entry>>,
+    capacity: usize,
+    ttl: Duration,
+    // Bumped on every index update, results computed before it are not cached
+    generation: AtomicU64,
+    tick: AtomicU64,
+}
+
+impl QueryCache {
+    /// Cache of at most `capacity` queries, zero capacity disables caching
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            capacity,
+            ttl,
+            generation: AtomicU64::new(0),
+            tick: AtomicU64::new(0),
+        }
+    }
+
+    /// Drops all cached results, called when the indexes change
+    pub fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.entries.write().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Full results of the query, computed on a miss and cached for the next pages
+    pub fn get_or_compute<T, E>(
+        &self,
+        key: String,
+        compute: impl FnOnce() -> Result<Vec<T>, E>,
+    ) -> Result<Arc<Vec<T>>, E>
+    where
+        T: Send + Sync + 'static,
+    {
+        if let Some(results) = self.get(&key) {
+            tracing::debug!("Query cache hit: {key}");
+            return Ok(results);
+        }
+        let generation = self.generation.load(Ordering::SeqCst);
+        let results = Arc::new(timings::timed("search", compute)?);
+        if self.capacity > 0 {
+            let mut entries = self.entries.write().unwrap();
+            if generation == self.generation.load(Ordering::SeqCst) {
+                entries.retain(|_, entry| entry.created.elapsed() < self.ttl);
+                if entries.len() >= self.capacity {
+                    let oldest = entries
+                        .iter()
+                        .min_by_key(|(_, entry)| entry.last_used.load(Ordering::Relaxed))
+                        .map(|(key, _)| key.clone());
+                    if let Some(oldest) = oldest {
+                        entries.remove(&oldest);
+                    }
+                }
+                entries.insert(
+                    key,
+                    Entry {
+                        results: results.clone(),
+                        created: Instant::now(),
+                        last_used: AtomicU64::new(self.tick.fetch_add(1, Ordering::Relaxed)),
+                    },
+                );
+            }
+        }
+        Ok(results)
+    }
+
+    fn get<T: Send + Sync + 'static>(&self, key: &str) -> Option<Arc<Vec<T>>> {
+        let entries = self.entries.read().unwrap();
+        let entry = entries.get(key)?;
+        if entry.created.elapsed() >= self.ttl {
+            return None;
+        }
+        entry
+            .last_used
+            .store(self.tick.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        entry.results.clone().downcast::<Vec<T>>().ok()
+    }
+}
+
+/// Page of the results at offset with the number of all results
+pub fn page<T: Clone>(results: &[T], offset: usize, limit: usize) -> (Vec<T>, usize) {
+    let total_found = results.len();
+    tracing::info!("Found {} matching images", total_found);
+    let start = offset.min(total_found);
+    let end = offset.saturating_add(limit).min(total_found);
+    tracing::info!("Returning images from {} to {}", start, end);
+    (results[start..end].to_vec(), total_found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::core::query_cache::{QueryCache, page};
+
+    fn compute(results: &[u32]) -> impl FnOnce() -> Result<Vec<u32>, ()> {
+        let results = results.to_vec();
+        move || Ok(results)
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let cache = QueryCache::new(2, Duration::from_secs(60));
+        assert_eq!(
+            *cache.get_or_compute("a".to_owned(), compute(&[1])).unwrap(),
+            [1]
+        );
+        // hit, not computed again
+        assert_eq!(
+            *cache.get_or_compute("a".to_owned(), compute(&[2])).unwrap(),
+            [1]
+        );
+        cache.get_or_compute("b".to_owned(), compute(&[3])).unwrap();
+        // "a" was used last, "b" is evicted
+        cache.get_or_compute("a".to_owned(), compute(&[2])).unwrap();
+        cache.get_or_compute("c".to_owned(), compute(&[4])).unwrap();
+        assert_eq!(
+            *cache.get_or_compute("b".to_owned(), compute(&[5])).unwrap(),
+            [5]
+        );
+        cache.invalidate();
+        assert!(cache.is_empty());
+        assert_eq!(
+            *cache.get_or_compute("a".to_owned(), compute(&[6])).unwrap(),
+            [6]
+        );
+    }
+
+    #[test]
+    fn test_page() {
+        assert_eq!(page(&[1, 2, 3], 1, 5), (vec![2, 3], 3));
+        assert_eq!(page(&[1, 2, 3], 5, usize::MAX), (vec![], 3));
+    }
+}
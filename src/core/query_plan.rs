@@ -0,0 +1,593 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::core::time_of_day::TimeOfDay;
+
+lazy_static! {
+    static ref YEAR_RE: Regex = Regex::new(r"\b((?:19|20)\d\d)\b").unwrap();
+    static ref OPEN_RANGE_RE: Regex =
+        Regex::new(r"\b(before|after|since)\s+((?:19|20)\d\d)\b").unwrap();
+    static ref PEOPLE_RE: Regex = Regex::new(
+        r"\b(?:(more than|over|at least|fewer than|less than|exactly)\s+)?(\d+|one|two|three|four|five|six|seven|eight|nine|ten)\s+(?:people|persons|person|faces)\b"
+    )
+    .unwrap();
+}
+
+/// Results per step of the plan
+const STEP_LIMIT: u32 = 100;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Seasons of the northern hemisphere within one year, winter spans two years and is not
+/// recognized
+const SEASONS: [(&str, u32, u32); 4] = [
+    ("spring", 3, 5),
+    ("summer", 6, 8),
+    ("autumn", 9, 11),
+    ("fall", 9, 11),
+];
+
+/// Phrases naming a part of the day, "night" alone is left to the labels of night shots
+const TIMES_OF_DAY: [(&str, TimeOfDay); 8] = [
+    ("golden hour", TimeOfDay::GoldenHour),
+    ("mornings", TimeOfDay::Morning),
+    ("morning", TimeOfDay::Morning),
+    ("midday", TimeOfDay::Midday),
+    ("noon", TimeOfDay::Midday),
+    ("evenings", TimeOfDay::Evening),
+    ("evening", TimeOfDay::Evening),
+    ("at night", TimeOfDay::Night),
+];
+
+const NUMBERS: [&str; 10] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+];
+
+/// Words carrying no search meaning
+const STOP_WORDS: [&str; 47] = [
+    "a",
+    "all",
+    "an",
+    "and",
+    "any",
+    "are",
+    "at",
+    "between",
+    "by",
+    "can",
+    "during",
+    "find",
+    "for",
+    "from",
+    "get",
+    "i",
+    "images",
+    "in",
+    "including",
+    "is",
+    "list",
+    "me",
+    "my",
+    "of",
+    "on",
+    "photo",
+    "photos",
+    "picture",
+    "pictures",
+    "please",
+    "search",
+    "shot",
+    "show",
+    "some",
+    "taken",
+    "that",
+    "the",
+    "there",
+    "to",
+    "took",
+    "using",
+    "we",
+    "were",
+    "where",
+    "which",
+    "with",
+    "year",
+];
+
+/// Camera models, detected object classes and analysis labels present in the collection,
+/// the request is matched against them
+#[derive(Debug, Clone, Default)]
+pub struct Vocabulary {
+    pub cameras: Vec<String>,
+    pub objects: Vec<String>,
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PeopleCountFilter {
+    pub operator: String,
+    pub value: u32,
+}
+
+/// Structured filter recognized in the request, all conditions must hold
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year_from: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year_to: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month_from: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month_to: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_of_day: Option<TimeOfDay>,
+    /// Partial match of the camera model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub objects: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub people_count: Option<PeopleCountFilter>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub portraits: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub smiling: bool,
+    /// Remaining words, searched as text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub include_hidden: bool,
+}
+
+/// Tool call with its arguments
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedCall {
+    pub tool: String,
+    pub arguments: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryPlan {
+    pub request: String,
+    pub filter: QueryFilter,
+    /// Tool calls executing the filter, photos returned by all of them (same zip file name
+    /// and index in zip) match the request
+    pub steps: Vec<PlannedCall>,
+    /// Words of the request which were not understood
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub unparsed: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+// Singular form of an English plural, good enough for object class names
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if let Some(stem) = word
+        .strip_suffix("ches")
+        .or_else(|| word.strip_suffix("shes"))
+        .or_else(|| word.strip_suffix("xes"))
+    {
+        format!("{stem}{}", &word[stem.len()..word.len() - 2])
+    } else if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+        word[..word.len() - 1].to_owned()
+    } else {
+        word.to_owned()
+    }
+}
+
+fn number(word: &str) -> Option<u32> {
+    word.parse().ok().or_else(|| {
+        NUMBERS
+            .iter()
+            .position(|n| *n == word)
+            .map(|i| i as u32 + 1)
+    })
+}
+
+// Removes the first occurrence of the matched phrase (whole words) from the remaining text
+fn consume(rest: &mut String, phrase: &str) {
+    if let Ok(re) = Regex::new(&format!(r"\b{}\b", regex::escape(phrase))) {
+        *rest = re.replace(rest, " ").to_string();
+    }
+}
+
+fn parse_dates(rest: &mut String, today: (u32, u32), filter: &mut QueryFilter) {
+    let (this_year, this_month) = today;
+    if rest.contains("last month") {
+        consume(rest, "last month");
+        let (year, month) = if this_month == 1 {
+            (this_year - 1, 12)
+        } else {
+            (this_year, this_month - 1)
+        };
+        filter.year_from = Some(year);
+        filter.year_to = Some(year);
+        filter.month_from = Some(month);
+        filter.month_to = Some(month);
+        return;
+    }
+    for (phrase, year) in [("last year", this_year - 1), ("this year", this_year)] {
+        if rest.contains(phrase) {
+            consume(rest, phrase);
+            filter.year_from = Some(year);
+            filter.year_to = Some(year);
+        }
+    }
+    if let Some(caps) = OPEN_RANGE_RE.captures(&rest.clone()) {
+        if let Ok(year) = caps[2].parse::<u32>() {
+            match &caps[1] {
+                "before" => filter.year_to = Some(year - 1),
+                "after" => filter.year_from = Some(year + 1),
+                _ => filter.year_from = Some(year),
+            }
+            consume(rest, &caps[0]);
+        }
+    }
+    let years = YEAR_RE
+        .captures_iter(rest)
+        .filter_map(|caps| caps[1].parse::<u32>().ok())
+        .collect::<Vec<u32>>();
+    if let (Some(min), Some(max)) = (years.iter().min(), years.iter().max()) {
+        filter.year_from = Some(*min);
+        filter.year_to = Some(*max);
+        *rest = YEAR_RE.replace_all(rest, " ").to_string();
+    }
+    let words = rest
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    for word in words {
+        if let Some(i) = MONTHS.iter().position(|m| *m == word) {
+            filter.month_from = Some(i as u32 + 1);
+            filter.month_to = Some(i as u32 + 1);
+            consume(rest, &word);
+        } else if let Some((_, from, to)) = SEASONS.iter().find(|(s, _, _)| *s == word) {
+            filter.month_from = Some(*from);
+            filter.month_to = Some(*to);
+            consume(rest, &word);
+        }
+    }
+}
+
+fn parse_time_of_day(rest: &mut String, filter: &mut QueryFilter) {
+    if let Some((phrase, time_of_day)) = TIMES_OF_DAY
+        .iter()
+        .find(|(phrase, _)| Regex::new(&format!(r"\b{phrase}\b")).is_ok_and(|re| re.is_match(rest)))
+    {
+        filter.time_of_day = Some(*time_of_day);
+        consume(rest, phrase);
+    }
+}
+
+fn parse_people(rest: &mut String, filter: &mut QueryFilter) {
+    if let Some(caps) = PEOPLE_RE.captures(&rest.clone()) {
+        if let Some(value) = number(&caps[2]) {
+            let operator = match caps.get(1).map(|m| m.as_str()) {
+                Some("more than") | Some("over") => ">",
+                Some("at least") => ">=",
+                Some("fewer than") | Some("less than") => "<",
+                _ => "==",
+            };
+            filter.people_count = Some(PeopleCountFilter {
+                operator: operator.to_owned(),
+                value,
+            });
+            consume(rest, &caps[0]);
+        }
+    }
+    for word in ["group", "crowd"] {
+        if rest.split_whitespace().any(|w| w == word) && filter.people_count.is_none() {
+            filter.people_count = Some(PeopleCountFilter {
+                operator: ">=".to_owned(),
+                value: 3,
+            });
+            consume(rest, word);
+        }
+    }
+    for word in ["portraits", "portrait", "selfies", "selfie"] {
+        if rest.split_whitespace().any(|w| w == word) {
+            filter.portraits = true;
+            consume(rest, word);
+        }
+    }
+    for word in ["smiling", "smiles", "smile"] {
+        if rest.split_whitespace().any(|w| w == word) {
+            filter.portraits = true;
+            filter.smiling = true;
+            consume(rest, word);
+        }
+    }
+}
+
+fn parse_camera(rest: &mut String, vocabulary: &Vocabulary, filter: &mut QueryFilter) {
+    // longest model named in the request, e.g. "canon eos 40d"
+    let mut cameras = vocabulary
+        .cameras
+        .iter()
+        .map(|c| c.to_lowercase())
+        .collect::<Vec<_>>();
+    cameras.sort_by_key(|c| std::cmp::Reverse(c.len()));
+    if let Some(camera) = cameras.iter().find(|c| !c.is_empty() && rest.contains(*c)) {
+        filter.camera_model = Some(camera.clone());
+        consume(rest, camera);
+        return;
+    }
+    // a brand or model word, e.g. "iphone" or "canon"
+    let words = rest
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    if let Some(word) = words.iter().find(|w| {
+        w.len() >= 4
+            && number(w).is_none()
+            && cameras
+                .iter()
+                .any(|c| c.split_whitespace().any(|part| part == w.as_str()))
+    }) {
+        filter.camera_model = Some(word.clone());
+        consume(rest, word);
+    }
+}
+
+fn parse_subjects(rest: &mut String, vocabulary: &Vocabulary, filter: &mut QueryFilter) {
+    let mut objects = vocabulary
+        .objects
+        .iter()
+        .map(|o| o.to_lowercase())
+        .collect::<Vec<_>>();
+    // aliases of detected classes are searched by the object search too, e.g. "puppy"
+    let aliases = crate::CLASS_ALIASES
+        .names()
+        .filter(|alias| {
+            crate::CLASS_ALIASES
+                .expand(alias)
+                .iter()
+                .any(|class| objects.contains(class))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    objects.extend(aliases);
+    let labels = vocabulary
+        .labels
+        .iter()
+        .map(|l| l.to_lowercase())
+        .collect::<Vec<_>>();
+    // two word names first, e.g. "teddy bear" or "cell phones"
+    let words = rest
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    for pair in words.windows(2) {
+        let name = format!("{} {}", pair[0], singular(&pair[1]));
+        if objects.contains(&name) && !filter.objects.contains(&name) {
+            filter.objects.push(name);
+            consume(rest, &format!("{} {}", pair[0], pair[1]));
+        } else if labels.contains(&name) && !filter.labels.contains(&name) {
+            filter.labels.push(name);
+            consume(rest, &format!("{} {}", pair[0], pair[1]));
+        }
+    }
+    let words = rest
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    for word in words {
+        // names in the response language too, e.g. "hund" for "dog"
+        let name = [
+            word.clone(),
+            singular(&word),
+            crate::TRANSLATIONS.untranslate(&word),
+            crate::TRANSLATIONS.untranslate(&singular(&word)),
+        ]
+        .into_iter()
+        .find(|name| objects.contains(name) || labels.contains(name));
+        match name {
+            Some(name) if objects.contains(&name) => {
+                if !filter.objects.contains(&name) {
+                    filter.objects.push(name);
+                }
+                consume(rest, &word);
+            }
+            Some(name) => {
+                if !filter.labels.contains(&name) {
+                    filter.labels.push(name);
+                }
+                consume(rest, &word);
+            }
+            None => {}
+        }
+    }
+}
+
+fn call(tool: &str, mut arguments: serde_json::Value, include_hidden: bool) -> PlannedCall {
+    if let Some(map) = arguments.as_object_mut() {
+        map.insert("offset".to_owned(), 0.into());
+        map.insert("limit".to_owned(), STEP_LIMIT.into());
+        if include_hidden {
+            map.insert("include_hidden".to_owned(), true.into());
+        }
+    }
+    PlannedCall {
+        tool: tool.to_owned(),
+        arguments,
+    }
+}
+
+fn exif_call(tag: &str, operator: &str, value: String, include_hidden: bool) -> PlannedCall {
+    call(
+        "photo_exif_search_tags",
+        serde_json::json!({"tag": tag, "operator": operator, "value": value}),
+        include_hidden,
+    )
+}
+
+fn steps(filter: &QueryFilter, full_text: bool, notes: &mut Vec<String>) -> Vec<PlannedCall> {
+    let hidden = filter.include_hidden;
+    let mut steps = Vec::new();
+    let single_month = filter.year_from.is_some()
+        && filter.year_from == filter.year_to
+        && filter.month_from.is_some()
+        && filter.month_from == filter.month_to;
+    if single_month {
+        steps.push(call(
+            "photo_search_by_year_month",
+            serde_json::json!({"year": filter.year_from, "month": filter.month_from}),
+            hidden,
+        ));
+    } else {
+        match (filter.year_from, filter.year_to) {
+            (Some(from), Some(to)) if from == to => {
+                steps.push(exif_call("year", "==", from.to_string(), hidden))
+            }
+            (from, to) => {
+                if let Some(from) = from {
+                    steps.push(exif_call("year", ">=", from.to_string(), hidden));
+                }
+                if let Some(to) = to {
+                    steps.push(exif_call("year", "<=", to.to_string(), hidden));
+                }
+            }
+        }
+        match (filter.month_from, filter.month_to) {
+            (Some(from), Some(to)) if from == to => {
+                steps.push(exif_call("month", "==", from.to_string(), hidden))
+            }
+            (Some(from), Some(to)) => {
+                steps.push(exif_call("month", ">=", from.to_string(), hidden));
+                steps.push(exif_call("month", "<=", to.to_string(), hidden));
+            }
+            _ => {}
+        }
+    }
+    if let Some(time_of_day) = &filter.time_of_day {
+        steps.push(exif_call(
+            "time_of_day",
+            "==",
+            time_of_day.name().to_owned(),
+            hidden,
+        ));
+    }
+    if let Some(model) = &filter.camera_model {
+        steps.push(exif_call("model", "contains", model.clone(), hidden));
+    }
+    for object in filter.objects.iter() {
+        steps.push(call(
+            "photo_search_by_object",
+            serde_json::json!({"class_name": object}),
+            hidden,
+        ));
+    }
+    for label in filter.labels.iter() {
+        steps.push(call(
+            "photo_search_by_label",
+            serde_json::json!({"label": label}),
+            hidden,
+        ));
+    }
+    if let Some(people) = &filter.people_count {
+        steps.push(call(
+            "photo_search_by_people_count",
+            serde_json::json!({"operator": people.operator, "value": people.value.to_string()}),
+            hidden,
+        ));
+    }
+    if filter.portraits {
+        let arguments = if filter.smiling {
+            serde_json::json!({"min_smile": 0.5})
+        } else {
+            serde_json::json!({})
+        };
+        steps.push(call("photo_search_portraits", arguments, hidden));
+    }
+    if let Some(text) = &filter.text {
+        if full_text {
+            steps.push(call(
+                "photo_text_search",
+                serde_json::json!({"query": text, "fuzzy": true}),
+                hidden,
+            ));
+        } else {
+            notes.push(format!(
+                "\"{text}\" is not searched, enable the full-text index to search remaining words"
+            ));
+        }
+    }
+    if steps.is_empty() {
+        notes.push("Nothing to search for was recognized, all photos match".to_owned());
+        steps.push(call("list_all_photos", serde_json::json!({}), false));
+    }
+    steps
+}
+
+/// Translates a natural-language request (e.g. "dogs on the beach in summer 2021 shot with
+/// my iPhone") into a structured filter and the tool calls executing it. Dates, people
+/// counts and portraits are recognized by phrases, cameras, objects and labels by the
+/// vocabulary of the collection, remaining words are searched as text. `today` is the
+/// current (year, month) for relative dates.
+pub fn plan(
+    request: &str,
+    vocabulary: &Vocabulary,
+    today: (u32, u32),
+    full_text: bool,
+) -> QueryPlan {
+    let mut rest = request
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>();
+    let mut filter = QueryFilter::default();
+    for word in ["hidden", "hide"] {
+        if rest.split_whitespace().any(|w| w == word) {
+            filter.include_hidden = true;
+            consume(&mut rest, word);
+        }
+    }
+    parse_dates(&mut rest, today, &mut filter);
+    parse_time_of_day(&mut rest, &mut filter);
+    parse_people(&mut rest, &mut filter);
+    parse_camera(&mut rest, vocabulary, &mut filter);
+    parse_subjects(&mut rest, vocabulary, &mut filter);
+
+    let unparsed = rest
+        .split_whitespace()
+        .filter(|w| !STOP_WORDS.contains(w))
+        .map(str::to_owned)
+        .collect::<Vec<String>>();
+    if !unparsed.is_empty() {
+        filter.text = Some(unparsed.join(" "));
+    }
+    let mut notes = Vec::new();
+    let steps = steps(&filter, full_text, &mut notes);
+    QueryPlan {
+        request: request.to_owned(),
+        filter,
+        steps,
+        unparsed: if full_text { Vec::new() } else { unparsed },
+        notes,
+    }
+}
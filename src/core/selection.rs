@@ -0,0 +1,59 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use crate::core::image_cache::PhotoInfo;
+
+/// Session key used when the transport has no sessions (stdio)
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Working set of photos built up by a conversation over multiple searches, kept in memory
+/// per MCP session. Photos keep the order they were added in.
+#[derive(Debug, Default)]
+pub struct SelectionStore {
+    sessions: RwLock<HashMap<String, Vec<PhotoInfo>>>,
+}
+
+impl SelectionStore {
+    /// Adds photos not selected yet, returns number of photos added
+    pub fn add(&self, session_id: &str, photo_infos: Vec<PhotoInfo>) -> usize {
+        let mut sessions = self.sessions.write().unwrap();
+        let selection = sessions.entry(session_id.to_owned()).or_default();
+        let mut added = 0;
+        for photo_info in photo_infos {
+            if !selection.contains(&photo_info) {
+                selection.push(photo_info);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Removes photos from the selection, returns number of photos removed
+    pub fn remove(&self, session_id: &str, photo_infos: &[PhotoInfo]) -> usize {
+        let mut sessions = self.sessions.write().unwrap();
+        let Some(selection) = sessions.get_mut(session_id) else {
+            return 0;
+        };
+        let before = selection.len();
+        selection.retain(|info| !photo_infos.contains(info));
+        before - selection.len()
+    }
+
+    pub fn get(&self, session_id: &str) -> Vec<PhotoInfo> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Empties the selection, returns number of photos removed
+    pub fn clear(&self, session_id: &str) -> usize {
+        self.sessions
+            .write()
+            .unwrap()
+            .remove(session_id)
+            .map(|selection| selection.len())
+            .unwrap_or(0)
+    }
+}
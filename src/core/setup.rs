@@ -0,0 +1,273 @@
+use std::{
+    io::Read,
+    path::Path,
+    process::{Command, Stdio},
+    time::Instant,
+};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::core::{config::Config, error::PhotoInsightError, exif, traversal, yolo, zip};
+
+/// Photos indexed to estimate the index time of the whole collection
+const SAMPLE_PHOTOS: usize = 20;
+/// Collections with more photos load EXIF lazily in the starter config
+const LAZY_EXIF_PHOTOS: usize = 100_000;
+/// TLS certificate and key of the server, see certs/gen-certs.sh
+const TLS_CERT: &str = "certs/server.crt";
+const TLS_KEY: &str = "certs/server.key";
+
+/// Outcome of one environment check
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupCheck {
+    pub check: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl SetupCheck {
+    fn new(check: &str, ok: bool, detail: impl Into<String>) -> Self {
+        Self {
+            check: check.to_owned(),
+            ok,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// What `init` found in the environment and the starter config it wrote
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupReport {
+    pub image_dir: String,
+    pub archives: usize,
+    pub archive_bytes: u64,
+    pub photos: usize,
+    /// Archives with an EXIF index from an earlier run
+    pub indexed_archives: usize,
+    /// Time of the first start indexing the archives not indexed yet, extrapolated from
+    /// indexing a sample of photos
+    pub estimated_index_secs: f64,
+    pub checks: Vec<SetupCheck>,
+    pub config_file: String,
+    /// False when the config file already existed, it is never overwritten
+    pub config_written: bool,
+    pub config: serde_json::Value,
+}
+
+/// Probes the image directory (archives, photos, index time), the detection model, ffmpeg
+/// and the TLS certificate, and writes a starter config tuned to the collection. An existing
+/// config file is validated and kept.
+pub fn init(
+    image_dir: &str,
+    config_file: &str,
+    write_config: bool,
+) -> Result<SetupReport, PhotoInsightError> {
+    if Path::new(config_file).exists() {
+        // the probes below read the index configuration
+        Config::load(config_file)?;
+    }
+    if !Path::new(image_dir).is_dir() {
+        return Err(PhotoInsightError::from_message(format!(
+            "image directory {image_dir} does not exist, set IMAGE_DIR"
+        )));
+    }
+    let mut checks = Vec::new();
+
+    let archives = traversal::list_directory_zip_files(image_dir)?;
+    let mut archive_bytes = 0;
+    let mut photos = 0;
+    let mut indexed_archives = 0;
+    let mut unindexed_photos = 0;
+    let mut sample = None;
+    for zip_file in archives.iter() {
+        archive_bytes += std::fs::metadata(Path::new(image_dir).join(zip_file))
+            .map(|m| m.len())
+            .unwrap_or_default();
+        let images = match zip::list_zip_archive(image_dir, zip_file) {
+            Ok(images) => images,
+            Err(e) => {
+                checks.push(SetupCheck::new(
+                    "archive",
+                    false,
+                    format!("{zip_file}: {e}"),
+                ));
+                continue;
+            }
+        };
+        photos += images.len();
+        let exif_sidecar = format!("{image_dir}/{zip_file}.exif.json");
+        if Path::new(&exif_sidecar).exists() {
+            indexed_archives += 1;
+        } else {
+            unindexed_photos += images.len();
+            if sample.is_none() && !images.is_empty() {
+                sample = Some((zip_file.clone(), images));
+            }
+        }
+    }
+    checks.push(SetupCheck::new(
+        "archives",
+        !archives.is_empty(),
+        format!("{} archives with {photos} photos", archives.len()),
+    ));
+    let estimated_index_secs = match sample {
+        Some((zip_file, images)) => {
+            let indices = images
+                .iter()
+                .take(SAMPLE_PHOTOS)
+                .map(|(index, _)| *index)
+                .collect();
+            index_seconds_per_photo(image_dir, &zip_file, indices)? * unindexed_photos as f64
+        }
+        None => 0.0,
+    };
+
+    let writable = sidecars_writable(image_dir);
+    checks.push(match &writable {
+        Ok(()) => SetupCheck::new("sidecars", true, format!("{image_dir} is writable")),
+        Err(e) => SetupCheck::new(
+            "sidecars",
+            false,
+            format!("{image_dir} is not writable, indexes are kept in memory: {e}"),
+        ),
+    });
+    let model = model_check();
+    let model_ok = model.ok;
+    checks.push(model);
+    checks.push(ffmpeg_check());
+    checks.push(tls_check());
+
+    let lazy_exif = photos > LAZY_EXIF_PHOTOS;
+    let config = serde_json::json!({
+        "storage": if writable.is_ok() { "sidecar" } else { "memory" },
+        "cache": {
+            "lazy_exif": lazy_exif,
+        },
+        "warm_up": {
+            "month_indexes": !lazy_exif,
+            "detection_model": model_ok,
+        },
+        "tools": {
+            "timeout_secs": 120,
+        },
+        "thumbnails": {
+            "read_ahead": 4,
+        },
+    });
+    serde_json::from_value::<Config>(config.clone())
+        .map_err(|e| PhotoInsightError::from_message(format!("invalid starter config: {e}")))?;
+    let config_written = write_config && !Path::new(config_file).exists();
+    if config_written {
+        let json = serde_json::to_string_pretty(&config).map_err(|e| PhotoInsightError::new(e))?;
+        std::fs::write(config_file, json + "\n").map_err(|e| PhotoInsightError::new(e))?;
+        tracing::info!("Wrote starter config {config_file}");
+    }
+
+    Ok(SetupReport {
+        image_dir: image_dir.to_owned(),
+        archives: archives.len(),
+        archive_bytes,
+        photos,
+        indexed_archives,
+        estimated_index_secs,
+        checks,
+        config_file: config_file.to_owned(),
+        config_written,
+        config,
+    })
+}
+
+// Indexing reads every photo twice, once for its EXIF and once for its content hash
+fn index_seconds_per_photo(
+    image_dir: &str,
+    zip_file: &str,
+    indices: Vec<usize>,
+) -> Result<f64, PhotoInsightError> {
+    let started = Instant::now();
+    let mut extract_secs = 0.0;
+    let mut sampled = 0;
+    let mut extracted = Instant::now();
+    zip::extract_zip_entries(image_dir, zip_file, indices, |_, data| {
+        extract_secs += extracted.elapsed().as_secs_f64();
+        let _ = exif::extract_exif_info(&data, false);
+        let _ = Sha256::digest(&data);
+        sampled += 1;
+        extracted = Instant::now();
+        true
+    })?;
+    if sampled == 0 {
+        return Ok(0.0);
+    }
+    Ok((started.elapsed().as_secs_f64() + extract_secs) / sampled as f64)
+}
+
+fn sidecars_writable(image_dir: &str) -> Result<(), std::io::Error> {
+    let probe = Path::new(image_dir).join(".photo-mcp-init-probe");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
+
+fn model_check() -> SetupCheck {
+    let model_path = std::env::var("YOLO_V8_MODEL_PATH").unwrap_or_default();
+    if model_path.is_empty() || !Path::new(&model_path).is_dir() {
+        return SetupCheck::new(
+            "detection_model",
+            false,
+            "YOLO_V8_MODEL_PATH is not set to the model directory, object detection is unavailable",
+        );
+    }
+    match yolo::load_model() {
+        Ok(()) => SetupCheck::new("detection_model", true, format!("loaded from {model_path}")),
+        Err(e) => SetupCheck::new("detection_model", false, format!("{model_path}: {e}")),
+    }
+}
+
+// ffmpeg extracts frames of live photos and encodes MP4 slideshows
+fn ffmpeg_check() -> SetupCheck {
+    let output = Command::new("ffmpeg")
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            let version = version.lines().next().unwrap_or_default().to_owned();
+            SetupCheck::new("ffmpeg", true, version)
+        }
+        _ => SetupCheck::new(
+            "ffmpeg",
+            false,
+            "ffmpeg not found, live photos and MP4 slideshows are unavailable",
+        ),
+    }
+}
+
+fn tls_check() -> SetupCheck {
+    let ssl_enabled = std::env::var("SSL_ENABLED")
+        .unwrap_or_default()
+        .to_lowercase()
+        == "true";
+    let pem = |file: &str, label: &str| {
+        let mut content = String::new();
+        std::fs::File::open(file)
+            .and_then(|mut f| f.read_to_string(&mut content))
+            .map_err(|e| format!("{file}: {e}"))?;
+        if content.contains("-----BEGIN ") && content.contains(label) {
+            Ok(())
+        } else {
+            Err(format!("{file} holds no PEM {label}"))
+        }
+    };
+    let certs = pem(TLS_CERT, "CERTIFICATE").and_then(|_| pem(TLS_KEY, "PRIVATE KEY"));
+    match (ssl_enabled, certs) {
+        (true, Ok(())) => SetupCheck::new("tls", true, format!("serving HTTPS with {TLS_CERT}")),
+        (true, Err(e)) => SetupCheck::new(
+            "tls",
+            false,
+            format!("SSL_ENABLED is set but {e}, run certs/gen-certs.sh"),
+        ),
+        (false, _) => SetupCheck::new("tls", true, "SSL_ENABLED is not set, serving plain HTTP"),
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use image::{
+    Delay, DynamicImage, Frame, RgbImage, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+};
+use serde::Serialize;
+
+use crate::core::{cancel::CancellationToken, error::PhotoInsightError};
+
+static NEXT_TMP: AtomicUsize = AtomicUsize::new(0);
+
+/// Frames per second of the Ken Burns animation
+const KEN_BURNS_FPS: u32 = 8;
+/// Zoom at the end of the Ken Burns pan of a photo
+const KEN_BURNS_ZOOM: f32 = 1.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlideshowFormat {
+    Gif,
+    Mp4,
+}
+
+impl SlideshowFormat {
+    pub fn parse(format: &str) -> Result<Self, PhotoInsightError> {
+        match format.to_lowercase().as_str() {
+            "gif" => Ok(SlideshowFormat::Gif),
+            "mp4" => Ok(SlideshowFormat::Mp4),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid slideshow format: {format}, use one of gif, mp4"
+            ))),
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            SlideshowFormat::Gif => "image/gif",
+            SlideshowFormat::Mp4 => "video/mp4",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SlideshowFormat::Gif => "gif",
+            SlideshowFormat::Mp4 => "mp4",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlideshowOptions {
+    pub format: SlideshowFormat,
+    pub seconds_per_photo: f32,
+    /// Width of the frames, the height is 3/4 of it
+    pub width: u32,
+    /// Slow zoom and pan over every photo
+    pub ken_burns: bool,
+}
+
+/// Rendered slideshow
+#[derive(Debug, Clone, Serialize)]
+pub struct Slideshow {
+    pub format: SlideshowFormat,
+    pub photos: usize,
+    pub frames: usize,
+    pub duration_seconds: f32,
+    pub bytes: usize,
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+// Fits the photo into the frame, the rest of the frame is black
+fn slide(img: &DynamicImage, width: u32, height: u32) -> RgbImage {
+    let fitted = img.resize(width, height, FilterType::Triangle).to_rgb8();
+    let mut frame = RgbImage::new(width, height);
+    let x = (width - fitted.width()) / 2;
+    let y = (height - fitted.height()) / 2;
+    imageops::replace(&mut frame, &fitted, x as i64, y as i64);
+    frame
+}
+
+// Frame of the Ken Burns pan at progress 0..1, the pan zooms in towards the top left corner,
+// every other photo plays it backwards
+fn ken_burns_frame(slide: &RgbImage, index: usize, progress: f32) -> RgbImage {
+    let (width, height) = slide.dimensions();
+    let progress = if index % 2 == 0 {
+        progress
+    } else {
+        1.0 - progress
+    };
+    let zoom = 1.0 + (KEN_BURNS_ZOOM - 1.0) * progress;
+    let crop_width = (width as f32 / zoom).round() as u32;
+    let crop_height = (height as f32 / zoom).round() as u32;
+    // the crop moves from the center towards the top left corner
+    let x = ((width - crop_width) as f32 / 2.0 * (1.0 - progress)).round() as u32;
+    let y = ((height - crop_height) as f32 / 2.0 * (1.0 - progress)).round() as u32;
+    let crop = imageops::crop_imm(slide, x, y, crop_width, crop_height).to_image();
+    imageops::resize(&crop, width, height, FilterType::Triangle)
+}
+
+/// Renders the photos into an animated GIF or an MP4 video (encoded by ffmpeg), photos which
+/// cannot be decoded are skipped
+pub fn render(
+    images: &[Vec<u8>],
+    options: &SlideshowOptions,
+    ffmpeg: &str,
+    cancel: &CancellationToken,
+) -> Result<Slideshow, PhotoInsightError> {
+    // even dimensions, required by the video encoder
+    let width = options.width & !1;
+    let height = (width * 3 / 4) & !1;
+    let frames_per_photo = if options.ken_burns {
+        ((options.seconds_per_photo * KEN_BURNS_FPS as f32).round() as usize).max(1)
+    } else {
+        1
+    };
+    let mut frames = Vec::new();
+    let mut photos = 0;
+    for data in images {
+        cancel.check()?;
+        let Ok(img) = image::load_from_memory(data) else {
+            continue;
+        };
+        let slide = slide(&img, width, height);
+        if options.ken_burns {
+            for i in 0..frames_per_photo {
+                let progress = i as f32 / frames_per_photo as f32;
+                frames.push(ken_burns_frame(&slide, photos, progress));
+            }
+        } else {
+            frames.push(slide);
+        }
+        photos += 1;
+    }
+    if frames.is_empty() {
+        return Err(PhotoInsightError::from_message("no images for slideshow"));
+    }
+    let frame_seconds = options.seconds_per_photo / frames_per_photo as f32;
+    let data = match options.format {
+        SlideshowFormat::Gif => encode_gif(&frames, frame_seconds, cancel)?,
+        SlideshowFormat::Mp4 => encode_mp4(&frames, frame_seconds, ffmpeg, cancel)?,
+    };
+    Ok(Slideshow {
+        format: options.format,
+        photos,
+        frames: frames.len(),
+        duration_seconds: photos as f32 * options.seconds_per_photo,
+        bytes: data.len(),
+        data,
+    })
+}
+
+fn encode_gif(
+    frames: &[RgbImage],
+    frame_seconds: f32,
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let mut data = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut data, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        let delay = Delay::from_numer_denom_ms((frame_seconds * 1000.0).round() as u32, 1);
+        for frame in frames {
+            cancel.check()?;
+            let rgba: RgbaImage = DynamicImage::ImageRgb8(frame.clone()).to_rgba8();
+            encoder
+                .encode_frame(Frame::from_parts(rgba, 0, 0, delay))
+                .map_err(|e| PhotoInsightError::new(e))?;
+        }
+    }
+    Ok(data)
+}
+
+// Frames are piped to ffmpeg as JPEG images, the video is written into a temporary file since
+// the MP4 index is written at the end
+fn encode_mp4(
+    frames: &[RgbImage],
+    frame_seconds: f32,
+    ffmpeg: &str,
+    cancel: &CancellationToken,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let path = std::env::temp_dir().join(format!(
+        "photo-mcp-slideshow-{}-{}.mp4",
+        std::process::id(),
+        NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut child = Command::new(ffmpeg)
+        .args(["-v", "error", "-y", "-f", "image2pipe", "-framerate"])
+        .arg(format!("1000/{}", (frame_seconds * 1000.0).round() as u32))
+        .args([
+            "-i",
+            "pipe:0",
+            "-c:v",
+            "libx264",
+            "-pix_fmt",
+            "yuv420p",
+            "-r",
+            "25",
+            "-movflags",
+            "+faststart",
+        ])
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| PhotoInsightError::from_message(format!("can't run {ffmpeg}: {e}")))?;
+    let written = child.stdin.take().map_or(Ok(()), |mut stdin| {
+        for frame in frames {
+            cancel.check()?;
+            let mut jpeg = std::io::Cursor::new(Vec::new());
+            DynamicImage::ImageRgb8(frame.clone())
+                .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+                .map_err(|e| PhotoInsightError::new(e))?;
+            stdin
+                .write_all(jpeg.get_ref())
+                .map_err(|e| PhotoInsightError::new(e))?;
+        }
+        Ok(())
+    });
+    if let Err(e) = written {
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| PhotoInsightError::new(e))?;
+    let video = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+    if !output.status.success() {
+        return Err(PhotoInsightError::from_message(format!(
+            "{ffmpeg} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    video.map_err(|e| PhotoInsightError::new(e))
+}
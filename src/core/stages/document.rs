@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    external::ExternalModel,
+    image_cache::PhotoInfo,
+    yolo::DetectedObject,
+};
+
+const DOCUMENT: &str = "document";
+const RECEIPT: &str = "receipt";
+const WHITEBOARD: &str = "whiteboard";
+const ID_CARD: &str = "id card";
+/// Classes the detector is asked for
+const DOCUMENT_CLASSES: [&str; 4] = [DOCUMENT, RECEIPT, WHITEBOARD, ID_CARD];
+/// Words found on receipts, a receipt also has several prices
+const RECEIPT_WORDS: [&str; 8] = [
+    "total", "subtotal", "tax", "vat", "cash", "change", "receipt", "invoice",
+];
+/// Words found on identity documents
+const ID_WORDS: [&str; 7] = [
+    "passport",
+    "identity",
+    "birth",
+    "nationality",
+    "licence",
+    "license",
+    "surname",
+];
+/// Prices on a photo making it a receipt
+const MIN_RECEIPT_PRICES: usize = 3;
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["ocr.py"], "min_text_density": 0.05}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct DocumentOptions {
+    /// OCR model, receives the whole photo and prints a JSON array of
+    /// `{"text": .., "confidence": .., "bbox": [xmin, ymin, xmax, ymax]}` in pixels
+    #[serde(flatten)]
+    ocr: Option<ExternalModel>,
+    /// Optional open-vocabulary detector asked for documents, receipts, whiteboards and ID
+    /// cards, see photo_detect_custom
+    detector: Option<ExternalModel>,
+    /// Text recognized with lower confidence is ignored
+    min_ocr_confidence: f32,
+    /// Share of the photo covered by text making it a document
+    min_text_density: f32,
+    /// Words a document has at least
+    min_words: usize,
+    /// Detections and labels with lower confidence are dropped
+    min_confidence: f32,
+}
+
+impl Default for DocumentOptions {
+    fn default() -> Self {
+        Self {
+            ocr: None,
+            detector: None,
+            min_ocr_confidence: 0.5,
+            min_text_density: 0.05,
+            min_words: 10,
+            min_confidence: 0.3,
+        }
+    }
+}
+
+/// Text found by the OCR model, expected output is a JSON array of these
+#[derive(Debug, Clone, Deserialize)]
+struct TextBox {
+    text: String,
+    confidence: f32,
+    bbox: (f32, f32, f32, f32),
+}
+
+/// Tags photos of documents, receipts, whiteboards and ID cards. The share of the photo
+/// covered by recognized text tells documents from photos with a sign in the background, the
+/// words tell receipts and IDs apart, the optional detector finds them without much text
+/// (e.g. a whiteboard with a drawing).
+pub struct DocumentAnalyzer {
+    options: DocumentOptions,
+}
+
+impl DocumentAnalyzer {
+    pub const NAME: &str = "document";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        Ok(Self {
+            options: StageConfig::parse_options(config, Self::NAME)?,
+        })
+    }
+
+    fn text_boxes(&self, photo_bytes: &[u8]) -> Result<Vec<TextBox>, PhotoInsightError> {
+        let ocr = self.options.ocr.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message("document stage has no OCR command configured")
+        })?;
+        let boxes: Vec<TextBox> =
+            serde_json::from_value(ocr.run(photo_bytes, &[])?).map_err(|e| {
+                PhotoInsightError::from_message(format!(
+                    "unexpected OCR output of {}: {e}",
+                    ocr.command
+                ))
+            })?;
+        Ok(boxes
+            .into_iter()
+            .filter(|b| b.confidence >= self.options.min_ocr_confidence)
+            .collect())
+    }
+
+    fn detections(&self, photo_bytes: &[u8]) -> Result<Vec<DetectedObject>, PhotoInsightError> {
+        let Some(detector) = self.options.detector.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let classes = DOCUMENT_CLASSES.map(str::to_owned);
+        serde_json::from_value(detector.run(photo_bytes, &classes)?).map_err(|e| {
+            PhotoInsightError::from_message(format!(
+                "unexpected detector output of {}: {e}",
+                detector.command
+            ))
+        })
+    }
+
+    // Labels suggested by the recognized text, confidence grows with the text density
+    fn text_labels(&self, text: &str, density: f32, words: usize) -> Vec<Label> {
+        if density < self.options.min_text_density || words < self.options.min_words {
+            return Vec::new();
+        }
+        let confidence = (density / (2.0 * self.options.min_text_density)).min(1.0);
+        let text = text.to_lowercase();
+        let has_word = |candidates: &[&str]| {
+            text.split(|c: char| !c.is_alphanumeric())
+                .any(|word| candidates.contains(&word))
+        };
+        let mut labels = vec![Label {
+            name: DOCUMENT.to_owned(),
+            confidence,
+        }];
+        if has_word(&RECEIPT_WORDS) || prices(&text) >= MIN_RECEIPT_PRICES {
+            labels.push(Label {
+                name: RECEIPT.to_owned(),
+                confidence,
+            });
+        }
+        if has_word(&ID_WORDS) {
+            labels.push(Label {
+                name: ID_CARD.to_owned(),
+                confidence,
+            });
+        }
+        labels
+    }
+}
+
+// Amounts with two decimal places, e.g. "12.50" or "3,99"
+fn prices(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|word| {
+            let word = word.trim_matches(|c: char| !c.is_ascii_digit());
+            match word.rsplit_once(['.', ',']) {
+                Some((units, cents)) => {
+                    !units.is_empty()
+                        && units.chars().all(|c| c.is_ascii_digit())
+                        && cents.len() == 2
+                        && cents.chars().all(|c| c.is_ascii_digit())
+                }
+                None => false,
+            }
+        })
+        .count()
+}
+
+// Share of the photo covered by the text boxes, overlaps are counted twice
+fn text_density(boxes: &[TextBox], width: u32, height: u32) -> f32 {
+    let area = width as f32 * height as f32;
+    if area <= 0.0 {
+        return 0.0;
+    }
+    let covered = boxes
+        .iter()
+        .map(|b| (b.bbox.2 - b.bbox.0).max(0.0) * (b.bbox.3 - b.bbox.1).max(0.0))
+        .sum::<f32>();
+    (covered / area).min(1.0)
+}
+
+impl Analyzer for DocumentAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    // needs an OCR model to be configured
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn analyze(
+        &self,
+        _photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let (width, height) = ::image::ImageReader::new(std::io::Cursor::new(photo_bytes))
+            .with_guessed_format()
+            .map_err(PhotoInsightError::new)?
+            .into_dimensions()
+            .map_err(PhotoInsightError::new)?;
+        let boxes = self.text_boxes(photo_bytes)?;
+        let text = boxes
+            .iter()
+            .map(|b| b.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let words = text.split_whitespace().count();
+        let density = text_density(&boxes, width, height);
+        let mut labels = self.text_labels(&text, density, words);
+        for detection in self.detections(photo_bytes)? {
+            let class_name = detection.class_name.to_lowercase();
+            if !DOCUMENT_CLASSES.contains(&class_name.as_str()) {
+                continue;
+            }
+            match labels.iter_mut().find(|l| l.name == class_name) {
+                Some(label) => label.confidence = label.confidence.max(detection.confidence),
+                None => labels.push(Label {
+                    name: class_name,
+                    confidence: detection.confidence,
+                }),
+            }
+        }
+        labels.retain(|l| l.confidence >= self.options.min_confidence);
+        labels.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        Ok(serde_json::json!({
+            "labels": labels,
+            "text_density": density,
+            "words": words,
+        }))
+    }
+}
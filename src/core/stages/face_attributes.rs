@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::Analyzer, config::StageConfig, error::PhotoInsightError, external::ExternalModel,
+    image_cache::PhotoInfo,
+};
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["face_attributes.py"]}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct FaceAttributesOptions {
+    /// Face attribute model, receives the whole photo
+    #[serde(flatten)]
+    model: Option<ExternalModel>,
+    /// Faces detected with lower confidence are dropped
+    min_face_confidence: f32,
+}
+
+impl Default for FaceAttributesOptions {
+    fn default() -> Self {
+        Self {
+            model: None,
+            min_face_confidence: 0.5,
+        }
+    }
+}
+
+/// Face found by the model, expected output is a JSON array of these
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Face {
+    bbox: (f32, f32, f32, f32),
+    confidence: f32,
+    /// Smile probability (0.0 - 1.0)
+    smile: f32,
+    /// Eyes open score (0.0 - 1.0)
+    eyes_open: f32,
+}
+
+/// Face attributes aggregated per photo, minimums tell whether everyone smiles / has open eyes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaceSummary {
+    pub face_count: usize,
+    pub min_smile: f32,
+    pub mean_smile: f32,
+    pub min_eyes_open: f32,
+    pub mean_eyes_open: f32,
+}
+
+impl FaceSummary {
+    fn of(faces: &[Face]) -> Self {
+        let n = faces.len();
+        let min = |f: fn(&Face) -> f32| faces.iter().map(f).fold(f32::INFINITY, f32::min);
+        let mean = |f: fn(&Face) -> f32| {
+            if n == 0 {
+                0.0
+            } else {
+                faces.iter().map(f).sum::<f32>() / n as f32
+            }
+        };
+        Self {
+            face_count: n,
+            min_smile: if n == 0 { 0.0 } else { min(|f| f.smile) },
+            mean_smile: mean(|f| f.smile),
+            min_eyes_open: if n == 0 { 0.0 } else { min(|f| f.eyes_open) },
+            mean_eyes_open: mean(|f| f.eyes_open),
+        }
+    }
+
+    /// Portrait quality, the worst face decides (one closed eye spoils a group shot)
+    pub fn score(&self) -> f32 {
+        self.min_smile * self.min_eyes_open
+    }
+}
+
+/// Smile probability and eyes open score per face, used to select the best portraits
+pub struct FaceAttributesAnalyzer {
+    options: FaceAttributesOptions,
+}
+
+impl FaceAttributesAnalyzer {
+    pub const NAME: &str = "face_attributes";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        Ok(Self {
+            options: StageConfig::parse_options(config, Self::NAME)?,
+        })
+    }
+
+    /// Per photo summary stored by this stage
+    pub fn summary_of(result: &serde_json::Value) -> Option<FaceSummary> {
+        result
+            .get("summary")
+            .and_then(|s| serde_json::from_value(s.clone()).ok())
+    }
+
+    /// Bounding boxes (xmin, ymin, xmax, ymax in pixels) of the faces stored by this stage
+    pub fn face_boxes_of(result: &serde_json::Value) -> Vec<(f32, f32, f32, f32)> {
+        result
+            .get("faces")
+            .and_then(|f| serde_json::from_value::<Vec<Face>>(f.clone()).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|f| f.bbox)
+            .collect()
+    }
+}
+
+impl Analyzer for FaceAttributesAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    // needs a face attribute model to be configured
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn processes_faces(&self) -> bool {
+        true
+    }
+
+    fn analyze(
+        &self,
+        _photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let model = self.options.model.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message("face_attributes stage has no model command configured")
+        })?;
+        let faces: Vec<Face> =
+            serde_json::from_value(model.run(photo_bytes, &[])?).map_err(|e| {
+                PhotoInsightError::from_message(format!(
+                    "unexpected face attribute output of {}: {e}",
+                    model.command
+                ))
+            })?;
+        let faces = faces
+            .into_iter()
+            .filter(|f| f.confidence >= self.options.min_face_confidence)
+            .collect::<Vec<_>>();
+        Ok(serde_json::json!({
+            "summary": FaceSummary::of(&faces),
+            "faces": faces,
+        }))
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    external::ExternalModel,
+    image_cache::PhotoInfo,
+};
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["landmarks.py"], "min_confidence": 0.3}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct LandmarkOptions {
+    /// On-device landmark recognition model, receives the whole photo
+    #[serde(flatten)]
+    classifier: Option<ExternalModel>,
+    /// Landmarks recognized with lower confidence are dropped
+    min_confidence: f32,
+    /// Maximal number of landmarks kept per photo
+    top_k: usize,
+}
+
+impl Default for LandmarkOptions {
+    fn default() -> Self {
+        Self {
+            classifier: None,
+            min_confidence: 0.3,
+            top_k: 3,
+        }
+    }
+}
+
+/// Names famous places in photos, useful for photos without GPS coordinates
+pub struct LandmarkAnalyzer {
+    options: LandmarkOptions,
+}
+
+impl LandmarkAnalyzer {
+    pub const NAME: &str = "landmark";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        Ok(Self {
+            options: StageConfig::parse_options(config, Self::NAME)?,
+        })
+    }
+}
+
+impl Analyzer for LandmarkAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    // needs a landmark model to be configured
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn analyze(
+        &self,
+        _photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let classifier = self.options.classifier.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message("landmark stage has no model command configured")
+        })?;
+        let mut landmarks = classifier
+            .classify(photo_bytes, &[])?
+            .into_iter()
+            .filter(|c| c.confidence >= self.options.min_confidence)
+            .collect::<Vec<_>>();
+        landmarks.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        landmarks.truncate(self.options.top_k);
+        let labels = landmarks
+            .into_iter()
+            .map(|c| Label {
+                name: c.label,
+                confidence: c.confidence,
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::json!({ "labels": labels }))
+    }
+}
@@ -0,0 +1,9 @@
+pub mod document;
+pub mod face_attributes;
+pub mod landmark;
+pub mod night_shot;
+pub mod occasions;
+pub mod people_count;
+pub mod pet_breed;
+pub mod tags;
+pub mod weather;
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    exif::exposure_of,
+    image_cache::PhotoInfo,
+};
+
+/// Night hours, from the first hour to the last hour exclusive over midnight
+const NIGHT_HOURS: (u32, u32) = (20, 5);
+
+/// Stage options, e.g. `{"max_luminance": 0.2, "min_iso": 3200}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct NightShotOptions {
+    /// Photos with brighter average luminance (0.0 - 1.0) are never night shots
+    max_luminance: f32,
+    /// ISO high enough to count as low light evidence
+    min_iso: u32,
+    /// Exposure time in seconds long enough to count as low light evidence
+    min_exposure_seconds: f64,
+    /// Night shots exposed at least this long are astro shots as well
+    astro_exposure_seconds: f64,
+}
+
+impl Default for NightShotOptions {
+    fn default() -> Self {
+        Self {
+            max_luminance: 0.25,
+            min_iso: 1600,
+            min_exposure_seconds: 1.0 / 15.0,
+            astro_exposure_seconds: 5.0,
+        }
+    }
+}
+
+/// Recognizes night and low-light photos from their average luminance together with the
+/// exposure (long exposure time, high ISO) and the hour taken. Night shots are labelled
+/// "night", long exposed ones "astro" as well, both searchable by label.
+pub struct NightShotAnalyzer {
+    options: NightShotOptions,
+}
+
+impl NightShotAnalyzer {
+    pub const NAME: &str = "night_shot";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        Ok(Self {
+            options: StageConfig::parse_options(config, Self::NAME)?,
+        })
+    }
+}
+
+// Average luminance from 0 (black) to 1 (white) of a small copy of the photo
+fn mean_luminance(photo_bytes: &[u8]) -> Result<f32, PhotoInsightError> {
+    let img = image::load_from_memory(photo_bytes).map_err(|e| PhotoInsightError::new(e))?;
+    let luma = img.thumbnail(64, 64).to_luma8();
+    let sum = luma.pixels().map(|p| p.0[0] as u64).sum::<u64>();
+    Ok(sum as f32 / (luma.pixels().len().max(1) as f32 * 255.0))
+}
+
+impl Analyzer for NightShotAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    fn analyze(
+        &self,
+        _photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let luminance = mean_luminance(photo_bytes)?;
+        let exposure = exposure_of(photo_bytes);
+        let night_hour = exposure
+            .hour
+            .is_some_and(|hour| hour >= NIGHT_HOURS.0 || hour < NIGHT_HOURS.1);
+        let long_exposure = exposure
+            .seconds
+            .is_some_and(|s| s >= self.options.min_exposure_seconds);
+        let high_iso = exposure.iso.is_some_and(|iso| iso >= self.options.min_iso);
+        let evidence = [night_hour, long_exposure, high_iso]
+            .iter()
+            .filter(|e| **e)
+            .count();
+        let night_shot = luminance <= self.options.max_luminance && evidence > 0;
+
+        let mut labels = Vec::new();
+        if night_shot {
+            // darker photos with more evidence are more certainly night shots
+            let confidence = (0.4 + 0.2 * evidence as f32
+                - luminance / self.options.max_luminance.max(f32::EPSILON) * 0.2)
+                .clamp(0.0, 1.0);
+            labels.push(Label {
+                name: "night".to_owned(),
+                confidence,
+            });
+            if exposure
+                .seconds
+                .is_some_and(|s| s >= self.options.astro_exposure_seconds)
+            {
+                labels.push(Label {
+                    name: "astro".to_owned(),
+                    confidence,
+                });
+            }
+        }
+        Ok(serde_json::json!({
+            "night_shot": night_shot,
+            "mean_luminance": luminance,
+            "exposure": exposure,
+            "labels": labels,
+        }))
+    }
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    image_cache::PhotoInfo,
+    overrides::{days_from_civil, parse_date_time},
+};
+
+/// Yearly occasion, e.g. `{"name": "Anna's birthday", "date": "05-17"}` or
+/// `{"name": "Good Friday", "date": "easter", "offset_days": -2}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Occasion {
+    name: String,
+    /// "MM-DD", or "easter" for Easter Sunday of the year
+    date: String,
+    /// Days added to the date, useful for holidays relative to Easter
+    #[serde(default)]
+    offset_days: i64,
+    /// Number of days the occasion lasts
+    #[serde(default = "one_day")]
+    days: i64,
+}
+
+fn one_day() -> i64 {
+    1
+}
+
+/// Stage options, e.g.
+/// `{"occasions": [{"name": "Anna's birthday", "date": "05-17"}], "include_defaults": true}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct OccasionOptions {
+    /// Family birthdays, anniversaries, national holidays
+    occasions: Vec<Occasion>,
+    /// Tags the common holidays (Christmas, New Year, Easter, ...) as well
+    include_defaults: bool,
+}
+
+impl Default for OccasionOptions {
+    fn default() -> Self {
+        Self {
+            occasions: Vec::new(),
+            include_defaults: true,
+        }
+    }
+}
+
+fn default_occasions() -> Vec<Occasion> {
+    [
+        ("New Year's Day", "01-01", 0, 1),
+        ("Valentine's Day", "02-14", 0, 1),
+        ("Easter", "easter", 0, 2),
+        ("Halloween", "10-31", 0, 1),
+        ("Christmas", "12-24", 0, 3),
+        ("New Year's Eve", "12-31", 0, 1),
+    ]
+    .into_iter()
+    .map(|(name, date, offset_days, days)| Occasion {
+        name: name.to_owned(),
+        date: date.to_owned(),
+        offset_days,
+        days,
+    })
+    .collect()
+}
+
+// Month and day of Easter Sunday (anonymous Gregorian algorithm)
+fn easter(year: i64) -> (i64, i64) {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    ((h + l - 7 * m + 114) / 31, (h + l - 7 * m + 114) % 31 + 1)
+}
+
+impl Occasion {
+    // First day of the occasion in the year, days since 1970-01-01
+    fn start(&self, year: i64) -> Result<i64, PhotoInsightError> {
+        let (month, day) = if self.date.eq_ignore_ascii_case("easter") {
+            easter(year)
+        } else {
+            self.date
+                .split_once('-')
+                .and_then(|(month, day)| Some((month.parse().ok()?, day.parse().ok()?)))
+                .filter(|(month, day)| (1..=12).contains(month) && (1..=31).contains(day))
+                .ok_or_else(|| {
+                    PhotoInsightError::from_message(format!(
+                        "Invalid date of occasion {}: {}, expected MM-DD or easter",
+                        self.name, self.date
+                    ))
+                })?
+        };
+        Ok(days_from_civil(year, month, day) + self.offset_days)
+    }
+
+    // Occasions lasting over the new year started in the previous year
+    fn covers(&self, year: i64, day: i64) -> Result<bool, PhotoInsightError> {
+        for start in [self.start(year)?, self.start(year - 1)?] {
+            if (start..start + self.days.max(1)).contains(&day) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Tags photos taken on holidays, birthdays and other yearly occasions with the occasion
+/// name (searchable by label search), derived from the time taken only
+pub struct OccasionAnalyzer {
+    options: OccasionOptions,
+    occasions: Vec<Occasion>,
+}
+
+impl OccasionAnalyzer {
+    pub const NAME: &str = "occasions";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        let options: OccasionOptions = StageConfig::parse_options(config, Self::NAME)?;
+        let mut occasions = options.occasions.clone();
+        if options.include_defaults {
+            occasions.extend(default_occasions());
+        }
+        // invalid dates are reported at startup instead of on every photo
+        for occasion in occasions.iter() {
+            occasion.start(2000)?;
+        }
+        Ok(Self { options, occasions })
+    }
+}
+
+impl Analyzer for OccasionAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    fn analyze(
+        &self,
+        photo_info: &PhotoInfo,
+        _photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let date = crate::IC
+            .exif_info(vec![photo_info])?
+            .pop()
+            .map(|result| result.into_parts().1)
+            .and_then(|exif| parse_date_time(exif.date_time.trim_matches('"')).ok())
+            .filter(|(year, ..)| *year > 0);
+        let mut labels = Vec::new();
+        if let Some((year, month, day, ..)) = date {
+            let day = days_from_civil(year as i64, month as i64, day as i64);
+            for occasion in self.occasions.iter() {
+                if occasion.covers(year as i64, day)? {
+                    labels.push(Label {
+                        name: occasion.name.clone(),
+                        confidence: 1.0,
+                    });
+                }
+            }
+        }
+        Ok(serde_json::json!({ "labels": labels }))
+    }
+}
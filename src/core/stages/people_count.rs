@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::Analyzer,
+    config::StageConfig,
+    error::PhotoInsightError,
+    external::ExternalModel,
+    image_cache::PhotoInfo,
+    yolo::{DetectedObject, ModelRegistry},
+};
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["pose.py"], "min_detection_confidence": 0.4}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct PeopleCountOptions {
+    /// Optional pose estimator, receives the whole photo
+    #[serde(flatten)]
+    pose_estimator: Option<ExternalModel>,
+    /// Minimal confidence of the person detection
+    min_detection_confidence: f32,
+    /// Minimal confidence of the estimated pose
+    min_pose_confidence: f32,
+    /// Boxes overlapping more than this share of the smaller box are the same person
+    overlap_threshold: f32,
+    /// Detection model providing the person boxes, default model if not set
+    detection_model: Option<String>,
+}
+
+impl Default for PeopleCountOptions {
+    fn default() -> Self {
+        Self {
+            pose_estimator: None,
+            min_detection_confidence: 0.4,
+            min_pose_confidence: 0.3,
+            overlap_threshold: 0.6,
+            detection_model: None,
+        }
+    }
+}
+
+/// Person found by the pose estimator, expected output is a JSON array of these
+#[derive(Debug, Clone, Deserialize)]
+struct Pose {
+    bbox: (f32, f32, f32, f32),
+    confidence: f32,
+}
+
+type BBox = (f32, f32, f32, f32);
+
+/// Counts people in photos from person detections, merged with estimated poses when a pose
+/// estimator is configured so that a person split into several boxes is counted once
+pub struct PeopleCountAnalyzer {
+    options: PeopleCountOptions,
+    detection_model: String,
+    /// Whether the background crawler runs the detection model
+    detection_analysed: bool,
+}
+
+impl PeopleCountAnalyzer {
+    pub const NAME: &str = "people_count";
+
+    pub fn from_config(
+        config: Option<&StageConfig>,
+        models: &ModelRegistry,
+    ) -> Result<Self, PhotoInsightError> {
+        let options: PeopleCountOptions = StageConfig::parse_options(config, Self::NAME)?;
+        let detection_model = models.resolve(&options.detection_model)?.id();
+        let detection_analysed = models
+            .analysis_models()
+            .iter()
+            .any(|model| model.id() == detection_model);
+        Ok(Self {
+            options,
+            detection_model,
+            detection_analysed,
+        })
+    }
+
+    /// People count stored for the photo by this stage
+    pub fn people_count_of(result: &serde_json::Value) -> Option<u32> {
+        result
+            .get("people_count")
+            .and_then(|c| c.as_u64())
+            .map(|c| c as u32)
+    }
+
+    // None when the detection model has not analysed the photo yet
+    fn persons(&self, photo_info: &PhotoInfo) -> Option<Vec<DetectedObject>> {
+        let object_detection = crate::IC.object_detection.read().unwrap();
+        object_detection
+            .get(&self.detection_model)
+            .and_then(|detections| detections.get(photo_info))
+            .map(|objects| {
+                objects
+                    .iter()
+                    .filter(|o| {
+                        o.class_name == "person"
+                            && o.confidence >= self.options.min_detection_confidence
+                    })
+                    .cloned()
+                    .collect()
+            })
+    }
+
+    fn count(
+        &self,
+        detections: Vec<DetectedObject>,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let poses = self.poses(photo_bytes)?;
+        let persons = merge_persons(poses, detections, self.options.overlap_threshold);
+        Ok(serde_json::json!({
+            "people_count": persons.len(),
+            "persons": persons,
+        }))
+    }
+
+    fn poses(&self, photo_bytes: &[u8]) -> Result<Vec<BBox>, PhotoInsightError> {
+        let Some(estimator) = self.options.pose_estimator.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let poses: Vec<Pose> =
+            serde_json::from_value(estimator.run(photo_bytes, &[])?).map_err(|e| {
+                PhotoInsightError::from_message(format!(
+                    "unexpected pose estimator output of {}: {e}",
+                    estimator.command
+                ))
+            })?;
+        Ok(poses
+            .into_iter()
+            .filter(|p| p.confidence >= self.options.min_pose_confidence)
+            .map(|p| p.bbox)
+            .collect())
+    }
+}
+
+// intersection area divided by the area of the smaller box, nested boxes overlap fully
+fn overlap(a: &BBox, b: &BBox) -> f32 {
+    let w = (a.2.min(b.2) - a.0.max(b.0)).max(0.0);
+    let h = (a.3.min(b.3) - a.1.max(b.1)).max(0.0);
+    let area_a = (a.2 - a.0) * (a.3 - a.1);
+    let area_b = (b.2 - b.0) * (b.3 - b.1);
+    let smaller = area_a.min(area_b);
+    if smaller <= 0.0 {
+        return 0.0;
+    }
+    w * h / smaller
+}
+
+/// Merges person boxes, every pose is a person, detections overlapping a known person are dropped
+fn merge_persons(
+    poses: Vec<BBox>,
+    mut detections: Vec<DetectedObject>,
+    threshold: f32,
+) -> Vec<BBox> {
+    detections.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let mut persons = poses;
+    for detection in detections {
+        if !persons
+            .iter()
+            .any(|p| overlap(p, &detection.bbox) >= threshold)
+        {
+            persons.push(detection.bbox);
+        }
+    }
+    persons
+}
+
+impl Analyzer for PeopleCountAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::json!({
+            "pose_estimator": self.options.pose_estimator,
+            "min_detection_confidence": self.options.min_detection_confidence,
+            "min_pose_confidence": self.options.min_pose_confidence,
+            "overlap_threshold": self.options.overlap_threshold,
+            "detection_model": self.detection_model,
+        })
+    }
+
+    // counts are made of the detections, nothing to count without the model analysed
+    fn available(&self) -> bool {
+        self.detection_analysed
+    }
+
+    fn analyze(
+        &self,
+        photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let detections = self.persons(photo_info).ok_or_else(|| {
+            PhotoInsightError::from_message(format!(
+                "{photo_info:?} has no detections of {}",
+                self.detection_model
+            ))
+        })?;
+        self.count(detections, photo_bytes)
+    }
+
+    // Photos without detections (the detection model failed on them) get no result
+    fn analyze_batch(
+        &self,
+        photos: &[(PhotoInfo, Vec<u8>)],
+    ) -> Result<Vec<(PhotoInfo, serde_json::Value)>, PhotoInsightError> {
+        let mut results = Vec::new();
+        for (photo_info, photo_bytes) in photos {
+            let Some(detections) = self.persons(photo_info) else {
+                continue;
+            };
+            match self.count(detections, photo_bytes) {
+                Ok(value) => results.push((photo_info.clone(), value)),
+                Err(e) => tracing::warn!("{} failed on {photo_info:?}: {e}", Self::NAME),
+            }
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    external::ExternalModel,
+    image::crop_to_jpeg,
+    image_cache::PhotoInfo,
+    yolo::{DetectedObject, ModelRegistry},
+};
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["breed_classifier.py"], "classes": ["dog", "cat"]}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct PetBreedOptions {
+    /// Breed classifier, receives the animal crop as JPEG
+    #[serde(flatten)]
+    classifier: Option<ExternalModel>,
+    /// Detected classes which are cropped and classified
+    classes: Vec<String>,
+    /// Minimal confidence of the animal detection
+    min_detection_confidence: f32,
+    /// Detection model providing the animal boxes, default model if not set
+    detection_model: Option<String>,
+}
+
+impl Default for PetBreedOptions {
+    fn default() -> Self {
+        Self {
+            classifier: None,
+            classes: vec!["dog".to_owned(), "cat".to_owned()],
+            min_detection_confidence: 0.4,
+            detection_model: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Animal {
+    class_name: String,
+    bbox: (f32, f32, f32, f32),
+    breed: String,
+    confidence: f32,
+}
+
+/// Fine-grained breed classification of dogs and cats found by object detection
+pub struct PetBreedAnalyzer {
+    options: PetBreedOptions,
+    detection_model: String,
+}
+
+impl PetBreedAnalyzer {
+    pub const NAME: &str = "pet_breed";
+
+    pub fn from_config(
+        config: Option<&StageConfig>,
+        models: &ModelRegistry,
+    ) -> Result<Self, PhotoInsightError> {
+        let options: PetBreedOptions = StageConfig::parse_options(config, Self::NAME)?;
+        let detection_model = models.resolve(&options.detection_model)?.id();
+        Ok(Self {
+            options,
+            detection_model,
+        })
+    }
+
+    fn animals(&self, photo_info: &PhotoInfo) -> Vec<DetectedObject> {
+        let object_detection = crate::IC.object_detection.read().unwrap();
+        object_detection
+            .get(&self.detection_model)
+            .and_then(|detections| detections.get(photo_info))
+            .map(|objects| {
+                objects
+                    .iter()
+                    .filter(|o| {
+                        self.options.classes.contains(&o.class_name)
+                            && o.confidence >= self.options.min_detection_confidence
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Analyzer for PetBreedAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::json!({
+            "classifier": self.options.classifier,
+            "classes": self.options.classes,
+            "min_detection_confidence": self.options.min_detection_confidence,
+            "detection_model": self.detection_model,
+        })
+    }
+
+    // needs a breed classifier to be configured
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn analyze(
+        &self,
+        photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let classifier = self.options.classifier.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message("pet_breed stage has no classifier command configured")
+        })?;
+        let candidates = self.animals(photo_info);
+        let mut animals = Vec::new();
+        let mut labels = Vec::new();
+        if !candidates.is_empty() {
+            let img =
+                image::load_from_memory(photo_bytes).map_err(|e| PhotoInsightError::new(e))?;
+            for candidate in candidates {
+                let crop = crop_to_jpeg(&img, candidate.bbox)?;
+                let best = classifier
+                    .classify(&crop, &[candidate.class_name.clone()])?
+                    .into_iter()
+                    .max_by(|a, b| a.confidence.total_cmp(&b.confidence));
+                if let Some(best) = best {
+                    labels.push(Label {
+                        name: best.label.clone(),
+                        confidence: best.confidence,
+                    });
+                    animals.push(Animal {
+                        class_name: candidate.class_name,
+                        bbox: candidate.bbox,
+                        breed: best.label,
+                        confidence: best.confidence,
+                    });
+                }
+            }
+        }
+        Ok(serde_json::json!({
+            "labels": labels,
+            "animals": animals,
+        }))
+    }
+}
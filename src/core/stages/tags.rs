@@ -0,0 +1,196 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    external::ExternalModel,
+    image_cache::PhotoInfo,
+};
+
+/// CLIP logit scale, similarities are multiplied by it before the softmax over the tags
+const LOGIT_SCALE: f32 = 100.0;
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["clip_embed.py"], "tags": ["wedding", "snow"]}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct TagOptions {
+    /// CLIP embedding model. Receives the photo on stdin and prints its embedding as a JSON
+    /// array of numbers, run with `--text` followed by texts it prints a JSON array of
+    /// their embeddings.
+    #[serde(flatten)]
+    embedder: Option<ExternalModel>,
+    /// Vocabulary of tags every photo is scored against
+    tags: Vec<String>,
+    /// Text embedded for a tag, `{tag}` is replaced by the tag
+    prompt: String,
+    /// Tags scored lower are dropped
+    min_confidence: f32,
+    /// Maximal number of tags kept per photo
+    top_k: usize,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        let tags = [
+            "beach",
+            "mountains",
+            "snow",
+            "forest",
+            "city",
+            "sunset",
+            "night",
+            "food",
+            "drinks",
+            "party",
+            "festival",
+            "concert",
+            "wedding",
+            "birthday",
+            "christmas",
+            "sports",
+            "car",
+            "airplane",
+            "boat",
+            "pets",
+            "baby",
+            "family",
+            "selfie",
+            "group photo",
+            "landscape",
+            "architecture",
+            "museum",
+            "flowers",
+            "garden",
+            "document",
+            "screenshot",
+        ];
+        Self {
+            embedder: None,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            prompt: "a photo of {tag}".to_owned(),
+            min_confidence: 0.1,
+            top_k: 5,
+        }
+    }
+}
+
+/// Zero-shot tagging: the photo's CLIP embedding is compared with embeddings of the tag
+/// vocabulary, the best scoring tags are stored as labels (searchable by label search). One
+/// embedding per photo covers the whole vocabulary, cheaper than a specialized model per tag.
+pub struct TagAnalyzer {
+    options: TagOptions,
+    /// Normalized embeddings of the tags, computed on first use
+    tag_embeddings: Mutex<Option<Vec<Vec<f32>>>>,
+}
+
+impl TagAnalyzer {
+    pub const NAME: &str = "tags";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        Ok(Self {
+            options: StageConfig::parse_options(config, Self::NAME)?,
+            tag_embeddings: Mutex::new(None),
+        })
+    }
+
+    fn embedder(&self) -> Result<&ExternalModel, PhotoInsightError> {
+        self.options.embedder.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message("tags stage has no embedding model command configured")
+        })
+    }
+
+    fn tag_embeddings(&self) -> Result<Vec<Vec<f32>>, PhotoInsightError> {
+        let mut tag_embeddings = self.tag_embeddings.lock().unwrap();
+        if let Some(embeddings) = tag_embeddings.as_ref() {
+            return Ok(embeddings.clone());
+        }
+        let mut args = vec!["--text".to_owned()];
+        args.extend(
+            self.options
+                .tags
+                .iter()
+                .map(|tag| self.options.prompt.replace("{tag}", tag)),
+        );
+        let value = self.embedder()?.run(&[], &args)?;
+        let embeddings: Vec<Vec<f32>> = serde_json::from_value(value).map_err(|e| {
+            PhotoInsightError::from_message(format!("unexpected text embeddings: {e}"))
+        })?;
+        if embeddings.len() != self.options.tags.len() {
+            return Err(PhotoInsightError::from_message(format!(
+                "expected {} text embeddings, got {}",
+                self.options.tags.len(),
+                embeddings.len()
+            )));
+        }
+        let embeddings = embeddings.into_iter().map(normalized).collect::<Vec<_>>();
+        *tag_embeddings = Some(embeddings.clone());
+        Ok(embeddings)
+    }
+}
+
+fn normalized(mut embedding: Vec<f32>) -> Vec<f32> {
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        embedding.iter_mut().for_each(|v| *v /= norm);
+    }
+    embedding
+}
+
+// Softmax of the scaled cosine similarities of the photo and the tags
+fn tag_scores(photo: &[f32], tags: &[Vec<f32>]) -> Vec<f32> {
+    let logits = tags
+        .iter()
+        .map(|tag| LOGIT_SCALE * tag.iter().zip(photo).map(|(t, p)| t * p).sum::<f32>())
+        .collect::<Vec<_>>();
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exp = logits.iter().map(|l| (l - max).exp()).collect::<Vec<_>>();
+    let sum = exp.iter().sum::<f32>();
+    exp.into_iter().map(|e| e / sum).collect()
+}
+
+impl Analyzer for TagAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    // needs an embedding model to be configured
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn analyze(
+        &self,
+        _photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let tags = self.tag_embeddings()?;
+        let value = self.embedder()?.run(photo_bytes, &[])?;
+        let embedding: Vec<f32> = serde_json::from_value(value).map_err(|e| {
+            PhotoInsightError::from_message(format!("unexpected image embedding: {e}"))
+        })?;
+        let mut labels = tag_scores(&normalized(embedding), &tags)
+            .into_iter()
+            .zip(&self.options.tags)
+            .filter(|(confidence, _)| *confidence >= self.options.min_confidence)
+            .map(|(confidence, tag)| Label {
+                name: tag.clone(),
+                confidence,
+            })
+            .collect::<Vec<_>>();
+        labels.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        labels.truncate(self.options.top_k);
+        Ok(serde_json::json!({ "labels": labels }))
+    }
+}
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    analyzer::{Analyzer, Label},
+    config::StageConfig,
+    error::PhotoInsightError,
+    exif::{GeoLocation, gps_of},
+    external::ExternalModel,
+    image_cache::PhotoInfo,
+};
+
+/// Stage options, e.g.
+/// `{"enabled": true, "command": "python3", "args": ["open_meteo_archive.py"]}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct WeatherOptions {
+    /// Historical weather provider (e.g. a wrapper of a weather archive API or an offline
+    /// dataset). Receives `{"latitude": .., "longitude": .., "date_time": "YYYY-MM-DD HH:MM:SS"}`
+    /// on stdin and prints `{"condition": "rain", "temperature_c": 12.5}`.
+    #[serde(flatten)]
+    provider: Option<ExternalModel>,
+}
+
+/// Weather at the place and hour a photo was taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Weather {
+    /// E.g. "sunny", "cloudy", "rain", "snow", "fog" or "storm", as named by the provider
+    pub condition: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_c: Option<f32>,
+}
+
+/// Enriches photos with location (set by photo_set_metadata or recorded by the camera) and
+/// time taken with the historical weather. The condition is stored as label (searchable by
+/// label search), the temperature is searchable by the "temperature" EXIF search tag.
+pub struct WeatherAnalyzer {
+    options: WeatherOptions,
+    /// Weather by place (rounded to 0.1 degree) and hour, photos of one outing share it
+    lookups: Mutex<HashMap<String, Weather>>,
+}
+
+impl WeatherAnalyzer {
+    pub const NAME: &str = "weather";
+
+    pub fn from_config(config: Option<&StageConfig>) -> Result<Self, PhotoInsightError> {
+        Ok(Self {
+            options: StageConfig::parse_options(config, Self::NAME)?,
+            lookups: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Temperature in degrees Celsius stored for the photo by this stage
+    pub fn temperature_of(result: &serde_json::Value) -> Option<f32> {
+        result
+            .get("weather")
+            .and_then(|w| w.get("temperature_c"))
+            .and_then(|t| t.as_f64())
+            .map(|t| t as f32)
+    }
+
+    fn lookup(
+        &self,
+        location: &GeoLocation,
+        date_time: &str,
+    ) -> Result<Weather, PhotoInsightError> {
+        let provider = self.options.provider.as_ref().ok_or_else(|| {
+            PhotoInsightError::from_message("weather stage has no provider command configured")
+        })?;
+        let key = format!(
+            "{:.1}|{:.1}|{}",
+            location.latitude,
+            location.longitude,
+            date_time.get(..13).unwrap_or(date_time)
+        );
+        if let Some(weather) = self.lookups.lock().unwrap().get(&key) {
+            return Ok(weather.clone());
+        }
+        let query = serde_json::json!({
+            "latitude": location.latitude,
+            "longitude": location.longitude,
+            "date_time": date_time,
+        });
+        let weather: Weather = serde_json::from_value(
+            provider.run(query.to_string().as_bytes(), &[])?,
+        )
+        .map_err(|e| {
+            PhotoInsightError::from_message(format!(
+                "unexpected weather provider output of {}: {e}",
+                provider.command
+            ))
+        })?;
+        self.lookups.lock().unwrap().insert(key, weather.clone());
+        Ok(weather)
+    }
+}
+
+impl Analyzer for WeatherAnalyzer {
+    fn name(&self) -> String {
+        Self::NAME.to_owned()
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.options).unwrap_or_default()
+    }
+
+    // needs a weather provider to be configured
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn analyze(
+        &self,
+        photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let exif = crate::IC
+            .exif_info(vec![photo_info])?
+            .pop()
+            .map(|result| result.into_parts().1);
+        let date_time = exif
+            .as_ref()
+            .map(|exif| exif.date_time.trim_matches('"').to_owned())
+            .filter(|date_time| date_time.as_str() != "unknown");
+        let location = exif
+            .and_then(|exif| exif.location)
+            .or_else(|| gps_of(photo_bytes));
+        // photos without place or time are not looked up again
+        let (Some(location), Some(date_time)) = (location, date_time) else {
+            return Ok(serde_json::json!({ "weather": null, "labels": [] }));
+        };
+        let weather = self.lookup(&location, &date_time)?;
+        let labels = vec![Label {
+            name: weather.condition.to_lowercase(),
+            confidence: 1.0,
+        }];
+        Ok(serde_json::json!({ "weather": weather, "labels": labels }))
+    }
+}
@@ -0,0 +1,413 @@
+use std::{collections::HashMap, path::Path, sync::RwLock};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ring::{
+    aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::core::{
+    config::{EncryptionConfig, StorageMode},
+    error::PhotoInsightError,
+};
+
+/// Prefix of encrypted sidecars, followed by the nonce and the sealed content
+const ENCRYPTED_MAGIC: &[u8] = b"PMCP-AES256GCM1";
+
+/// Length of the AES-256 key in bytes
+const KEY_LEN: usize = 32;
+
+/// Persistence of indexes and other derived data (sidecars), keyed by the sidecar file path
+pub trait Store: Send + Sync {
+    fn exists(&self, name: &str) -> bool;
+    fn read(&self, name: &str) -> Result<Vec<u8>, PhotoInsightError>;
+    fn write(&self, name: &str, data: Vec<u8>) -> Result<(), PhotoInsightError>;
+    fn remove(&self, name: &str) -> Result<(), PhotoInsightError>;
+    /// Stored size in bytes, none when missing
+    fn size(&self, name: &str) -> Option<u64>;
+
+    /// Sidecar content as written into exported files (bundles), encrypted stores keep it
+    /// encrypted
+    fn seal(&self, _name: &str, data: Vec<u8>) -> Result<Vec<u8>, PhotoInsightError> {
+        Ok(data)
+    }
+
+    /// Sidecar content of an exported file
+    fn open(&self, _name: &str, data: Vec<u8>) -> Result<Vec<u8>, PhotoInsightError> {
+        Ok(data)
+    }
+}
+
+pub fn build(
+    mode: StorageMode,
+    encryption: &EncryptionConfig,
+) -> Result<Box<dyn Store>, PhotoInsightError> {
+    match mode {
+        StorageMode::Sidecar => match encryption.secret()? {
+            Some(secret) => {
+                tracing::info!("Sidecars are encrypted");
+                Ok(Box::new(EncryptedStore::new(
+                    Box::new(FileStore),
+                    &decode_key(&secret)?,
+                    encryption.migrate_plaintext,
+                )?))
+            }
+            None => Ok(Box::new(FileStore)),
+        },
+        StorageMode::Memory => {
+            tracing::info!("Indexes are kept in memory only, no sidecars are read or written");
+            Ok(Box::new(MemoryStore::default()))
+        }
+    }
+}
+
+/// Decodes the configured key, 32 bytes as base64 or hex. Anything else is refused, the key
+/// is used as it is and a passphrase would make a guessable key.
+pub(crate) fn decode_key(secret: &[u8]) -> Result<[u8; KEY_LEN], PhotoInsightError> {
+    let secret = std::str::from_utf8(secret.trim_ascii()).unwrap_or_default();
+    let key = if secret.len() == 2 * KEY_LEN && secret.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..KEY_LEN)
+            .map(|i| u8::from_str_radix(&secret[2 * i..2 * i + 2], 16).unwrap_or_default())
+            .collect()
+    } else {
+        STANDARD.decode(secret).unwrap_or_default()
+    };
+    key.try_into().map_err(|_| {
+        PhotoInsightError::from_message(format!(
+            "encryption key must be {KEY_LEN} random bytes encoded as base64 or hex, \
+             e.g. the output of `openssl rand -base64 {KEY_LEN}`"
+        ))
+    })
+}
+
+/// JSON sidecar files next to the photo archives
+pub struct FileStore;
+
+impl Store for FileStore {
+    fn exists(&self, name: &str) -> bool {
+        Path::new(name).exists()
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, PhotoInsightError> {
+        std::fs::read(name).map_err(|e| PhotoInsightError::new(e))
+    }
+
+    fn write(&self, name: &str, data: Vec<u8>) -> Result<(), PhotoInsightError> {
+        std::fs::write(name, data).map_err(|e| PhotoInsightError::new(e))
+    }
+
+    fn remove(&self, name: &str) -> Result<(), PhotoInsightError> {
+        match std::fs::remove_file(name) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(PhotoInsightError::new(e)),
+            _ => Ok(()),
+        }
+    }
+
+    fn size(&self, name: &str) -> Option<u64> {
+        std::fs::metadata(name).ok().map(|m| m.len())
+    }
+}
+
+/// Sidecars kept in memory for the lifetime of the server, nothing is written to disk
+#[derive(Default)]
+pub struct MemoryStore {
+    files: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl Store for MemoryStore {
+    fn exists(&self, name: &str) -> bool {
+        self.files.read().unwrap().contains_key(name)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, PhotoInsightError> {
+        self.files
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| PhotoInsightError::from_message(format!("{name} not found")))
+    }
+
+    fn write(&self, name: &str, data: Vec<u8>) -> Result<(), PhotoInsightError> {
+        self.files.write().unwrap().insert(name.to_owned(), data);
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> Result<(), PhotoInsightError> {
+        self.files.write().unwrap().remove(name);
+        Ok(())
+    }
+
+    fn size(&self, name: &str) -> Option<u64> {
+        self.files
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|data| data.len() as u64)
+    }
+}
+
+/// Encrypts sidecars of the wrapped store with AES-256-GCM. The file name of the sidecar is
+/// authenticated with the content, so a sidecar copied over another one is refused.
+/// Sidecars written without encryption are refused unless they are being migrated.
+pub struct EncryptedStore {
+    inner: Box<dyn Store>,
+    key: LessSafeKey,
+    rng: SystemRandom,
+    /// Unencrypted sidecars are read and encrypted in place
+    migrate_plaintext: bool,
+}
+
+impl EncryptedStore {
+    pub fn new(
+        inner: Box<dyn Store>,
+        key: &[u8; KEY_LEN],
+        migrate_plaintext: bool,
+    ) -> Result<Self, PhotoInsightError> {
+        let key = UnboundKey::new(&AES_256_GCM, key)
+            .map_err(|_| PhotoInsightError::from_message("invalid encryption key"))?;
+        Ok(Self {
+            inner,
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+            migrate_plaintext,
+        })
+    }
+}
+
+// Associated data of the sidecar, its file name: sidecars stay readable when the image
+// directory is moved, exported or backed up
+fn aad(name: &str) -> Aad<&[u8]> {
+    Aad::from(
+        Path::new(name)
+            .file_name()
+            .map_or(name.as_bytes(), |file_name| file_name.as_encoded_bytes()),
+    )
+}
+
+impl Store for EncryptedStore {
+    fn exists(&self, name: &str) -> bool {
+        self.inner.exists(name)
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>, PhotoInsightError> {
+        let data = self.inner.read(name)?;
+        let encrypted = data.starts_with(ENCRYPTED_MAGIC);
+        let content = self.open(name, data)?;
+        if !encrypted {
+            self.write(name, content.clone())?;
+            tracing::info!("Encrypted sidecar {name}");
+        }
+        Ok(content)
+    }
+
+    fn write(&self, name: &str, data: Vec<u8>) -> Result<(), PhotoInsightError> {
+        self.inner.write(name, self.seal(name, data)?)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), PhotoInsightError> {
+        self.inner.remove(name)
+    }
+
+    fn size(&self, name: &str) -> Option<u64> {
+        self.inner.size(name)
+    }
+
+    fn seal(&self, name: &str, mut data: Vec<u8>) -> Result<Vec<u8>, PhotoInsightError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| PhotoInsightError::from_message("can't generate nonce"))?;
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), aad(name), &mut data)
+            .map_err(|_| PhotoInsightError::from_message(format!("can't encrypt {name}")))?;
+        let mut sealed = Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + data.len());
+        sealed.extend_from_slice(ENCRYPTED_MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&data);
+        Ok(sealed)
+    }
+
+    fn open(&self, name: &str, data: Vec<u8>) -> Result<Vec<u8>, PhotoInsightError> {
+        let Some(sealed) = data.strip_prefix(ENCRYPTED_MAGIC) else {
+            if self.migrate_plaintext {
+                return Ok(data);
+            }
+            return Err(PhotoInsightError::from_message(format!(
+                "{name} is not encrypted, set encryption.migrate_plaintext to encrypt \
+                 sidecars written before encryption was configured"
+            )));
+        };
+        if sealed.len() < NONCE_LEN {
+            return Err(PhotoInsightError::from_message(format!(
+                "{name} is truncated"
+            )));
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| PhotoInsightError::from_message(format!("{name} is truncated")))?;
+        let mut content = sealed.to_vec();
+        let len = self
+            .key
+            .open_in_place(nonce, aad(name), &mut content)
+            .map_err(|_| {
+                PhotoInsightError::from_message(format!(
+                    "can't decrypt {name}, wrong key or corrupted file"
+                ))
+            })?
+            .len();
+        content.truncate(len);
+        Ok(content)
+    }
+}
+
+pub(crate) fn read_json<T: DeserializeOwned>(name: &str) -> Result<T, PhotoInsightError> {
+    let data = crate::STORE.read(name)?;
+    serde_json::from_slice(&data).map_err(|e| PhotoInsightError::new(e))
+}
+
+pub(crate) fn write_json<T: Serialize + ?Sized>(
+    name: &str,
+    value: &T,
+) -> Result<(), PhotoInsightError> {
+    let data = serde_json::to_vec_pretty(value).map_err(|e| PhotoInsightError::new(e))?;
+    crate::STORE.write(name, data)
+}
+
+pub(crate) fn exists(name: &str) -> bool {
+    crate::STORE.exists(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::store::{
+        ENCRYPTED_MAGIC, EncryptedStore, KEY_LEN, MemoryStore, Store, decode_key,
+    };
+
+    const SECRET: &[u8] = b"q8nD2xVt0b6l3Yk7RfJ1sPzW9mHc4eUa5gLoNiTr+Ks=";
+
+    fn encrypted(secret: &[u8]) -> EncryptedStore {
+        migrating(secret, false)
+    }
+
+    fn migrating(secret: &[u8], migrate_plaintext: bool) -> EncryptedStore {
+        let key = decode_key(secret).unwrap();
+        EncryptedStore::new(Box::new(MemoryStore::default()), &key, migrate_plaintext).unwrap()
+    }
+
+    #[test]
+    fn test_decode_key() {
+        let key = decode_key(SECRET).unwrap();
+        assert_eq!(
+            decode_key(b"q8nD2xVt0b6l3Yk7RfJ1sPzW9mHc4eUa5gLoNiTr+Ks=\n").unwrap(),
+            key
+        );
+        let hex = key.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        assert_eq!(decode_key(hex.as_bytes()).unwrap(), key);
+        assert_eq!(decode_key(hex.to_uppercase().as_bytes()).unwrap(), key);
+        // passphrases, short keys and keys of another length are refused
+        assert!(decode_key(b"correct horse battery staple").is_err());
+        assert!(decode_key(b"another secret").is_err());
+        assert!(decode_key(b"c2hvcnQga2V5").is_err());
+        assert!(decode_key(&hex.as_bytes()[..2 * KEY_LEN - 2]).is_err());
+        assert!(decode_key(b"").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let store = encrypted(SECRET);
+        store
+            .write("index.json", b"{\"photos\":[]}".to_vec())
+            .unwrap();
+        let sealed = store.inner.read("index.json").unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_MAGIC));
+        assert!(!sealed.windows(6).any(|w| w == b"photos"));
+        assert_eq!(store.read("index.json").unwrap(), b"{\"photos\":[]}");
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let store = encrypted(SECRET);
+        store.write("index.json", b"{}".to_vec()).unwrap();
+        let sealed = store.inner.read("index.json").unwrap();
+        let other = encrypted(b"3gT9wLq1Zc7vN0kX5mR2pB8yH4dF6sJaUeQiOoVtWnI=");
+        other.inner.write("index.json", sealed).unwrap();
+        let error = other.read("index.json").unwrap_err().to_string();
+        assert!(error.contains("wrong key"), "{error}");
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let store = encrypted(SECRET);
+        store.write("index.json", b"{}".to_vec()).unwrap();
+        let sealed = store.inner.read("index.json").unwrap();
+        store
+            .inner
+            .write("index.json", sealed[..ENCRYPTED_MAGIC.len() + 4].to_vec())
+            .unwrap();
+        assert!(store.read("index.json").is_err());
+        // nonce present, authentication tag cut off
+        store
+            .inner
+            .write("index.json", sealed[..sealed.len() - 1].to_vec())
+            .unwrap();
+        assert!(store.read("index.json").is_err());
+    }
+
+    #[test]
+    fn test_sidecar_name_authenticated() {
+        let store = encrypted(SECRET);
+        store
+            .write("/photos/a.zip.exif.json", b"{}".to_vec())
+            .unwrap();
+        let sealed = store.inner.read("/photos/a.zip.exif.json").unwrap();
+        // same file name in another directory
+        store
+            .inner
+            .write("/backup/a.zip.exif.json", sealed.clone())
+            .unwrap();
+        assert_eq!(store.read("/backup/a.zip.exif.json").unwrap(), b"{}");
+        store
+            .inner
+            .write("/photos/b.zip.exif.json", sealed)
+            .unwrap();
+        let error = store
+            .read("/photos/b.zip.exif.json")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("wrong key"), "{error}");
+    }
+
+    #[test]
+    fn test_plaintext_refused() {
+        let store = encrypted(SECRET);
+        store.inner.write("index.json", b"{}".to_vec()).unwrap();
+        let error = store.read("index.json").unwrap_err().to_string();
+        assert!(error.contains("migrate_plaintext"), "{error}");
+        assert!(store.open("index.json", b"{}".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_plaintext_migration() {
+        let store = migrating(SECRET, true);
+        store.inner.write("index.json", b"{}".to_vec()).unwrap();
+        assert_eq!(store.read("index.json").unwrap(), b"{}");
+        assert!(
+            store
+                .inner
+                .read("index.json")
+                .unwrap()
+                .starts_with(ENCRYPTED_MAGIC)
+        );
+        assert_eq!(store.read("index.json").unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_sealed_export() {
+        let store = encrypted(SECRET);
+        let sealed = store.seal("a.zip.exif.json", b"{}".to_vec()).unwrap();
+        assert!(sealed.starts_with(ENCRYPTED_MAGIC));
+        assert_eq!(store.open("/other/a.zip.exif.json", sealed).unwrap(), b"{}");
+    }
+}
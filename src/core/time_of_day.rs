@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::core::{error::PhotoInsightError, exif::GeoLocation, overrides};
+
+/// Sun elevation in degrees below which it is night (end of civil twilight)
+const NIGHT_ELEVATION: f64 = -6.0;
+/// Sun elevation in degrees below which the light is warm and soft
+const GOLDEN_HOUR_ELEVATION: f64 = 6.0;
+/// Buckets by the hour taken of photos without location, a rough average over the seasons
+/// of the mid latitudes
+const HOURS: [(u32, TimeOfDay); 7] = [
+    (5, TimeOfDay::Night),
+    (7, TimeOfDay::GoldenHour),
+    (11, TimeOfDay::Morning),
+    (15, TimeOfDay::Midday),
+    (18, TimeOfDay::Evening),
+    (21, TimeOfDay::GoldenHour),
+    (24, TimeOfDay::Night),
+];
+
+/// Part of the day a photo was taken in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeOfDay {
+    Morning,
+    /// Sun just above or below the horizon, around sunrise and sunset
+    GoldenHour,
+    Midday,
+    Evening,
+    Night,
+}
+
+impl TimeOfDay {
+    pub const NAMES: [&str; 5] = ["morning", "golden_hour", "midday", "evening", "night"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeOfDay::Morning => "morning",
+            TimeOfDay::GoldenHour => "golden_hour",
+            TimeOfDay::Midday => "midday",
+            TimeOfDay::Evening => "evening",
+            TimeOfDay::Night => "night",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, PhotoInsightError> {
+        [
+            TimeOfDay::Morning,
+            TimeOfDay::GoldenHour,
+            TimeOfDay::Midday,
+            TimeOfDay::Evening,
+            TimeOfDay::Night,
+        ]
+        .into_iter()
+        .find(|t| t.name() == name.trim().to_lowercase().replace(' ', "_"))
+        .ok_or_else(|| {
+            PhotoInsightError::from_message(format!(
+                "Invalid time of day: {name}, use one of {}",
+                Self::NAMES.join(", ")
+            ))
+        })
+    }
+}
+
+// Sun elevation in degrees. The time taken is local time without time zone, it is taken as
+// the time of the zone of the longitude (daylight saving time shifts it by an hour).
+fn solar_elevation(location: &GeoLocation, day_of_year: u32, hours: f64) -> f64 {
+    let declination = 23.44f64.to_radians()
+        * (2.0 * std::f64::consts::PI * (284 + day_of_year) as f64 / 365.0).sin();
+    let zone_offset = location.longitude - 15.0 * (location.longitude / 15.0).round();
+    let solar_hours = hours + zone_offset / 15.0;
+    let hour_angle = (15.0 * (solar_hours - 12.0)).to_radians();
+    let latitude = location.latitude.to_radians();
+    (latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+/// Part of the day of the time taken ("YYYY-MM-DD HH:MM:SS"), from the sun elevation at the
+/// location when it is known, from the hour otherwise. None when the time taken is unknown.
+pub fn time_of_day(date_time: &str, location: Option<&GeoLocation>) -> Option<TimeOfDay> {
+    let date_time = date_time.trim_matches('"');
+    let (year, _, _, hour, minute, _) = overrides::parse_date_time(date_time).ok()?;
+    if year == 0 {
+        return None;
+    }
+    let Some(location) = location else {
+        return HOURS.iter().find(|(to, _)| hour < *to).map(|(_, t)| *t);
+    };
+    let new_year = overrides::timestamp_of(&format!("{year:04}-01-01 00:00:00")).ok()?;
+    let day_of_year = ((overrides::timestamp_of(date_time).ok()? - new_year) / 86400) as u32 + 1;
+    let hours = hour as f64 + minute as f64 / 60.0;
+    let elevation = solar_elevation(location, day_of_year, hours);
+    Some(if elevation < NIGHT_ELEVATION {
+        TimeOfDay::Night
+    } else if elevation < GOLDEN_HOUR_ELEVATION {
+        TimeOfDay::GoldenHour
+    } else if hours < 11.0 {
+        TimeOfDay::Morning
+    } else if hours < 15.0 {
+        TimeOfDay::Midday
+    } else {
+        TimeOfDay::Evening
+    })
+}
@@ -0,0 +1,90 @@
+use std::{cell::RefCell, time::Instant};
+
+use serde::Serialize;
+
+thread_local! {
+    // Timings of the tool call running on this thread, `None` unless the call asked for them
+    static CURRENT: RefCell<Option<Vec<StageTiming>>> = const { RefCell::new(None) };
+}
+
+/// Time spent in one stage of a tool call, e.g. "search", "zip_open", "extract", "decode",
+/// "resize", "encode" or "inference"
+#[derive(Debug, Clone, Serialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub calls: usize,
+    pub ms: f64,
+}
+
+/// Timings being collected on this thread
+pub struct Collector {
+    enabled: bool,
+}
+
+/// Starts collecting the time spent in the stages of the tool call when `enabled`, stages run
+/// on other threads (e.g. the crawler) are not counted
+pub fn start(enabled: bool) -> Collector {
+    if enabled {
+        CURRENT.with(|current| current.replace(Some(Vec::new())));
+    }
+    Collector { enabled }
+}
+
+impl Collector {
+    /// Stops collecting, returns the timings if they were collected
+    pub fn finish(self) -> Option<Vec<StageTiming>> {
+        if !self.enabled {
+            return None;
+        }
+        CURRENT.with(|current| current.replace(None))
+    }
+}
+
+/// Whether the tool call running on this thread collects timings, work it hands over to other
+/// threads reports its timings with `add`
+pub fn enabled() -> bool {
+    CURRENT.with(|current| current.borrow().is_some())
+}
+
+/// Adds timings collected on another thread to the timings of the tool call
+pub fn add(timings: Vec<StageTiming>) {
+    CURRENT.with(|current| {
+        if let Some(collected) = current.borrow_mut().as_mut() {
+            for timing in timings {
+                match collected.iter_mut().find(|t| t.stage == timing.stage) {
+                    Some(existing) => {
+                        existing.calls += timing.calls;
+                        existing.ms += timing.ms;
+                    }
+                    None => collected.push(timing),
+                }
+            }
+        }
+    });
+}
+
+/// Runs one stage, its time is added to the timings of the tool call if they are collected
+pub fn timed<R>(stage: &str, run: impl FnOnce() -> R) -> R {
+    if CURRENT.with(|current| current.borrow().is_none()) {
+        return run();
+    }
+    let started = Instant::now();
+    let result = run();
+    let ms = started.elapsed().as_secs_f64() * 1000.0;
+    CURRENT.with(|current| {
+        if let Some(timings) = current.borrow_mut().as_mut() {
+            match timings.iter_mut().find(|t| t.stage == stage) {
+                Some(timing) => {
+                    timing.calls += 1;
+                    timing.ms += ms;
+                }
+                None => timings.push(StageTiming {
+                    stage: stage.to_owned(),
+                    calls: 1,
+                    ms,
+                }),
+            }
+        }
+    });
+    result
+}
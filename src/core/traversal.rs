@@ -1,6 +1,25 @@
 use crate::core::error::PhotoInsightError;
 
+/// Zip files of the directory, archives excluded by the index configuration are left out
 pub fn list_directory_zip_files(dir_path: &str) -> Result<Vec<String>, PhotoInsightError> {
+    let (included, excluded): (Vec<String>, Vec<String>) = list_all_zip_files(dir_path)?
+        .into_iter()
+        .partition(|zip| !crate::CONFIG.index.archive_excluded(zip));
+    if !excluded.is_empty() {
+        tracing::info!("Excluded {} archives from indexing", excluded.len());
+    }
+    Ok(included)
+}
+
+/// Zip files of the directory excluded by the index configuration
+pub fn list_excluded_zip_files(dir_path: &str) -> Result<Vec<String>, PhotoInsightError> {
+    Ok(list_all_zip_files(dir_path)?
+        .into_iter()
+        .filter(|zip| crate::CONFIG.index.archive_excluded(zip))
+        .collect())
+}
+
+fn list_all_zip_files(dir_path: &str) -> Result<Vec<String>, PhotoInsightError> {
     use std::fs;
     use std::path::Path;
 
@@ -26,3 +45,67 @@ pub fn list_directory_zip_files(dir_path: &str) -> Result<Vec<String>, PhotoInsi
     }
     Ok(zip_files)
 }
+
+/// Case-insensitive match of a name against a pattern with `*` and `?` wildcards, a pattern
+/// without wildcards matches any name containing it
+pub(crate) fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    if !pattern.contains(['*', '?']) {
+        return name.contains(&pattern);
+    }
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+    // greedy matching with backtracking to the last star
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::traversal::pattern_matches;
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches(
+            "Screenshots/",
+            "Takeout/Screenshots/IMG_1.png"
+        ));
+        assert!(pattern_matches(
+            "screenshots/",
+            "Takeout/SCREENSHOTS/IMG_1.png"
+        ));
+        assert!(!pattern_matches("Screenshots/", "Takeout/Photos/IMG_1.png"));
+        assert!(pattern_matches(
+            "takeout-*-003.zip",
+            "takeout-20240101-003.zip"
+        ));
+        assert!(!pattern_matches(
+            "takeout-*-003.zip",
+            "takeout-20240101-004.zip"
+        ));
+        // wildcard patterns match the whole name
+        assert!(!pattern_matches("*.zip", "takeout.zip.bak"));
+        assert!(pattern_matches("*edited*", "Photos/IMG_1-edited.jpg"));
+        assert!(pattern_matches("IMG_?.jpg", "img_1.jpg"));
+        assert!(!pattern_matches("IMG_?.jpg", "IMG_10.jpg"));
+        assert!(pattern_matches("a*b*c", "aXbYbZc"));
+        assert!(pattern_matches("*", ""));
+    }
+}
@@ -0,0 +1,75 @@
+use std::{sync::RwLock, time::Instant};
+
+use serde::Serialize;
+
+use crate::core::{config::WarmUpConfig, error::PhotoInsightError, yolo};
+
+/// Outcome of a warm-up task run at startup
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmUpStep {
+    pub task: String,
+    pub elapsed_ms: u128,
+    /// What was warmed up, e.g. number of photos or the preloaded archives
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Warm-up tasks run so far, reported by the index health tool
+#[derive(Debug, Default)]
+pub struct WarmUp {
+    steps: RwLock<Vec<WarmUpStep>>,
+}
+
+impl WarmUp {
+    pub fn steps(&self) -> Vec<WarmUpStep> {
+        self.steps.read().unwrap().clone()
+    }
+
+    fn step(&self, task: &str, run: impl FnOnce() -> Result<serde_json::Value, PhotoInsightError>) {
+        let started = Instant::now();
+        let result = run();
+        let elapsed_ms = started.elapsed().as_millis();
+        match &result {
+            Ok(_) => tracing::info!("Warm-up {task} finished in {elapsed_ms} ms"),
+            Err(e) => tracing::warn!("Warm-up {task} failed: {e}"),
+        }
+        let (detail, error) = match result {
+            Ok(detail) => (Some(detail), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.steps.write().unwrap().push(WarmUpStep {
+            task: task.to_owned(),
+            elapsed_ms,
+            detail,
+            error,
+        });
+    }
+
+    /// Runs the configured warm-up tasks, the photo listing is always loaded first
+    pub fn run(&self, config: &WarmUpConfig) {
+        self.step("photo_index", || Ok(crate::IC.images.len().into()));
+        if config.preload_archives > 0 {
+            self.step("preload_archives", || {
+                Ok(crate::IC.preload_archives(config.preload_archives).into())
+            });
+        }
+        if config.month_indexes {
+            self.step("month_indexes", || {
+                let months = crate::IC
+                    .year_month_index()
+                    .values()
+                    .map(|by_month| by_month.len())
+                    .sum::<usize>();
+                Ok(months.into())
+            });
+        }
+        if config.detection_model {
+            self.step("detection_model", || {
+                yolo::load_model()?;
+                Ok(crate::MODELS.default_model().id().into())
+            });
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::io::Write;
+
+use serde::Serialize;
+
+use crate::core::{
+    analyzer::{Label, labels_of},
+    config::WatcherConfig,
+    error::PhotoInsightError,
+    image_cache::PhotoInfo,
+    yolo::DetectedObject,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchMatch {
+    pub watcher: String,
+    pub stage: String,
+    pub file: PhotoInfo,
+    pub found: Vec<Label>,
+}
+
+/// Fires webhooks or writes reports when the background analysis finds watched content
+/// in newly analyzed photos
+pub struct Watchers {
+    watchers: Vec<WatcherConfig>,
+}
+
+impl Watchers {
+    pub fn new(watchers: &[WatcherConfig]) -> Self {
+        for watcher in watchers {
+            if watcher.webhook.is_none() && watcher.report.is_none() {
+                tracing::warn!("Watcher {} has neither webhook nor report", watcher.name);
+            }
+        }
+        Self {
+            watchers: watchers.to_vec(),
+        }
+    }
+
+    // object detection stages produce detected objects, other stages labels
+    fn findings(value: &serde_json::Value) -> (Vec<Label>, Vec<Label>) {
+        match serde_json::from_value::<Vec<DetectedObject>>(value.clone()) {
+            Ok(objects) => (
+                objects
+                    .into_iter()
+                    .map(|o| Label {
+                        name: o.class_name,
+                        confidence: o.confidence,
+                    })
+                    .collect(),
+                Vec::new(),
+            ),
+            Err(_) => (Vec::new(), labels_of(value)),
+        }
+    }
+
+    fn matches(
+        watcher: &WatcherConfig,
+        stage: &str,
+        results: &[(PhotoInfo, serde_json::Value)],
+    ) -> Vec<WatchMatch> {
+        let mut matches = Vec::new();
+        for (photo_info, value) in results {
+            let (classes, labels) = Self::findings(value);
+            let mut found = classes
+                .into_iter()
+                .filter(|c| {
+                    c.confidence >= watcher.min_confidence
+                        && watcher
+                            .classes
+                            .iter()
+                            .any(|w| w.eq_ignore_ascii_case(&c.name))
+                })
+                .collect::<Vec<Label>>();
+            found.extend(labels.into_iter().filter(|l| {
+                l.confidence >= watcher.min_confidence
+                    && watcher
+                        .labels
+                        .iter()
+                        .any(|w| l.name.to_lowercase().contains(&w.to_lowercase()))
+            }));
+            if !found.is_empty() {
+                matches.push(WatchMatch {
+                    watcher: watcher.name.clone(),
+                    stage: stage.to_owned(),
+                    file: photo_info.clone(),
+                    found,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Checks results of a stage, notifies every watcher with matches once
+    pub fn check(&self, stage: &str, results: &[(PhotoInfo, serde_json::Value)]) {
+        for watcher in self.watchers.iter() {
+            let matches = Self::matches(watcher, stage, results);
+            if matches.is_empty() {
+                continue;
+            }
+            tracing::info!(
+                "Watcher {} matched {} photos in {stage} results",
+                watcher.name,
+                matches.len()
+            );
+            if let Some(report) = &watcher.report {
+                if let Err(e) = write_report(report, &matches) {
+                    tracing::error!("can't write report of watcher {}: {e}", watcher.name);
+                }
+            }
+            if let Some(webhook) = &watcher.webhook {
+                if let Err(e) = post_webhook(webhook, &watcher.name, &matches) {
+                    tracing::error!("webhook of watcher {} failed: {e}", watcher.name);
+                }
+            }
+        }
+    }
+}
+
+fn write_report(report: &str, matches: &[WatchMatch]) -> Result<(), PhotoInsightError> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(report)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    for m in matches {
+        let line = serde_json::to_string(m).map_err(|e| PhotoInsightError::new(e))?;
+        writeln!(file, "{line}").map_err(|e| PhotoInsightError::new(e))?;
+    }
+    Ok(())
+}
+
+// the analysis runs on its own thread outside of the server runtime
+fn post_webhook(
+    webhook: &str,
+    watcher: &str,
+    matches: &[WatchMatch],
+) -> Result<(), PhotoInsightError> {
+    let payload = serde_json::json!({
+        "watcher": watcher,
+        "matches": matches,
+    });
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| PhotoInsightError::new(e))?;
+    runtime.block_on(async {
+        reqwest::Client::new()
+            .post(webhook)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|e| PhotoInsightError::new(e))
+    })
+}
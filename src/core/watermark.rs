@@ -0,0 +1,473 @@
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage, imageops};
+
+use crate::core::{
+    config::{Corner, WatermarkConfig},
+    error::PhotoInsightError,
+    image_cache::PhotoInfo,
+};
+
+/// Glyphs of the built-in 5x7 font, rows top to bottom with the leftmost pixel in bit 4.
+/// Lowercase letters are drawn as uppercase, other characters as `?`.
+const FONT: [(char, [u8; 7]); 50] = [
+    (
+        'A',
+        [
+            0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'B',
+        [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+        ],
+    ),
+    (
+        'C',
+        [
+            0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        'D',
+        [
+            0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110,
+        ],
+    ),
+    (
+        'E',
+        [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+        ],
+    ),
+    (
+        'F',
+        [
+            0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+    ),
+    (
+        'G',
+        [
+            0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+        ],
+    ),
+    (
+        'H',
+        [
+            0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'I',
+        [
+            0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+    ),
+    (
+        'J',
+        [
+            0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+        ],
+    ),
+    (
+        'K',
+        [
+            0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+        ],
+    ),
+    (
+        'L',
+        [
+            0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+        ],
+    ),
+    (
+        'M',
+        [
+            0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'N',
+        [
+            0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'O',
+        [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        'P',
+        [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+        ],
+    ),
+    (
+        'Q',
+        [
+            0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+        ],
+    ),
+    (
+        'R',
+        [
+            0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+        ],
+    ),
+    (
+        'S',
+        [
+            0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+        ],
+    ),
+    (
+        'T',
+        [
+            0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+        ],
+    ),
+    (
+        'U',
+        [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        'V',
+        [
+            0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+        ],
+    ),
+    (
+        'W',
+        [
+            0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+        ],
+    ),
+    (
+        'X',
+        [
+            0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+        ],
+    ),
+    (
+        'Y',
+        [
+            0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+        ],
+    ),
+    (
+        'Z',
+        [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+        ],
+    ),
+    (
+        '0',
+        [
+            0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '1',
+        [
+            0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+        ],
+    ),
+    (
+        '2',
+        [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+        ],
+    ),
+    (
+        '3',
+        [
+            0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '4',
+        [
+            0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+        ],
+    ),
+    (
+        '5',
+        [
+            0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '6',
+        [
+            0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '7',
+        [
+            0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+        ],
+    ),
+    (
+        '8',
+        [
+            0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+        ],
+    ),
+    (
+        '9',
+        [
+            0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+        ],
+    ),
+    (
+        ' ',
+        [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+    ),
+    (
+        '.',
+        [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+        ],
+    ),
+    (
+        ',',
+        [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+        ],
+    ),
+    (
+        '-',
+        [
+            0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+        ],
+    ),
+    (
+        '_',
+        [
+            0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+        ],
+    ),
+    (
+        ':',
+        [
+            0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+        ],
+    ),
+    (
+        '/',
+        [
+            0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+        ],
+    ),
+    (
+        '!',
+        [
+            0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+        ],
+    ),
+    (
+        '?',
+        [
+            0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+        ],
+    ),
+    (
+        '\'',
+        [
+            0b01100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+        ],
+    ),
+    (
+        '&',
+        [
+            0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+        ],
+    ),
+    (
+        '@',
+        [
+            0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+        ],
+    ),
+    (
+        '(',
+        [
+            0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+        ],
+    ),
+    (
+        ')',
+        [
+            0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+        ],
+    ),
+];
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+fn glyph(c: char) -> &'static [u8; 7] {
+    let c = match c {
+        '©' => 'C',
+        c => c.to_ascii_uppercase(),
+    };
+    FONT.iter()
+        .find(|(g, _)| *g == c)
+        .or_else(|| FONT.iter().find(|(g, _)| *g == '?'))
+        .map(|(_, rows)| rows)
+        .unwrap()
+}
+
+// White text with a dark outline so that it reads on light and dark photos, one glyph pixel
+// is `pixel` image pixels
+fn render_text(text: &str, pixel: u32) -> RgbaImage {
+    let chars = text.chars().collect::<Vec<char>>();
+    let width = (chars.len() as u32 * (GLYPH_WIDTH + 1) + 1) * pixel;
+    let height = (GLYPH_HEIGHT + 2) * pixel;
+    let mut mask = vec![vec![false; (width / pixel) as usize]; (height / pixel) as usize];
+    for (i, c) in chars.iter().enumerate() {
+        for (row, bits) in glyph(*c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                    mask[row + 1][i * (GLYPH_WIDTH as usize + 1) + column as usize + 1] = true;
+                }
+            }
+        }
+    }
+    let on = |x: i64, y: i64| {
+        y >= 0
+            && x >= 0
+            && (y as usize) < mask.len()
+            && (x as usize) < mask[0].len()
+            && mask[y as usize][x as usize]
+    };
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (gx, gy) = ((x / pixel) as i64, (y / pixel) as i64);
+        if on(gx, gy) {
+            Rgba([255, 255, 255, 255])
+        } else if (-1..=1).any(|dy| (-1..=1).any(|dx| on(gx + dx, gy + dy))) {
+            Rgba([0, 0, 0, 160])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+}
+
+/// Watermark of images returned to clients, see `WatermarkConfig`
+pub struct Watermark {
+    config: WatermarkConfig,
+    logo: Option<DynamicImage>,
+}
+
+impl Watermark {
+    /// `None` when neither text nor logo is configured
+    pub fn from_config(config: &WatermarkConfig) -> Result<Option<Self>, PhotoInsightError> {
+        if config.text.is_none() && config.logo.is_none() {
+            return Ok(None);
+        }
+        let logo = match &config.logo {
+            Some(path) => Some(image::open(path).map_err(|e| {
+                PhotoInsightError::from_message(format!("can't read watermark logo {path}: {e}"))
+            })?),
+            None => None,
+        };
+        Ok(Some(Self {
+            config: config.clone(),
+            logo,
+        }))
+    }
+
+    // Mark scaled to the share of the image width, the logo above the text
+    fn mark(&self, image_width: u32) -> RgbaImage {
+        let target = ((image_width as f32 * self.config.scale).round() as u32).max(1);
+        let logo = self.logo.as_ref().map(|logo| {
+            let height =
+                (logo.height() as u64 * target as u64 / logo.width().max(1) as u64).max(1) as u32;
+            logo.resize_exact(target, height, imageops::FilterType::Triangle)
+                .to_rgba8()
+        });
+        let text = self.config.text.as_deref().map(|text| {
+            let columns = text.chars().count() as u32 * (GLYPH_WIDTH + 1) + 1;
+            render_text(text, (target / columns).max(1))
+        });
+        match (logo, text) {
+            (Some(logo), Some(text)) => {
+                let mut mark = RgbaImage::new(
+                    logo.width().max(text.width()),
+                    logo.height() + text.height(),
+                );
+                imageops::overlay(&mut mark, &logo, 0, 0);
+                imageops::overlay(&mut mark, &text, 0, logo.height() as i64);
+                mark
+            }
+            (Some(mark), None) | (None, Some(mark)) => mark,
+            (None, None) => RgbaImage::new(1, 1),
+        }
+    }
+
+    /// Draws the watermark into the corner of the image with the configured opacity. PNG
+    /// images stay PNG, other images are returned as JPEG.
+    pub fn apply(&self, mime: &str, data: &[u8]) -> Result<(String, Vec<u8>), PhotoInsightError> {
+        let img = image::load_from_memory(data).map_err(|e| PhotoInsightError::new(e))?;
+        let mut canvas = img.to_rgba8();
+        let mut mark = self.mark(canvas.width());
+        let opacity = self.config.opacity.clamp(0.0, 1.0);
+        mark.pixels_mut()
+            .for_each(|p| p.0[3] = (p.0[3] as f32 * opacity).round() as u8);
+        let margin = (canvas.width().min(canvas.height()) as f32 * 0.02).round() as i64;
+        let right = canvas.width() as i64 - mark.width() as i64 - margin;
+        let bottom = canvas.height() as i64 - mark.height() as i64 - margin;
+        let (x, y) = match self.config.corner {
+            Corner::TopLeft => (margin, margin),
+            Corner::TopRight => (right, margin),
+            Corner::BottomLeft => (margin, bottom),
+            Corner::BottomRight => (right, bottom),
+        };
+        imageops::overlay(&mut canvas, &mark, x, y);
+        let (format, mime) = if mime == "image/png" {
+            (ImageFormat::Png, mime.to_owned())
+        } else {
+            (ImageFormat::Jpeg, "image/jpeg".to_owned())
+        };
+        let marked = match format {
+            ImageFormat::Png => DynamicImage::ImageRgba8(canvas),
+            _ => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8()),
+        };
+        let mut buf = std::io::Cursor::new(Vec::new());
+        marked
+            .write_to(&mut buf, format)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        Ok((mime, buf.into_inner()))
+    }
+}
+
+/// Watermarks an image returned to clients when a watermark is configured
+pub fn watermark_image(
+    mime: String,
+    data: Vec<u8>,
+) -> Result<(String, Vec<u8>), PhotoInsightError> {
+    match crate::WATERMARK.as_ref() {
+        Some(watermark) => watermark.apply(&mime, &data),
+        None => Ok((mime, data)),
+    }
+}
+
+/// Watermarks photos returned to clients, see `watermark_image`
+pub fn watermarked(
+    images: Vec<(PhotoInfo, String, Vec<u8>)>,
+) -> Result<Vec<(PhotoInfo, String, Vec<u8>)>, PhotoInsightError> {
+    images
+        .into_iter()
+        .map(|(info, mime, data)| {
+            let (mime, data) = watermark_image(mime, data)?;
+            Ok((info, mime, data))
+        })
+        .collect()
+}
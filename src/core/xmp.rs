@@ -0,0 +1,220 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::core::{
+    color_label::ColorLabel, error::PhotoInsightError, exif::GeoLocation, image_cache::PhotoInfo,
+    overrides::normalize_date_time,
+};
+
+/// Default export directory, in the image directory
+pub const DEFAULT_EXPORT: &str = "photo-mcp.export";
+
+/// File name of the sidecar next to the photo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SidecarNaming {
+    /// `IMG_0001.jpg.xmp`, read by darktable and digiKam
+    Darktable,
+    /// `IMG_0001.xmp`, read by Lightroom, Bridge and Capture One
+    Lightroom,
+}
+
+impl SidecarNaming {
+    pub fn parse(naming: &str) -> Result<Self, PhotoInsightError> {
+        match naming.trim().to_lowercase().as_str() {
+            "darktable" => Ok(Self::Darktable),
+            "lightroom" => Ok(Self::Lightroom),
+            _ => Err(PhotoInsightError::from_message(format!(
+                "Invalid sidecar naming: {naming}, use darktable or lightroom"
+            ))),
+        }
+    }
+
+    fn sidecar_of(&self, photo: &Path) -> PathBuf {
+        match self {
+            Self::Darktable => {
+                let mut name = photo.as_os_str().to_owned();
+                name.push(".xmp");
+                PathBuf::from(name)
+            }
+            Self::Lightroom => photo.with_extension("xmp"),
+        }
+    }
+}
+
+/// Metadata of a photo written into its sidecar: overrides set by photo_set_metadata and the
+/// labels of detected objects and analysis stages as keywords
+#[derive(Debug, Clone, Default)]
+pub struct XmpMetadata {
+    /// Star rating from 1 to 5, 0 is written as rejected (-1)
+    pub rating: Option<u8>,
+    pub color_label: Option<ColorLabel>,
+    pub keywords: Vec<String>,
+    pub description: Option<String>,
+    /// Date and time the photo was taken, as EXIF or an override records it
+    pub date_time: Option<String>,
+    pub location: Option<GeoLocation>,
+}
+
+impl XmpMetadata {
+    /// XMP packet with the metadata in the properties Lightroom and darktable read
+    pub fn to_xmp(&self) -> String {
+        let mut attributes = Vec::new();
+        if let Some(rating) = self.rating {
+            let rating = if rating == 0 { -1 } else { rating.min(5) as i8 };
+            attributes.push(format!("xmp:Rating=\"{rating}\""));
+        }
+        if let Some(label) = self.color_label.filter(|l| *l != ColorLabel::None) {
+            attributes.push(format!("xmp:Label=\"{}\"", label.xmp_name()));
+        }
+        if let Some(date_time) = self
+            .date_time
+            .as_deref()
+            .and_then(|d| normalize_date_time(d.trim_matches('"')).ok())
+        {
+            attributes.push(format!(
+                "xmp:CreateDate=\"{}\"",
+                date_time.replacen(' ', "T", 1)
+            ));
+        }
+        if let Some(location) = &self.location {
+            attributes.push(format!(
+                "exif:GPSLatitude=\"{}\"",
+                gps_coordinate(location.latitude, ['N', 'S'])
+            ));
+            attributes.push(format!(
+                "exif:GPSLongitude=\"{}\"",
+                gps_coordinate(location.longitude, ['E', 'W'])
+            ));
+        }
+        let mut elements = String::new();
+        if !self.keywords.is_empty() {
+            elements.push_str("   <dc:subject>\n    <rdf:Bag>\n");
+            for keyword in &self.keywords {
+                elements.push_str(&format!("     <rdf:li>{}</rdf:li>\n", escape(keyword)));
+            }
+            elements.push_str("    </rdf:Bag>\n   </dc:subject>\n");
+        }
+        if let Some(description) = self.description.as_deref().filter(|d| !d.is_empty()) {
+            elements.push_str(&format!(
+                "   <dc:description>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{}</rdf:li>\n    </rdf:Alt>\n   </dc:description>\n",
+                escape(description)
+            ));
+        }
+        let attributes = attributes
+            .iter()
+            .map(|attribute| format!("\n    {attribute}"))
+            .collect::<String>();
+        format!(
+            r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="photo-mcp-server {}">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:exif="http://ns.adobe.com/exif/1.0/"{attributes}>
+{elements}  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#,
+            env!("CARGO_PKG_VERSION")
+        )
+    }
+}
+
+/// Photo to export with its metadata, the photo content is copied next to the sidecar when set
+pub struct ExportEntry {
+    pub info: PhotoInfo,
+    pub metadata: XmpMetadata,
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct XmpExport {
+    pub path: String,
+    pub naming: SidecarNaming,
+    pub sidecars: usize,
+    /// Photos copied next to their sidecars
+    pub photos: usize,
+    /// Photos whose sidecar exists already, kept unless overwrite is set
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<String>,
+}
+
+/// Writes an XMP sidecar of every photo into `output_dir`, mirroring the archive (without its
+/// extension) and the path of the photo inside it, so the sidecars sit next to the photos
+/// extracted or copied there
+pub fn export(
+    output_dir: &str,
+    entries: Vec<ExportEntry>,
+    naming: SidecarNaming,
+    overwrite: bool,
+) -> Result<XmpExport, PhotoInsightError> {
+    let mut summary = XmpExport {
+        path: output_dir.to_owned(),
+        naming,
+        sidecars: 0,
+        photos: 0,
+        skipped: Vec::new(),
+    };
+    for entry in entries {
+        let photo = Path::new(output_dir).join(photo_path(&entry.info));
+        let sidecar = naming.sidecar_of(&photo);
+        if sidecar.exists() && !overwrite {
+            summary.skipped.push(sidecar.to_string_lossy().to_string());
+            continue;
+        }
+        if let Some(dir) = photo.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| PhotoInsightError::new(e))?;
+        }
+        if let Some(data) = entry.data {
+            std::fs::write(&photo, data).map_err(|e| PhotoInsightError::new(e))?;
+            summary.photos += 1;
+        }
+        std::fs::write(&sidecar, entry.metadata.to_xmp()).map_err(|e| PhotoInsightError::new(e))?;
+        summary.sidecars += 1;
+    }
+    tracing::info!(
+        "Exported {} XMP sidecars into {output_dir}",
+        summary.sidecars
+    );
+    Ok(summary)
+}
+
+// Relative path of the exported photo, components leaving the export directory are dropped
+fn photo_path(info: &PhotoInfo) -> PathBuf {
+    let archive = info
+        .zip_file_name
+        .strip_suffix(".zip")
+        .unwrap_or(&info.zip_file_name);
+    let mut path = PathBuf::new();
+    for part in [archive, info.photo_file_name.as_str()] {
+        path.extend(
+            Path::new(part)
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_))),
+        );
+    }
+    path
+}
+
+// Degrees and decimal minutes with the hemisphere, e.g. "50,4.8123N"
+fn gps_coordinate(value: f64, hemispheres: [char; 2]) -> String {
+    let hemisphere = if value < 0.0 {
+        hemispheres[1]
+    } else {
+        hemispheres[0]
+    };
+    let value = value.abs();
+    let degrees = value.trunc();
+    format!("{},{:.4}{hemisphere}", degrees, (value - degrees) * 60.0)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
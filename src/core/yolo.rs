@@ -1,44 +1,319 @@
-use serde::Serialize;
+use std::collections::HashMap;
 
-use crate::core::{error::PhotoInsightError, image_cache::PhotoInfo};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+use crate::core::{
+    analyzer::Analyzer,
+    cancel::CancellationToken,
+    config::DetectionConfig,
+    error::PhotoInsightError,
+    image_cache::{PhotoCache, PhotoInfo},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetectedObject {
     pub class_name: String,
     pub confidence: f32,
     pub bbox: (f32, f32, f32, f32), // (xmin, ymin, xmax, ymax)
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AnalysisResult {
     pub(crate) photo_info: PhotoInfo,
+    /// Id of the model which produced the detections
+    pub(crate) model: String,
     pub(crate) object_detection: Vec<DetectedObject>,
 }
 
 impl AnalysisResult {
-    fn new(photo_info: PhotoInfo, object_detection: Vec<DetectedObject>) -> Self {
+    pub(crate) fn new(
+        photo_info: PhotoInfo,
+        model: String,
+        object_detection: Vec<DetectedObject>,
+    ) -> Self {
         Self {
             photo_info,
+            model,
             object_detection,
         }
     }
 }
 
+/// Detection model description, stamped into every persisted detection result.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelSpec {
+    /// Model family, "yolov8" of the built-in weights
+    pub name: String,
+    /// Model version, a variant of the built-in weights, e.g. "strict"
+    pub version: String,
+    /// Minimal confidence of a detection to be reported
+    pub confidence_threshold: f64,
+    /// IoU threshold used by non-max suppression
+    pub iou_threshold: f64,
+}
+
+impl ModelSpec {
+    /// Unique model id, used in sidecar file names and tool parameters
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.name, self.version)
+    }
+}
+
+/// Set of detection models the server can run and search results of.
+pub struct ModelRegistry {
+    models: Vec<ModelSpec>,
+    default_model: String,
+    analysis_models: Vec<String>,
+}
+
+impl ModelRegistry {
+    /// The model used before the registry existed, results of it are stored in legacy sidecars
+    pub fn builtin() -> ModelSpec {
+        ModelSpec {
+            name: "yolov8".to_owned(),
+            version: "default".to_owned(),
+            confidence_threshold: 0.25,
+            iou_threshold: 0.7,
+        }
+    }
+
+    pub fn from_config(config: &DetectionConfig) -> Result<Self, PhotoInsightError> {
+        let builtin = Self::builtin();
+        let mut models = vec![builtin.clone()];
+        for spec in &config.models {
+            // the detector loads its built-in weights, other models would run them under
+            // another name
+            if spec.name != builtin.name {
+                return Err(PhotoInsightError::from_message(format!(
+                    "detection model {}: only variants of the built-in {} weights are supported",
+                    spec.id(),
+                    builtin.name
+                )));
+            }
+            if models.iter().any(|m| m.id() == spec.id()) {
+                return Err(PhotoInsightError::from_message(format!(
+                    "duplicate detection model {}",
+                    spec.id()
+                )));
+            }
+            models.push(spec.clone());
+        }
+        let default_model = config
+            .default_model
+            .clone()
+            .unwrap_or_else(|| Self::builtin().id());
+        let analysis_models = if config.analysis_models.is_empty() {
+            vec![default_model.clone()]
+        } else {
+            config.analysis_models.clone()
+        };
+        let registry = Self {
+            models,
+            default_model,
+            analysis_models,
+        };
+        for id in registry
+            .analysis_models
+            .iter()
+            .chain([&registry.default_model])
+        {
+            registry.get(id)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn models(&self) -> &[ModelSpec] {
+        &self.models
+    }
+
+    pub fn get(&self, id: &str) -> Result<&ModelSpec, PhotoInsightError> {
+        self.models
+            .iter()
+            .find(|m| m.id() == id)
+            .ok_or_else(|| PhotoInsightError::from_message(format!("unknown model: {id}")))
+    }
+
+    pub fn default_model(&self) -> &ModelSpec {
+        self.get(&self.default_model)
+            .expect("validated default model")
+    }
+
+    /// Resolves an optional model id coming from a tool call
+    pub fn resolve(&self, id: &Option<String>) -> Result<&ModelSpec, PhotoInsightError> {
+        match id {
+            Some(id) => self.get(id),
+            None => Ok(self.default_model()),
+        }
+    }
+
+    /// Models run by the background crawler
+    pub fn analysis_models(&self) -> Vec<&ModelSpec> {
+        self.analysis_models
+            .iter()
+            .filter_map(|id| self.get(id).ok())
+            .collect()
+    }
+}
+
+/// Analysis stage running object detection with a registered model
+pub struct ObjectDetectionAnalyzer {
+    model: ModelSpec,
+    enabled_by_default: bool,
+}
+
+impl ObjectDetectionAnalyzer {
+    pub fn new(model: ModelSpec, enabled_by_default: bool) -> Self {
+        Self {
+            model,
+            enabled_by_default,
+        }
+    }
+}
+
+impl Analyzer for ObjectDetectionAnalyzer {
+    fn name(&self) -> String {
+        format!("object_detection.{}", self.model.id())
+    }
+
+    fn version(&self) -> String {
+        "1".to_owned()
+    }
+
+    fn params(&self) -> serde_json::Value {
+        serde_json::to_value(&self.model).unwrap_or_default()
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        self.enabled_by_default
+    }
+
+    fn analyze(
+        &self,
+        photo_info: &PhotoInfo,
+        photo_bytes: &[u8],
+    ) -> Result<serde_json::Value, PhotoInsightError> {
+        let mut results = self.analyze_batch(&[(photo_info.clone(), photo_bytes.to_vec())])?;
+        results
+            .pop()
+            .map(|(_, value)| value)
+            .ok_or_else(|| PhotoInsightError::from_message("no detection result"))
+    }
+
+    // the model is loaded once per chunk
+    fn analyze_batch(
+        &self,
+        photos: &[(PhotoInfo, Vec<u8>)],
+    ) -> Result<Vec<(PhotoInfo, serde_json::Value)>, PhotoInsightError> {
+        analyze_images_using_yolo(photos.to_vec(), &self.model, &CancellationToken::default())?
+            .into_iter()
+            .map(|r| {
+                serde_json::to_value(&r.object_detection)
+                    .map(|v| (r.photo_info, v))
+                    .map_err(|e| PhotoInsightError::new(e))
+            })
+            .collect()
+    }
+
+    fn store(&self, cache: &PhotoCache, results: HashMap<PhotoInfo, serde_json::Value>) {
+        let detections = results.into_iter().filter_map(|(info, value)| {
+            serde_json::from_value::<Vec<DetectedObject>>(value)
+                .ok()
+                .map(|objects| (info, objects))
+        });
+        cache
+            .object_detection
+            .write()
+            .unwrap()
+            .entry(self.model.id())
+            .or_insert_with(HashMap::new)
+            .extend(detections);
+    }
+
+    fn legacy_suffix(&self) -> Option<String> {
+        if self.model == ModelRegistry::builtin() {
+            Some("object_detection".to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+/// Loads the YOLO model once to check it is usable, e.g. at warm-up. Detections load their own
+/// instance, repeated loads are served from the file system cache.
+#[cfg(feature = "detection")]
+pub fn load_model() -> Result<(), PhotoInsightError> {
+    detector().map(|_| ())
+}
+
+// Detector with the verified weights. The detector loads the built-in weights from
+// YOLO_V8_MODEL_PATH, all registered models are variants of them.
+#[cfg(feature = "detection")]
+fn detector() -> Result<yolo_v8::YoloV8ObjectDetection, PhotoInsightError> {
+    let weights_dir = crate::MODEL_FILES.ensure(&ModelRegistry::builtin().id())?;
+    let model_path = std::env::var("YOLO_V8_MODEL_PATH").unwrap_or_default();
+    if let Some(weights_dir) = weights_dir
+        && weights_dir != std::path::Path::new(&model_path)
+    {
+        return Err(PhotoInsightError::from_message(format!(
+            "verified weights are in {}, the detector loads them from YOLO_V8_MODEL_PATH={model_path}, \
+             set models.cache_dir to the same directory",
+            weights_dir.display()
+        )));
+    }
+    yolo_v8::YoloV8ObjectDetection::new().map_err(|e| PhotoInsightError::new(e))
+}
+
+#[cfg(not(feature = "detection"))]
+pub fn load_model() -> Result<(), PhotoInsightError> {
+    Err(not_compiled())
+}
+
+#[cfg(not(feature = "detection"))]
+fn not_compiled() -> PhotoInsightError {
+    PhotoInsightError::from_message(
+        "object detection is not compiled in, build with the detection feature",
+    )
+}
+
+#[cfg(not(feature = "detection"))]
+pub fn analyze_images_using_yolo(
+    _images: Vec<(PhotoInfo, Vec<u8>)>,
+    _model: &ModelSpec,
+    _cancel: &CancellationToken,
+) -> Result<Vec<AnalysisResult>, PhotoInsightError> {
+    Err(not_compiled())
+}
+
+#[cfg(feature = "detection")]
 pub fn analyze_images_using_yolo(
     images: Vec<(PhotoInfo, Vec<u8>)>,
+    model: &ModelSpec,
+    cancel: &CancellationToken,
 ) -> Result<Vec<AnalysisResult>, PhotoInsightError> {
+    use crate::core::timings;
     use yolo_v8::YoloV8ObjectDetection;
 
-    let yolo = YoloV8ObjectDetection::new().map_err(|e| PhotoInsightError::new(e))?;
+    let yolo = detector()?;
 
     let mut results = Vec::new();
     for (photo_info, image_data) in images {
-        let image = yolo_v8::image::Image::load_from_memory(
-            &image_data,
-            YoloV8ObjectDetection::input_dimension(),
-        )
+        cancel.check()?;
+        if cancel.timed_out() {
+            tracing::warn!("Timed out, returning {} analyzed photos", results.len());
+            break;
+        }
+        let image = timings::timed("decode", || {
+            yolo_v8::image::Image::load_from_memory(
+                &image_data,
+                YoloV8ObjectDetection::input_dimension(),
+            )
+        })
         .map_err(|e| PhotoInsightError::new(e))?;
-        let detections = yolo.predict(&image, 0.25, 0.7).postprocess().0;
+        let detections = timings::timed("inference", || {
+            yolo.predict(&image, model.confidence_threshold, model.iou_threshold)
+                .postprocess()
+                .0
+        });
         let result: Vec<DetectedObject> = detections
             .into_iter()
             .map(|bbox| DetectedObject {
@@ -52,7 +327,7 @@ pub fn analyze_images_using_yolo(
                 ),
             })
             .collect();
-        results.push(AnalysisResult::new(photo_info, result));
+        results.push(AnalysisResult::new(photo_info, model.id(), result));
     }
     Ok(results)
 }
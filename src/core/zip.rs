@@ -1,7 +1,540 @@
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    fs::File,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+};
 
-use crate::core::{error::PhotoInsightError, image_cache::PhotoInfo};
-use std::io::Read;
+use flate2::read::DeflateDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::{error::PhotoInsightError, image_cache::PhotoInfo, timings};
+use std::io::{Read, Seek, SeekFrom};
+
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+// End of central directory record with the longest comment
+const MAX_END_OF_CENTRAL_DIRECTORY: u64 = 22 + 0xFFFF;
+
+/// Opened zip archive, dereferences to the archive reader
+pub(crate) struct Archive {
+    zip: zip::ZipArchive<ArchiveReader>,
+    /// Entry index => raw name of entries renamed because their name was already taken
+    renamed: HashMap<usize, Vec<u8>>,
+}
+
+impl Deref for Archive {
+    type Target = zip::ZipArchive<ArchiveReader>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.zip
+    }
+}
+
+impl DerefMut for Archive {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.zip
+    }
+}
+
+/// Opens the zip archive. Takeout parts may exceed 4GB and hold more than 65535 entries,
+/// these are Zip64 archives: sizes, offsets and the entry count are read from the Zip64
+/// extra fields and end of central directory record. Entry indexes are positions in the
+/// central directory, so they stay stable across openings of the same archive. Split
+/// archives are read as one archive (see `split_parts`), entries with a name already taken
+/// by another entry are renamed, otherwise only the last of them could be read.
+pub(crate) fn open_archive(zip_path: &Path) -> Result<Archive, PhotoInsightError> {
+    let failed = |e: &dyn std::fmt::Display| {
+        PhotoInsightError::from_message(format!("Failed to open zip {}: {e}", zip_path.display()))
+    };
+    let parts = split_parts(zip_path);
+    let archive = if parts.is_empty() {
+        let file = File::open(zip_path).map_err(|e| PhotoInsightError::new(e))?;
+        let archive = zip::ZipArchive::new(ArchiveReader::File(file)).map_err(|e| failed(&e))?;
+        let mut reader = PatchedReader::open(&[zip_path.to_path_buf()]).map_err(|e| failed(&e))?;
+        let entries = reader
+            .locate_central_directory()
+            .map_err(|e| failed(&e))?
+            .entries;
+        if entries > archive.len() as u64 {
+            tracing::debug!(
+                "Zip {} has {} entries with duplicate names",
+                zip_path.display(),
+                entries - archive.len() as u64
+            );
+            reader.rewrite_central_directory().map_err(|e| failed(&e))?;
+            Archive::new(reader)?
+        } else {
+            Archive {
+                zip: archive,
+                renamed: HashMap::new(),
+            }
+        }
+    } else {
+        tracing::debug!(
+            "Split archive {} with {} parts",
+            zip_path.display(),
+            parts.len()
+        );
+        let mut reader = PatchedReader::open(&parts).map_err(|e| failed(&e))?;
+        reader.rewrite_central_directory().map_err(|e| failed(&e))?;
+        Archive::new(reader)?
+    };
+    if is_zip64(&archive) {
+        tracing::debug!(
+            "Zip64 archive {} with {} entries",
+            zip_path.display(),
+            archive.len()
+        );
+    }
+    Ok(archive)
+}
+
+impl Archive {
+    fn new(reader: PatchedReader) -> Result<Self, PhotoInsightError> {
+        let renamed = reader.renamed.clone();
+        let zip = zip::ZipArchive::new(ArchiveReader::Patched(reader))
+            .map_err(|e| PhotoInsightError::new(e))?;
+        Ok(Self { zip, renamed })
+    }
+}
+
+/// The archive has the Zip64 end of central directory record
+pub(crate) fn is_zip64(archive: &zip::ZipArchive<ArchiveReader>) -> bool {
+    archive.zip64_comment().is_some()
+}
+
+/// Parts of a split archive in their order: `name.z01`, `name.z02`, ... and the last part
+/// `name.zip`. Empty for an archive which is not split.
+pub fn split_parts(zip_path: &Path) -> Vec<PathBuf> {
+    let mut parts = (1..)
+        .map(|n| zip_path.with_extension(format!("z{n:02}")))
+        .take_while(|part| part.is_file())
+        .collect::<Vec<_>>();
+    if !parts.is_empty() {
+        parts.push(zip_path.to_path_buf());
+    }
+    parts
+}
+
+/// Reader of a zip archive, the archive file or the archive with rewritten central directory
+pub(crate) enum ArchiveReader {
+    File(File),
+    Patched(PatchedReader),
+}
+
+impl Read for ArchiveReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            ArchiveReader::File(file) => file.read(buf),
+            ArchiveReader::Patched(patched) => patched.read(buf),
+        }
+    }
+}
+
+impl Seek for ArchiveReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            ArchiveReader::File(file) => file.seek(pos),
+            ArchiveReader::Patched(patched) => patched.seek(pos),
+        }
+    }
+}
+
+/// Archive parts (a single part unless the archive is split) joined into one stream with the
+/// central directory rewritten. Offsets in the central directory of a split archive are
+/// relative to the part (disk) they point into, they are rewritten to offsets into the
+/// joined stream with a single disk end of central directory, so the joined stream reads as
+/// an ordinary archive. Entries with duplicate names get unique names.
+pub(crate) struct PatchedReader {
+    /// Part file, its start in the joined stream and its length
+    parts: Vec<(File, u64, u64)>,
+    /// Length of the joined parts up to the original central directory
+    data_len: u64,
+    /// Rewritten central directory and end of central directory records
+    tail: Vec<u8>,
+    position: u64,
+    /// Entry index => raw name of renamed entries
+    renamed: HashMap<usize, Vec<u8>>,
+}
+
+/// Central directory location, the offset is relative to its part (disk)
+struct CentralDirectory {
+    disk: usize,
+    offset: u64,
+    size: u64,
+    entries: u64,
+    zip64: bool,
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_owned())
+}
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+// Inserts `~occurrence` before the extension of the file name
+fn disambiguate(name: &[u8], occurrence: usize) -> Vec<u8> {
+    let file_start = name
+        .iter()
+        .rposition(|b| *b == b'/')
+        .map(|slash| slash + 1)
+        .unwrap_or(0);
+    let insert_at = match name[file_start..].iter().rposition(|b| *b == b'.') {
+        Some(dot) if dot > 0 => file_start + dot,
+        _ => name.len(),
+    };
+    let mut unique = name[..insert_at].to_vec();
+    unique.extend(format!("~{occurrence}").as_bytes());
+    unique.extend(&name[insert_at..]);
+    unique
+}
+
+impl PatchedReader {
+    fn open(parts: &[PathBuf]) -> std::io::Result<Self> {
+        let mut files = Vec::new();
+        let mut start = 0;
+        for part in parts {
+            let file = File::open(part)?;
+            let len = file.metadata()?.len();
+            files.push((file, start, len));
+            start += len;
+        }
+        Ok(Self {
+            parts: files,
+            data_len: start,
+            tail: Vec::new(),
+            position: 0,
+            renamed: HashMap::new(),
+        })
+    }
+
+    fn part_start(&self, disk: usize) -> std::io::Result<u64> {
+        self.parts
+            .get(disk)
+            .map(|(_, start, _)| *start)
+            .ok_or_else(|| invalid("missing part of split archive"))
+    }
+
+    fn read_at(&mut self, position: u64, len: u64) -> std::io::Result<Vec<u8>> {
+        let mut data = vec![0; len as usize];
+        self.seek(SeekFrom::Start(position))?;
+        self.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    // Reads the end of central directory record, preceded by the Zip64 locator in Zip64
+    // archives
+    fn locate_central_directory(&mut self) -> std::io::Result<CentralDirectory> {
+        let tail_len = self.data_len.min(MAX_END_OF_CENTRAL_DIRECTORY + 20);
+        let tail = self.read_at(self.data_len - tail_len, tail_len)?;
+        let eocd = (0..=tail.len().saturating_sub(22))
+            .rev()
+            .find(|at| u32_at(&tail, *at) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+            .ok_or_else(|| invalid("end of central directory not found"))?;
+        let zip64_locator = eocd
+            .checked_sub(20)
+            .filter(|at| u32_at(&tail, *at) == ZIP64_LOCATOR_SIGNATURE);
+        match zip64_locator {
+            Some(locator) => {
+                let disk = u32_at(&tail, locator + 4) as usize;
+                let position = self.part_start(disk)? + u64_at(&tail, locator + 8);
+                let record = self.read_at(position, 56)?;
+                if u32_at(&record, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+                    return Err(invalid("Zip64 end of central directory not found"));
+                }
+                Ok(CentralDirectory {
+                    disk: u32_at(&record, 20) as usize,
+                    offset: u64_at(&record, 48),
+                    size: u64_at(&record, 40),
+                    entries: u64_at(&record, 32),
+                    zip64: true,
+                })
+            }
+            None => Ok(CentralDirectory {
+                disk: u16_at(&tail, eocd + 6) as usize,
+                offset: u32_at(&tail, eocd + 16) as u64,
+                size: u32_at(&tail, eocd + 12) as u64,
+                entries: u16_at(&tail, eocd + 10) as u64,
+                zip64: false,
+            }),
+        }
+    }
+
+    fn rewrite_central_directory(&mut self) -> std::io::Result<()> {
+        let directory = self.locate_central_directory()?;
+        let cd_start = self.part_start(directory.disk)? + directory.offset;
+        let cd = self.read_at(cd_start, directory.size)?;
+
+        let mut rewritten = Vec::with_capacity(cd.len());
+        let mut occurrences: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut entries = 0u64;
+        let mut at = 0;
+        while at + 46 <= cd.len() {
+            if u32_at(&cd, at) != CENTRAL_HEADER_SIGNATURE {
+                return Err(invalid("invalid central directory"));
+            }
+            let name_len = u16_at(&cd, at + 28) as usize;
+            let extra_len = u16_at(&cd, at + 30) as usize;
+            let comment_len = u16_at(&cd, at + 32) as usize;
+            let end = at + 46 + name_len + extra_len + comment_len;
+            if end > cd.len() {
+                return Err(invalid("invalid central directory"));
+            }
+            let mut entry = cd[at..end].to_vec();
+            let disk = u16_at(&entry, 34);
+            let offset = u32_at(&entry, 42);
+            let extra_start = 46 + name_len;
+            // values too large for the header are in the Zip64 extra field, in this order
+            let (mut zip64_offset, mut zip64_disk) = (None, None);
+            let mut field = extra_start;
+            while field + 4 <= extra_start + extra_len {
+                let size = u16_at(&entry, field + 2) as usize;
+                if u16_at(&entry, field) == ZIP64_EXTRA_FIELD {
+                    let mut value = field + 4;
+                    if u32_at(&entry, 24) == u32::MAX {
+                        value += 8;
+                    }
+                    if u32_at(&entry, 20) == u32::MAX {
+                        value += 8;
+                    }
+                    if offset == u32::MAX {
+                        zip64_offset = Some(value);
+                        value += 8;
+                    }
+                    if disk == u16::MAX {
+                        zip64_disk = Some(value);
+                    }
+                }
+                field += 4 + size;
+            }
+            let disk = match zip64_disk {
+                Some(value) => u32_at(&entry, value) as usize,
+                None => disk as usize,
+            };
+            let offset = match zip64_offset {
+                Some(value) => u64_at(&entry, value),
+                None => offset as u64,
+            };
+            let offset = self.part_start(disk)? + offset;
+            match zip64_offset {
+                Some(value) => entry[value..value + 8].copy_from_slice(&offset.to_le_bytes()),
+                None if offset < u32::MAX as u64 => {
+                    entry[42..46].copy_from_slice(&(offset as u32).to_le_bytes())
+                }
+                None => return Err(invalid("entry offset exceeds 4GB without Zip64 field")),
+            }
+            match zip64_disk {
+                Some(value) => entry[value..value + 4].copy_from_slice(&0u32.to_le_bytes()),
+                None => entry[34..36].copy_from_slice(&0u16.to_le_bytes()),
+            }
+
+            let name = entry[46..extra_start].to_vec();
+            let occurrence = occurrences.entry(name.clone()).or_default();
+            *occurrence += 1;
+            if *occurrence > 1 {
+                let unique = disambiguate(&name, *occurrence);
+                entry.splice(46..extra_start, unique.iter().copied());
+                entry[28..30].copy_from_slice(&(unique.len() as u16).to_le_bytes());
+                self.renamed.insert(entries as usize, name);
+            }
+            rewritten.extend(entry);
+            at = end;
+            entries += 1;
+        }
+
+        let cd_size = rewritten.len() as u64;
+        let mut tail = rewritten;
+        let zip64 = directory.zip64
+            || entries >= u16::MAX as u64
+            || cd_size >= u32::MAX as u64
+            || cd_start >= u32::MAX as u64;
+        if zip64 {
+            let record = cd_start + cd_size;
+            tail.extend(ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+            tail.extend(44u64.to_le_bytes());
+            tail.extend(45u16.to_le_bytes());
+            tail.extend(45u16.to_le_bytes());
+            tail.extend(0u32.to_le_bytes());
+            tail.extend(0u32.to_le_bytes());
+            tail.extend(entries.to_le_bytes());
+            tail.extend(entries.to_le_bytes());
+            tail.extend(cd_size.to_le_bytes());
+            tail.extend(cd_start.to_le_bytes());
+            tail.extend(ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+            tail.extend(0u32.to_le_bytes());
+            tail.extend(record.to_le_bytes());
+            tail.extend(1u32.to_le_bytes());
+        }
+        let entries = entries.min(u16::MAX as u64) as u16;
+        tail.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        tail.extend(0u16.to_le_bytes());
+        tail.extend(0u16.to_le_bytes());
+        tail.extend(entries.to_le_bytes());
+        tail.extend(entries.to_le_bytes());
+        tail.extend((cd_size.min(u32::MAX as u64) as u32).to_le_bytes());
+        tail.extend((cd_start.min(u32::MAX as u64) as u32).to_le_bytes());
+        tail.extend(0u16.to_le_bytes());
+
+        self.data_len = cd_start;
+        self.tail = tail;
+        self.position = 0;
+        Ok(())
+    }
+
+    fn len(&self) -> u64 {
+        self.data_len + self.tail.len() as u64
+    }
+}
+
+impl Read for PatchedReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let position = self.position;
+        let read = if position >= self.data_len {
+            let at = ((position - self.data_len) as usize).min(self.tail.len());
+            let len = buf.len().min(self.tail.len() - at);
+            buf[..len].copy_from_slice(&self.tail[at..at + len]);
+            len
+        } else {
+            let data_len = self.data_len;
+            let Some((file, start, len)) = self
+                .parts
+                .iter_mut()
+                .find(|(_, start, len)| position < *start + *len)
+            else {
+                return Ok(0);
+            };
+            let available = (*start + *len).min(data_len) - position;
+            let max = buf.len().min(available as usize);
+            file.seek(SeekFrom::Start(position - *start))?;
+            file.read(&mut buf[..max])?
+        };
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for PatchedReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| invalid("seek before the start of archive"))?;
+        Ok(self.position)
+    }
+}
+
+/// SHA-256 (lowercase hex) of the central directory and the end of central directory records,
+/// it identifies the archive content regardless of the file name. Only the tail of the
+/// archive is read.
+pub fn central_directory_hash(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<String, PhotoInsightError> {
+    let zip_path = Path::new(image_dir).join(zip_file_name);
+    let archive = open_archive(&zip_path)?;
+    let start = archive.central_directory_start();
+    let mut reader = archive.zip.into_inner();
+    reader
+        .seek(SeekFrom::Start(start))
+        .map_err(|e| PhotoInsightError::new(e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher).map_err(|e| PhotoInsightError::new(e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Decoded name of a zip entry
+#[derive(Debug, Clone)]
+pub(crate) struct EntryName {
+    pub name: String,
+    /// Name as stored in the archive when the decoded name differs (transcoded or made
+    /// unique), bytes other than printable ASCII are percent-encoded
+    pub original: Option<String>,
+}
+
+// Decodes the raw entry name, `decoded` is the name decoded by the zip crate: UTF-8 for names
+// flagged as UTF-8, CP437 otherwise
+fn decode_entry_name(raw: &[u8], decoded: &str) -> String {
+    if raw.is_ascii() || std::str::from_utf8(raw) == Ok(decoded) {
+        return decoded.to_owned();
+    }
+    let configured = crate::CONFIG
+        .index
+        .entry_name_encoding
+        .as_deref()
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()));
+    if let Some(encoding) = configured {
+        return encoding.decode_without_bom_handling(raw).0.into_owned();
+    }
+    // many archivers write UTF-8 names without setting the flag
+    if let Ok(name) = std::str::from_utf8(raw) {
+        return name.to_owned();
+    }
+    // Japanese phones and archivers use Shift-JIS, accepted only when it decodes without
+    // errors into Japanese characters
+    let japanese = |c: char| matches!(c, '\u{3040}'..='\u{30ff}' | '\u{4e00}'..='\u{9fff}');
+    match encoding_rs::SHIFT_JIS.decode_without_bom_handling_and_without_replacement(raw) {
+        Some(name) if name.chars().any(japanese) => name.into_owned(),
+        _ => decoded.to_owned(),
+    }
+}
+
+fn percent_encode(raw: &[u8]) -> String {
+    raw.iter()
+        .map(|b| match b {
+            b' '..=b'~' if *b != b'%' => (*b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Names of all entries of the archive by index, names not stored as UTF-8 are transcoded
+/// (see `decode_entry_name`)
+pub(crate) fn entry_names(archive: &mut Archive) -> Result<Vec<EntryName>, PhotoInsightError> {
+    let mut names = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let file = archive
+            .zip
+            .by_index_raw(i)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        let raw = archive.renamed.get(&i).map(Vec::as_slice);
+        let raw = raw.unwrap_or(file.name_raw());
+        let name = decode_entry_name(file.name_raw(), file.name());
+        let original = (name.as_bytes() != raw).then(|| percent_encode(raw));
+        names.push(EntryName { name, original });
+    }
+    Ok(names)
+}
+
+/// Original names of entries stored under a different name (transcoded or made unique),
+/// entry index => name as stored in the archive
+pub fn list_original_names(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<HashMap<usize, String>, PhotoInsightError> {
+    let mut archive = open_archive(&Path::new(image_dir).join(zip_file_name))?;
+    Ok(entry_names(&mut archive)?
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, name)| name.original.map(|original| (i, original)))
+        .collect())
+}
 
 /// Extracts file_number from a zip archive into memory.
 /// Returns  tuple of file name and file contents as Vec<u8>.
@@ -10,12 +543,27 @@ pub fn extract_zip_archive(
     zip_file_name: &str,
     file_number: Vec<usize>,
 ) -> Result<Vec<(PhotoInfo, Vec<u8>)>, PhotoInsightError> {
+    let mut result = Vec::new();
+    extract_zip_entries(image_dir, zip_file_name, file_number, |info, buf| {
+        result.push((info, buf));
+        true
+    })?;
+    Ok(result)
+}
+
+/// Extracts the entries one by one, each is passed to `each` as soon as it is read.
+/// Extraction stops when `each` returns false.
+pub fn extract_zip_entries(
+    image_dir: &str,
+    zip_file_name: &str,
+    file_number: Vec<usize>,
+    mut each: impl FnMut(PhotoInfo, Vec<u8>) -> bool,
+) -> Result<(), PhotoInsightError> {
     let zip_path = Path::new(image_dir).join(zip_file_name);
 
-    let mut result = Vec::new();
     if zip_path.is_file() {
-        let file = std::fs::File::open(&zip_path).map_err(|e| PhotoInsightError::new(e))?;
-        let mut archive = zip::ZipArchive::new(file).map_err(|e| PhotoInsightError::new(e))?;
+        let mut archive = timings::timed("zip_open", || open_archive(&zip_path))?;
+        let names = entry_names(&mut archive)?;
 
         for idx in &file_number {
             if *idx >= archive.len() {
@@ -28,18 +576,18 @@ pub fn extract_zip_archive(
             let mut file = archive
                 .by_index(*idx)
                 .map_err(|e| PhotoInsightError::new(e))?;
-            let file_name = file.name().to_string();
 
             let mut buf = Vec::new();
-            file.read_to_end(&mut buf)
+            timings::timed("extract", || file.read_to_end(&mut buf))
                 .map_err(|e| PhotoInsightError::new(e))?;
-            result.push((
-                PhotoInfo::new(zip_file_name.to_owned(), file_name, *idx),
-                buf,
-            ));
+            let mut info = PhotoInfo::new(zip_file_name.to_owned(), names[*idx].name.clone(), *idx);
+            info.original_name = names[*idx].original.clone();
+            if !each(info, buf) {
+                break;
+            }
         }
 
-        Ok(result)
+        Ok(())
     } else {
         Err(PhotoInsightError::from_message(
             "Provided zip file path is not a file",
@@ -47,6 +595,170 @@ pub fn extract_zip_archive(
     }
 }
 
+/// Data of an entry stored uncompressed in the archive file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StoredRange {
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Data range of the entry when it is stored uncompressed and unencrypted in a single file
+/// archive, none otherwise (deflated entries, split archives, archives with a rewritten
+/// central directory)
+pub fn stored_range(
+    image_dir: &str,
+    zip_file_name: &str,
+    index: usize,
+) -> Result<Option<StoredRange>, PhotoInsightError> {
+    let zip_path = Path::new(image_dir).join(zip_file_name);
+    if !split_parts(&zip_path).is_empty() {
+        return Ok(None);
+    }
+    let mut archive = timings::timed("zip_open", || open_archive(&zip_path))?;
+    if !archive.renamed.is_empty() {
+        return Ok(None);
+    }
+    let entry = archive
+        .by_index_raw(index)
+        .map_err(|e| PhotoInsightError::new(e))?;
+    if entry.compression() != zip::CompressionMethod::Stored || entry.encrypted() {
+        return Ok(None);
+    }
+    Ok(Some(StoredRange {
+        offset: entry.data_start(),
+        len: entry.compressed_size(),
+    }))
+}
+
+/// Original bytes of the entry. Stored entries (most Takeout JPEGs) are read straight from
+/// the archive file, skipping the zip reader and its CRC check, other entries are extracted.
+pub fn read_original(
+    image_dir: &str,
+    zip_file_name: &str,
+    index: usize,
+) -> Result<Vec<u8>, PhotoInsightError> {
+    let Some(range) = stored_range(image_dir, zip_file_name, index)? else {
+        return extract_zip_archive(image_dir, zip_file_name, vec![index])?
+            .pop()
+            .map(|(_, data)| data)
+            .ok_or_else(|| PhotoInsightError::from_message("entry not found"));
+    };
+    timings::timed("extract", || {
+        let mut file = File::open(Path::new(image_dir).join(zip_file_name))?;
+        file.seek(SeekFrom::Start(range.offset))?;
+        let mut data = vec![0; range.len as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    })
+    .map_err(|e: std::io::Error| PhotoInsightError::new(e))
+}
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const LOCAL_HEADER_LEN: usize = 30;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+
+/// Position and compression of an entry in a single file archive, persisted in the manifest
+/// so photos are read by seeking to their local header instead of walking the central
+/// directory of the archive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EntryLocation {
+    pub header_offset: u64,
+    pub compressed_size: u64,
+    pub size: u64,
+    /// Compression method as recorded in the archive, 0 is stored, 8 is deflated
+    pub method: u16,
+}
+
+/// Locations of the stored and deflated entries of the archive, empty for split archives and
+/// archives with a rewritten central directory whose offsets do not point into the archive file
+pub fn list_entry_locations(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<HashMap<usize, EntryLocation>, PhotoInsightError> {
+    let zip_path = Path::new(image_dir).join(zip_file_name);
+    if !split_parts(&zip_path).is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut archive = open_archive(&zip_path)?;
+    if !archive.renamed.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut locations = HashMap::new();
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| PhotoInsightError::new(e))?;
+        #[allow(deprecated)]
+        let method = entry.compression().to_u16();
+        if entry.encrypted() || ![METHOD_STORED, METHOD_DEFLATED].contains(&method) {
+            continue;
+        }
+        locations.insert(
+            i,
+            EntryLocation {
+                header_offset: entry.header_start(),
+                compressed_size: entry.compressed_size(),
+                size: entry.size(),
+                method,
+            },
+        );
+    }
+    Ok(locations)
+}
+
+// Reads the entry at its persisted location, fails when the local header found there does
+// not match (the archive changed since it was indexed). The data follows the local header,
+// its sizes are taken from the location as the header of entries with a data descriptor
+// (written by streaming zip tools) has none.
+fn read_located(file: &mut File, location: &EntryLocation) -> Result<Vec<u8>, std::io::Error> {
+    file.seek(SeekFrom::Start(location.header_offset))?;
+    let mut header = [0u8; LOCAL_HEADER_LEN];
+    file.read_exact(&mut header)?;
+    if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE || u16_at(&header, 8) != location.method {
+        return Err(invalid("entry location is stale"));
+    }
+    let names_len = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+    file.seek(SeekFrom::Current(names_len))?;
+    let compressed = file.by_ref().take(location.compressed_size);
+    let mut data = Vec::with_capacity(location.size as usize);
+    match location.method {
+        METHOD_STORED => compressed.take(location.size).read_to_end(&mut data)?,
+        METHOD_DEFLATED => DeflateDecoder::new(compressed)
+            .take(location.size)
+            .read_to_end(&mut data)?,
+        _ => return Err(invalid("unsupported compression method")),
+    };
+    if data.len() as u64 != location.size {
+        return Err(invalid("entry size does not match its location"));
+    }
+    Ok(data)
+}
+
+/// Reads the photos at their persisted locations, each is passed to `each` as soon as it is
+/// read, reading stops when `each` returns false. Stored entries are read straight from the
+/// archive file. Fails when a location is stale, the photos passed to `each` so far are
+/// valid.
+pub fn read_located_entries(
+    image_dir: &str,
+    zip_file_name: &str,
+    entries: Vec<(PhotoInfo, EntryLocation)>,
+    mut each: impl FnMut(PhotoInfo, Vec<u8>) -> bool,
+) -> Result<(), PhotoInsightError> {
+    let mut file = timings::timed("zip_open", || {
+        File::open(Path::new(image_dir).join(zip_file_name))
+    })
+    .map_err(|e| PhotoInsightError::new(e))?;
+    for (photo_info, location) in entries {
+        let data = timings::timed("extract", || read_located(&mut file, &location))
+            .map_err(|e| PhotoInsightError::new(e))?;
+        if !each(photo_info, data) {
+            break;
+        }
+    }
+    Ok(())
+}
+
 pub fn list_zip_archive(
     image_dir: &str,
     zip_file_name: &str,
@@ -55,13 +767,11 @@ pub fn list_zip_archive(
     let mut image_files = Vec::new();
 
     if zip_path.is_file() {
-        let file = std::fs::File::open(&zip_path).map_err(|e| PhotoInsightError::new(e))?;
-        let mut archive = zip::ZipArchive::new(file).map_err(|e| PhotoInsightError::new(e))?;
+        let mut archive = open_archive(&zip_path)?;
 
-        for i in 0..archive.len() {
-            let file = archive.by_index(i).map_err(|e| PhotoInsightError::new(e))?;
-            let file_name = file.name().to_string();
-            if is_image_file(&file_name) {
+        for (i, entry) in entry_names(&mut archive)?.into_iter().enumerate() {
+            let file_name = entry.name;
+            if is_image_file(&file_name) && !crate::CONFIG.index.entry_excluded(&file_name) {
                 image_files.push((i, file_name));
             }
         }
@@ -73,7 +783,261 @@ pub fn list_zip_archive(
     Ok(image_files)
 }
 
+/// Zip entry with its sizes, read from the central directory without decompressing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZipEntry {
+    pub index: usize,
+    pub name: String,
+    pub compressed_size: u64,
+    pub size: u64,
+}
+
+pub fn list_zip_entries(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<Vec<ZipEntry>, PhotoInsightError> {
+    let zip_path = Path::new(image_dir).join(zip_file_name);
+    let mut entries = Vec::new();
+
+    if zip_path.is_file() {
+        let mut archive = open_archive(&zip_path)?;
+        let names = entry_names(&mut archive)?;
+
+        for (i, entry) in names.into_iter().enumerate() {
+            let file = archive
+                .by_index_raw(i)
+                .map_err(|e| PhotoInsightError::new(e))?;
+            if file.is_dir() {
+                continue;
+            }
+            entries.push(ZipEntry {
+                index: i,
+                name: entry.name,
+                compressed_size: file.compressed_size(),
+                size: file.size(),
+            });
+        }
+    } else {
+        return Err(PhotoInsightError::from_message(
+            "Provided zip file path is not a file",
+        ));
+    }
+    Ok(entries)
+}
+
+/// Pairs live photo stills with their motion part, a video stored next to the still under
+/// the same name (e.g. IMG_1234.JPG + IMG_1234.MOV). Returns still index => motion part.
+pub fn list_motion_pairs(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<HashMap<usize, ZipEntry>, PhotoInsightError> {
+    let entries = list_zip_entries(image_dir, zip_file_name)?;
+    let stem = |name: &str| match name.rfind('.') {
+        Some(dot) => name[..dot].to_lowercase(),
+        None => name.to_lowercase(),
+    };
+    let videos: HashMap<String, &ZipEntry> = entries
+        .iter()
+        .filter(|e| media_type(&e.name) == "video")
+        .map(|e| (stem(&e.name), e))
+        .collect();
+    Ok(entries
+        .iter()
+        .filter(|e| is_image_file(&e.name))
+        .filter_map(|e| {
+            videos
+                .get(&stem(&e.name))
+                .map(|video| (e.index, (*video).clone()))
+        })
+        .collect())
+}
+
+/// SHA-256 hashes (lowercase hex) of image entries, the archive is read in full
+pub fn hash_zip_archive(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<HashMap<usize, String>, PhotoInsightError> {
+    let zip_path = Path::new(image_dir).join(zip_file_name);
+    let mut hashes = HashMap::new();
+
+    if zip_path.is_file() {
+        let mut archive = open_archive(&zip_path)?;
+
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|e| PhotoInsightError::new(e))?;
+            if is_image_file(file.name()) {
+                let mut hasher = Sha256::new();
+                std::io::copy(&mut file, &mut hasher).map_err(|e| PhotoInsightError::new(e))?;
+                hashes.insert(i, format!("{:x}", hasher.finalize()));
+            }
+        }
+    } else {
+        return Err(PhotoInsightError::from_message(
+            "Provided zip file path is not a file",
+        ));
+    }
+    Ok(hashes)
+}
+
+/// Image entries of the archive excluded by the index configuration
+pub fn list_excluded_entries(
+    image_dir: &str,
+    zip_file_name: &str,
+) -> Result<Vec<String>, PhotoInsightError> {
+    Ok(list_zip_entries(image_dir, zip_file_name)?
+        .into_iter()
+        .filter(|e| is_image_file(&e.name) && crate::CONFIG.index.entry_excluded(&e.name))
+        .map(|e| e.name)
+        .collect())
+}
+
+/// Media type of a zip entry by its extension: image, video, metadata or other
+pub(crate) fn media_type(file_name: &str) -> &'static str {
+    let lower = file_name.to_lowercase();
+    let ext = lower.rsplit('.').next().unwrap_or_default();
+    match ext {
+        "jpg" | "jpeg" | "png" | "heic" | "heif" | "gif" | "webp" | "dng" | "cr2" | "nef"
+        | "arw" => "image",
+        "mp4" | "mov" | "m4v" | "avi" | "3gp" | "mkv" | "mts" => "video",
+        "json" => "metadata",
+        _ => "other",
+    }
+}
+
 pub(crate) fn is_image_file(file_name: &str) -> bool {
     let lower = file_name.to_lowercase();
-    lower.ends_with(".jpg") || lower.ends_with(".jpeg") || lower.ends_with(".png")
+    lower.ends_with(".jpg")
+        || lower.ends_with(".jpeg")
+        || lower.ends_with(".png")
+        || lower.ends_with(".tif")
+        || lower.ends_with(".tiff")
+        || lower.ends_with(".gif")
+        || lower.ends_with(".webp")
+        || lower.ends_with(".heic")
+        || lower.ends_with(".heif")
+        || lower.ends_with(".cr3")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+
+    use crate::core::image_cache::PhotoInfo;
+    use crate::core::zip::{
+        LOCAL_HEADER_LEN, disambiguate, extract_zip_archive, is_zip64, list_entry_locations,
+        list_zip_archive, open_archive, read_located_entries, u16_at,
+    };
+
+    // More entries than fit the 16 bit entry count of the classic end of central directory
+    const ENTRIES: usize = 70_000;
+
+    #[test]
+    fn test_zip64_archive() {
+        let image_dir = std::env::temp_dir().join(format!("zip64-test-{}", std::process::id()));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let zip_file_name = "takeout-zip64.zip";
+        let zip_path = image_dir.join(zip_file_name);
+
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored)
+            .large_file(true);
+        for i in 0..ENTRIES {
+            writer
+                .start_file(format!("Takeout/Photos/IMG_{i:05}.jpg"), options)
+                .unwrap();
+            writer.write_all(format!("photo {i}").as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let image_dir = image_dir.to_str().unwrap();
+        assert!(is_zip64(&open_archive(&zip_path).unwrap()));
+
+        let listed = list_zip_archive(image_dir, zip_file_name).unwrap();
+        assert_eq!(listed.len(), ENTRIES);
+        assert_eq!(
+            listed[ENTRIES - 1],
+            (ENTRIES - 1, "Takeout/Photos/IMG_69999.jpg".to_owned())
+        );
+        assert_eq!(listed, list_zip_archive(image_dir, zip_file_name).unwrap());
+
+        let extracted =
+            extract_zip_archive(image_dir, zip_file_name, vec![65_535, 65_536, ENTRIES - 1])
+                .unwrap();
+        let extracted = extracted
+            .iter()
+            .map(|(info, data)| (info.photo_index_in_zip, String::from_utf8_lossy(data)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            extracted,
+            vec![
+                (65_535, "photo 65535".into()),
+                (65_536, "photo 65536".into()),
+                (ENTRIES - 1, "photo 69999".into()),
+            ]
+        );
+        assert!(extract_zip_archive(image_dir, zip_file_name, vec![ENTRIES]).is_err());
+
+        std::fs::remove_dir_all(image_dir).unwrap();
+    }
+
+    #[test]
+    fn test_data_descriptor_locations() {
+        let image_dir = std::env::temp_dir().join(format!("zip-dd-test-{}", std::process::id()));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let zip_file_name = "takeout-stream.zip";
+        let zip_path = image_dir.join(zip_file_name);
+
+        // streamed archives have no sizes in the local headers, a data descriptor follows the data
+        let mut writer = zip::ZipWriter::new_stream(std::fs::File::create(&zip_path).unwrap());
+        let photos = (0..3)
+            .map(|i| format!("photo {i} ").repeat(1000 + i))
+            .collect::<Vec<_>>();
+        for (i, photo) in photos.iter().enumerate() {
+            let method = if i == 1 {
+                zip::CompressionMethod::Stored
+            } else {
+                zip::CompressionMethod::Deflated
+            };
+            writer
+                .start_file(
+                    format!("Takeout/Photos/IMG_{i}.jpg"),
+                    SimpleFileOptions::default().compression_method(method),
+                )
+                .unwrap();
+            writer.write_all(photo.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let archive = std::fs::read(&zip_path).unwrap();
+        assert_ne!(u16_at(&archive[..LOCAL_HEADER_LEN], 6) & 0x8, 0);
+
+        let image_dir = image_dir.to_str().unwrap();
+        let locations = list_entry_locations(image_dir, zip_file_name).unwrap();
+        assert_eq!(locations.len(), photos.len());
+        let entries = (0..photos.len())
+            .map(|i| {
+                let info = PhotoInfo::new(zip_file_name.to_owned(), format!("IMG_{i}.jpg"), i);
+                (info, locations[&i])
+            })
+            .collect();
+        let mut read = Vec::new();
+        read_located_entries(image_dir, zip_file_name, entries, |_, data| {
+            read.push(String::from_utf8(data).unwrap());
+            true
+        })
+        .unwrap();
+        assert_eq!(read, photos);
+
+        std::fs::remove_dir_all(image_dir).unwrap();
+    }
+
+    #[test]
+    fn test_disambiguate() {
+        assert_eq!(disambiguate(b"dir/IMG_1.jpg", 2), b"dir/IMG_1~2.jpg");
+        assert_eq!(disambiguate(b"dir.d/README", 3), b"dir.d/README~3");
+        assert_eq!(disambiguate(b".hidden", 2), b".hidden~2");
+    }
 }
@@ -1,10 +1,17 @@
-use crate::resources::photo::PhotoResource;
+use crate::core::collections::{Access, TOKEN_CAPABILITY};
+use crate::core::error::PhotoInsightError;
+use crate::core::features;
+use crate::core::selection::DEFAULT_SESSION;
+use crate::core::timings;
+use crate::resources::photo::{PHOTO_SCHEME, PhotoResource};
+use crate::{CONFIG, IC, IN_FLIGHT};
 // use crate::tools::fs::FsTools;
 use crate::tools::photo::PhotoTools;
+use crate::tools::{elicitation, output_schema};
 use async_trait::async_trait;
 use rust_mcp_sdk::schema::{
-    CallToolRequest, CallToolResult, ListToolsRequest, ListToolsResult, RpcError,
-    schema_utils::CallToolError,
+    CallToolRequest, CallToolResult, CancelledNotification, ListToolsRequest, ListToolsResult,
+    RpcError, schema_utils::CallToolError,
 };
 use rust_mcp_sdk::schema::{
     ListResourceTemplatesRequest, ListResourceTemplatesResult, ReadResourceRequest,
@@ -12,6 +19,7 @@ use rust_mcp_sdk::schema::{
 };
 use rust_mcp_sdk::{McpServer, mcp_server::ServerHandler};
 use std::sync::Arc;
+use std::time::Instant;
 // Custom Handler to handle MCP Messages
 pub struct PhotoInsightServerHandler {}
 
@@ -21,6 +29,19 @@ impl PhotoInsightServerHandler {
     }
 }
 
+// Collections the client may access by the API token of its initialize request
+fn access(runtime: &Arc<dyn McpServer>) -> Result<Access, PhotoInsightError> {
+    let capability = runtime
+        .client_info()
+        .and_then(|info| info.capabilities.experimental?.remove(TOKEN_CAPABILITY));
+    let field = |name: &str| {
+        capability
+            .as_ref()
+            .and_then(|capability| capability.get(name)?.as_str())
+    };
+    IC.access(field("token"), field("collection"))
+}
+
 // To check out a list of all the methods in the trait that you can override, take a look at
 // https://github.com/rust-mcp-stack/rust-mcp-sdk/blob/main/crates/rust-mcp-sdk/src/mcp_handlers/mcp_server_handler.rs
 
@@ -35,7 +56,7 @@ impl ServerHandler for PhotoInsightServerHandler {
     ) -> std::result::Result<ListToolsResult, RpcError> {
         // let mut tools = FsTools::tools();
         let mut tools = Vec::new();
-        tools.extend(PhotoTools::tools());
+        tools.extend(output_schema::tools());
         Ok(ListToolsResult {
             meta: None,
             next_cursor: None,
@@ -53,7 +74,30 @@ impl ServerHandler for PhotoInsightServerHandler {
         // let tool_params = FsTools::try_from(request.params.clone());
         // if tool_params.is_err() {
         // If conversion to GreetingTools fails, try converting to PhotoTools enum
-        let photo_tool_params = PhotoTools::try_from(request.params.clone());
+        // debug_timings is accepted by every tool, it is not a parameter of the tool itself
+        let mut params = request.params.clone();
+        let debug_timings = params
+            .arguments
+            .as_mut()
+            .and_then(|arguments| arguments.remove(output_schema::DEBUG_TIMINGS))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        if let Some(feature) = features::missing_for_tool(&request.params.name) {
+            return Err(CallToolError::from_message(format!(
+                "{} is not available, the server was built without the {feature} feature",
+                request.params.name
+            )));
+        }
+        if CONFIG.tools.disabled(&request.params.name) {
+            return Err(CallToolError::from_message(format!(
+                "{} is disabled on this server",
+                request.params.name
+            )));
+        }
+        let access = access(&runtime).map_err(CallToolError::new)?;
+        // ambiguous searches ask the client to narrow them down before they run
+        elicitation::refine(&runtime, &mut params, access.collection.as_deref()).await;
+        let photo_tool_params = PhotoTools::try_from(params);
         if photo_tool_params.is_err() {
             // If both conversions fail, return an error indicating unknown tool parameters
             return Err(CallToolError::new(std::io::Error::new(
@@ -62,21 +106,107 @@ impl ServerHandler for PhotoInsightServerHandler {
             )));
         }
         let photo_tool_params = photo_tool_params.unwrap();
+        // state kept per session (e.g. photo selection), stdio transport has a single session
+        let session_id = runtime
+            .session_id()
+            .unwrap_or_else(|| DEFAULT_SESSION.to_owned());
+        // long running tools check the token, see handle_cancelled_notification
+        let timeout = CONFIG.tools.timeout(&request.params.name);
+        let (call_id, cancel) = IN_FLIGHT.start(&session_id, timeout);
+        // every log line of the call carries the request id, errors returned to the client too
+        let span = tracing::info_span!(
+            "tool_call",
+            request_id = call_id,
+            tool = %request.params.name,
+            session = %session_id,
+        );
+        let started = Instant::now();
+        let collector = timings::start(debug_timings);
         // Match the PhotoTools variant and execute its corresponding logic
 
-        return match photo_tool_params {
-            PhotoTools::PhotoExifTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoViewByNameTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoViewByYearMonthTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoSearchByNameTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoSearchByYearMonthTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoExifTagTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoExifSearchTagTool(tool) => tool.call_tool(),
-            PhotoTools::ListAllPhotosTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoObjectDetectionTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoGlobalSummaryTool(tool) => tool.call_tool(),
-            PhotoTools::PhotoStatsByYearTool(tool) => tool.call_tool(),
-        };
+        // the tool sees the photos of the client's collection only
+        let result = span.in_scope(|| {
+            IC.scoped(access.collection.as_deref(), || match photo_tool_params {
+                PhotoTools::PhotoExifTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoViewByNameTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoViewByYearMonthTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoSearchByNameTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSearchByYearMonthTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoExifTagTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoExifSearchTagTool(tool) => tool.call_tool(),
+                PhotoTools::ListAllPhotosTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoObjectDetectionTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoDetectCustomTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoDecodeBarcodesTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoDetectionModelsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSearchByObjectTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSearchByLabelTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSearchByPeopleCountTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSearchPortraitsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoGlobalSummaryTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoStatsByYearTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSettingsInsightsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoShutterCountsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoExposureStatsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoStorageReportTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoArchiveCoversTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoCacheStatsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoIndexHealthTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoExportCatalogTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoExportBundleTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoImportBundleTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoBackupDerivedTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoRestoreDerivedTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoCacheCleanupTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoPeriodSummaryTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoYearInReviewTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoSubjectTimelineTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoHighlightsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoStoryPayloadTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoFindGapsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoManifestCoverageTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoCompareTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoTextSearchTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoQueryPlanTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoDescribeToolsTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoCalendarTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoSetMetadataTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoShiftTimeTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoHideTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoListHiddenTool(tool) => tool.call_tool(),
+                PhotoTools::PhotoSelectionAddTool(tool) => tool.call_tool(&session_id),
+                PhotoTools::PhotoSelectionRemoveTool(tool) => tool.call_tool(&session_id),
+                PhotoTools::PhotoSelectionListTool(tool) => tool.call_tool(&session_id),
+                PhotoTools::PhotoSelectionClearTool(tool) => tool.call_tool(&session_id),
+                PhotoTools::PhotoRenderSlideshowTool(tool) => tool.call_tool(&session_id, &cancel),
+                PhotoTools::PhotoExportXmpTool(tool) => tool.call_tool(&session_id),
+                PhotoTools::PhotoTransformTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoBenchmarkTool(tool) => tool.call_tool(&cancel),
+                PhotoTools::PhotoFederatedSearchTool(tool) => tool.call_tool(&access.authorized),
+            })
+            .unwrap_or_else(|e| Err(CallToolError::new(e)))
+        });
+        let stage_timings = collector.finish();
+        IN_FLIGHT.finish(&session_id, call_id);
+        span.in_scope(|| match &result {
+            Ok(_) => tracing::info!(elapsed_ms = started.elapsed().as_millis(), "tool call done"),
+            Err(e) => tracing::warn!(
+                elapsed_ms = started.elapsed().as_millis(),
+                error = %e,
+                "tool call failed"
+            ),
+        });
+        let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        result
+            .map(|result| {
+                let result = output_schema::translated(result);
+                let result = output_schema::with_structured_content(&request.params.name, result);
+                match stage_timings {
+                    Some(stages) => output_schema::with_timings(result, elapsed_ms, stages),
+                    None => result,
+                }
+            })
+            .map_err(|e| CallToolError::from_message(format!("request {call_id}: {e}")))
         // } else {
         //     let tool_params = tool_params.unwrap();
 
@@ -97,10 +227,40 @@ impl ServerHandler for PhotoInsightServerHandler {
         Ok(ListResourceTemplatesResult {
             meta: None,
             next_cursor: None,
-            resource_templates: vec![PhotoResource::get()],
+            resource_templates: vec![
+                PhotoResource::get(),
+                PhotoResource::by_id(),
+                PhotoResource::original_by_id(),
+            ],
         })
     }
 
+    /// Cancels the tool call in progress of the session. The handler can't tell which call the
+    /// request id belongs to, so concurrent calls of the session are not cancelled.
+    async fn handle_cancelled_notification(
+        &self,
+        notification: CancelledNotification,
+        runtime: Arc<dyn McpServer>,
+    ) -> Result<(), RpcError> {
+        let session_id = runtime
+            .session_id()
+            .unwrap_or_else(|| DEFAULT_SESSION.to_owned());
+        match IN_FLIGHT.cancel_single(&session_id) {
+            1 => tracing::info!(
+                "Request {:?} cancelled ({:?}), cancelling the call of session {session_id}",
+                notification.params.request_id,
+                notification.params.reason
+            ),
+            running => tracing::warn!(
+                "Request {:?} cancelled ({:?}), {running} calls of session {session_id} in progress, \
+                 none cancelled as the request can't be matched to one of them",
+                notification.params.request_id,
+                notification.params.reason
+            ),
+        }
+        Ok(())
+    }
+
     /// Handle Resource Request
     async fn handle_read_resource_request(
         &self,
@@ -108,7 +268,25 @@ impl ServerHandler for PhotoInsightServerHandler {
         runtime: Arc<dyn McpServer>,
     ) -> Result<ReadResourceResult, RpcError> {
         println!("request: {request:#?}");
+        let access =
+            access(&runtime).map_err(|e| RpcError::invalid_request().with_message(e.message))?;
         let uri = request.params.uri;
+        if uri.starts_with(PHOTO_SCHEME) {
+            let blobs = IC
+                .scoped(access.collection.as_deref(), || {
+                    PhotoResource::read_photo(&uri)
+                })
+                .and_then(|blobs| blobs)
+                .map_err(|e| RpcError::invalid_params().with_message(e.message))?;
+            let contents = blobs
+                .into_iter()
+                .map(ReadResourceResultContentsItem::BlobResourceContents)
+                .collect();
+            return Ok(ReadResourceResult {
+                meta: None,
+                contents,
+            });
+        }
         let splitted = uri.split("###").collect::<Vec<&str>>();
         if splitted.len() != 4 {
             tracing::error!("invalid params: uri={uri} splitted={splitted:#?}");
@@ -126,7 +304,11 @@ impl ServerHandler for PhotoInsightServerHandler {
         let limit = limit
             .parse::<usize>()
             .map_err(|e| RpcError::invalid_params().with_message(e.to_string()))?;
-        let blobs = PhotoResource::read_resource(zip_file, image_file, offset, limit)
+        let blobs = IC
+            .scoped(access.collection.as_deref(), || {
+                PhotoResource::read_resource(zip_file, image_file, offset, limit)
+            })
+            .and_then(|blobs| blobs)
             .map_err(|e| RpcError::internal_error().with_message(e.message))?;
         let contents = blobs
             .iter()
@@ -3,13 +3,60 @@ pub mod core;
 use lazy_static::lazy_static;
 use std::env;
 lazy_static! {
+    // Path of the JSON configuration file, defaulting to "photo-mcp.json" in the working directory
+    pub static ref CONFIG_FILE: String =
+        env::var("PHOTO_MCP_CONFIG").unwrap_or_else(|_| "photo-mcp.json".to_owned());
+
+    // Server configuration, all sections are optional
+    pub static ref CONFIG: core::config::Config =
+        core::config::Config::load(CONFIG_FILE.as_str()).unwrap();
+
+    // Response language of class names and labels
+    pub static ref TRANSLATIONS: core::i18n::Translations =
+        core::i18n::Translations::from_config(&CONFIG.i18n);
+
+    // Synonyms and groups of detected object classes
+    pub static ref CLASS_ALIASES: core::class_aliases::ClassAliases =
+        core::class_aliases::ClassAliases::new(&CONFIG.search.class_aliases);
+
+    // Persistence of indexes and analysis results
+    pub static ref STORE: Box<dyn core::store::Store> = core::store::build(CONFIG.storage, &CONFIG.encryption).unwrap();
+
+    // Registry of detection models we can run and search results of
+    pub static ref MODELS: core::yolo::ModelRegistry =
+        core::yolo::ModelRegistry::from_config(&CONFIG.detection).unwrap();
+
+    // Weights of the detection models, downloaded on first use
+    pub static ref MODEL_FILES: core::models::ModelFiles =
+        core::models::ModelFiles::from_config(&CONFIG.models);
+
+    // Stages of the background analysis pipeline
+    pub static ref ANALYZERS: core::analyzer::AnalyzerRegistry =
+        core::analyzer::AnalyzerRegistry::build(&CONFIG.analysis, &MODELS).unwrap();
+
     // Define the directory where images are stored, defaulting to "$HOME/Pictures" if not set
     pub static ref IMAGE_DIR: String =
         env::var("IMAGE_DIR").unwrap_or_else(|_| format!("{}/Pictures", env::var("HOME").unwrap()));
 
-    // Initialize a global instance of ImageCache using the specified image directory
-    pub static ref IC: core::image_cache::PhotoCache =
-        core::image_cache::PhotoCache::build(IMAGE_DIR.as_str()).unwrap();
+    // Photo cache of the collection the current tool call or crawler works on, the default
+    // collection is in the image directory
+    pub static ref IC: core::collections::Collections =
+        core::collections::Collections::new(IMAGE_DIR.as_str(), &CONFIG.collections).unwrap();
+
+    // Tool calls in progress, cancelled by MCP cancellation notifications
+    pub static ref IN_FLIGHT: core::cancel::InFlightCalls =
+        core::cancel::InFlightCalls::default();
+
+    // Outcome of the startup warm-up tasks
+    pub static ref WARM_UP: core::warm_up::WarmUp = core::warm_up::WarmUp::default();
+
+    // Photo selections of MCP sessions
+    pub static ref SELECTION: core::selection::SelectionStore =
+        core::selection::SelectionStore::default();
+
+    // Watermark of images returned to clients, none unless configured
+    pub static ref WATERMARK: Option<core::watermark::Watermark> =
+        core::watermark::Watermark::from_config(&CONFIG.watermark).unwrap();
 }
 pub mod handler;
 pub mod resources;
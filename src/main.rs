@@ -1,8 +1,16 @@
-use std::thread;
+use std::{
+    io::{IsTerminal, Write},
+    thread,
+};
 
-use photo_mcp_server::{IC, server};
+use photo_mcp_server::core::backup;
+use photo_mcp_server::core::bundle;
+use photo_mcp_server::core::catalog::{self, CatalogFormat};
+use photo_mcp_server::core::error::PhotoInsightError;
+use photo_mcp_server::core::setup;
+use photo_mcp_server::{CONFIG, CONFIG_FILE, IC, IMAGE_DIR, WARM_UP, server};
 use rust_mcp_sdk::error::SdkResult;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> SdkResult<()> {
@@ -11,15 +19,144 @@ async fn main() -> SdkResult<()> {
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        // closing spans logs their timing, e.g. a tool call or an archive being indexed
+        .with(tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE))
         .init();
 
-    let _ = IC.search_image_by_name(&".".to_owned(), &None, 0, 20);
+    // photo-mcp-server init
+    // photo-mcp-server export-catalog <csv|parquet> [output_file]
+    // photo-mcp-server export-bundle [bundle_file]
+    // photo-mcp-server import-bundle <bundle_file>
+    // photo-mcp-server backup-derived [backup_dir]
+    // photo-mcp-server restore-derived <backup_dir>
+    let args = std::env::args().collect::<Vec<String>>();
+    match args.get(1).map(String::as_str) {
+        Some("init") => {
+            init();
+            return Ok(());
+        }
+        Some("export-catalog") => {
+            export_catalog(args.get(2), args.get(3));
+            return Ok(());
+        }
+        Some("export-bundle") => {
+            let bundle_file = args
+                .get(2)
+                .cloned()
+                .unwrap_or_else(|| bundle::DEFAULT_BUNDLE.to_owned());
+            report(bundle::export(IMAGE_DIR.as_str(), &bundle_file));
+            return Ok(());
+        }
+        Some("import-bundle") => {
+            let Some(bundle_file) = args.get(2) else {
+                eprintln!("Usage: import-bundle <bundle_file>");
+                std::process::exit(2);
+            };
+            report(bundle::import(IMAGE_DIR.as_str(), bundle_file));
+            return Ok(());
+        }
+        Some("backup-derived") => {
+            let backup_dir = args
+                .get(2)
+                .cloned()
+                .unwrap_or_else(|| backup::DEFAULT_BACKUP.to_owned());
+            report(backup::backup(IMAGE_DIR.as_str(), &backup_dir));
+            return Ok(());
+        }
+        Some("restore-derived") => {
+            let Some(backup_dir) = args.get(2) else {
+                eprintln!("Usage: restore-derived <backup_dir>");
+                std::process::exit(2);
+            };
+            report(backup::restore(IMAGE_DIR.as_str(), backup_dir));
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    WARM_UP.run(&CONFIG.warm_up);
     thread::spawn(|| {
         IC.crawl_and_analyse();
     });
+    // every named collection is indexed by its own crawler
+    for name in IC.names() {
+        thread::spawn(move || {
+            if let Err(e) = IC.scoped(Some(&name), || IC.crawl_and_analyse()) {
+                tracing::error!("Collection {name} not crawled: {e}");
+            }
+        });
+    }
 
     server::start_server().await?;
 
     Ok(())
 }
+
+// Checks the environment and writes a starter config, the image directory and the config
+// file are asked for when run on a terminal
+fn init() {
+    let interactive = std::io::stdin().is_terminal();
+    let (image_dir, config_file) = if interactive {
+        (
+            ask("Image directory with the zip archives", IMAGE_DIR.as_str()),
+            ask("Config file", CONFIG_FILE.as_str()),
+        )
+    } else {
+        (IMAGE_DIR.clone(), CONFIG_FILE.clone())
+    };
+    let write_config = !interactive || ask("Write starter config? (y/n)", "y") == "y";
+    let summary = setup::init(&image_dir, &config_file, write_config);
+    if let Ok(summary) = &summary {
+        if config_file != CONFIG_FILE.as_str() {
+            eprintln!("Set PHOTO_MCP_CONFIG={config_file} to use the config");
+        }
+        if image_dir != IMAGE_DIR.as_str() {
+            eprintln!("Set IMAGE_DIR={image_dir} to serve the archives");
+        }
+        for check in summary.checks.iter().filter(|check| !check.ok) {
+            eprintln!("{}: {}", check.check, check.detail);
+        }
+    }
+    report(summary);
+}
+
+// Asks on the terminal, an empty answer takes the default
+fn ask(question: &str, default: &str) -> String {
+    print!("{question} [{default}]: ");
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    match std::io::stdin().read_line(&mut answer) {
+        Ok(_) if !answer.trim().is_empty() => answer.trim().to_owned(),
+        _ => default.to_owned(),
+    }
+}
+
+// Writes the metadata catalog of all photos and exits, the server is not started
+fn export_catalog(format: Option<&String>, output_file: Option<&String>) {
+    let format = match CatalogFormat::parse(format.map(String::as_str).unwrap_or("csv")) {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    };
+    let output_file = output_file
+        .cloned()
+        .unwrap_or_else(|| format!("photo-catalog.{}", format.extension()));
+    let rows = IC.catalog(true);
+    if let Err(e) = catalog::export(&rows, format, &output_file) {
+        eprintln!("Failed to export catalog: {e}");
+        std::process::exit(1);
+    }
+    println!("Exported {} photos to {output_file}", rows.len());
+}
+
+fn report<T: serde::Serialize>(summary: Result<T, PhotoInsightError>) {
+    match summary {
+        Ok(summary) => println!("{}", serde_json::to_string_pretty(&summary).unwrap()),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,6 +1,29 @@
-use rust_mcp_sdk::schema::{BlobResourceContents, ResourceTemplate};
+use rust_mcp_sdk::schema::{BlobResourceContents, ResourceLink, ResourceTemplate};
 
-use crate::{IC, core::error::PhotoInsightError};
+use crate::{
+    IC,
+    core::{
+        cancel::CancellationToken, error::PhotoInsightError, image_cache::PhotoInfo,
+        name_folding::NameFolding, watermark,
+    },
+};
+
+/// Scheme of photo resources: `photo://{photo_id}`, photos without ID (content hash not
+/// computed yet) are `photo://{zip_archive}/{photo_index_in_zip}`
+pub const PHOTO_SCHEME: &str = "photo://";
+/// Suffix of photo resource URIs asking for the original photo instead of the thumbnail
+pub const ORIGINAL_SUFFIX: &str = "?original";
+
+fn mime_of_name(file_name: &str) -> &'static str {
+    let lower = file_name.to_lowercase();
+    match lower.rsplit('.').next() {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("tif" | "tiff") => "image/tiff",
+        _ => "image/jpeg",
+    }
+}
 
 pub struct PhotoResource {}
 
@@ -17,22 +40,125 @@ impl PhotoResource {
         }
     }
 
+    pub fn by_id() -> ResourceTemplate {
+        ResourceTemplate {
+            annotations: None,
+            description: Some(
+                "Photo image by its photo_id, resource links returned by photo view tools"
+                    .to_owned(),
+            ),
+            meta: None,
+            mime_type: None,
+            name: "photo".to_owned(),
+            title: Some("Photo by ID".to_owned()),
+            uri_template: format!("{PHOTO_SCHEME}{{photo_id}}"),
+        }
+    }
+
+    pub fn original_by_id() -> ResourceTemplate {
+        ResourceTemplate {
+            annotations: None,
+            description: Some(
+                "Original photo by its photo_id, in full quality as stored in the archive"
+                    .to_owned(),
+            ),
+            meta: None,
+            mime_type: None,
+            name: "photo_original".to_owned(),
+            title: Some("Original photo by ID".to_owned()),
+            uri_template: format!("{PHOTO_SCHEME}{{photo_id}}{ORIGINAL_SUFFIX}"),
+        }
+    }
+
+    pub fn uri_of(photo_info: &PhotoInfo) -> String {
+        match IC.ids.id_of(photo_info) {
+            Some(photo_id) => format!("{PHOTO_SCHEME}{photo_id}"),
+            None => format!(
+                "{PHOTO_SCHEME}{}/{}",
+                photo_info.zip_file_name, photo_info.photo_index_in_zip
+            ),
+        }
+    }
+
+    /// Link to the photo resource, returned instead of the image data
+    pub fn link(photo_info: &PhotoInfo) -> ResourceLink {
+        ResourceLink::new(
+            photo_info.photo_file_name.clone(),
+            Self::uri_of(photo_info),
+            None,
+            None,
+            None,
+            Some(mime_of_name(&photo_info.photo_file_name).to_owned()),
+            None,
+            None,
+        )
+    }
+
+    /// Reads a `photo://` resource, the thumbnail or the original photo
+    pub fn read_photo(uri: &str) -> Result<Vec<BlobResourceContents>, PhotoInsightError> {
+        let not_found = || PhotoInsightError::from_message(format!("Photo {uri} not found"));
+        let key = uri.strip_prefix(PHOTO_SCHEME).ok_or_else(not_found)?;
+        let (key, original) = match key.strip_suffix(ORIGINAL_SUFFIX) {
+            Some(key) => (key, true),
+            None => (key, false),
+        };
+        let photo_info = match key.rsplit_once('/') {
+            Some((zip_file, index)) => {
+                let index = index.parse::<usize>().map_err(|_| not_found())?;
+                IC.images
+                    .iter()
+                    .find(|info| info.zip_file_name == zip_file && info.photo_index_in_zip == index)
+                    .cloned()
+            }
+            None => IC.ids.photo_of(key).cloned(),
+        }
+        .ok_or_else(not_found)?;
+        let image_data = if original {
+            let data = IC.original_data(&photo_info)?;
+            let mime = mime_of_name(&photo_info.photo_file_name).to_owned();
+            vec![(photo_info, mime, data)]
+        } else {
+            IC.image_data(vec![&photo_info], &CancellationToken::default())?
+        };
+        // originals are re-encoded only when a watermark is configured
+        let image_data = watermark::watermarked(image_data)?;
+        Ok(image_data
+            .into_iter()
+            .map(|(photo_info, mime, image_data)| BlobResourceContents {
+                meta: Some(IC.image_meta(&photo_info, &image_data)),
+                blob: base64::encode(image_data),
+                mime_type: Some(mime),
+                uri: uri.to_owned(),
+            })
+            .collect())
+    }
+
     pub fn read_resource(
         zip_file: String,
         image_file: String,
         offset: usize,
         limit: usize,
     ) -> Result<Vec<BlobResourceContents>, PhotoInsightError> {
-        let (infos, _) =
-            IC.search_image_by_name(&image_file, &Some(zip_file.clone()), offset, limit);
-        let image_data = IC.image_data(infos)?;
+        // resources are read by exact name, hidden photos and trash included
+        let (infos, _) = IC.search_image_by_name(
+            &image_file,
+            &Some(zip_file.clone()),
+            offset,
+            limit,
+            true,
+            true,
+            true,
+            &NameFolding::configured(),
+        );
+        let image_data =
+            watermark::watermarked(IC.image_data(infos, &CancellationToken::default())?)?;
 
         let blobs = image_data
             .iter()
-            .map(|(_, mime, image_data)| BlobResourceContents {
+            .map(|(photo_info, mime, image_data)| BlobResourceContents {
                 blob: base64::encode(image_data),
                 mime_type: Some(mime.clone()),
-                meta: None,
+                meta: Some(IC.image_meta(photo_info, image_data)),
                 uri: format!("file:///{zip_file}/{image_file}/?offset={offset}&limit={limit}"),
             })
             .collect::<Vec<BlobResourceContents>>();
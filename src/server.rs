@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -5,6 +6,8 @@ use rust_mcp_sdk::event_store::InMemoryEventStore;
 use rust_mcp_sdk::mcp_server::{HyperServerOptions, hyper_server};
 
 use crate::handler::PhotoInsightServerHandler;
+use crate::tools::output_schema;
+use crate::{CONFIG, IC};
 use rust_mcp_sdk::schema::{
     Implementation, InitializeResult, LATEST_PROTOCOL_VERSION, ServerCapabilities,
     ServerCapabilitiesResources, ServerCapabilitiesTools,
@@ -17,6 +20,65 @@ pub struct AppStateX<H: ServerHandler> {
     pub handler: H,
 }
 
+// Instructions reflecting the tools offered by this server, the size and time span of the
+// collection and how far the background analysis got
+fn instructions() -> String {
+    let tools = output_schema::tools()
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect::<HashSet<_>>();
+    let offered = |name: &str| tools.contains(name);
+    let summary = IC.summary();
+    let mut lines = vec![
+        "This server provides help on deeper understanding of the photo collection we have in our zip files.".to_owned(),
+        "It provides methods for listing of photos, describing exif tags of photos, searching of photos by name, exif tags or by year and month.".to_owned(),
+        "Most responses contain pagination to help browse on results (next_offset, next_limit). If next_offset is not present or null, there are no more pages.".to_owned(),
+        "Image file description contains zip_file - that is the file on the file system, image_file - \
+the file inside the zip archive and image_index_in_zip which describes the \
+archive index number in the zip (for fast extraction).".to_owned(),
+    ];
+    if offered("photo_view_by_name") || offered("photo_view_by_year_month") {
+        lines.push("There are also helpers on viewing photos that send the ImageContent (base64 \
+encoded). Those methods do not have pagination but offset and limit can be used and derived from non-view methods.".to_owned());
+    }
+    let span = match (summary.first_year, summary.last_year) {
+        (Some(first), Some(last)) if first != last => format!(", taken from {first} to {last}"),
+        (Some(year), _) => format!(", taken in {year}"),
+        _ => String::new(),
+    };
+    lines.push(format!(
+        "The collection has {} photos in {} archives{span}.",
+        summary.photos, summary.archives
+    ));
+    if offered("photo_object_detection") {
+        lines.push("photo_object_detection runs object detection on photos on demand.".to_owned());
+    }
+    if !summary.stages.is_empty() {
+        let progress = if summary.analyzed_archives < summary.archives {
+            format!(
+                "is in progress ({} of {} archives done), searches by detected objects and labels cover the analyzed archives only",
+                summary.analyzed_archives, summary.archives
+            )
+        } else {
+            "is complete".to_owned()
+        };
+        lines.push(format!(
+            "Background analysis ({}) {progress}.",
+            summary.stages.join(", ")
+        ));
+    }
+    if offered("photo_text_search") && CONFIG.full_text.enabled {
+        lines.push(
+            "photo_text_search ranks photos by file name, description, labels, camera and date."
+                .to_owned(),
+        );
+    }
+    if !IC.names().is_empty() {
+        lines.push("Clients bound to a named collection see only its photos, the numbers above are of the default collection.".to_owned());
+    }
+    lines.join("\n")
+}
+
 pub async fn start_server() -> SdkResult<()> {
     // STEP 1: Define server details and capabilities
     let server_details = InitializeResult {
@@ -27,25 +89,16 @@ pub async fn start_server() -> SdkResult<()> {
             title: Some("PhotoTool Organizer, Insight helper".to_string()),
         },
         capabilities: ServerCapabilities {
-            resources: Some(ServerCapabilitiesResources { list_changed: None, subscribe: Some(false) }),
+            resources: Some(ServerCapabilitiesResources {
+                list_changed: None,
+                subscribe: Some(false),
+            }),
             // indicates that server support mcp tools
             tools: Some(ServerCapabilitiesTools { list_changed: None }),
             ..Default::default() // Using default values for other fields
         },
         meta: None,
-        instructions: Some(
-            vec![
-                "This server provides help on deeper understanding of the photo collection we have in our zip files.",
-                "It provides methods for listing of photos, describing exif tags of photos, searching of photos by name, exif tags or by year and month.",
-                "Most responses contain pagination to help browse on results (next_offset, next_limit). If next_offset is not present or null, there are no more pages.",
-                "Image file description contains zip_file - that is the file on the file system, image_file - \
-the file inside the zip archive and image_index_in_zip which describes the \
-archive index number in the zip (for fast extraction).",
-                "There are also helpers on viewing photos that send the ImageContent (base64 \
-encoded). Those methods do not have pagination but offset and limit can be used and derived from non-view methods.",
-            ]
-            .join("\n"),
-        ),
+        instructions: Some(instructions()),
         protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
     };
 
@@ -0,0 +1,118 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rust_mcp_sdk::McpServer;
+use rust_mcp_sdk::schema::{
+    CallToolRequestParams, ElicitRequestParams, ElicitRequestedSchema, ElicitResult,
+    ElicitResultAction, ElicitResultContent, EnumSchema,
+};
+
+use crate::core::name_folding::NameFolding;
+use crate::{CONFIG, IC};
+
+/// Tools searching photos by a name, they can be narrowed down to one archive
+const NAME_TOOLS: [&str; 2] = ["photo_search_by_name", "photo_view_by_name"];
+/// Archives offered to choose from, the ones with most matches
+const MAX_CHOICES: usize = 20;
+/// Choice keeping the search over all archives
+const ALL_ARCHIVES: &str = "*";
+
+/// Asks the user which archive to search when a name search without an archive matches
+/// more photos than `tools.elicit_above` in several archives, instead of returning the first
+/// page of all of them. The chosen archive is set as `zip_file_name` of the call. Clients
+/// without elicitation support, declined requests and failures leave the call as it is.
+pub async fn refine(
+    runtime: &Arc<dyn McpServer>,
+    params: &mut CallToolRequestParams,
+    collection: Option<&str>,
+) {
+    let threshold = CONFIG.tools.elicit_above;
+    if threshold == 0 || !NAME_TOOLS.contains(&params.name.as_str()) {
+        return;
+    }
+    let supported = runtime
+        .client_info()
+        .is_some_and(|info| info.capabilities.elicitation.is_some());
+    let Some(arguments) = params.arguments.as_mut().filter(|_| supported) else {
+        return;
+    };
+    if arguments
+        .get("zip_file_name")
+        .is_some_and(|zip| !zip.is_null())
+    {
+        return;
+    }
+    let Some(file_name) = arguments.get("file_name").and_then(|name| name.as_str()) else {
+        return;
+    };
+    let file_name = file_name.to_owned();
+    let Ok(mut archives) = IC.scoped(collection, || matches_by_archive(&file_name)) else {
+        return;
+    };
+    let total = archives.iter().map(|(_, count)| count).sum::<usize>();
+    if total <= threshold || archives.len() < 2 {
+        return;
+    }
+    archives.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let message = format!(
+        "\"{file_name}\" matches {total} photos in {} archives. Which archive should be searched?",
+        archives.len()
+    );
+    archives.truncate(MAX_CHOICES);
+    let (mut values, mut names) = (
+        vec![ALL_ARCHIVES.to_owned()],
+        vec!["All archives".to_owned()],
+    );
+    for (archive, count) in archives {
+        names.push(format!("{archive} ({count} photos)"));
+        values.push(archive);
+    }
+    let archive_schema = EnumSchema::new(
+        values,
+        names,
+        Some("Archive the photos are searched in".to_owned()),
+        Some("Archive".to_owned()),
+    );
+    let request = ElicitRequestParams {
+        message,
+        requested_schema: ElicitRequestedSchema::new(
+            HashMap::from([("archive".to_owned(), archive_schema.into())]),
+            vec!["archive".to_owned()],
+        ),
+    };
+    match runtime.request_elicitation(request).await {
+        Ok(ElicitResult {
+            action: ElicitResultAction::Accept,
+            content: Some(content),
+            ..
+        }) => {
+            if let Some(ElicitResultContent::String(archive)) = content.get("archive") {
+                if archive != ALL_ARCHIVES {
+                    tracing::info!("Search of {file_name} narrowed down to {archive}");
+                    arguments.insert("zip_file_name".to_owned(), archive.clone().into());
+                }
+            }
+        }
+        Ok(result) => tracing::info!("Refinement of {file_name} search: {}", result.action),
+        Err(e) => tracing::warn!("can't ask for the archive of {file_name} search: {e}"),
+    }
+}
+
+// Archive => number of photos whose name matches, hidden photos and photos in the trash are
+// left out as the tools do by default
+fn matches_by_archive(file_name: &String) -> Vec<(String, usize)> {
+    let (infos, _) = IC.search_image_by_name(
+        file_name,
+        &None,
+        0,
+        usize::MAX,
+        false,
+        false,
+        false,
+        &NameFolding::with(None, &None),
+    );
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for info in infos {
+        *counts.entry(info.zip_file_name.clone()).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
@@ -1 +1,3 @@
+pub mod elicitation;
+pub mod output_schema;
 pub mod photo;
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use rust_mcp_sdk::schema::{CallToolResult, ContentBlock, Tool, ToolOutputSchema};
+use serde_json::{Map, Value, json};
+
+use crate::core::features;
+use crate::core::timings::StageTiming;
+use crate::tools::photo::PhotoTools;
+use crate::{CONFIG, TRANSLATIONS};
+
+/// Shape of the `result` member of the JSON envelope
+#[derive(Debug, Clone, Copy)]
+enum ResultKind {
+    Array,
+    Object,
+}
+
+/// JSON envelope a tool returns as its first text content
+#[derive(Debug, Clone, Copy)]
+struct Envelope {
+    query: bool,
+    result: ResultKind,
+    pagination: bool,
+    truncated: bool,
+}
+
+const fn envelope(query: bool, result: ResultKind, pagination: bool, truncated: bool) -> Envelope {
+    Envelope {
+        query,
+        result,
+        pagination,
+        truncated,
+    }
+}
+
+const LIST: Envelope = envelope(false, ResultKind::Array, true, false);
+const SEARCH: Envelope = envelope(true, ResultKind::Array, true, false);
+const QUERY: Envelope = envelope(true, ResultKind::Object, false, false);
+const REPORT: Envelope = envelope(false, ResultKind::Object, false, false);
+const IMAGES: Envelope = envelope(true, ResultKind::Object, false, true);
+
+/// Envelopes of tools returning JSON, photo view tools return images only and have none
+const TOOL_OUTPUTS: [(&str, Envelope); 54] = [
+    ("list_all_photos", LIST),
+    (
+        "photo_exif_tags",
+        envelope(false, ResultKind::Array, false, false),
+    ),
+    ("photo_exif_search_tags", SEARCH),
+    ("photo_search_by_name", SEARCH),
+    ("photo_search_by_year_month", SEARCH),
+    ("photo_exif_info", SEARCH),
+    (
+        "photo_object_detection",
+        envelope(true, ResultKind::Array, true, true),
+    ),
+    ("photo_detect_custom", SEARCH),
+    ("photo_decode_barcodes", SEARCH),
+    (
+        "photo_detection_models",
+        envelope(false, ResultKind::Array, false, false),
+    ),
+    ("photo_search_by_object", SEARCH),
+    ("photo_search_by_label", SEARCH),
+    ("photo_search_by_people_count", SEARCH),
+    ("photo_search_portraits", SEARCH),
+    ("photo_stats_summary", REPORT),
+    ("photo_stats_by_year", REPORT),
+    ("photo_settings_insights", QUERY),
+    ("photo_exposure_stats", QUERY),
+    (
+        "photo_shutter_counts",
+        envelope(true, ResultKind::Array, false, false),
+    ),
+    ("photo_storage_report", REPORT),
+    (
+        "photo_archive_covers",
+        envelope(false, ResultKind::Array, false, true),
+    ),
+    ("photo_cache_stats", REPORT),
+    ("photo_index_health", REPORT),
+    ("photo_export_catalog", QUERY),
+    ("photo_export_bundle", REPORT),
+    ("photo_import_bundle", REPORT),
+    ("photo_backup_derived", REPORT),
+    ("photo_restore_derived", REPORT),
+    ("photo_cache_cleanup", REPORT),
+    (
+        "photo_calendar",
+        envelope(true, ResultKind::Array, false, true),
+    ),
+    ("photo_period_summary", IMAGES),
+    ("photo_year_in_review", IMAGES),
+    ("photo_story_payload", QUERY),
+    (
+        "photo_find_gaps",
+        envelope(true, ResultKind::Array, false, false),
+    ),
+    (
+        "photo_manifest_coverage",
+        envelope(true, ResultKind::Object, true, false),
+    ),
+    ("photo_render_slideshow", QUERY),
+    ("photo_export_xmp", QUERY),
+    ("photo_transform", QUERY),
+    ("photo_benchmark", QUERY),
+    (
+        "photo_highlights",
+        envelope(true, ResultKind::Array, false, false),
+    ),
+    (
+        "photo_subject_timeline",
+        envelope(true, ResultKind::Array, false, false),
+    ),
+    ("photo_compare", QUERY),
+    ("photo_text_search", SEARCH),
+    ("photo_federated_search", SEARCH),
+    ("photo_query_plan", QUERY),
+    ("photo_describe_tools", REPORT),
+    ("photo_set_metadata", QUERY),
+    ("photo_shift_time", QUERY),
+    ("photo_hide", QUERY),
+    ("photo_list_hidden", LIST),
+    ("photo_selection_add", REPORT),
+    ("photo_selection_remove", REPORT),
+    ("photo_selection_list", LIST),
+    ("photo_selection_clear", REPORT),
+];
+
+/// Tools returning image content besides (or instead of) the JSON envelope
+const IMAGE_TOOLS: [&str; 7] = [
+    "photo_view_by_name",
+    "photo_view_by_year_month",
+    "photo_calendar",
+    "photo_archive_covers",
+    "photo_period_summary",
+    "photo_year_in_review",
+    "photo_compare",
+];
+
+fn property(value: Value) -> Map<String, Value> {
+    value.as_object().cloned().unwrap_or_default()
+}
+
+fn pagination_schema() -> Map<String, Value> {
+    property(json!({
+        "type": "object",
+        "description": "Page of the results, request the next page with next_offset and next_limit",
+        "properties": {
+            "offset": {"type": "integer"},
+            "limit": {"type": "integer"},
+            "total": {"type": "integer"},
+            "next_offset": {"type": ["integer", "null"]},
+            "next_limit": {"type": "integer"},
+        },
+    }))
+}
+
+// Reports with their own top level members
+fn custom_properties(tool_name: &str) -> Option<HashMap<String, Map<String, Value>>> {
+    let properties = match tool_name {
+        "photo_stats_summary" => json!({
+            "camera_model_photo_count": {"type": "object", "additionalProperties": {"type": "integer"}},
+            "lens_model_photo_count": {"type": "object", "additionalProperties": {"type": "integer"}},
+            "years_range": {"type": "array", "items": {"type": "integer"}},
+            "total_photos": {"type": "integer"},
+        }),
+        "photo_stats_by_year" => json!({
+            "years": {"type": "object", "description": "Year => photo count, per month counts and cameras"},
+            "total": {"type": "integer"},
+        }),
+        "photo_exif_tags" => json!({
+            "result": {"type": "array", "items": {"type": "object"}},
+            "next_cursor": {"type": ["string", "null"], "description": "Cursor of the next page, null on the last page"},
+        }),
+        "photo_detection_models" => json!({
+            "result": {"type": "array", "items": {"type": "object"}},
+            "default_model": {"type": "string"},
+        }),
+        _ => return None,
+    };
+    Some(
+        property(properties)
+            .into_iter()
+            .map(|(name, schema)| (name, property(schema)))
+            .collect(),
+    )
+}
+
+/// Output schema of the tool's JSON envelope, `None` for tools returning images only
+pub fn output_schema(tool_name: &str) -> Option<ToolOutputSchema> {
+    let (_, envelope) = TOOL_OUTPUTS.iter().find(|(name, _)| *name == tool_name)?;
+    if let Some(properties) = custom_properties(tool_name) {
+        let required = properties.keys().cloned().collect();
+        return Some(ToolOutputSchema::new(required, Some(properties)));
+    }
+    let mut properties = HashMap::new();
+    let mut required = vec!["result".to_owned()];
+    let result = match envelope.result {
+        ResultKind::Array => json!({"type": "array", "items": {"type": "object"}}),
+        ResultKind::Object => json!({"type": "object"}),
+    };
+    properties.insert("result".to_owned(), property(result));
+    if envelope.query {
+        properties.insert(
+            "query".to_owned(),
+            property(
+                json!({"type": "object", "description": "Arguments the result was computed for"}),
+            ),
+        );
+        required.push("query".to_owned());
+    }
+    if envelope.pagination {
+        properties.insert("pagination".to_owned(), pagination_schema());
+        required.push("pagination".to_owned());
+        properties.insert(
+            "preview".to_owned(),
+            property(json!({"type": ["object", "null"], "description": "Set in preview mode, the result is a sample spread evenly over the time taken"})),
+        );
+    }
+    if envelope.truncated {
+        properties.insert(
+            "truncated".to_owned(),
+            property(json!({"type": "boolean", "description": "Set when the call ran out of time and returns partial results"})),
+        );
+    }
+    Some(ToolOutputSchema::new(required, Some(properties)))
+}
+
+/// Argument of every tool asking for the time spent in the stages of the call
+pub const DEBUG_TIMINGS: &str = "debug_timings";
+
+/// Tool listing with output schemas, tools of features not compiled in and tools disabled
+/// by the configuration are left out
+pub fn tools() -> Vec<Tool> {
+    PhotoTools::tools()
+        .into_iter()
+        .filter(|tool| features::missing_for_tool(&tool.name).is_none())
+        .filter(|tool| !CONFIG.tools.disabled(&tool.name))
+        .map(|mut tool| {
+            tool.output_schema = output_schema(&tool.name);
+            tool.input_schema
+                .properties
+                .get_or_insert_with(HashMap::new)
+                .insert(
+                    DEBUG_TIMINGS.to_owned(),
+                    property(json!({"type": "boolean", "description": "Optional, reports time spent in the stages of the call (search, zip_open, extract, decode, resize, encode, inference) in the response meta"})),
+                );
+            tool
+        })
+        .collect()
+}
+
+/// Adds the timings of the call to the response meta, under "timings"
+pub fn with_timings(
+    mut result: CallToolResult,
+    total_ms: f64,
+    stages: Vec<StageTiming>,
+) -> CallToolResult {
+    result.meta.get_or_insert_with(Map::new).insert(
+        "timings".to_owned(),
+        json!({"total_ms": total_ms, "stages": stages}),
+    );
+    result
+}
+
+pub fn returns_images(tool_name: &str) -> bool {
+    IMAGE_TOOLS.contains(&tool_name)
+}
+
+/// Copies the JSON envelope (first text content) into structured content of tools with an
+/// output schema, clients validate structured content against the schema
+pub fn with_structured_content(tool_name: &str, mut result: CallToolResult) -> CallToolResult {
+    if output_schema(tool_name).is_none() || result.structured_content.is_some() {
+        return result;
+    }
+    let envelope = result.content.iter().find_map(|content| match content {
+        ContentBlock::TextContent(text) => serde_json::from_str::<Value>(&text.text).ok(),
+        _ => None,
+    });
+    if let Some(Value::Object(envelope)) = envelope {
+        result.structured_content = Some(envelope);
+    }
+    result
+}
+
+/// Translates class names and labels of JSON text contents into the response language
+pub fn translated(mut result: CallToolResult) -> CallToolResult {
+    if TRANSLATIONS.is_empty() {
+        return result;
+    }
+    for content in result.content.iter_mut() {
+        if let ContentBlock::TextContent(text) = content
+            && let Ok(mut envelope) = serde_json::from_str::<Value>(&text.text)
+        {
+            TRANSLATIONS.translate_response(&mut envelope);
+            text.text = envelope.to_string();
+        }
+    }
+    result
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use rust_mcp_sdk::schema::ImageContent;
+use rust_mcp_sdk::schema::{BlobResourceContents, EmbeddedResource, ImageContent};
 use rust_mcp_sdk::schema::{CallToolResult, TextContent, schema_utils::CallToolError};
 use rust_mcp_sdk::{
     macros::{JsonSchema, mcp_tool},
@@ -8,13 +8,194 @@ use rust_mcp_sdk::{
 };
 use serde::Serialize;
 
-use crate::IC;
-use crate::core::exif::ExifInfo;
+use crate::core::backup;
+use crate::core::benchmark::{self, BenchmarkOptions};
+use crate::core::bundle;
+use crate::core::cancel::CancellationToken;
+use crate::core::catalog::{self, CatalogFormat};
+use crate::core::cleanup::{self, CleanupOptions};
+use crate::core::collections::DEFAULT_COLLECTION;
+use crate::core::color_label::ColorLabel;
+use crate::core::compare;
+use crate::core::coverage;
+use crate::core::digest;
+use crate::core::error::PhotoInsightError;
+use crate::core::exif::{self, ExifInfo, GeoLocation};
+use crate::core::highlights::MAX_RATING;
+use crate::core::image::{self, Transform};
+use crate::core::image_cache::{
+    Collapse, Collapsed, ExifResult, LabelFilter, PhotoInfo, PhotoResult, PreviewSample,
+    Representative, TimeBucket, TimeGroup,
+};
+use crate::core::insights::{Scenario, exposure_analytics, settings_insights, shutter_counts};
+use crate::core::name_folding::NameFolding;
+use crate::core::overrides::{
+    MetadataOverride, normalize_date_time, parse_date_time, shift_date_time,
+};
+use crate::core::query_cache;
+use crate::core::query_plan;
+use crate::core::slideshow::{self, SlideshowFormat, SlideshowOptions};
+use crate::core::watermark;
+use crate::core::xmp::{self, ExportEntry, SidecarNaming};
+use crate::resources::photo::PhotoResource;
+use crate::tools::output_schema;
+use crate::{CLASS_ALIASES, IC, MODELS, SELECTION, TRANSLATIONS};
 
 const MAX_PHOTO_VIEW_SEARCH_LIMIT: u32 = 50;
 const MAX_PHOTO_FILES_SEARCH_LIMIT: u32 = 10000;
 const MAX_PHOTO_EXIF_SEARCH_LIMIT: u32 = 1000;
+const MAX_PHOTO_TEXT_SEARCH_LIMIT: u32 = 1000;
 const MAX_PHOTO_YOLO_ANALYZE_LIMIT: u32 = 50;
+const MAX_CUSTOM_DETECTION_CLASSES: usize = 20;
+const MAX_CALENDAR_THUMBNAILS: usize = 240;
+const MAX_TIME_SHIFT_SAMPLE: usize = 100;
+const DEFAULT_SUMMARY_THUMBNAILS: u32 = 6;
+const MAX_SUMMARY_THUMBNAILS: u32 = 20;
+const DEFAULT_STORY_PHOTOS: u32 = 50;
+const MAX_STORY_PHOTOS: u32 = 200;
+const DEFAULT_GAP_DAYS: u32 = 30;
+const DEFAULT_SLIDESHOW_WIDTH: u32 = 480;
+const MAX_SLIDESHOW_WIDTH: u32 = 1280;
+const MAX_SLIDESHOW_PHOTOS: usize = 50;
+const MAX_XMP_EXPORT_PHOTOS: usize = 1000;
+const DEFAULT_TRANSFORM_SIZE: u32 = 1024;
+const MAX_TRANSFORM_SIZE: u32 = 4096;
+const DEFAULT_BENCHMARK_SAMPLES: u32 = 20;
+const MAX_BENCHMARK_SAMPLES: u32 = 200;
+const DEFAULT_BENCHMARK_REPEAT: u32 = 3;
+const MAX_BENCHMARK_REPEAT: u32 = 20;
+const DEFAULT_REVIEW_EVENTS: u32 = 5;
+const MAX_REVIEW_EVENTS: u32 = 10;
+const MAX_HIGHLIGHTS: u32 = 50;
+const REVIEW_CANDIDATES_PER_MONTH: usize = 3;
+const COLLAGE_COLUMNS: u32 = 4;
+const COLLAGE_CELL: u32 = 256;
+
+// Whether view tools return resource links instead of the image data
+fn returns_resources(return_as: &Option<String>) -> Result<bool, CallToolError> {
+    match return_as.as_deref() {
+        None | Some("image") => Ok(false),
+        Some("resource") => Ok(true),
+        Some(other) => Err(CallToolError::from_message(format!(
+            "Invalid return_as: {other}, expected image or resource"
+        ))),
+    }
+}
+
+fn time_group(group_by: &Option<String>) -> Result<Option<TimeGroup>, CallToolError> {
+    group_by
+        .as_deref()
+        .map(TimeGroup::parse)
+        .transpose()
+        .map_err(|e| CallToolError::from_message(e.to_string()))
+}
+
+fn collapse_of(collapse: &Option<String>) -> Result<Option<Collapse>, CallToolError> {
+    collapse
+        .as_deref()
+        .map(Collapse::parse)
+        .transpose()
+        .map_err(|e| CallToolError::from_message(e.to_string()))
+}
+
+// Server path named by the client, kept in the caller's collection
+fn client_path(path: &str) -> Result<String, CallToolError> {
+    IC.client_path(path)
+        .map_err(|e| CallToolError::from_message(e.to_string()))
+}
+
+/// Page of a search tool with its preview, grouping and collapsing options
+struct SearchPage {
+    offset: usize,
+    limit: usize,
+    /// Results taken from offset, preview and grouping take all of them
+    page: usize,
+    preview: bool,
+    group: Option<TimeGroup>,
+    collapse: Option<Collapse>,
+}
+
+impl SearchPage {
+    fn new(
+        offset: u32,
+        limit: u32,
+        preview_mode: Option<bool>,
+        group_by: &Option<String>,
+        collapse: &Option<String>,
+    ) -> Result<Self, CallToolError> {
+        let preview = preview_mode.unwrap_or(false);
+        let group = time_group(group_by)?;
+        let limit = limit as usize;
+        // preview and grouping take all results instead of the page at offset
+        let (offset, page) = if preview || group.is_some() {
+            (0, usize::MAX)
+        } else {
+            (offset as usize, limit)
+        };
+        Ok(Self {
+            offset,
+            limit,
+            page,
+            preview,
+            group,
+            collapse: collapse_of(collapse)?,
+        })
+    }
+
+    // Offset and limit of the search, collapsing takes all results and pages the groups instead
+    fn window(&self) -> (usize, usize) {
+        match self.collapse {
+            Some(_) => (0, usize::MAX),
+            None => (self.offset, self.page),
+        }
+    }
+
+    // Copies are collapsed into their duplicate groups rather than left out
+    fn include_duplicates(&self, include_duplicates: Option<bool>) -> bool {
+        include_duplicates.unwrap_or(false) || self.collapse == Some(Collapse::Duplicates)
+    }
+
+    /// Collapses, samples and groups the search results: the page, the time buckets, the
+    /// preview and the pagination. Previews and buckets cover all results, there is no next
+    /// page of them.
+    fn paginate<T: PhotoResult + Serialize>(
+        &self,
+        results: Vec<T>,
+        total: usize,
+    ) -> (
+        Vec<Collapsed<T>>,
+        Option<Vec<TimeBucket>>,
+        Option<PreviewSample>,
+        serde_json::Value,
+    ) {
+        let (results, total) = IC.collapse(results, total, self.collapse, self.offset, self.page);
+        let (results, preview) =
+            IC.preview_sample(results, self.limit, self.preview && self.group.is_none());
+        let buckets = self.group.map(|group| IC.group_by_time(&results, group));
+        let next_offset = (!self.preview && buckets.is_none())
+            .then(|| self.offset + results.len())
+            .filter(|next_offset| *next_offset < total);
+        let pagination = serde_json::json!({
+            "offset": self.offset,
+            "limit": self.limit,
+            "total": total,
+            "next_offset": next_offset,
+            "next_limit": self.limit,
+        });
+        (results, buckets, preview, pagination)
+    }
+}
+
+// Search results, or their counts per time bucket when grouped
+fn result_or_buckets<T: Serialize>(
+    results: &[T],
+    buckets: Option<Vec<TimeBucket>>,
+) -> serde_json::Value {
+    match buckets {
+        Some(buckets) => serde_json::json!(buckets),
+        None => serde_json::json!(results),
+    }
+}
 
 #[mcp_tool(
     name = "list_all_photos",
@@ -28,27 +209,59 @@ pub struct ListAllPhotosTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
 }
 
 impl ListAllPhotosTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
-        let offset = self.offset as usize;
-        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
-        tracing::info!("list all images : offset: {offset} Limiting results to {limit}");
-        let (infos, total) = IC.list_all_images(offset, limit);
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        tracing::info!(
+            "list all images : offset: {} Limiting results to {}",
+            page.offset,
+            page.limit
+        );
+        let (infos, total) = IC.list_all_images(
+            from,
+            count,
+            self.include_hidden.unwrap_or(false),
+            page.include_duplicates(self.include_duplicates),
+            self.include_trash.unwrap_or(false),
+        );
 
-        let next_offset = offset + infos.len();
-        let next_limit = limit;
+        let (infos, buckets, preview_sample, pagination) = page.paginate(infos, total);
 
         let json_info = serde_json::json!({
-            "result": infos,
-            "pagination": {
-                "offset": offset,
-                "limit": limit,
-                "total": total,
-                "next_offset": if next_offset < total { Some(next_offset) } else { None },
-                "next_limit": next_limit,
-            },
+            "result": result_or_buckets(&infos, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
         });
 
         Ok(CallToolResult::text_content(vec![TextContent::from(
@@ -59,26 +272,54 @@ impl ListAllPhotosTool {
 
 #[mcp_tool(
     name = "photo_exif_tags",
-    description = "List searchable EXIF tags in the photo collection. You can use these tags with photo_exif_search_tags tool."
+    description = "List searchable EXIF tags in the photo collection with their type, operators and the most common values in the collection. Tags of analysis results and metadata overrides (label, rating, temperature) are listed once photos have them. You can use these tags with photo_exif_search_tags tool."
 )]
 #[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
-pub struct PhotoExifTagTool {}
+pub struct PhotoExifTagTool {
+    /// Optionally continue the listing after this tag, the next_cursor of the previous page
+    /// Example: "lens"
+    cursor: Option<String>,
+    /// Optionally limit number of tags returned
+    /// Example: 10
+    limit: Option<u32>,
+}
 impl PhotoExifTagTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
-        tracing::info!("photo_exif_tags (list supported exif tags");
+        tracing::info!(
+            "photo_exif_tags (list supported exif tags): cursor={:?}, limit={:?}",
+            self.cursor,
+            self.limit
+        );
+        let tags = IC.exif_tags().map_err(CallToolError::new)?;
+        // EXIF tags keep their place, dynamic tags (sorted by name) may come and go, a page
+        // continues after the place of the cursor tag even when it is no longer listed
+        let start = match &self.cursor {
+            Some(cursor) => tags
+                .iter()
+                .position(|tag| tag.name == cursor)
+                .map(|position| position + 1)
+                .or_else(|| {
+                    (!exif::SEARCH_TAGS.iter().any(|tag| tag.name == cursor)).then(|| {
+                        tags.iter()
+                            .position(|tag| {
+                                exif::DYNAMIC_TAGS.iter().any(|d| d.name == tag.name)
+                                    && tag.name > cursor.as_str()
+                            })
+                            .unwrap_or(tags.len())
+                    })
+                })
+                .ok_or_else(|| CallToolError::from_message(format!("Unknown cursor: {cursor}")))?,
+            None => 0,
+        };
+        let limit = self.limit.map_or(usize::MAX, |limit| limit as usize);
+        let page = &tags[start..start.saturating_add(limit).min(tags.len())];
+        let next_cursor = page
+            .last()
+            .filter(|_| start + page.len() < tags.len())
+            .map(|tag| tag.name);
         let json_info = serde_json::json!({
-            "result": [
-                {"name": "width", "type": "Integer", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "height", "type": "Integer", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "month", "type": "Integer", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "year", "type": "Integer", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "aperture", "type": "Float", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "focal_len", "type": "Float", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "iso", "type": "Float", "allowed_operators": ["==", ">", "<", ">=", "<=", "!="]},
-                {"name": "shutter_speed", "type": "Float", "allowed_operators": ["!=", "==", ">", "<", ">=", "<=", "!="]},
-                {"name": "lens", "type": "String", "allowed_operators": ["!=", "==", "contains", "starts_with", "ends_with"]},
-                {"name": "model", "type": "String", "allowed_operators": ["!=", "==", "contains", "starts_with", "ends_with"]},
-            ]
+            "result": page,
+            "next_cursor": next_cursor,
         });
 
         Ok(CallToolResult::text_content(vec![TextContent::from(
@@ -105,6 +346,28 @@ pub struct PhotoExifSearchTagTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
 }
 impl PhotoExifSearchTagTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
@@ -116,16 +379,33 @@ impl PhotoExifSearchTagTool {
             self.operator,
             self.value,
         );
-        let offset = self.offset as usize;
-        let limit = self.limit.min(MAX_PHOTO_EXIF_SEARCH_LIMIT) as usize;
-        tracing::info!("search image by EXIF tag : Limiting results to {limit}");
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_EXIF_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        tracing::info!(
+            "search image by EXIF tag : Limiting results to {}",
+            page.limit
+        );
         let (exifs, total) = IC
-            .search_image_by_exif_tags(&self.tag, &self.value, &self.operator, offset, limit)
+            .search_image_by_exif_tags(
+                &self.tag,
+                &self.value,
+                &self.operator,
+                from,
+                count,
+                self.include_hidden.unwrap_or(false),
+                page.include_duplicates(self.include_duplicates),
+                self.include_trash.unwrap_or(false),
+            )
             .map_err(|e| {
                 CallToolError::from_message(format!("Failed to search images by EXIF tag: {}", e))
             })?;
-        let next_offset = offset + exifs.len();
-        let next_limit = limit;
+        let (exifs, buckets, preview_sample, pagination) = page.paginate(exifs, total);
 
         let json_info = serde_json::json!({
             "query":{
@@ -133,14 +413,9 @@ impl PhotoExifSearchTagTool {
                 "value": self.value,
                 "operator": self.operator,
             },
-            "result": exifs,
-            "pagination": {
-                "offset": offset,
-                "limit": limit,
-                "total": total,
-                "next_offset": if next_offset < total { Some(next_offset) } else { None },
-                "next_limit": next_limit,
-            },
+            "result": result_or_buckets(&exifs, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
         });
 
         Ok(CallToolResult::text_content(vec![TextContent::from(
@@ -167,6 +442,40 @@ pub struct PhotoSearchByNameTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
+    /// Optionally ignore accents, "cafe" then matches "café" (defaults to the server
+    /// configuration)
+    /// Example: true
+    accent_insensitive: Option<bool>,
+    /// Optionally language of the file names for case-insensitive matching, e.g. "tr" for
+    /// Turkish dotted and dotless i
+    /// Example: "tr"
+    locale: Option<String>,
+    /// Optionally tolerate typos: return photos with names similar to file_name, best matches
+    /// first, each with its similarity score (1 for names containing file_name)
+    /// Example: true
+    fuzzy: Option<bool>,
 }
 impl PhotoSearchByNameTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
@@ -177,23 +486,57 @@ impl PhotoSearchByNameTool {
             self.offset,
             self.limit
         );
-        let offset = self.offset as usize;
-        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
-        tracing::info!("search image by name :  Limiting results to {limit}");
-        let (infos, total) =
-            IC.search_image_by_name(&self.file_name, &self.zip_file_name, offset, limit);
-        let next_offset = offset + infos.len();
-        let next_limit = limit;
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        tracing::info!("search image by name :  Limiting results to {}", page.limit);
+        let folding = NameFolding::with(self.accent_insensitive, &self.locale);
+        let include_hidden = self.include_hidden.unwrap_or(false);
+        let include_duplicates = page.include_duplicates(self.include_duplicates);
+        let include_trash = self.include_trash.unwrap_or(false);
+        if self.fuzzy.unwrap_or(false) {
+            let (matches, total) = IC.search_image_by_name_fuzzy(
+                &self.file_name,
+                &self.zip_file_name,
+                from,
+                count,
+                include_hidden,
+                include_duplicates,
+                include_trash,
+                &folding,
+            );
+            return self.respond(matches, total, &page);
+        }
+        let (infos, total) = IC.search_image_by_name(
+            &self.file_name,
+            &self.zip_file_name,
+            from,
+            count,
+            include_hidden,
+            include_duplicates,
+            include_trash,
+            &folding,
+        );
+        self.respond(infos, total, &page)
+    }
+
+    fn respond<T: PhotoResult + Serialize>(
+        &self,
+        infos: Vec<T>,
+        total: usize,
+        page: &SearchPage,
+    ) -> Result<CallToolResult, CallToolError> {
+        let (infos, buckets, preview_sample, pagination) = page.paginate(infos, total);
         let json_info = serde_json::json!({
-            "query": {"file" : self.file_name },
-            "result": infos,
-            "pagination": {
-                "offset": offset,
-                "limit": limit,
-                "total": total,
-                "next_offset": if next_offset < total { Some(next_offset) } else { None },
-                "next_limit": next_limit,
-            },
+            "query": {"file" : self.file_name, "fuzzy": self.fuzzy.unwrap_or(false) },
+            "result": result_or_buckets(&infos, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
         });
 
         Ok(CallToolResult::text_content(vec![TextContent::from(
@@ -218,6 +561,28 @@ pub struct PhotoSearchByYearMonthTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
 }
 impl PhotoSearchByYearMonthTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
@@ -228,25 +593,33 @@ impl PhotoSearchByYearMonthTool {
             self.offset,
             self.limit
         );
-        let offset = self.offset as usize;
-        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
-        tracing::info!("search image by name : Limiting results to {limit}");
-        let (infos, total) = IC.search_image_by_year_month(self.year, self.month, offset, limit);
-        let next_offset = offset + infos.len();
-        let next_limit = limit;
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        tracing::info!("search image by name : Limiting results to {}", page.limit);
+        let (infos, total) = IC.search_image_by_year_month(
+            self.year,
+            self.month,
+            from,
+            count,
+            self.include_hidden.unwrap_or(false),
+            page.include_duplicates(self.include_duplicates),
+            self.include_trash.unwrap_or(false),
+        );
+        let (infos, buckets, preview_sample, pagination) = page.paginate(infos, total);
         let json_info = serde_json::json!({
             "query": {
                 "year": self.year,
                 "month": self.month,
             },
-            "result":  infos,
-            "pagination": {
-                "offset": offset,
-                "limit": limit,
-                "total": total,
-                "next_offset": if next_offset < total { Some(next_offset) } else { None },
-                "next_limit": next_limit,
-            },
+            "result": result_or_buckets(&infos, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
         });
 
         Ok(CallToolResult::text_content(vec![TextContent::from(
@@ -257,7 +630,7 @@ impl PhotoSearchByYearMonthTool {
 
 #[mcp_tool(
     name = "photo_view_by_name",
-    description = "Accepts photo file name and returns photo image data"
+    description = "Accepts photo file name and returns photo image data. For live photos (has_motion in EXIF info) a frame of the motion part can be returned instead of the still"
 )]
 #[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
 pub struct PhotoViewByNameTool {
@@ -273,10 +646,24 @@ pub struct PhotoViewByNameTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return a frame of the motion part of live photos taken at the given second
+    /// instead of the still, photos without motion part are returned as stills
+    /// Example: 1.5
+    motion_frame: Option<f32>,
+    /// Optionally "resource" to return photo:// resource links (read them with resources/read)
+    /// instead of the image data, "image" by default
+    /// Example: "resource"
+    return_as: Option<String>,
 }
 
 impl PhotoViewByNameTool {
-    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
         tracing::info!(
             "photo view by name: name={}, zip={:?}, offset={}m limit={}",
             self.file_name,
@@ -287,30 +674,44 @@ impl PhotoViewByNameTool {
         let limit = self.limit.min(MAX_PHOTO_VIEW_SEARCH_LIMIT) as usize;
         tracing::info!("Limiting results to {}", limit);
         let offset = self.offset as usize;
-        let (infos, _) =
-            IC.search_image_by_name(&self.file_name, &self.zip_file_name, offset, limit);
-        let image_data = IC
-            .image_data(infos)
-            .map_err(|e| {
-                CallToolError::from_message(format!("Failed to extract image data: {}", e))
-            })?
-            .iter()
-            .map(|(file_name, mime, data)| {
-                ImageContent::new(
-                    base64::encode(data),
-                    mime.clone(),
-                    None,
-                    Some(
-                        serde_json::json!({"name":file_name})
-                            .as_object()
-                            .cloned()
-                            .unwrap(),
-                    ),
-                )
-            })
-            .collect();
+        let (infos, _) = IC.search_image_by_name(
+            &self.file_name,
+            &self.zip_file_name,
+            offset,
+            limit,
+            self.include_hidden.unwrap_or(false),
+            self.zip_file_name.is_some(),
+            self.include_trash.unwrap_or(false),
+            &NameFolding::configured(),
+        );
+        if returns_resources(&self.return_as)? {
+            let links = infos.into_iter().map(PhotoResource::link).collect();
+            return Ok(CallToolResult::resource_link(links));
+        }
+        let image_data = match self.motion_frame {
+            Some(at) => IC.motion_frames(infos, at, cancel),
+            None => IC.image_data(infos, cancel),
+        }
+        .and_then(watermark::watermarked)
+        .map_err(|e| CallToolError::from_message(format!("Failed to extract image data: {}", e)))?
+        .iter()
+        .map(|(file_name, mime, data)| {
+            ImageContent::new(
+                base64::encode(data),
+                mime.clone(),
+                None,
+                Some(IC.image_meta(file_name, data)),
+            )
+        })
+        .collect();
 
-        Ok(CallToolResult::image_content(image_data))
+        let mut result = CallToolResult::image_content(image_data);
+        if cancel.timed_out() {
+            result = result.add_content(
+                TextContent::from(serde_json::json!({"truncated": true}).to_string()).into(),
+            );
+        }
+        Ok(result)
     }
 }
 
@@ -330,10 +731,20 @@ pub struct PhotoViewByYearMonthTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally "resource" to return photo:// resource links (read them with resources/read)
+    /// instead of the image data, "image" by default
+    /// Example: "resource"
+    return_as: Option<String>,
 }
 
 impl PhotoViewByYearMonthTool {
-    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
         tracing::info!(
             "photo view by name: year={}, month={:?}, offset={}m limit={}",
             self.year,
@@ -344,9 +755,22 @@ impl PhotoViewByYearMonthTool {
         let limit = self.limit.min(MAX_PHOTO_VIEW_SEARCH_LIMIT) as usize;
         tracing::info!("Limiting results to {}", limit);
         let offset = self.offset as usize;
-        let (infos, _) = IC.search_image_by_year_month(self.year, self.month, offset, limit);
+        let (infos, _) = IC.search_image_by_year_month(
+            self.year,
+            self.month,
+            offset,
+            limit,
+            self.include_hidden.unwrap_or(false),
+            false,
+            self.include_trash.unwrap_or(false),
+        );
+        if returns_resources(&self.return_as)? {
+            let links = infos.iter().map(PhotoResource::link).collect();
+            return Ok(CallToolResult::resource_link(links));
+        }
         let image_data = IC
-            .image_data(infos)
+            .image_data(infos.iter().collect(), cancel)
+            .and_then(watermark::watermarked)
             .map_err(|e| {
                 CallToolError::from_message(format!("Failed to extract image data: {}", e))
             })?
@@ -356,29 +780,35 @@ impl PhotoViewByYearMonthTool {
                     base64::encode(data),
                     mime.clone(),
                     None,
-                    Some(
-                        serde_json::json!({"name":file_name})
-                            .as_object()
-                            .cloned()
-                            .unwrap(),
-                    ),
+                    Some(IC.image_meta(file_name, data)),
                 )
             })
             .collect();
 
-        Ok(CallToolResult::image_content(image_data))
+        let mut result = CallToolResult::image_content(image_data);
+        if cancel.timed_out() {
+            result = result.add_content(
+                TextContent::from(serde_json::json!({"truncated": true}).to_string()).into(),
+            );
+        }
+        Ok(result)
     }
 }
 
 #[mcp_tool(
     name = "photo_exif_info",
-    description = "Accepts photo file name and returns photo meta data (EXIF data) information (can match multiple files if partial name is given or if the photo is in multiple zip files)"
+    description = "Accepts photo file name and returns photo meta data (EXIF data) information (can match multiple files if partial name is given or if the photo is in multiple zip files). Alternatively accepts photo_ids of photos returned by previous calls and returns EXIF of exactly those photos without searching"
 )]
 #[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
 pub struct PhotoExifTool {
     /// Photo file name. Can be partial, e.g. "IMG_1234" will match "IMG_1234.jpg", "IMG_1234 (1).jpg", etc.
+    /// Required unless photo_ids are given
     /// Example: "IMG_1234.jpg"
-    file_name: String,
+    file_name: Option<String>,
+    /// Optionally photo IDs (photo_id of returned photos) or "zip_file_name|photo_file_name|photo_index_in_zip"
+    /// keys of the photos, EXIF of these photos is returned in the given order, file_name is ignored
+    /// Example: ["3f1a9c0e7b2d4e61"]
+    photo_ids: Option<Vec<String>>,
     /// Optionally you can provide zip file name to restrict the search on a given zip file
     /// Example: takeout-20230906T142745Z-050.zip
     zip_file_name: Option<String>,
@@ -388,13 +818,20 @@ pub struct PhotoExifTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
 }
 
 impl PhotoExifTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
         tracing::info!(
-            "exif tool: file_name={}, zip_file_name={:?}, offset={}, limit={}",
+            "exif tool: file_name={:?}, photo_ids={:?}, zip_file_name={:?}, offset={}, limit={}",
             self.file_name,
+            self.photo_ids,
             self.zip_file_name,
             self.offset,
             self.limit
@@ -402,8 +839,22 @@ impl PhotoExifTool {
         let offset = self.offset as usize;
         let limit = self.limit.min(MAX_PHOTO_EXIF_SEARCH_LIMIT) as usize;
         tracing::info!("Limiting results to {}", limit);
-        let (infos, total) =
-            IC.search_image_by_name(&self.file_name, &self.zip_file_name, offset, limit);
+        if let Some(photo_ids) = &self.photo_ids {
+            return self.exif_of_photos(photo_ids, offset, limit);
+        }
+        let file_name = self.file_name.as_ref().ok_or_else(|| {
+            CallToolError::from_message("Provide file_name or photo_ids".to_owned())
+        })?;
+        let (infos, total) = IC.search_image_by_name(
+            file_name,
+            &self.zip_file_name,
+            offset,
+            limit,
+            self.include_hidden.unwrap_or(false),
+            self.zip_file_name.is_some(),
+            self.include_trash.unwrap_or(false),
+            &NameFolding::configured(),
+        );
         let info_len = infos.len();
         let exifs = IC.exif_info(infos).map_err(|e| {
             CallToolError::from_message(format!("Failed to extract EXIF info: {}", e))
@@ -430,8 +881,42 @@ impl PhotoExifTool {
             json_info.to_string(),
         )]))
     }
-}
 
+    // EXIF of the listed photos, a page of them when more than limit are listed
+    fn exif_of_photos(
+        &self,
+        photo_ids: &[String],
+        offset: usize,
+        limit: usize,
+    ) -> Result<CallToolResult, CallToolError> {
+        let total = photo_ids.len();
+        let page = &photo_ids[offset.min(total)..offset.saturating_add(limit).min(total)];
+        let infos = IC
+            .resolve_photos(page)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let exifs = IC.exif_info(infos.iter().collect()).map_err(|e| {
+            CallToolError::from_message(format!("Failed to extract EXIF info: {}", e))
+        })?;
+        let next_offset = offset + page.len();
+        let json_info = serde_json::json!({
+            "query": {
+                "photo_ids": photo_ids,
+            },
+            "result": exifs,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": limit,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
 #[mcp_tool(
     name = "photo_object_detection",
     description = "Accepts photo file name and returns object detections using YOLOv8 (returns vector of images provided, each contains vector of detected objects)"
@@ -450,10 +935,19 @@ pub struct PhotoObjectDetectionTool {
     /// Limit number of results returned
     /// Example: 5
     limit: u32,
+    /// Optionally you can provide detection model id (see photo_detection_models), default model is used otherwise
+    /// Example: yolov8-default
+    model: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
 }
 
 impl PhotoObjectDetectionTool {
-    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
         tracing::info!(
             "photo object detection tool: file_name={}, zip_file_name={:?}, offset={}, limit={}",
             self.file_name,
@@ -464,10 +958,21 @@ impl PhotoObjectDetectionTool {
         let offset = self.offset as usize;
         let limit = self.limit.min(MAX_PHOTO_YOLO_ANALYZE_LIMIT) as usize;
         tracing::info!("Limiting results to {}", limit);
-        let (infos, total) =
-            IC.search_image_by_name(&self.file_name, &self.zip_file_name, offset, limit);
+        let model = MODELS
+            .resolve(&self.model)
+            .map_err(|e| CallToolError::from_message(format!("Invalid model: {}", e)))?;
+        let (infos, total) = IC.search_image_by_name(
+            &self.file_name,
+            &self.zip_file_name,
+            offset,
+            limit,
+            self.include_hidden.unwrap_or(false),
+            self.zip_file_name.is_some(),
+            self.include_trash.unwrap_or(false),
+            &NameFolding::configured(),
+        );
         let info_len = infos.len();
-        let object_detections = IC.yolo_v8_analysis(infos).map_err(|e| {
+        let object_detections = IC.yolo_v8_analysis(infos, model, cancel).map_err(|e| {
             CallToolError::from_message(format!("Failed to analyze images using YOLOv8: {}", e))
         })?;
 
@@ -476,8 +981,10 @@ impl PhotoObjectDetectionTool {
         let json_info = serde_json::json!({
             "query":{
                 "file_name": self.file_name,
+                "model": model.id(),
             },
             "result": object_detections,
+            "truncated": cancel.timed_out(),
             "pagination": {
                 "offset": offset,
                 "limit": limit,
@@ -494,46 +1001,94 @@ impl PhotoObjectDetectionTool {
 }
 
 #[mcp_tool(
-    name = "photo_stats_summary",
-    description = "Returns global summary statistics"
+    name = "photo_detect_custom",
+    description = "Accepts photo file name and arbitrary text classes (e.g. \"red bicycle\", \"wedding cake\") and returns bounding boxes found by an open-vocabulary detector (returns vector of images provided, each contains vector of detected objects). Slow, use small limit."
 )]
 #[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
-pub struct PhotoGlobalSummaryTool {}
+pub struct PhotoDetectCustomTool {
+    /// Photo file name. Can be partial, e.g. "IMG_1234" will match "IMG_1234.jpg", "IMG_1234 (1).jpg", etc.
+    /// Example: "IMG_1234.jpg"
+    file_name: String,
+    /// Optionally you can provide zip file name to restrict the search on a given zip file
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: Option<String>,
+    /// Text classes to detect
+    /// Example: ["red bicycle", "wedding cake"]
+    classes: Vec<String>,
+    /// Optional minimal confidence of the detection (0.0 - 1.0)
+    /// Example: 0.2
+    min_confidence: Option<f32>,
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+}
 
-impl PhotoGlobalSummaryTool {
+impl PhotoDetectCustomTool {
     pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
-        tracing::info!("photo global stats");
-
-        let mut years_range = vec![];
-        let mut all_years = IC
-            .by_year_month
-            .keys()
-            .filter(|year| **year > 0) // in case we don't know the year, we assign 0
-            .cloned()
-            .collect::<Vec<u32>>();
-        all_years.sort();
-        let l = all_years.len();
-        if all_years.len() > 0 {
-            years_range.push(all_years[0]);
-            years_range.push(all_years[l - 1]);
-        }
-
-        let exifs = IC.exif_cache.values().cloned().collect::<Vec<ExifInfo>>();
-        let mut camera_model_counts = HashMap::new();
-        let mut lens_model_counts = HashMap::new();
-        for exif in exifs.iter() {
-            *camera_model_counts.entry(exif.model.as_str()).or_insert(0) += 1;
-            *lens_model_counts.entry(exif.lens.as_str()).or_insert(0) += 1;
+        tracing::info!(
+            "photo detect custom: file_name={}, zip_file_name={:?}, classes={:?}, offset={}, limit={}",
+            self.file_name,
+            self.zip_file_name,
+            self.classes,
+            self.offset,
+            self.limit
+        );
+        if self.classes.is_empty() || self.classes.len() > MAX_CUSTOM_DETECTION_CLASSES {
+            return Err(CallToolError::from_message(format!(
+                "Provide 1 to {MAX_CUSTOM_DETECTION_CLASSES} classes"
+            )));
         }
-        let total = IC.images.len();
+        let offset = self.offset as usize;
+        let limit = self.limit.min(MAX_PHOTO_YOLO_ANALYZE_LIMIT) as usize;
+        tracing::info!("Limiting results to {}", limit);
+        let (infos, total) = IC.search_image_by_name(
+            &self.file_name,
+            &self.zip_file_name,
+            offset,
+            limit,
+            self.include_hidden.unwrap_or(false),
+            self.zip_file_name.is_some(),
+            self.include_trash.unwrap_or(false),
+            &NameFolding::configured(),
+        );
+        let info_len = infos.len();
+        let classes = self
+            .classes
+            .iter()
+            .map(|class| TRANSLATIONS.untranslate(class))
+            .collect::<Vec<_>>();
+        let object_detections = IC
+            .open_vocabulary_analysis(infos, &classes, self.min_confidence.unwrap_or(0.0))
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to run custom detection: {}", e))
+            })?;
 
+        let next_offset = offset + info_len;
+        let next_limit = limit;
         let json_info = serde_json::json!({
-            "camera_model_photo_count": camera_model_counts,
-            "lens_model_photo_count": lens_model_counts,
-            "years_range": years_range,
-            "total_photos": total
-        }
-        );
+            "query":{
+                "file_name": self.file_name,
+                "classes": self.classes,
+                "min_confidence": self.min_confidence,
+            },
+            "result": object_detections,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": next_limit,
+            },
+        });
 
         Ok(CallToolResult::text_content(vec![TextContent::from(
             json_info.to_string(),
@@ -542,41 +1097,644 @@ impl PhotoGlobalSummaryTool {
 }
 
 #[mcp_tool(
-    name = "photo_stats_by_year",
-    description = "Returns summary statistics based on year/month search (year aggregation API)"
+    name = "photo_decode_barcodes",
+    description = "Scans photos for QR codes and barcodes and returns the decoded payloads (e.g. tickets, boarding passes, Wi-Fi QR codes). Photos are given by photo IDs, by file name or by the year and month taken. Returns the scanned photos with their barcodes and reference to the next page (next_offset, next_limit). Slow, use small limit. Requires detection.barcode_decoder in the configuration"
 )]
 #[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
-pub struct PhotoStatsByYearTool {
-    /// Start year for statistics
-    /// Example: 2004
-    year_start: u32,
-    /// End year for statistics
-    /// Example: 2025
-    year_end: u32,
+pub struct PhotoDecodeBarcodesTool {
+    /// Optionally photo IDs (photo_id of returned photos) or "zip_file_name|photo_file_name|photo_index_in_zip"
+    /// keys of the photos to scan, file_name, year and month are ignored
+    /// Example: ["3f1a9c0e7b2d4e61"]
+    photo_ids: Option<Vec<String>>,
+    /// Optionally photo file name, can be partial
+    /// Example: "IMG_1234"
+    file_name: Option<String>,
+    /// Optionally you can provide zip file name to restrict the search by file name on a given zip file
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: Option<String>,
+    /// Optionally year taken, scans photos of the year and month when no file name is given
+    /// Example: 2023
+    year: Option<u32>,
+    /// Optionally month taken (1-12)
+    /// Example: 6
+    month: Option<u32>,
+    /// Optionally return only the scanned photos with a barcode
+    /// Example: true
+    only_found: Option<bool>,
+    /// Offset into the photos to scan
+    /// Example: 0
+    offset: u32,
+    /// Limit number of photos scanned
+    /// Example: 10
+    limit: u32,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
 }
-impl PhotoStatsByYearTool {
-    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
-        #[derive(Serialize)]
-        struct YearAggregation {
-            count: usize,
-            months: HashMap<u32, MonthSummary>,
-        }
-        #[derive(Serialize)]
-        struct MonthSummary {
-            count: usize,
-            camera: HashMap<String, usize>,
-            lens: HashMap<String, usize>,
-        }
-        tracing::info!(
-            "photo stats by year: start_year={:?}, end_year={:?}",
-            self.year_start,
-            self.year_end,
-        );
 
-        let year_start = self.year_start;
-        let year_end = self.year_end;
-        let years_selected = IC
-            .by_year_month
+impl PhotoDecodeBarcodesTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "decode barcodes: photo_ids={:?}, file_name={:?}, year={:?}, month={:?}, offset={}, limit={}",
+            self.photo_ids,
+            self.file_name,
+            self.year,
+            self.month,
+            self.offset,
+            self.limit
+        );
+        let offset = self.offset as usize;
+        let limit = self.limit.min(MAX_PHOTO_YOLO_ANALYZE_LIMIT) as usize;
+        let include_hidden = self.include_hidden.unwrap_or(false);
+        let include_trash = self.include_trash.unwrap_or(false);
+        let (infos, total) = if let Some(photo_ids) = &self.photo_ids {
+            let total = photo_ids.len();
+            let page = &photo_ids[offset.min(total)..offset.saturating_add(limit).min(total)];
+            let infos = IC
+                .resolve_photos(page)
+                .map_err(|e| CallToolError::from_message(e.to_string()))?;
+            (infos, total)
+        } else if let Some(file_name) = &self.file_name {
+            let (infos, total) = IC.search_image_by_name(
+                file_name,
+                &self.zip_file_name,
+                offset,
+                limit,
+                include_hidden,
+                self.zip_file_name.is_some(),
+                include_trash,
+                &NameFolding::configured(),
+            );
+            (infos.into_iter().cloned().collect(), total)
+        } else if let (Some(year), Some(month)) = (self.year, self.month) {
+            IC.search_image_by_year_month(
+                year,
+                month,
+                offset,
+                limit,
+                include_hidden,
+                false,
+                include_trash,
+            )
+        } else {
+            return Err(CallToolError::from_message(
+                "Provide photo_ids, file_name or year and month".to_owned(),
+            ));
+        };
+        let scanned = infos.len();
+        let mut results = IC
+            .barcode_analysis(infos.iter().collect(), cancel)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to decode barcodes: {}", e))
+            })?;
+        if self.only_found.unwrap_or(false) {
+            results.retain(|result| !result.barcodes.is_empty());
+        }
+
+        let next_offset = offset + scanned;
+        let json_info = serde_json::json!({
+            "query": {
+                "photo_ids": self.photo_ids,
+                "file_name": self.file_name,
+                "year": self.year,
+                "month": self.month,
+            },
+            "result": results,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": limit,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_detection_models",
+    description = "List registered object detection models (id, name, version, thresholds), the default model and models used by the background analysis. Model ids can be passed to photo_object_detection and photo_search_by_object tools."
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoDetectionModelsTool {}
+
+impl PhotoDetectionModelsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo detection models");
+        let models = MODELS
+            .models()
+            .iter()
+            .map(|m| {
+                serde_json::json!({
+                    "id": m.id(),
+                    "name": m.name,
+                    "version": m.version,
+                    "confidence_threshold": m.confidence_threshold,
+                    "iou_threshold": m.iou_threshold,
+                })
+            })
+            .collect::<Vec<_>>();
+        let json_info = serde_json::json!({
+            "result": models,
+            "default_model": MODELS.default_model().id(),
+            "analysis_models": MODELS.analysis_models().iter().map(|m| m.id()).collect::<Vec<_>>(),
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_search_by_object",
+    description = "Search photos by object class detected during background analysis (e.g. person, dog, car) or its synonym or group (e.g. puppy, automobile, vehicle, animal), returns photo files with matching detections per model and reference to the next page (next_offset, next_limit) if more results are available"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSearchByObjectTool {
+    /// Detected object class name (case insensitive), a synonym of it (e.g. "puppy") or a group
+    /// of classes (e.g. "animal")
+    /// Example: "dog"
+    class_name: String,
+    /// Optionally you can restrict the search on results of a single model, results of all models are returned side by side otherwise
+    /// Example: yolov8-default
+    model: Option<String>,
+    /// Optional minimal confidence of the detection (0.0 - 1.0)
+    /// Example: 0.5
+    min_confidence: Option<f32>,
+    /// Optionally search only the photo's k most confident detected classes, to leave out
+    /// incidental findings
+    /// Example: 3
+    top_k_per_photo: Option<u32>,
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
+}
+
+impl PhotoSearchByObjectTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "search by object: class_name={}, model={:?}, min_confidence={:?}, offset={}, limit={}",
+            self.class_name,
+            self.model,
+            self.min_confidence,
+            self.offset,
+            self.limit
+        );
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        let class_name = TRANSLATIONS.untranslate(&self.class_name);
+        // classes the name stands for, reported in the query
+        let mut classes = CLASS_ALIASES
+            .expand(&class_name)
+            .into_iter()
+            .collect::<Vec<_>>();
+        classes.sort();
+        let (results, total) = IC
+            .search_image_by_object(
+                &class_name,
+                &self.model,
+                &LabelFilter::new(self.min_confidence, self.top_k_per_photo),
+                from,
+                count,
+                self.include_hidden.unwrap_or(false),
+                page.include_duplicates(self.include_duplicates),
+                self.include_trash.unwrap_or(false),
+            )
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to search images by object: {}", e))
+            })?;
+        let (results, buckets, preview_sample, pagination) = page.paginate(results, total);
+        let json_info = serde_json::json!({
+            "query": {
+                "class_name": self.class_name,
+                "classes": classes,
+                "model": self.model,
+                "min_confidence": self.min_confidence,
+                "top_k_per_photo": self.top_k_per_photo,
+            },
+            "result": result_or_buckets(&results, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_search_by_label",
+    description = "Search photos by label produced by background analysis stages (e.g. pet breeds like \"beagle\", landmarks like \"Eiffel Tower\", zero-shot tags like \"wedding\" or \"snow\", documents like \"receipt\", \"whiteboard\" or \"id card\", night shots \"night\" and long exposed \"astro\", weather like \"rain\" or \"snow\", occasions like \"Christmas\" or configured birthdays), label is matched case insensitive and can be partial. Returns photo files with matching labels per stage and reference to the next page (next_offset, next_limit) if more results are available"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSearchByLabelTool {
+    /// Label to search for
+    /// Example: "beagle"
+    label: String,
+    /// Optionally you can restrict the search on results of a single analysis stage
+    /// Example: pet_breed
+    stage: Option<String>,
+    /// Optional minimal confidence of the label (0.0 - 1.0)
+    /// Example: 0.5
+    min_confidence: Option<f32>,
+    /// Optionally search only the photo's k most confident labels, to leave out
+    /// incidental findings
+    /// Example: 3
+    top_k_per_photo: Option<u32>,
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
+}
+
+impl PhotoSearchByLabelTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "search by label: label={}, stage={:?}, min_confidence={:?}, offset={}, limit={}",
+            self.label,
+            self.stage,
+            self.min_confidence,
+            self.offset,
+            self.limit
+        );
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        let (results, total) = IC
+            .search_image_by_label(
+                &TRANSLATIONS.untranslate(&self.label),
+                &self.stage,
+                &LabelFilter::new(self.min_confidence, self.top_k_per_photo),
+                from,
+                count,
+                self.include_hidden.unwrap_or(false),
+                page.include_duplicates(self.include_duplicates),
+                self.include_trash.unwrap_or(false),
+            )
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to search images by label: {}", e))
+            })?;
+        let (results, buckets, preview_sample, pagination) = page.paginate(results, total);
+        let json_info = serde_json::json!({
+            "query": {
+                "label": self.label,
+                "stage": self.stage,
+                "min_confidence": self.min_confidence,
+                "top_k_per_photo": self.top_k_per_photo,
+            },
+            "result": result_or_buckets(&results, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_search_by_people_count",
+    description = "Search photos by number of people in them (e.g. group photos with more than 5 people), value is compared using the operator like numeric EXIF tags. Returns photo files with their people count and reference to the next page (next_offset, next_limit) if more results are available"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSearchByPeopleCountTool {
+    /// Number of people to compare with
+    /// Example: "5"
+    value: String,
+    /// Operator to use for comparison, one of "==", ">", "<", ">=", "<=", "!="
+    /// Example: ">"
+    operator: String,
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
+}
+
+impl PhotoSearchByPeopleCountTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "search by people count: {} {}, offset={}, limit={}",
+            self.operator,
+            self.value,
+            self.offset,
+            self.limit
+        );
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        let (results, total) = IC
+            .search_image_by_people_count(
+                &self.value,
+                &self.operator,
+                from,
+                count,
+                self.include_hidden.unwrap_or(false),
+                page.include_duplicates(self.include_duplicates),
+                self.include_trash.unwrap_or(false),
+            )
+            .map_err(|e| {
+                CallToolError::from_message(format!(
+                    "Failed to search images by people count: {}",
+                    e
+                ))
+            })?;
+        let (results, buckets, preview_sample, pagination) = page.paginate(results, total);
+        let json_info = serde_json::json!({
+            "query": {
+                "value": self.value,
+                "operator": self.operator,
+            },
+            "result": result_or_buckets(&results, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_search_portraits",
+    description = "Search portraits and group shots where everyone smiles and has eyes open (e.g. pick the best group shot where everyone is smiling). Faces are scored by the face_attributes analysis stage, the worst face of the photo must pass the thresholds. Returns best shots first with reference to the next page (next_offset, next_limit) if more results are available"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSearchPortraitsTool {
+    /// Optional minimal number of faces in the photo, use it to find group shots
+    /// Example: 3
+    min_faces: Option<u32>,
+    /// Optional minimal smile probability of every face (0.0 - 1.0)
+    /// Example: 0.7
+    min_smile: Option<f32>,
+    /// Optional minimal eyes open score of every face (0.0 - 1.0)
+    /// Example: 0.8
+    min_eyes_open: Option<f32>,
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
+}
+
+impl PhotoSearchPortraitsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "search portraits: min_faces={:?}, min_smile={:?}, min_eyes_open={:?}, offset={}, limit={}",
+            self.min_faces,
+            self.min_smile,
+            self.min_eyes_open,
+            self.offset,
+            self.limit
+        );
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        let (results, total) = IC
+            .search_portraits(
+                self.min_faces.unwrap_or(1) as usize,
+                self.min_smile.unwrap_or(0.0),
+                self.min_eyes_open.unwrap_or(0.0),
+                from,
+                count,
+                self.include_hidden.unwrap_or(false),
+                page.include_duplicates(self.include_duplicates),
+                self.include_trash.unwrap_or(false),
+            )
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to search portraits: {}", e))
+            })?;
+        let (results, buckets, preview_sample, pagination) = page.paginate(results, total);
+        let json_info = serde_json::json!({
+            "query": {
+                "min_faces": self.min_faces,
+                "min_smile": self.min_smile,
+                "min_eyes_open": self.min_eyes_open,
+            },
+            "result": result_or_buckets(&results, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_stats_summary",
+    description = "Returns global summary statistics"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoGlobalSummaryTool {}
+
+impl PhotoGlobalSummaryTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo global stats");
+
+        let mut years_range = vec![];
+        let mut all_years = IC
+            .year_month_index()
+            .keys()
+            .filter(|year| **year > 0) // in case we don't know the year, we assign 0
+            .cloned()
+            .collect::<Vec<u32>>();
+        all_years.sort();
+        let l = all_years.len();
+        if all_years.len() > 0 {
+            years_range.push(all_years[0]);
+            years_range.push(all_years[l - 1]);
+        }
+
+        let mut camera_model_counts = HashMap::new();
+        let mut lens_model_counts = HashMap::new();
+        IC.for_each_exif(|_, exif| {
+            if let Some(exif) = exif {
+                *camera_model_counts.entry(exif.model.clone()).or_insert(0) += 1;
+                *lens_model_counts.entry(exif.lens.clone()).or_insert(0) += 1;
+            }
+        });
+        let total = IC.images.len();
+
+        let json_info = serde_json::json!({
+            "camera_model_photo_count": camera_model_counts,
+            "lens_model_photo_count": lens_model_counts,
+            "years_range": years_range,
+            "total_photos": total
+        }
+        );
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_stats_by_year",
+    description = "Returns summary statistics based on year/month search (year aggregation API)"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoStatsByYearTool {
+    /// Start year for statistics
+    /// Example: 2004
+    year_start: u32,
+    /// End year for statistics
+    /// Example: 2025
+    year_end: u32,
+}
+impl PhotoStatsByYearTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        #[derive(Serialize)]
+        struct YearAggregation {
+            count: usize,
+            months: HashMap<u32, MonthSummary>,
+        }
+        #[derive(Serialize)]
+        struct MonthSummary {
+            count: usize,
+            camera: HashMap<String, usize>,
+            lens: HashMap<String, usize>,
+        }
+        tracing::info!(
+            "photo stats by year: start_year={:?}, end_year={:?}",
+            self.year_start,
+            self.year_end,
+        );
+
+        let year_start = self.year_start;
+        let year_end = self.year_end;
+        let by_year_month = IC.year_month_index();
+        let exif_cache = IC.exif_index();
+        let years_selected = by_year_month
             .keys()
             .filter(|year| **year >= year_start && **year <= year_end && **year > 0)
             .cloned()
@@ -584,7 +1742,7 @@ impl PhotoStatsByYearTool {
 
         let mut year_aggregation = HashMap::new();
 
-        for (year, by_month) in IC.by_year_month.iter() {
+        for (year, by_month) in by_year_month.iter() {
             if years_selected.contains(year) {
                 let mut count = 0;
                 let mut month_agg = HashMap::new();
@@ -593,7 +1751,7 @@ impl PhotoStatsByYearTool {
                     let mut camera = HashMap::new();
                     let mut lens = HashMap::new();
                     for photo_info in infos {
-                        if let Some(exif) = IC.exif_cache.get(photo_info) {
+                        if let Some(exif) = exif_cache.get(photo_info) {
                             *camera.entry(exif.model.clone()).or_insert(0) += 1;
                             *lens.entry(exif.lens.clone()).or_insert(0) += 1;
                         }
@@ -631,6 +1789,2376 @@ impl PhotoStatsByYearTool {
     }
 }
 
+#[mcp_tool(
+    name = "photo_settings_insights",
+    description = "Analyzes EXIF data of the photo collection and reports typical shooting settings per scenario (e.g. focal length distribution for a lens, ISO vs shutter speed patterns at night). Returns min/median/max and most frequent values of focal length, aperture, ISO and shutter speed, most frequent ISO/shutter combinations, cameras and lenses"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSettingsInsightsTool {
+    /// Optionally restrict to camera model (case insensitive, partial match)
+    /// Example: "Canon EOS 40D"
+    camera: Option<String>,
+    /// Optionally restrict to lens (case insensitive, partial match)
+    /// Example: "EF 50mm"
+    lens: Option<String>,
+    /// Optional scenario by time of day the photo was taken, one of "all", "day", "night"
+    /// Example: "night"
+    scenario: Option<String>,
+}
+
+impl PhotoSettingsInsightsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo settings insights: camera={:?}, lens={:?}, scenario={:?}",
+            self.camera,
+            self.lens,
+            self.scenario
+        );
+        let scenario = Scenario::parse(self.scenario.as_deref().unwrap_or("all"))
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let insights =
+            settings_insights(IC.exif_index().values(), &self.camera, &self.lens, scenario);
+
+        let json_info = serde_json::json!({
+            "query": {
+                "camera": self.camera,
+                "lens": self.lens,
+                "scenario": scenario,
+            },
+            "result": insights,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_exposure_stats",
+    description = "Summarizes flash usage (fired, not fired, unknown), exposure program (manual, program, aperture_priority, ...) and metering mode (pattern, center_weighted, spot, ...) distributions across the collection and per year, optionally for one camera and a range of years"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoExposureStatsTool {
+    /// Optionally restrict to camera model (case insensitive, partial match)
+    /// Example: "Canon EOS 40D"
+    camera: Option<String>,
+    /// Optional first year taken
+    /// Example: 2010
+    year_start: Option<u32>,
+    /// Optional last year taken
+    /// Example: 2025
+    year_end: Option<u32>,
+}
+
+impl PhotoExposureStatsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo exposure stats: camera={:?}, year_start={:?}, year_end={:?}",
+            self.camera,
+            self.year_start,
+            self.year_end
+        );
+        let analytics = exposure_analytics(
+            IC.exif_index().values(),
+            &self.camera,
+            self.year_start,
+            self.year_end,
+        );
+
+        let json_info = serde_json::json!({
+            "query": {
+                "camera": self.camera,
+                "year_start": self.year_start,
+                "year_end": self.year_end,
+            },
+            "result": analytics,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_shutter_counts",
+    description = "Estimated shutter actuations over time per camera body (model and serial number), from the shutter counts cameras record in the MakerNote of each photo (Nikon bodies). Returns per body the first and last count, actuations per day and the actuations per period, useful for tracking gear wear from the archive alone. Bodies of other makes record no count and are left out"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoShutterCountsTool {
+    /// Optionally restrict to camera model (case insensitive, partial match)
+    /// Example: "NIKON D750"
+    camera: Option<String>,
+    /// Optional period of the histogram, one of day, week, month, quarter, year (default month)
+    /// Example: "year"
+    group_by: Option<String>,
+}
+
+impl PhotoShutterCountsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo shutter counts: camera={:?}, group_by={:?}",
+            self.camera,
+            self.group_by
+        );
+        let group = TimeGroup::parse(self.group_by.as_deref().unwrap_or("month"))
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let bodies = shutter_counts(IC.exif_index().values(), &self.camera, group);
+
+        let json_info = serde_json::json!({
+            "query": {
+                "camera": self.camera,
+                "group_by": group,
+            },
+            "result": bodies,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_storage_report",
+    description = "Returns storage usage of the photo collection, compressed and uncompressed sizes (in bytes) and number of files of all zip archive entries grouped by archive, year and media type (image, video, metadata, other)"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoStorageReportTool {}
+
+impl PhotoStorageReportTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo storage report");
+        let report = IC.storage_report().map_err(|e| {
+            CallToolError::from_message(format!("Failed to create storage report: {}", e))
+        })?;
+
+        let json_info = serde_json::json!({
+            "result": report,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_archive_covers",
+    description = "Returns every archive with its photo count and cover photo, picked by quality (resolution, open eyes and smiles), number of people and diversity (covers of neighbouring archives show different subjects). Covers are returned as JSON text followed by their thumbnails, thumbnail meta contains the archive name"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoArchiveCoversTool {}
+
+impl PhotoArchiveCoversTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo archive covers");
+        let archives = IC.archive_covers();
+        let covers = archives
+            .iter()
+            .take(MAX_CALENDAR_THUMBNAILS)
+            .filter_map(|a| a.cover.as_ref())
+            .collect::<Vec<&PhotoInfo>>();
+        let image_data = IC
+            .image_data(covers, cancel)
+            .and_then(watermark::watermarked)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to extract image data: {}", e))
+            })?;
+
+        let json_info = serde_json::json!({
+            "result": archives,
+            "truncated": cancel.timed_out(),
+        });
+        let mut result =
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())]);
+        for archive in archives.iter() {
+            let Some((file_name, mime, data)) = image_data
+                .iter()
+                .find(|(info, _, _)| archive.cover.as_ref() == Some(info))
+            else {
+                continue;
+            };
+            result = result.add_content(
+                ImageContent::new(
+                    base64::encode(data),
+                    mime.clone(),
+                    None,
+                    Some({
+                        let mut meta = IC.image_meta(file_name, data);
+                        meta.insert("archive".to_owned(), archive.zip_file_name.clone().into());
+                        meta
+                    }),
+                )
+                .into(),
+            );
+        }
+        Ok(result)
+    }
+}
+
+#[mcp_tool(
+    name = "photo_cache_stats",
+    description = "Returns number of entries and estimated memory consumption (in bytes) of the server caches: photo listing, EXIF, year/month index, object detections per model and analysis results per stage, together with number of archives with loaded and unloaded (lazy loaded or evicted) EXIF, the configured memory budget and number of searches with cached results. Per archive it reports number of photos, load time of its index, estimated EXIF memory and sidecar size, together with the manifest load time, sidecar sizes by kind and index cardinalities (distinct camera models, lenses, years and months) to explain slow startup or searches"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoCacheStatsTool {}
+
+impl PhotoCacheStatsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo cache stats");
+        let json_info = serde_json::json!({
+            "result": IC.cache_stats(),
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_index_health",
+    description = "Returns number of indexed archives and photos together with the archives and photo entries excluded from indexing by the configured exclude/include patterns, archives renamed since the last start whose indexes were re-linked to the new name, and the startup warm-up tasks (photo listing, preloaded archives, month indexes, detection model) with their timing and errors"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoIndexHealthTool {}
+
+impl PhotoIndexHealthTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo index health");
+        let health = IC.index_health().map_err(|e| {
+            CallToolError::from_message(format!("Failed to check index health: {}", e))
+        })?;
+        let json_info = serde_json::json!({
+            "result": health,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_export_catalog",
+    description = "Exports the metadata catalog of all photos (photo info, content hash, EXIF, GPS location and labels of detected objects and analysis stages) into a CSV or Parquet file for analysis in pandas or DuckDB, returns the written file name and number of rows"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoExportCatalogTool {
+    /// Format of the file, one of "csv", "parquet"
+    /// Example: "parquet"
+    format: String,
+    /// Optional output file, defaults to photo-catalog.<format> in the image directory,
+    /// relative to the collection directory when collections are configured
+    /// Example: "/tmp/photo-catalog.parquet"
+    output_file: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoExportCatalogTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo export catalog: format={}, output_file={:?}",
+            self.format,
+            self.output_file
+        );
+        let format = CatalogFormat::parse(&self.format)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let output_file = match &self.output_file {
+            Some(path) => client_path(path)?,
+            None => format!("{}/photo-catalog.{}", IC.image_dir(), format.extension()),
+        };
+        let rows = IC.catalog(self.include_hidden.unwrap_or(false));
+        catalog::export(&rows, format, &output_file)
+            .map_err(|e| CallToolError::from_message(format!("Failed to export catalog: {}", e)))?;
+        let json_info = serde_json::json!({
+            "query": {
+                "format": format,
+            },
+            "result": {
+                "file": output_file,
+                "rows": rows.len(),
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_export_bundle",
+    description = "Exports all indexes and analysis results (EXIF, year/month indexes, content hashes, object detections, analysis stages, metadata overrides, hidden photos) into one bundle file, which can be imported by photo_import_bundle on another machine with the same archives instead of analyzing the photos again. With encryption configured the data stays encrypted in the bundle"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoExportBundleTool {
+    /// Optional bundle file, defaults to photo-mcp.bundle in the image directory,
+    /// relative to the collection directory when collections are configured
+    /// Example: "/tmp/photo-mcp.bundle"
+    output_file: Option<String>,
+}
+
+impl PhotoExportBundleTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo export bundle: output_file={:?}", self.output_file);
+        let output_file = match &self.output_file {
+            Some(path) => client_path(path)?,
+            None => format!("{}/{}", IC.image_dir(), bundle::DEFAULT_BUNDLE),
+        };
+        let summary = bundle::export(IC.image_dir(), &output_file)
+            .map_err(|e| CallToolError::from_message(format!("Failed to export bundle: {}", e)))?;
+        let json_info = serde_json::json!({
+            "result": summary,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_import_bundle",
+    description = "Imports indexes and analysis results from a bundle written by photo_export_bundle, existing data is replaced. Data of archives missing here or differing in size is skipped. The server has to be restarted to load the imported data"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoImportBundleTool {
+    /// Bundle file, relative to the collection directory when collections are configured
+    /// Example: "/tmp/photo-mcp.bundle"
+    bundle_file: String,
+}
+
+impl PhotoImportBundleTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo import bundle: bundle_file={}", self.bundle_file);
+        let bundle_file = client_path(&self.bundle_file)?;
+        let summary = bundle::import(IC.image_dir(), &bundle_file)
+            .map_err(|e| CallToolError::from_message(format!("Failed to import bundle: {}", e)))?;
+        let json_info = serde_json::json!({
+            "result": summary,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_backup_derived",
+    description = "Backs up all derived data (indexes, analysis results, metadata overrides, hidden photos, manifest, privacy audit log) into a directory with a SHA-256 checksum of every file, restored by photo_restore_derived"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoBackupDerivedTool {
+    /// Optional backup directory, defaults to photo-mcp.backup in the image directory,
+    /// relative to the collection directory when collections are configured
+    /// Example: "/mnt/backup/photo-mcp"
+    target_path: Option<String>,
+}
+
+impl PhotoBackupDerivedTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo backup derived: target_path={:?}", self.target_path);
+        let target_path = match &self.target_path {
+            Some(path) => client_path(path)?,
+            None => format!("{}/{}", IC.image_dir(), backup::DEFAULT_BACKUP),
+        };
+        let summary = backup::backup(IC.image_dir(), &target_path)
+            .map_err(|e| CallToolError::from_message(format!("Failed to back up: {}", e)))?;
+        let json_info = serde_json::json!({
+            "result": summary,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_restore_derived",
+    description = "Restores derived data from a backup written by photo_backup_derived, existing data is replaced. Checksums of all files are verified first, nothing is restored from a corrupted backup. Data of archives missing here or differing in size and results of analysis stages of another version are skipped. The server has to be restarted to load the restored data"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoRestoreDerivedTool {
+    /// Backup directory, relative to the collection directory when collections are configured
+    /// Example: "/mnt/backup/photo-mcp"
+    backup_path: String,
+}
+
+impl PhotoRestoreDerivedTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo restore derived: backup_path={}", self.backup_path);
+        let backup_path = client_path(&self.backup_path)?;
+        let summary = backup::restore(IC.image_dir(), &backup_path)
+            .map_err(|e| CallToolError::from_message(format!("Failed to restore: {}", e)))?;
+        let json_info = serde_json::json!({
+            "result": summary,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_cache_cleanup",
+    description = "Finds sidecars of archives no longer in the image directory, sidecars of analysis stages (e.g. detection models) no longer configured and analysis results of photos no longer in their archive. Orphan sidecars are deleted or moved into an archive directory, stale results are removed from their sidecars. Reports the files found and the reclaimed space. Runs dry by default, only reporting them, nothing is changed unless dry_run is false"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoCacheCleanupTool {
+    /// Optionally only report what would be removed, defaults to true, set to false to remove
+    /// Example: false
+    dry_run: Option<bool>,
+    /// Optional directory orphan sidecars are moved into instead of being deleted, sidecars
+    /// with stale results are copied there before they are rewritten, relative to the collection directory when collections are configured
+    /// Example: "/mnt/backup/photo-mcp-orphans"
+    archive_dir: Option<String>,
+}
+
+impl PhotoCacheCleanupTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo cache cleanup: dry_run={:?}, archive_dir={:?}",
+            self.dry_run,
+            self.archive_dir
+        );
+        let options = CleanupOptions {
+            dry_run: self.dry_run.unwrap_or(true),
+            archive_dir: self.archive_dir.as_deref().map(client_path).transpose()?,
+        };
+        let report = cleanup::run(&IC, &options)
+            .map_err(|e| CallToolError::from_message(format!("Failed to clean up: {}", e)))?;
+        let json_info = serde_json::json!({
+            "result": report,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_calendar",
+    description = "Returns year-at-a-glance calendar: for each year/month with photos the photo count and one representative thumbnail (first, random, best photo of the month or its cover picked by quality, faces and diversity). Calendar is returned as JSON text followed by thumbnails, thumbnail meta contains the year and month"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoCalendarTool {
+    /// Start year of the calendar
+    /// Example: 2020
+    year_start: u32,
+    /// End year of the calendar
+    /// Example: 2021
+    year_end: u32,
+    /// Optional representative photo of the month, one of "first", "random", "best" (highest resolution), "cover" (quality, faces and diversity), defaults to "first"
+    /// Example: "cover"
+    representative: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoCalendarTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo calendar: year_start={}, year_end={}, representative={:?}",
+            self.year_start,
+            self.year_end,
+            self.representative
+        );
+        let pick = Representative::parse(self.representative.as_deref().unwrap_or("first"))
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let calendar = IC.calendar(
+            self.year_start,
+            self.year_end,
+            pick,
+            self.include_hidden.unwrap_or(false),
+        );
+        if calendar.len() > MAX_CALENDAR_THUMBNAILS {
+            tracing::info!(
+                "Limiting thumbnails to {} of {} months",
+                MAX_CALENDAR_THUMBNAILS,
+                calendar.len()
+            );
+        }
+        let representatives = calendar
+            .iter()
+            .take(MAX_CALENDAR_THUMBNAILS)
+            .filter_map(|m| m.representative.as_ref())
+            .collect::<Vec<&PhotoInfo>>();
+        let image_data = IC
+            .image_data(representatives, cancel)
+            .and_then(watermark::watermarked)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to extract image data: {}", e))
+            })?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "year_start": self.year_start,
+                "year_end": self.year_end,
+                "representative": pick,
+            },
+            "result": calendar,
+            "truncated": cancel.timed_out(),
+        });
+        let mut result =
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())]);
+        for month in calendar.iter() {
+            let Some((file_name, mime, data)) = image_data
+                .iter()
+                .find(|(info, _, _)| month.representative.as_ref() == Some(info))
+            else {
+                continue;
+            };
+            result = result.add_content(
+                ImageContent::new(
+                    base64::encode(data),
+                    mime.clone(),
+                    None,
+                    Some({
+                        let mut meta = IC.image_meta(file_name, data);
+                        meta.insert("year".to_owned(), month.year.into());
+                        meta.insert("month".to_owned(), month.month.into());
+                        meta
+                    }),
+                )
+                .into(),
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+#[mcp_tool(
+    name = "photo_period_summary",
+    description = "Summarizes photos taken in a date range (e.g. \"your June in photos\"): photo count per month, most frequent detected objects and labels, cameras used, locations visited, notable events (bursts of photos with their top labels and place) and a handful of representative thumbnails. Summary is returned as JSON text followed by the thumbnails"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoPeriodSummaryTool {
+    /// Start of the period, "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    /// Example: "2023-06-01"
+    date_from: String,
+    /// End of the period (inclusive), "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    /// Example: "2023-06-30"
+    date_to: String,
+    /// Optional number of thumbnails, defaults to 6
+    /// Example: 6
+    thumbnails: Option<u32>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoPeriodSummaryTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo period summary: date_from={}, date_to={}, thumbnails={:?}",
+            self.date_from,
+            self.date_to,
+            self.thumbnails
+        );
+        let photos = IC
+            .digest_photos(
+                &self.date_from,
+                &self.date_to,
+                self.include_hidden.unwrap_or(false),
+            )
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let mut summary = digest::period_summary(&self.date_from, &self.date_to, &photos);
+        IC.event_covers(&mut summary.events);
+        let thumbnails = self
+            .thumbnails
+            .unwrap_or(DEFAULT_SUMMARY_THUMBNAILS)
+            .min(MAX_SUMMARY_THUMBNAILS) as usize;
+        let representatives = digest::representatives(&summary, &photos, thumbnails);
+        let image_data = IC
+            .image_data(representatives, cancel)
+            .and_then(watermark::watermarked)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to extract image data: {}", e))
+            })?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "date_from": self.date_from,
+                "date_to": self.date_to,
+            },
+            "result": summary,
+            "truncated": cancel.timed_out(),
+        });
+        let mut result =
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())]);
+        for (file_name, mime, data) in image_data {
+            result = result.add_content(
+                ImageContent::new(
+                    base64::encode(&data),
+                    mime,
+                    None,
+                    Some(IC.image_meta(&file_name, &data)),
+                )
+                .into(),
+            );
+        }
+        Ok(result)
+    }
+}
+
+#[mcp_tool(
+    name = "photo_story_payload",
+    description = "Story skeleton of an event or a date range in one call: photos in time order grouped into chapters (bursts of photos split at gaps longer than 4 hours) with the time taken, location, detected subjects and caption of every photo, and the top subjects and location of every chapter. For an event pass its start and end from photo_period_summary. Long stories list a sample of photos evenly spread over the range"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoStoryPayloadTool {
+    /// Start of the story, "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    /// Example: "2023-07-14 09:12:03"
+    date_from: String,
+    /// End of the story (inclusive), "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    /// Example: "2023-07-16 21:40:55"
+    date_to: String,
+    /// Optional number of photos listed, defaults to 50
+    /// Example: 50
+    max_photos: Option<u32>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoStoryPayloadTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo story payload: date_from={}, date_to={}, max_photos={:?}",
+            self.date_from,
+            self.date_to,
+            self.max_photos
+        );
+        let photos = IC
+            .digest_photos(
+                &self.date_from,
+                &self.date_to,
+                self.include_hidden.unwrap_or(false),
+            )
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let max_photos = self
+            .max_photos
+            .unwrap_or(DEFAULT_STORY_PHOTOS)
+            .clamp(1, MAX_STORY_PHOTOS) as usize;
+        let story = digest::story_payload(&self.date_from, &self.date_to, &photos, max_photos);
+
+        let json_info = serde_json::json!({
+            "query": {
+                "date_from": self.date_from,
+                "date_to": self.date_to,
+                "max_photos": max_photos,
+            },
+            "result": story,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_find_gaps",
+    description = "Finds periods longer than the given number of days with no photos, reported per year with the photo count of the year, to spot missing Takeout parts or SD cards that were never imported. Every gap has the time of the last photo before and the first photo after it. Hidden photos count, copies and photos in the Takeout trash don't"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoFindGapsTool {
+    /// Optional shortest gap in days, defaults to 30
+    /// Example: 30
+    min_days: Option<u32>,
+    /// Optionally first year to check
+    /// Example: 2015
+    year_from: Option<u32>,
+    /// Optionally last year to check
+    /// Example: 2024
+    year_to: Option<u32>,
+}
+
+impl PhotoFindGapsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo find gaps: min_days={:?}, years={:?}-{:?}",
+            self.min_days,
+            self.year_from,
+            self.year_to
+        );
+        let min_days = self.min_days.unwrap_or(DEFAULT_GAP_DAYS).max(1);
+        let photos = IC
+            .digest_photos(
+                &format!("{:04}-01-01", self.year_from.unwrap_or(1)),
+                &format!("{:04}-12-31", self.year_to.unwrap_or(9999)),
+                true,
+            )
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let gaps = digest::gaps(&photos, min_days as i64);
+
+        let json_info = serde_json::json!({
+            "query": {
+                "min_days": min_days,
+                "year_from": self.year_from,
+                "year_to": self.year_to,
+            },
+            "result": gaps,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_manifest_coverage",
+    description = "Compares the indexed photos with a manifest of expected photos and reports which of them are missing from the archives, e.g. to check that all Takeout parts were downloaded. The manifest is a CSV with a filename (or name, title) column, a plain list of file names or a JSON album export (Google Photos mediaItems with filename). Photos are matched by file name, case insensitive, or by the name without extension (a HEIC photo exported as JPEG). Returns counts and a page of the missing names with reference to the next page (next_offset, next_limit)"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoManifestCoverageTool {
+    /// Manifest file on the server, relative to the collection directory when collections are configured
+    /// Example: "/tmp/album.csv"
+    manifest_file: String,
+    /// Offset into missing photos
+    /// Example: 0
+    offset: u32,
+    /// Limit number of missing photos returned
+    /// Example: 100
+    limit: u32,
+}
+
+impl PhotoManifestCoverageTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo manifest coverage: manifest_file={}, offset={}, limit={}",
+            self.manifest_file,
+            self.offset,
+            self.limit
+        );
+        let offset = self.offset as usize;
+        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
+        let expected = coverage::read_manifest(&client_path(&self.manifest_file)?)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let mut coverage = coverage::compare(&expected, &IC.images);
+        let (missing, total) = query_cache::page(&coverage.missing, offset, limit);
+        coverage.missing = missing;
+        let next_offset = offset + coverage.missing.len();
+        let next_limit = limit;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "manifest_file": self.manifest_file,
+            },
+            "result": coverage,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": next_limit,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_year_in_review",
+    description = "Year in review: photo count and the best (sharpest of the highest resolution) photo of every month, biggest events of the year, most photographed subjects from detected objects and labels, and cameras used. Review is returned as JSON text followed by thumbnails of the best photos of the months, or a single collage of them"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoYearInReviewTool {
+    /// Year to review
+    /// Example: 2023
+    year: u32,
+    /// Optional number of biggest events, defaults to 5
+    /// Example: 5
+    events: Option<u32>,
+    /// Optionally return a single collage image instead of the thumbnails
+    /// Example: true
+    collage: Option<bool>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoYearInReviewTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo year in review: year={}, events={:?}, collage={:?}",
+            self.year,
+            self.events,
+            self.collage
+        );
+        let photos = IC
+            .digest_photos(
+                &format!("{}-01-01", self.year),
+                &format!("{}-12-31", self.year),
+                self.include_hidden.unwrap_or(false),
+            )
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let events = self
+            .events
+            .unwrap_or(DEFAULT_REVIEW_EVENTS)
+            .min(MAX_REVIEW_EVENTS) as usize;
+        let mut review = digest::year_in_review(self.year, &photos, events);
+        IC.event_covers(&mut review.events);
+        let candidates = review
+            .months
+            .iter()
+            .flat_map(|m| digest::month_candidates(&photos, m.month, REVIEW_CANDIDATES_PER_MONTH))
+            .collect::<Vec<&PhotoInfo>>();
+        let image_data = IC.image_data(candidates, cancel).map_err(|e| {
+            CallToolError::from_message(format!("Failed to extract image data: {}", e))
+        })?;
+
+        let month_of = |info: &PhotoInfo| {
+            photos
+                .iter()
+                .find(|p| &p.info == info)
+                .map(|p| p.exif.month)
+        };
+        let mut best: HashMap<u32, (f64, usize)> = HashMap::new();
+        for (i, (info, _, data)) in image_data.iter().enumerate() {
+            let (Some(month), Some(sharpness)) = (month_of(info), image::sharpness(data)) else {
+                continue;
+            };
+            if best.get(&month).is_none_or(|(s, _)| sharpness > *s) {
+                best.insert(month, (sharpness, i));
+            }
+        }
+        for highlight in review.months.iter_mut() {
+            if let Some((sharpness, i)) = best.get(&highlight.month) {
+                highlight.best = Some(image_data[*i].0.clone());
+                highlight.sharpness = Some(*sharpness);
+            }
+        }
+        let mut picked = review
+            .months
+            .iter()
+            .filter_map(|m| best.get(&m.month).map(|(_, i)| &image_data[*i]))
+            .collect::<Vec<_>>();
+        picked.sort_by_key(|(info, _, _)| month_of(info));
+
+        let json_info = serde_json::json!({
+            "query": {
+                "year": self.year,
+                "events": events,
+            },
+            "result": review,
+            "truncated": cancel.timed_out(),
+        });
+        let mut result =
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())]);
+        if self.collage.unwrap_or(false) && !picked.is_empty() {
+            let images = picked
+                .iter()
+                .map(|(_, _, data)| data.as_slice())
+                .collect::<Vec<&[u8]>>();
+            let (mime, collage) = image::collage_to_jpeg(&images, COLLAGE_COLUMNS, COLLAGE_CELL)
+                .and_then(|collage| watermark::watermark_image("image/jpeg".to_owned(), collage))
+                .map_err(|e| CallToolError::from_message(e.to_string()))?;
+            return Ok(result.add_content(
+                ImageContent::new(
+                    base64::encode(collage),
+                    mime,
+                    None,
+                    Some(
+                        serde_json::json!({"name": format!("{} in review", self.year)})
+                            .as_object()
+                            .cloned()
+                            .unwrap(),
+                    ),
+                )
+                .into(),
+            ));
+        }
+        let picked = watermark::watermarked(picked.into_iter().cloned().collect())
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        for (file_name, mime, data) in picked.iter() {
+            result = result.add_content(
+                ImageContent::new(
+                    base64::encode(data),
+                    mime.clone(),
+                    None,
+                    Some(IC.image_meta(file_name, data)),
+                )
+                .into(),
+            );
+        }
+        Ok(result)
+    }
+}
+
+#[mcp_tool(
+    name = "photo_highlights",
+    description = "Highlight reel of a date range: the top photos ranked by resolution, faces (people smiling with eyes open), star rating set by photo_set_metadata and diversity (each pick penalizes photos showing the same things or taken at the same moment). Returns the ranked photos with the justification of each pick. Copies, trash and rejected photos (rating 0) are left out"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoHighlightsTool {
+    /// Start of the range, "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    /// Example: "2023-07-01"
+    date_from: String,
+    /// End of the range (inclusive), "YYYY-MM-DD" or "YYYY-MM-DD HH:MM:SS"
+    /// Example: "2023-07-31"
+    date_to: String,
+    /// Number of highlights, 10 by default
+    /// Example: 10
+    count: Option<u32>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoHighlightsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo highlights: date_from={}, date_to={}, count={:?}",
+            self.date_from,
+            self.date_to,
+            self.count
+        );
+        let count = self.count.unwrap_or(10).min(MAX_HIGHLIGHTS) as usize;
+        let highlights = IC
+            .highlights(
+                &self.date_from,
+                &self.date_to,
+                count,
+                self.include_hidden.unwrap_or(false),
+            )
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to select highlights: {}", e))
+            })?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "date_from": self.date_from,
+                "date_to": self.date_to,
+                "count": count,
+            },
+            "result": highlights,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_subject_timeline",
+    description = "Growing-up sequence of a person or pet: one photo per month or quarter across the years of the photos with the subject, the best photo of each period (quality, faces, differing from the neighbouring periods). The subject is an analysis label (e.g. a pet breed like \"beagle\" or a tag like \"baby\") or a detected object class (e.g. \"dog\"). Returns the periods in time order with their photo counts"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSubjectTimelineTool {
+    /// Analysis label of the subject, matched case insensitive and can be partial
+    /// Example: "beagle"
+    label: Option<String>,
+    /// Detected object class of the subject when no label is given
+    /// Example: "dog"
+    class_name: Option<String>,
+    /// One photo per "month" (default) or "quarter"
+    /// Example: "quarter"
+    period: Option<String>,
+    /// Optionally first year of the timeline
+    /// Example: 2019
+    year_from: Option<u32>,
+    /// Optionally last year of the timeline
+    /// Example: 2024
+    year_to: Option<u32>,
+    /// Optional minimal confidence of the label or detection (0.0 - 1.0)
+    /// Example: 0.5
+    min_confidence: Option<f32>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+}
+
+impl PhotoSubjectTimelineTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "subject timeline: label={:?}, class_name={:?}, period={:?}, years={:?}-{:?}",
+            self.label,
+            self.class_name,
+            self.period,
+            self.year_from,
+            self.year_to
+        );
+        let group = match self.period.as_deref().unwrap_or("month") {
+            "month" => TimeGroup::Month,
+            "quarter" => TimeGroup::Quarter,
+            other => {
+                return Err(CallToolError::from_message(format!(
+                    "Invalid period: {other}, use month or quarter"
+                )));
+            }
+        };
+        let filter = LabelFilter::new(self.min_confidence, None);
+        let include_hidden = self.include_hidden.unwrap_or(false);
+        let (subject, photos) = if let Some(label) = &self.label {
+            let (results, _) = IC
+                .search_image_by_label(
+                    &TRANSLATIONS.untranslate(label),
+                    &None,
+                    &filter,
+                    0,
+                    usize::MAX,
+                    include_hidden,
+                    false,
+                    false,
+                )
+                .map_err(|e| {
+                    CallToolError::from_message(format!("Failed to search photos by label: {}", e))
+                })?;
+            let photos = results.iter().map(|r| r.photo_info().clone()).collect();
+            (label, photos)
+        } else if let Some(class_name) = &self.class_name {
+            let (results, _) = IC
+                .search_image_by_object(
+                    &TRANSLATIONS.untranslate(class_name),
+                    &None,
+                    &filter,
+                    0,
+                    usize::MAX,
+                    include_hidden,
+                    false,
+                    false,
+                )
+                .map_err(|e| {
+                    CallToolError::from_message(format!("Failed to search photos by object: {}", e))
+                })?;
+            let photos = results.iter().map(|r| r.photo_info().clone()).collect();
+            (class_name, photos)
+        } else {
+            return Err(CallToolError::from_message(
+                "Provide label or class_name of the subject".to_owned(),
+            ));
+        };
+        let timeline = IC
+            .subject_timeline(subject, photos, group)
+            .into_iter()
+            .filter(|entry| {
+                let year = entry.period.get(..4).and_then(|y| y.parse::<u32>().ok());
+                year.is_some_and(|year| {
+                    self.year_from.is_none_or(|from| year >= from)
+                        && self.year_to.is_none_or(|to| year <= to)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let json_info = serde_json::json!({
+            "query": {
+                "label": self.label,
+                "class_name": self.class_name,
+                "period": group,
+                "year_from": self.year_from,
+                "year_to": self.year_to,
+                "min_confidence": self.min_confidence,
+            },
+            "result": timeline,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_compare",
+    description = "Compares two photos to help decide which one to keep: EXIF fields that differ, visual similarity (0 to 1), whether the content is identical, quality metrics (resolution, sharpness, brightness, contrast, clipped shadows and highlights) and a suggestion of the better photo. Comparison is returned as JSON text followed by a side by side preview image"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoCompareTool {
+    /// Zip file name of the first photo
+    /// Example: takeout-20230906T142745Z-050.zip
+    first_zip_file_name: String,
+    /// First photo file name inside the zip file (exact match)
+    /// Example: "Takeout/Google Photos/Photos from 2019/IMG_1234.jpg"
+    first_file_name: String,
+    /// Zip file name of the second photo
+    /// Example: takeout-20230906T142745Z-050.zip
+    second_zip_file_name: String,
+    /// Second photo file name inside the zip file (exact match)
+    /// Example: "Takeout/Google Photos/Photos from 2019/IMG_1235.jpg"
+    second_file_name: String,
+}
+
+impl PhotoCompareTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo compare: first={}/{}, second={}/{}",
+            self.first_zip_file_name,
+            self.first_file_name,
+            self.second_zip_file_name,
+            self.second_file_name
+        );
+        let first = IC
+            .find_photo(&self.first_zip_file_name, &self.first_file_name)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let second = IC
+            .find_photo(&self.second_zip_file_name, &self.second_file_name)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        if first == second {
+            return Err(CallToolError::from_message(
+                "Provide two different photos to compare",
+            ));
+        }
+        let mut exif = IC
+            .exif_info(vec![&first, &second])
+            .map_err(|e| CallToolError::from_message(e.to_string()))?
+            .into_iter()
+            .map(ExifResult::into_parts)
+            .collect::<HashMap<PhotoInfo, ExifInfo>>();
+        let image_data = IC.image_data(vec![&first, &second], cancel).map_err(|e| {
+            CallToolError::from_message(format!("Failed to extract image data: {}", e))
+        })?;
+        let mut parts = Vec::new();
+        for info in [&first, &second] {
+            let (photo_info, exif) = exif.remove_entry(info).ok_or_else(|| {
+                CallToolError::from_message(format!("No EXIF info of {}", info.photo_file_name))
+            })?;
+            let data = image_data
+                .iter()
+                .find(|(i, _, _)| i == info)
+                .map(|(_, _, data)| data.as_slice())
+                .ok_or_else(|| {
+                    CallToolError::from_message(format!(
+                        "Failed to extract image data of {}",
+                        info.photo_file_name
+                    ))
+                })?;
+            parts.push((photo_info, exif, data));
+        }
+        let second_part = parts.pop().unwrap();
+        let first_part = parts.pop().unwrap();
+        let (comparison, (mime, preview)) = compare::compare(first_part, second_part)
+            .and_then(|(comparison, preview)| {
+                Ok((
+                    comparison,
+                    watermark::watermark_image("image/jpeg".to_owned(), preview)?,
+                ))
+            })
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "first_zip_file_name": self.first_zip_file_name,
+                "first_file_name": self.first_file_name,
+                "second_zip_file_name": self.second_zip_file_name,
+                "second_file_name": self.second_file_name,
+            },
+            "result": comparison,
+        });
+        Ok(
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())])
+                .add_content(
+                    ImageContent::new(
+                        base64::encode(preview),
+                        mime,
+                        None,
+                        Some(
+                            serde_json::json!({"name": "comparison"})
+                                .as_object()
+                                .cloned()
+                                .unwrap(),
+                        ),
+                    )
+                    .into(),
+                ),
+        )
+    }
+}
+
+#[mcp_tool(
+    name = "photo_text_search",
+    description = "Full-text search over photo file names, descriptions, detected objects and labels, camera model, lens and date, ranked by relevance. Query supports AND, OR, -term, \"phrases\" and field:term (file_name, description, labels, camera, date); all terms must match by default. Optionally fuzzy, matching words with a typo. Returns photo info objects with their scores, best first, with pagination. Requires full_text.enabled in the configuration"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoTextSearchTool {
+    /// Text to search for
+    /// Example: "birthday cake"
+    query: String,
+    /// Optionally match words differing in one character (typos)
+    /// Example: false
+    fuzzy: Option<bool>,
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 20
+    limit: u32,
+    /// Optionally return a sample of `limit` photos spread evenly over the time taken instead
+    /// of the page at offset, to characterize large result sets before paging
+    /// Example: false
+    preview_mode: Option<bool>,
+    /// Optionally return photo counts per day, week, month or year of the time taken (with a
+    /// representative photo of each) instead of the photos
+    /// Example: "month"
+    group_by: Option<String>,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include copies of photos stored more than once (e.g. in several Takeout parts)
+    /// Example: false
+    include_duplicates: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+    /// Optionally return one photo per group with the group size: "bursts" (photos taken by
+    /// the same camera in quick succession) or "duplicates" (copies of the same photo), pages
+    /// are then taken from the groups
+    /// Example: "bursts"
+    collapse: Option<String>,
+}
+
+impl PhotoTextSearchTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo text search: query={}, fuzzy={:?}, offset={}, limit={}",
+            self.query,
+            self.fuzzy,
+            self.offset,
+            self.limit
+        );
+        let page = SearchPage::new(
+            self.offset,
+            self.limit.min(MAX_PHOTO_TEXT_SEARCH_LIMIT),
+            self.preview_mode,
+            &self.group_by,
+            &self.collapse,
+        )?;
+        let (from, count) = page.window();
+        let (results, total) = IC
+            .text_search(
+                &self.query,
+                self.fuzzy.unwrap_or(false),
+                from,
+                count,
+                self.include_hidden.unwrap_or(false),
+                page.include_duplicates(self.include_duplicates),
+                self.include_trash.unwrap_or(false),
+            )
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to search photos by text: {}", e))
+            })?;
+        let (results, buckets, preview_sample, pagination) = page.paginate(results, total);
+
+        let json_info = serde_json::json!({
+            "query": {
+                "query": self.query,
+                "fuzzy": self.fuzzy,
+            },
+            "result": result_or_buckets(&results, buckets),
+            "preview": preview_sample,
+            "pagination": pagination,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_federated_search",
+    description = "Searches all photo collections the client's API token is bound to at once and merges the results in time order, each labelled with its collection. Search by file name (query), year and month, detected object class (query), analysis label (query) or full text (query). Returns the page at offset with the reference to the next page (next_offset, next_limit) of the merged results"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoFederatedSearchTool {
+    /// What to search by: "name", "year_month", "object", "label" or "text"
+    /// Example: "object"
+    search: String,
+    /// File name part, object class, label or text to search for, not used by "year_month"
+    /// Example: "dog"
+    query: Option<String>,
+    /// Year taken, required by "year_month"
+    /// Example: 2021
+    year: Option<u32>,
+    /// Month taken (1-12), required by "year_month"
+    /// Example: 7
+    month: Option<u32>,
+    /// Offset into the merged results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 20
+    limit: u32,
+    /// Optionally include photos hidden by photo_hide tool
+    /// Example: false
+    include_hidden: Option<bool>,
+    /// Optionally include photos in the Takeout trash (deleted in Google Photos)
+    /// Example: false
+    include_trash: Option<bool>,
+}
+
+impl PhotoFederatedSearchTool {
+    pub fn call_tool(&self, authorized: &[String]) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "federated search: search={}, query={:?}, year={:?}, month={:?}, collections={:?}",
+            self.search,
+            self.query,
+            self.year,
+            self.month,
+            authorized
+        );
+        let include_hidden = self.include_hidden.unwrap_or(false);
+        let include_trash = self.include_trash.unwrap_or(false);
+        let query = || {
+            self.query.as_ref().ok_or_else(|| {
+                PhotoInsightError::from_message(format!("{} search requires query", self.search))
+            })
+        };
+        match self.search.as_str() {
+            "name" => self.respond(authorized, || {
+                let (infos, _) = IC.search_image_by_name(
+                    query()?,
+                    &None,
+                    0,
+                    usize::MAX,
+                    include_hidden,
+                    false,
+                    include_trash,
+                    &NameFolding::configured(),
+                );
+                Ok(infos.into_iter().cloned().collect())
+            }),
+            "year_month" => {
+                let (Some(year), Some(month)) = (self.year, self.month) else {
+                    return Err(CallToolError::from_message(
+                        "year_month search requires year and month",
+                    ));
+                };
+                self.respond(authorized, || {
+                    let (infos, _) = IC.search_image_by_year_month(
+                        year,
+                        month,
+                        0,
+                        usize::MAX,
+                        include_hidden,
+                        false,
+                        include_trash,
+                    );
+                    Ok(infos)
+                })
+            }
+            "object" => self.respond(authorized, || {
+                let (results, _) = IC.search_image_by_object(
+                    &TRANSLATIONS.untranslate(query()?),
+                    &None,
+                    &LabelFilter::default(),
+                    0,
+                    usize::MAX,
+                    include_hidden,
+                    false,
+                    include_trash,
+                )?;
+                Ok(results)
+            }),
+            "label" => self.respond(authorized, || {
+                let (results, _) = IC.search_image_by_label(
+                    &TRANSLATIONS.untranslate(query()?),
+                    &None,
+                    &LabelFilter::default(),
+                    0,
+                    usize::MAX,
+                    include_hidden,
+                    false,
+                    include_trash,
+                )?;
+                Ok(results)
+            }),
+            "text" => self.respond(authorized, || {
+                let (results, _) = IC.text_search(
+                    query()?,
+                    false,
+                    0,
+                    usize::MAX,
+                    include_hidden,
+                    false,
+                    include_trash,
+                )?;
+                Ok(results)
+            }),
+            other => Err(CallToolError::from_message(format!(
+                "Unknown search {other}, use name, year_month, object, label or text"
+            ))),
+        }
+    }
+
+    fn respond<T: PhotoResult + Clone + Serialize>(
+        &self,
+        authorized: &[String],
+        search: impl Fn() -> Result<Vec<T>, PhotoInsightError>,
+    ) -> Result<CallToolResult, CallToolError> {
+        let offset = self.offset as usize;
+        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
+        let (results, total) = IC
+            .federated_search(authorized, offset, limit, search)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to search collections: {}", e))
+            })?;
+        let next_offset = offset + results.len();
+        let json_info = serde_json::json!({
+            "query": {
+                "search": self.search,
+                "query": self.query,
+                "year": self.year,
+                "month": self.month,
+                "collections": if authorized.is_empty() {
+                    vec![DEFAULT_COLLECTION.to_owned()]
+                } else {
+                    authorized.to_vec()
+                },
+            },
+            "result": results,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": limit,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_query_plan",
+    description = "Translates a natural-language photo request (e.g. \"dogs on the beach in summer 2021 shot with my iPhone\") into the structured filter the server would search for (years, months, time of day, camera model, detected objects, labels, people count, portraits, remaining text) and the search tool calls with their arguments executing it. Nothing is searched; photos returned by all the planned calls match the request. Words which were not understood are listed"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoQueryPlanTool {
+    /// Natural-language request
+    /// Example: "photos of my dog from last summer"
+    request: String,
+}
+
+impl PhotoQueryPlanTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo query plan: request={}", self.request);
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        let today = shift_date_time("1970-01-01 00:00:00", now)
+            .and_then(|date_time| parse_date_time(&date_time))
+            .map(|(year, month, ..)| (year, month))
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let plan = query_plan::plan(
+            &self.request,
+            &IC.query_vocabulary(),
+            today,
+            crate::CONFIG.full_text.enabled,
+        );
+
+        let json_info = serde_json::json!({
+            "query": {
+                "request": self.request,
+            },
+            "result": plan,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_describe_tools",
+    description = "Describes the tools of the server for client-side validation and code generation: name, description, JSON schema of the arguments (input_schema) and of the JSON result envelope (output_schema, missing for tools returning images only) and whether images are returned besides the JSON. Optionally describes a single tool"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoDescribeToolsTool {
+    /// Optional tool name, all tools are described when missing
+    /// Example: "photo_search_by_name"
+    tool_name: Option<String>,
+}
+
+impl PhotoDescribeToolsTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo describe tools: tool_name={:?}", self.tool_name);
+        let tools = output_schema::tools()
+            .into_iter()
+            .filter(|tool| {
+                self.tool_name
+                    .as_ref()
+                    .is_none_or(|name| *name == tool.name)
+            })
+            .map(|tool| {
+                serde_json::json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "input_schema": tool.input_schema,
+                    "output_schema": tool.output_schema,
+                    "returns_images": output_schema::returns_images(&tool.name),
+                })
+            })
+            .collect::<Vec<_>>();
+        if tools.is_empty() {
+            return Err(CallToolError::from_message(format!(
+                "Unknown tool {}",
+                self.tool_name.clone().unwrap_or_default()
+            )));
+        }
+
+        let json_info = serde_json::json!({
+            "result": {
+                "tools": tools,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_set_metadata",
+    description = "Corrects metadata of a single photo (e.g. wrong EXIF date when the camera clock was off). Corrections of date, location and description, the star rating and the color label (Lightroom compatible, searchable by the color_label EXIF tag) are stored separately from the original archives and applied on top of EXIF in all searches and responses. Returns the resulting EXIF info"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSetMetadataTool {
+    /// Zip file name of the photo
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: String,
+    /// Photo file name inside the zip file (exact match)
+    /// Example: "Takeout/Google Photos/Photos from 2019/IMG_1234.jpg"
+    file_name: String,
+    /// Optional corrected date and time
+    /// Example: "2019-07-14 18:30:00"
+    date_time: Option<String>,
+    /// Optional latitude of the photo location, requires longitude
+    /// Example: 48.8584
+    latitude: Option<f64>,
+    /// Optional longitude of the photo location, requires latitude
+    /// Example: 2.2945
+    longitude: Option<f64>,
+    /// Optional description of the photo
+    /// Example: "Grandma's 80th birthday"
+    description: Option<String>,
+    /// Optional star rating from 1 to 5, 0 rejects the photo from highlights
+    /// Example: 5
+    rating: Option<u8>,
+    /// Optional color label: red, yellow, green, blue, purple, or none to remove the label
+    /// read from the photo's XMP
+    /// Example: "green"
+    color_label: Option<String>,
+    /// Optionally remove all corrections of the photo instead, EXIF values are used again
+    /// Example: false
+    clear: Option<bool>,
+}
+
+impl PhotoSetMetadataTool {
+    fn metadata_override(&self) -> Result<MetadataOverride, PhotoInsightError> {
+        let date_time = self
+            .date_time
+            .as_deref()
+            .map(normalize_date_time)
+            .transpose()?;
+        let location = match (self.latitude, self.longitude) {
+            (Some(latitude), Some(longitude)) => {
+                if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                    return Err(PhotoInsightError::from_message(
+                        "Latitude must be within -90..90 and longitude within -180..180",
+                    ));
+                }
+                Some(GeoLocation {
+                    latitude,
+                    longitude,
+                })
+            }
+            (None, None) => None,
+            _ => {
+                return Err(PhotoInsightError::from_message(
+                    "Provide both latitude and longitude",
+                ));
+            }
+        };
+        if self.rating.is_some_and(|rating| rating > MAX_RATING) {
+            return Err(PhotoInsightError::from_message(format!(
+                "Rating must be within 0..{MAX_RATING}"
+            )));
+        }
+        let color_label = self
+            .color_label
+            .as_deref()
+            .map(ColorLabel::parse)
+            .transpose()?;
+        let value = MetadataOverride {
+            date_time,
+            location,
+            description: self.description.clone(),
+            rating: self.rating,
+            color_label,
+        };
+        if value == MetadataOverride::default() {
+            return Err(PhotoInsightError::from_message(
+                "Provide date_time, location, description, rating or color_label to set",
+            ));
+        }
+        Ok(value)
+    }
+
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo set metadata: zip_file_name={}, file_name={}, date_time={:?}, latitude={:?}, longitude={:?}, description={:?}, rating={:?}, color_label={:?}, clear={:?}",
+            self.zip_file_name,
+            self.file_name,
+            self.date_time,
+            self.latitude,
+            self.longitude,
+            self.description,
+            self.rating,
+            self.color_label,
+            self.clear
+        );
+        let value = if self.clear.unwrap_or(false) {
+            None
+        } else {
+            Some(
+                self.metadata_override()
+                    .map_err(|e| CallToolError::from_message(e.to_string()))?,
+            )
+        };
+        let photo_info = IC
+            .find_photo(&self.zip_file_name, &self.file_name)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let exif = IC.set_metadata(&photo_info, value).map_err(|e| {
+            CallToolError::from_message(format!("Failed to set photo metadata: {}", e))
+        })?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "zip_file_name": self.zip_file_name,
+                "file_name": self.file_name,
+                "clear": self.clear,
+            },
+            "result": exif,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_shift_time",
+    description = "Fixes camera clock offsets: shifts date and time of all photos matching the query (camera model, zip file, date range) by the given delta. The shift is recorded as metadata correction, original archives are untouched, photos are moved to the right year/month. Use dry_run to preview the change. Returns number of shifted photos and a sample of the changes"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoShiftTimeTool {
+    /// Optional camera model (case insensitive, partial match)
+    /// Example: "Canon EOS 40D"
+    camera_model: Option<String>,
+    /// Optional zip file name (case insensitive, partial match)
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: Option<String>,
+    /// Optional start of the date range (inclusive)
+    /// Example: "2019-07-01"
+    date_from: Option<String>,
+    /// Optional end of the date range (inclusive)
+    /// Example: "2019-07-31"
+    date_to: Option<String>,
+    /// Days to shift, negative to shift back
+    /// Example: 0
+    days: Option<i64>,
+    /// Hours to shift, negative to shift back
+    /// Example: -1
+    hours: Option<i64>,
+    /// Minutes to shift, negative to shift back
+    /// Example: 30
+    minutes: Option<i64>,
+    /// Seconds to shift, negative to shift back
+    /// Example: 0
+    seconds: Option<i64>,
+    /// Only report the changes without storing them
+    /// Example: true
+    dry_run: Option<bool>,
+}
+
+impl PhotoShiftTimeTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo shift time: {:?}", self);
+        if self.camera_model.is_none()
+            && self.zip_file_name.is_none()
+            && self.date_from.is_none()
+            && self.date_to.is_none()
+        {
+            return Err(CallToolError::from_message(
+                "Provide camera_model, zip_file_name or date range of the photos to shift",
+            ));
+        }
+        let delta = [
+            (self.days, 86400),
+            (self.hours, 3600),
+            (self.minutes, 60),
+            (self.seconds, 1),
+        ]
+        .into_iter()
+        .try_fold(0i64, |delta, (value, unit)| {
+            value.unwrap_or(0).checked_mul(unit)?.checked_add(delta)
+        })
+        .ok_or_else(|| CallToolError::from_message("Time delta is out of range"))?;
+        if delta == 0 {
+            return Err(CallToolError::from_message("Time delta must not be zero"));
+        }
+        let dry_run = self.dry_run.unwrap_or(false);
+        let shifts = IC
+            .shift_time(
+                &self.camera_model,
+                &self.zip_file_name,
+                &self.date_from,
+                &self.date_to,
+                delta,
+                dry_run,
+            )
+            .map_err(|e| CallToolError::from_message(format!("Failed to shift time: {}", e)))?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "camera_model": self.camera_model,
+                "zip_file_name": self.zip_file_name,
+                "date_from": self.date_from,
+                "date_to": self.date_to,
+                "delta_seconds": delta,
+                "dry_run": dry_run,
+            },
+            "result": {
+                "shifted": shifts.len(),
+                "sample": shifts.iter().take(MAX_TIME_SHIFT_SAMPLE).collect::<Vec<_>>(),
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_hide",
+    description = "Hides unwanted photos (soft delete) or shows them again. Hidden photos are excluded from all searches unless include_hidden is set, the original archives are never touched. Use photo_list_hidden tool to review hidden photos"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoHideTool {
+    /// Zip file name of the photos
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: String,
+    /// Photo file names inside the zip file (exact match)
+    /// Example: ["Takeout/Google Photos/Photos from 2019/IMG_1234.jpg"]
+    file_names: Vec<String>,
+    /// Optionally set to false to show the photos again
+    /// Example: true
+    hidden: Option<bool>,
+}
+
+impl PhotoHideTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo hide: zip_file_name={}, file_names={:?}, hidden={:?}",
+            self.zip_file_name,
+            self.file_names,
+            self.hidden
+        );
+        let hide = self.hidden.unwrap_or(true);
+        let photo_infos = self
+            .file_names
+            .iter()
+            .map(|file_name| IC.find_photo(&self.zip_file_name, file_name))
+            .collect::<Result<Vec<PhotoInfo>, PhotoInsightError>>()
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let changed = IC
+            .hide_photos(photo_infos, hide)
+            .map_err(|e| CallToolError::from_message(format!("Failed to hide photos: {}", e)))?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "zip_file_name": self.zip_file_name,
+                "file_names": self.file_names,
+                "hidden": hide,
+            },
+            "result": {
+                "changed": changed,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_list_hidden",
+    description = "Lists photos hidden by photo_hide tool for review, returns photo files and reference to the next page (next_offset, next_limit) if more results are available"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoListHiddenTool {
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+}
+
+impl PhotoListHiddenTool {
+    pub fn call_tool(&self) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo list hidden: offset={}, limit={}",
+            self.offset,
+            self.limit
+        );
+        let offset = self.offset as usize;
+        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
+        let (infos, total) = IC.list_hidden(offset, limit);
+        let next_offset = offset + infos.len();
+        let next_limit = limit;
+        let json_info = serde_json::json!({
+            "result": infos,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": next_limit,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_render_slideshow",
+    description = "Renders photos into a slideshow, an animated GIF or an MP4 video (encoded by ffmpeg), to share a curated set as a single file. Photos are the given photo IDs or the selection of this session in its order, each shown for a fixed duration, optionally with a slow zoom and pan (Ken Burns effect). Returns JSON text with the number of photos, frames and duration followed by the slideshow as an embedded resource"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoRenderSlideshowTool {
+    /// Optionally photo IDs (photo_id of returned photos) or "zip_file_name|photo_file_name|photo_index_in_zip"
+    /// keys of the photos in slideshow order, the selection of this session is used otherwise
+    /// Example: ["3f1a9c0e7b2d4e61", "9b0c2d7e5a1f3c48"]
+    photo_ids: Option<Vec<String>>,
+    /// Optional format, "gif" (default) or "mp4"
+    /// Example: "gif"
+    format: Option<String>,
+    /// Optional seconds each photo is shown, defaults to 2
+    /// Example: 3
+    seconds_per_photo: Option<f32>,
+    /// Optional width of the slideshow in pixels (height is 3/4 of it), defaults to 480
+    /// Example: 640
+    width: Option<u32>,
+    /// Optionally zoom and pan slowly over every photo
+    /// Example: true
+    ken_burns: Option<bool>,
+}
+
+impl PhotoRenderSlideshowTool {
+    pub fn call_tool(
+        &self,
+        session_id: &str,
+        cancel: &CancellationToken,
+    ) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo render slideshow: session={}, photo_ids={:?}, format={:?}, seconds_per_photo={:?}, width={:?}, ken_burns={:?}",
+            session_id,
+            self.photo_ids,
+            self.format,
+            self.seconds_per_photo,
+            self.width,
+            self.ken_burns
+        );
+        let options = SlideshowOptions {
+            format: SlideshowFormat::parse(self.format.as_deref().unwrap_or("gif"))
+                .map_err(|e| CallToolError::from_message(e.to_string()))?,
+            seconds_per_photo: self.seconds_per_photo.unwrap_or(2.0).clamp(0.1, 10.0),
+            width: self
+                .width
+                .unwrap_or(DEFAULT_SLIDESHOW_WIDTH)
+                .clamp(64, MAX_SLIDESHOW_WIDTH),
+            ken_burns: self.ken_burns.unwrap_or(false),
+        };
+        let infos = match &self.photo_ids {
+            Some(photo_ids) => IC
+                .resolve_photos(photo_ids)
+                .map_err(|e| CallToolError::from_message(e.to_string()))?,
+            None => SELECTION.get(session_id),
+        };
+        if infos.is_empty() {
+            return Err(CallToolError::from_message(
+                "Provide photo_ids or add photos to the selection first",
+            ));
+        }
+        if infos.len() > MAX_SLIDESHOW_PHOTOS {
+            return Err(CallToolError::from_message(format!(
+                "Slideshow has {} photos, at most {MAX_SLIDESHOW_PHOTOS} are allowed",
+                infos.len()
+            )));
+        }
+        let image_data = IC
+            .image_data(infos.iter().collect(), cancel)
+            .and_then(watermark::watermarked)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to extract image data: {}", e))
+            })?;
+        let images = image_data
+            .into_iter()
+            .map(|(_, _, data)| data)
+            .collect::<Vec<_>>();
+        let slideshow = slideshow::render(&images, &options, &crate::CONFIG.motion.ffmpeg, cancel)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to render slideshow: {}", e))
+            })?;
+
+        let json_info = serde_json::json!({
+            "query": options,
+            "result": slideshow,
+        });
+        let resource = BlobResourceContents {
+            blob: base64::encode(&slideshow.data),
+            meta: None,
+            mime_type: Some(options.format.mime().to_owned()),
+            uri: format!("slideshow://slideshow.{}", options.format.extension()),
+        };
+        Ok(
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())])
+                .add_content(EmbeddedResource::new(resource.into(), None, None).into()),
+        )
+    }
+}
+
+#[mcp_tool(
+    name = "photo_export_xmp",
+    description = "Exports metadata produced by the server (ratings, color labels and captions set by photo_set_metadata, labels of detected objects and analysis as keywords) into XMP sidecar files, so Lightroom, darktable and other editors pick it up. Photos are the given photo IDs or the selection of this session, sidecars are written into the output directory in a folder per archive mirroring the photo paths inside it, optionally next to copies of the original photos. Existing sidecars are kept unless overwrite is set"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoExportXmpTool {
+    /// Optionally photo IDs (photo_id of returned photos) or "zip_file_name|photo_file_name|photo_index_in_zip"
+    /// keys of the photos, the selection of this session is used otherwise
+    /// Example: ["3f1a9c0e7b2d4e61", "9b0c2d7e5a1f3c48"]
+    photo_ids: Option<Vec<String>>,
+    /// Optional output directory, defaults to photo-mcp.export in the image directory,
+    /// relative to the collection directory when collections are configured
+    /// Example: "/home/me/Pictures/export"
+    output_dir: Option<String>,
+    /// Optional sidecar naming, "darktable" (IMG_0001.jpg.xmp, default) or "lightroom" (IMG_0001.xmp)
+    /// Example: "lightroom"
+    naming: Option<String>,
+    /// Optionally copy the original photos next to their sidecars, defaults to true
+    /// Example: false
+    include_photos: Option<bool>,
+    /// Optionally replace existing sidecars (and photos)
+    /// Example: true
+    overwrite: Option<bool>,
+}
+
+impl PhotoExportXmpTool {
+    pub fn call_tool(&self, session_id: &str) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo export xmp: session={}, photo_ids={:?}, output_dir={:?}, naming={:?}, include_photos={:?}, overwrite={:?}",
+            session_id,
+            self.photo_ids,
+            self.output_dir,
+            self.naming,
+            self.include_photos,
+            self.overwrite
+        );
+        let naming = SidecarNaming::parse(self.naming.as_deref().unwrap_or("darktable"))
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let output_dir = match &self.output_dir {
+            Some(path) => client_path(path)?,
+            None => format!("{}/{}", IC.image_dir(), xmp::DEFAULT_EXPORT),
+        };
+        let include_photos = self.include_photos.unwrap_or(true);
+        let infos = match &self.photo_ids {
+            Some(photo_ids) => IC
+                .resolve_photos(photo_ids)
+                .map_err(|e| CallToolError::from_message(e.to_string()))?,
+            None => SELECTION.get(session_id),
+        };
+        if infos.is_empty() {
+            return Err(CallToolError::from_message(
+                "Provide photo_ids or add photos to the selection first",
+            ));
+        }
+        if infos.len() > MAX_XMP_EXPORT_PHOTOS {
+            return Err(CallToolError::from_message(format!(
+                "Export has {} photos, at most {MAX_XMP_EXPORT_PHOTOS} are allowed",
+                infos.len()
+            )));
+        }
+        let mut photos = if include_photos {
+            IC.extract_photos(infos.iter().collect())
+                .map_err(|e| {
+                    CallToolError::from_message(format!("Failed to extract photos: {}", e))
+                })?
+                .into_iter()
+                .collect::<HashMap<PhotoInfo, Vec<u8>>>()
+        } else {
+            HashMap::new()
+        };
+        let metadata = IC.xmp_metadata(&infos);
+        let entries = infos
+            .into_iter()
+            .zip(metadata)
+            .map(|(info, metadata)| ExportEntry {
+                data: photos.remove(&info),
+                info,
+                metadata,
+            })
+            .collect();
+        let export = xmp::export(
+            &output_dir,
+            entries,
+            naming,
+            self.overwrite.unwrap_or(false),
+        )
+        .map_err(|e| CallToolError::from_message(format!("Failed to export XMP: {}", e)))?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "output_dir": output_dir,
+                "naming": naming,
+                "include_photos": include_photos,
+            },
+            "result": export,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_transform",
+    description = "Applies simple transforms to a preview of a photo: rotate 90/180/270 degrees clockwise, flip horizontally or vertically, auto-contrast and auto-white-balance. Original photos are never modified. Returns JSON text with the applied transforms and the preview size followed by the transformed preview"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoTransformTool {
+    /// Photo ID (photo_id of returned photos) or "zip_file_name|photo_file_name|photo_index_in_zip"
+    /// Example: "3f1a9c0e7b2d4e61"
+    photo_id: String,
+    /// Transforms applied in order, any of "rotate_90", "rotate_180", "rotate_270", "flip_horizontal",
+    /// "flip_vertical", "auto_contrast", "auto_white_balance"
+    /// Example: ["rotate_90", "auto_contrast"]
+    transforms: Vec<String>,
+    /// Optional longest side of the preview in pixels, defaults to 1024
+    /// Example: 2048
+    size: Option<u32>,
+}
+
+impl PhotoTransformTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo transform: photo_id={}, transforms={:?}, size={:?}",
+            self.photo_id,
+            self.transforms,
+            self.size
+        );
+        let transforms = self
+            .transforms
+            .iter()
+            .map(|t| Transform::parse(t))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let size = self
+            .size
+            .unwrap_or(DEFAULT_TRANSFORM_SIZE)
+            .clamp(16, MAX_TRANSFORM_SIZE);
+        let infos = IC
+            .resolve_photos(std::slice::from_ref(&self.photo_id))
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let (photo_info, _, data) = IC
+            .image_data(infos.iter().collect(), cancel)
+            .map_err(|e| {
+                CallToolError::from_message(format!("Failed to extract image data: {}", e))
+            })?
+            .pop()
+            .ok_or_else(|| {
+                CallToolError::from_message(format!(
+                    "Failed to extract image data of {}",
+                    self.photo_id
+                ))
+            })?;
+        let img = ::image::load_from_memory(&data).map_err(|e| {
+            CallToolError::from_message(format!("Failed to decode {}: {e}", self.photo_id))
+        })?;
+        let preview = image::transform(img.thumbnail(size, size), &transforms);
+        let (width, height) = (preview.width(), preview.height());
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        ::image::DynamicImage::ImageRgb8(preview.to_rgb8())
+            .write_to(&mut jpeg, ::image::ImageFormat::Jpeg)
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let (mime, jpeg) = watermark::watermark_image("image/jpeg".to_owned(), jpeg.into_inner())
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+
+        let json_info = serde_json::json!({
+            "query": {
+                "photo_id": self.photo_id,
+                "transforms": transforms,
+                "size": size,
+            },
+            "result": {
+                "photo": photo_info,
+                "width": width,
+                "height": height,
+            },
+        });
+        Ok(
+            CallToolResult::text_content(vec![TextContent::from(json_info.to_string())])
+                .add_content(
+                    ImageContent::new(
+                        base64::encode(&jpeg),
+                        mime,
+                        None,
+                        Some(IC.image_meta(&photo_info, &jpeg)),
+                    )
+                    .into(),
+                ),
+        )
+    }
+}
+
+#[mcp_tool(
+    name = "photo_benchmark",
+    description = "Measures performance on the live collection: index load time, latency of every search type (first and repeated runs), photo extraction and decode throughput and inference throughput of analysis stages on a sample of photos. Returns a JSON report for comparing performance before and after a change, analysis results are not stored"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoBenchmarkTool {
+    /// Optional number of photos extracted, decoded and analyzed, defaults to 20, at most 200
+    samples: Option<u32>,
+    /// Optional number of runs of every search, defaults to 3
+    repeat: Option<u32>,
+    /// Optional analysis stages whose inference throughput is measured, defaults to the
+    /// enabled stages, an empty list skips inference
+    /// Example: ["object_detection"]
+    stages: Option<Vec<String>>,
+}
+
+impl PhotoBenchmarkTool {
+    pub fn call_tool(&self, cancel: &CancellationToken) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo benchmark: samples={:?}, repeat={:?}, stages={:?}",
+            self.samples,
+            self.repeat,
+            self.stages
+        );
+        let options = BenchmarkOptions {
+            samples: self
+                .samples
+                .unwrap_or(DEFAULT_BENCHMARK_SAMPLES)
+                .clamp(1, MAX_BENCHMARK_SAMPLES) as usize,
+            repeat: self
+                .repeat
+                .unwrap_or(DEFAULT_BENCHMARK_REPEAT)
+                .clamp(1, MAX_BENCHMARK_REPEAT) as usize,
+            stages: self.stages.clone().unwrap_or_else(|| {
+                crate::ANALYZERS
+                    .enabled()
+                    .iter()
+                    .map(|stage| stage.name())
+                    .collect()
+            }),
+        };
+        let report = benchmark::run(&IC, &options, cancel)
+            .map_err(|e| CallToolError::from_message(format!("Benchmark failed: {}", e)))?;
+        let json_info = serde_json::json!({
+            "query": {
+                "samples": options.samples,
+                "repeat": options.repeat,
+                "stages": options.stages,
+            },
+            "result": report,
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_selection_add",
+    description = "Adds photos to the selection, a working set of photos kept for this session. Build up the selection over multiple searches and then use it with other tools in one go. Returns number of added photos and size of the selection"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSelectionAddTool {
+    /// Zip file name of the photos
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: String,
+    /// Photo file names inside the zip file (exact match)
+    /// Example: ["Takeout/Google Photos/Photos from 2019/IMG_1234.jpg"]
+    file_names: Vec<String>,
+}
+
+impl PhotoSelectionAddTool {
+    pub fn call_tool(&self, session_id: &str) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo selection add: session={}, zip_file_name={}, file_names={:?}",
+            session_id,
+            self.zip_file_name,
+            self.file_names
+        );
+        let photo_infos = self
+            .file_names
+            .iter()
+            .map(|file_name| IC.find_photo(&self.zip_file_name, file_name))
+            .collect::<Result<Vec<PhotoInfo>, PhotoInsightError>>()
+            .map_err(|e| CallToolError::from_message(e.to_string()))?;
+        let added = SELECTION.add(session_id, photo_infos);
+
+        let json_info = serde_json::json!({
+            "result": {
+                "added": added,
+                "selected": SELECTION.get(session_id).len(),
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_selection_remove",
+    description = "Removes photos from the selection of this session. Returns number of removed photos and size of the selection"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSelectionRemoveTool {
+    /// Zip file name of the photos
+    /// Example: takeout-20230906T142745Z-050.zip
+    zip_file_name: String,
+    /// Photo file names inside the zip file (exact match)
+    /// Example: ["Takeout/Google Photos/Photos from 2019/IMG_1234.jpg"]
+    file_names: Vec<String>,
+}
+
+impl PhotoSelectionRemoveTool {
+    pub fn call_tool(&self, session_id: &str) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo selection remove: session={}, zip_file_name={}, file_names={:?}",
+            session_id,
+            self.zip_file_name,
+            self.file_names
+        );
+        let photo_infos = SELECTION
+            .get(session_id)
+            .into_iter()
+            .filter(|info| {
+                info.zip_file_name == self.zip_file_name
+                    && self.file_names.contains(&info.photo_file_name)
+            })
+            .collect::<Vec<PhotoInfo>>();
+        let removed = SELECTION.remove(session_id, &photo_infos);
+
+        let json_info = serde_json::json!({
+            "result": {
+                "removed": removed,
+                "selected": SELECTION.get(session_id).len(),
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_selection_list",
+    description = "Lists photos in the selection of this session in the order they were added, returns photo files and reference to the next page (next_offset, next_limit) if more results are available"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSelectionListTool {
+    /// Offset into results
+    /// Example: 0
+    offset: u32,
+    /// Limit number of results returned
+    /// Example: 5
+    limit: u32,
+}
+
+impl PhotoSelectionListTool {
+    pub fn call_tool(&self, session_id: &str) -> Result<CallToolResult, CallToolError> {
+        tracing::info!(
+            "photo selection list: session={}, offset={}, limit={}",
+            session_id,
+            self.offset,
+            self.limit
+        );
+        let offset = self.offset as usize;
+        let limit = self.limit.min(MAX_PHOTO_FILES_SEARCH_LIMIT) as usize;
+        let selection = SELECTION.get(session_id);
+        let total = selection.len();
+        let start = offset.min(total);
+        let end = (offset + limit).min(total);
+        let infos = &selection[start..end];
+        let next_offset = offset + infos.len();
+        let next_limit = limit;
+        let json_info = serde_json::json!({
+            "result": infos,
+            "pagination": {
+                "offset": offset,
+                "limit": limit,
+                "total": total,
+                "next_offset": if next_offset < total { Some(next_offset) } else { None },
+                "next_limit": next_limit,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
+#[mcp_tool(
+    name = "photo_selection_clear",
+    description = "Empties the selection of this session, returns number of removed photos"
+)]
+#[derive(Debug, ::serde::Deserialize, ::serde::Serialize, JsonSchema)]
+pub struct PhotoSelectionClearTool {}
+
+impl PhotoSelectionClearTool {
+    pub fn call_tool(&self, session_id: &str) -> Result<CallToolResult, CallToolError> {
+        tracing::info!("photo selection clear: session={}", session_id);
+        let removed = SELECTION.clear(session_id);
+
+        let json_info = serde_json::json!({
+            "result": {
+                "removed": removed,
+            },
+        });
+
+        Ok(CallToolResult::text_content(vec![TextContent::from(
+            json_info.to_string(),
+        )]))
+    }
+}
+
 tool_box!(
     PhotoTools,
     [
@@ -643,7 +4171,52 @@ tool_box!(
         PhotoExifTagTool,
         PhotoExifSearchTagTool,
         PhotoObjectDetectionTool,
+        PhotoDetectCustomTool,
+        PhotoDecodeBarcodesTool,
+        PhotoDetectionModelsTool,
+        PhotoSearchByObjectTool,
+        PhotoSearchByLabelTool,
+        PhotoSearchByPeopleCountTool,
+        PhotoSearchPortraitsTool,
         PhotoGlobalSummaryTool,
         PhotoStatsByYearTool,
+        PhotoSettingsInsightsTool,
+        PhotoShutterCountsTool,
+        PhotoExposureStatsTool,
+        PhotoStorageReportTool,
+        PhotoArchiveCoversTool,
+        PhotoCacheStatsTool,
+        PhotoIndexHealthTool,
+        PhotoExportCatalogTool,
+        PhotoExportBundleTool,
+        PhotoImportBundleTool,
+        PhotoBackupDerivedTool,
+        PhotoRestoreDerivedTool,
+        PhotoCacheCleanupTool,
+        PhotoPeriodSummaryTool,
+        PhotoYearInReviewTool,
+        PhotoSubjectTimelineTool,
+        PhotoHighlightsTool,
+        PhotoStoryPayloadTool,
+        PhotoFindGapsTool,
+        PhotoManifestCoverageTool,
+        PhotoRenderSlideshowTool,
+        PhotoExportXmpTool,
+        PhotoTransformTool,
+        PhotoBenchmarkTool,
+        PhotoCompareTool,
+        PhotoTextSearchTool,
+        PhotoFederatedSearchTool,
+        PhotoQueryPlanTool,
+        PhotoDescribeToolsTool,
+        PhotoCalendarTool,
+        PhotoSetMetadataTool,
+        PhotoShiftTimeTool,
+        PhotoHideTool,
+        PhotoListHiddenTool,
+        PhotoSelectionAddTool,
+        PhotoSelectionRemoveTool,
+        PhotoSelectionListTool,
+        PhotoSelectionClearTool,
     ]
 );